@@ -0,0 +1,53 @@
+//! Cost of looking up a cached schema, e.g. `Vec<HashMap<String, i32>>::get_prompt_schema()`
+//! on every request, from one thread and from many at once.
+//!
+//! Run with `cargo bench --bench schema_cache`.
+
+use llm_xml_caster::LlmPrompt;
+use std::collections::{BTreeMap, HashMap};
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LOOKUPS: u32 = 2_000_000;
+
+type Lookup = fn() -> &'static str;
+
+/// Three levels of containers, which are looked up as fast as a single one once cached.
+type Nested = Vec<Option<BTreeMap<String, Vec<i32>>>>;
+
+/// The time `threads` threads take to call `lookup` `LOOKUPS` times each, all at once.
+fn lookups(threads: usize, lookup: Lookup) -> Duration {
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..LOOKUPS {
+                    black_box(lookup());
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn main() {
+    let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+    let cases: [(&str, Lookup); 3] = [
+        (
+            "Vec<HashMap<String, i32>>::get_prompt_schema",
+            Vec::<HashMap<String, i32>>::get_prompt_schema,
+        ),
+        ("Nested::get_prompt_schema", Nested::get_prompt_schema),
+        ("Nested::root_name", Nested::root_name),
+    ];
+    for (name, lookup) in cases {
+        // renders the schema once
+        lookup();
+        for threads in [1, parallelism.max(8)] {
+            let calls = LOOKUPS as u64 * threads as u64;
+            let per_call = lookups(threads, lookup).as_nanos() as f64 / calls as f64;
+            println!("{:<46} {:>2} threads: {:>6.1} ns", name, threads, per_call);
+        }
+    }
+}
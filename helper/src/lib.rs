@@ -4,7 +4,9 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    Expr, Field, Fields, GenericArgument, Item, Lit, Meta, PathArguments, Type, parse::Parser,
+    Attribute, Expr, Field, Fields, GenericArgument, Ident, Item, LitBool, LitInt, LitStr,
+    PathArguments, Type,
+    parse::{ParseStream, Parser},
     parse_macro_input, parse_quote,
 };
 
@@ -17,23 +19,63 @@ use syn::{
 ///
 /// Use `#[prompt("Description")]` on struct fields or enum variants to provide guidance
 /// for the Large Language Model.
+///
+/// Use `#[prompt("...", one_of("a", "b"))]` on a `String` field to restrict it to a fixed set of
+/// values, matched case-insensitively and normalized to the spelling given here.
+///
+/// Use `#[prompt("...", min = 0.0, max = 1.0)]` on a numeric field to enforce an inclusive range;
+/// either bound may be given alone.
+///
+/// Use `#[prompt("...", pattern = r"^\d{4}$", desc = "a year")]` on a `String` field to require a
+/// regex match (needs the `regex` feature); `desc` is an optional readable name for the pattern.
+///
+/// Use `#[prompt("...", min_items = 3, max_items = 5)]` on a `Vec` field to bound how many items
+/// it may hold; either bound may be given alone.
+///
+/// Use `#[prompt("...", on_duplicate = "error")]` on a map field to reject a key written twice
+/// instead of keeping the last entry (`"last"`, the default).
+///
+/// Use `#[prompt("...", coerce_float)]` on an integer field to accept a whole number written as a
+/// float, e.g. `7.0` or `4.2e1`; a fractional part such as `7.5` is still an error.
+///
+/// Use `#[prompt("...", percent)]` on a float field to read `85%` as `0.85`, and
+/// `#[prompt("...", strip_suffix = "points")]` to ignore a unit after the number, e.g.
+/// `3.2 points`; the unit is named in the schema and may be left out (`strip_suffix`).
+///
+/// String fields are trimmed; use `#[prompt("...", preserve_whitespace)]` to keep the text
+/// verbatim, e.g. for indented code, or `#[prompt("...", clean)]` to also unwrap a value the LLM
+/// put in quotes, backticks or `**emphasis**`. Add `decode_entities` to decode HTML entities
+/// such as `&lt;`, `&#8217;` or a double-escaped `&amp;amp;` left in the text.
+///
+/// An empty element (`<name/>`, `<name></name>`) of an `Option` field is read as `None`; use
+/// `#[prompt("...", keep_empty)]` to parse it as the inner type instead, e.g. `Some("")`.
+///
+/// Sequences also accept items under any tag name, or a single bare value, since LLMs often
+/// drop the `<item>` wrapper, and stray children next to `<item>` or `<entry>` elements are
+/// skipped; use `#[prompt("...", strict_seq)]` to reject anything but `<item>` and `<entry>`.
+///
+/// Use `#[prompt(flatten)]` on a struct-typed field to inline its fields into the parent
+/// instead of nesting them under an extra element.
+///
+/// Use `#[llm_prompt(strict)]` to turn a field without a `#[prompt("...")]` description
+/// into a compile error instead of silently emitting an empty description.
+///
+/// Use `#[llm_prompt(root = "name")]` on a struct to replace its ident as the root element,
+/// instead of a `#[serde(rename = "...")]` on it.
+///
+/// Use `#[llm_prompt(text_enum)]` on an enum with only unit variants to accept the variant
+/// name as text content (`<color>Red</color>`, case-insensitive) besides `<color><Red/></color>`.
 #[proc_macro_attribute]
 pub fn llm_prompt(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mut weak = false;
-    let attr_parser = syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated;
-    if let Ok(metas) = attr_parser.parse(attr) {
-        for meta in metas {
-            if let Meta::NameValue(nv) = meta {
-                if nv.path.is_ident("weak") {
-                    if let Expr::Lit(el) = &nv.value {
-                        if let Lit::Bool(b) = &el.lit {
-                            weak = b.value;
-                        }
-                    }
-                }
-            }
-        }
-    }
+    let LlmPromptArgs {
+        weak,
+        strict,
+        text_enum,
+        root,
+    } = match parse_llm_prompt_args.parse(attr) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     let mut input = parse_macro_input!(item as Item);
     let (item_ident, item_name) = match &input {
@@ -49,41 +91,115 @@ pub fn llm_prompt(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     match &mut input {
         Item::Struct(s) => {
+            if text_enum {
+                return quote! { compile_error!("llm_prompt(text_enum) only supports Enum"); }
+                    .into();
+            }
             let name = &s.ident;
-            let root_tag = name.to_string();
-            let mut field_generators = Vec::new();
+            let root_tag = root.clone().unwrap_or_else(|| name.to_string());
+            if let Some(root) = &root {
+                // Two names for the root would leave the schema and quick_xml disagreeing
+                if let Some(rename) = s
+                    .attrs
+                    .iter()
+                    .find(|attr| serde_flag(std::slice::from_ref(*attr), "rename"))
+                {
+                    return syn::Error::new_spanned(
+                        rename,
+                        "use either llm_prompt(root = \"...\") or serde(rename = \"...\") to name the root element, not both",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+                // Keep quick_xml expecting the same tag the schema advertises
+                s.attrs.push(parse_quote! { #[serde(rename = #root)] });
+            }
+            let rename_all = serde_rename_all(&s.attrs, "rename_all");
+            let mut field_tokens = FieldTokens::default();
 
             if let Fields::Named(fields) = &mut s.fields {
                 for field in &mut fields.named {
-                    let field_quote = process_field(&item_name, None, field, &mut field_generators);
+                    let field_quote = process_field(
+                        &item_name,
+                        None,
+                        field,
+                        strict,
+                        rename_all,
+                        &mut field_tokens,
+                    );
                     extra_functions.push(field_quote);
                 }
             }
+            let FieldTokens {
+                examples: field_examples,
+                nodes: field_nodes,
+                leaves: field_leaves,
+            } = field_tokens;
 
+            // A struct of leaves only has its English schema written at compile time
+            let english_schemas = english_schemas(name, &root_tag, &field_leaves);
+            let english_prompt = english_schemas
+                .as_ref()
+                .map(|_| english_return(name, "__LLM_ENGLISH_SCHEMA"));
+            let english_fields = english_schemas
+                .as_ref()
+                .map(|_| english_return(name, "__LLM_ENGLISH_FIELDS_SCHEMA"));
+
+            let struct_example = fields_example(&field_examples);
             extra_impls.push(quote! {
+                #english_schemas
                 impl ::llm_xml_caster::LlmPrompt for #name {
                     fn get_prompt_schema() -> &'static str {
-                        use std::sync::OnceLock;
-                        static SCHEMA_CACHE: OnceLock<String> = OnceLock::new();
-                        SCHEMA_CACHE.get_or_init(|| {
-                            let mut parts = Vec::new();
-                            #( parts.push(#field_generators); )*
-                            format!("<{root}>\n  {inner}\n</{root}>",
-                                root = #root_tag, inner = parts.join("\n  "))
+                        #english_prompt
+                        ::llm_xml_caster::Cache::<Self>::get().prompt_schema.get_or_init(|| {
+                            ::llm_xml_caster::Cache::<Self>::build_schema(|| Self::schema_node().render())
+                        })
+                    }
+                    fn get_fields_schema() -> &'static str {
+                        #english_fields
+                        ::llm_xml_caster::Cache::<Self>::get().fields_schema.get_or_init(|| {
+                            ::llm_xml_caster::Cache::<Self>::build_schema(|| Self::schema_node().render_fields())
                         })
                     }
                     fn root_name() -> &'static str { #root_tag }
+                    #struct_example
+                    fn example_item(depth: usize) -> Option<String> {
+                        Some(::llm_xml_caster::example_element(#root_tag, Self::example_content(depth)))
+                    }
+                    fn schema_node() -> ::llm_xml_caster::SchemaNode {
+                        #[allow(unused_mut)]
+                        let mut fields = Vec::new();
+                        #( #field_nodes )*
+                        ::llm_xml_caster::SchemaNode::Struct { root: #root_tag, fields }
+                    }
                     const IS_ENUM: bool = false;
                 }
             });
         }
         Item::Enum(e) => {
+            if root.is_some() {
+                return quote! { compile_error!("llm_prompt(root = \"...\") only supports Struct, enums have no root element"); }.into();
+            }
             let name = &e.ident;
-            let mut variants_schemas = Vec::new();
+            let variant_rename_all = serde_rename_all(&e.attrs, "rename_all");
+            let fields_rename_all = serde_rename_all(&e.attrs, "rename_all_fields");
+            let mut text_variants = Vec::new();
+            let mut variant_tags = Vec::new();
+            // the example of each variant, with its number of fields
+            let mut variant_examples = Vec::new();
+            let mut variant_nodes = Vec::new();
 
             for variant in &mut e.variants {
                 let v_ident = &variant.ident;
                 let v_name = v_ident.to_string();
+                // The tag quick_xml expects for this variant, following serde's renaming
+                let v_tag = serde_rename(&variant.attrs).unwrap_or_else(|| {
+                    variant_rename_all
+                        .map(|rule| rule.apply_to_variant(&v_name))
+                        .unwrap_or_else(|| v_name.clone())
+                });
+                let v_fields_rename_all =
+                    serde_rename_all(&variant.attrs, "rename_all").or(fields_rename_all);
 
                 // Extract variant description
                 let mut v_desc = String::new();
@@ -91,6 +207,9 @@ pub fn llm_prompt(attr: TokenStream, item: TokenStream) -> TokenStream {
                     if attr.path().is_ident("prompt")
                         && let Ok(lit) = attr.parse_args::<syn::LitStr>()
                     {
+                        if let Err(e) = check_description(&lit) {
+                            return e.to_compile_error().into();
+                        }
                         v_desc = lit.value();
                     }
                 }
@@ -98,54 +217,167 @@ pub fn llm_prompt(attr: TokenStream, item: TokenStream) -> TokenStream {
                 // Remove #[prompt] from variant attributes
                 variant.attrs.retain(|attr| !attr.path().is_ident("prompt"));
 
-                let mut f_parts = Vec::new();
+                if text_enum {
+                    if !matches!(variant.fields, Fields::Unit) {
+                        return syn::Error::new_spanned(
+                            &variant.ident,
+                            "llm_prompt(text_enum) only supports unit variants",
+                        )
+                        .to_compile_error()
+                        .into();
+                    }
+                    text_variants.push((variant.ident.clone(), v_tag, v_desc));
+                    continue;
+                }
+
+                variant_tags.push(v_tag.clone());
+                let mut f_tokens = FieldTokens::default();
                 if let Fields::Named(fields) = &mut variant.fields {
                     for field in &mut fields.named {
-                        let field_quote =
-                            process_field(&item_name, Some(&v_name), field, &mut f_parts);
+                        let field_quote = process_field(
+                            &item_name,
+                            Some(&v_name),
+                            field,
+                            strict,
+                            v_fields_rename_all,
+                            &mut f_tokens,
+                        );
                         extra_functions.push(field_quote);
                     }
                 }
-
-                let fields_prompt_quote = if f_parts.is_empty() {
-                    quote! { String::new() }
+                let FieldTokens {
+                    examples: f_examples,
+                    nodes: f_nodes,
+                    ..
+                } = f_tokens;
+                let variant_example = if f_examples.is_empty() {
+                    quote! { ::llm_xml_caster::example_element(#v_tag, None) }
                 } else {
-                    quote! { vec![#(#f_parts),*].join("\n") }
+                    quote! {{
+                        let fields: Vec<Option<String>> = vec![#(#f_examples),*];
+                        ::llm_xml_caster::example_element(#v_tag, Some(fields.into_iter().flatten().collect()))
+                    }}
                 };
-
-                variants_schemas.push(quote! {
-                    {
-                        let inner_xml = #fields_prompt_quote;
-                        let desc = #v_desc;
-                        if inner_xml.is_empty() {
-                            format!("<{name}/> <!-- {desc} -->", name = #v_name, desc = desc)
-                        } else {
-                            let indented_inner = inner_xml.lines()
-                                .map(|line| format!("  {}", line))
-                                .collect::<Vec<_>>()
-                                .join("\n");
-                            format!("<{name}>\n{inner}\n</{name}> <!-- {desc} -->",
-                                name = #v_name, inner = indented_inner, desc = desc)
-                        }
+                variant_examples.push((variant_example, f_examples.len()));
+                variant_nodes.push(quote! {
+                    ::llm_xml_caster::SchemaVariant {
+                        tag: #v_tag,
+                        description: #v_desc,
+                        fields: {
+                            #[allow(unused_mut)]
+                            let mut fields = Vec::new();
+                            #( #f_nodes )*
+                            fields
+                        },
                     }
                 });
             }
 
-            extra_impls.push(quote! {
-                impl ::llm_xml_caster::LlmPrompt for #name {
-                    fn get_prompt_schema() -> &'static str {
-                        use std::sync::OnceLock;
-                        static SCHEMA_CACHE: OnceLock<String> = OnceLock::new();
-                        SCHEMA_CACHE.get_or_init(|| {
-                            let mut parts = vec!["The following are possible XML structures for the current enum type:".to_string()];
-                            #( parts.push(#variants_schemas); )*
-                            parts.join("\n")
-                        })
+            if text_enum {
+                let idents = text_variants.iter().map(|(ident, _, _)| ident);
+                let tags = text_variants.iter().map(|(_, tag, _)| tag);
+                let leaf_names = text_variants
+                    .iter()
+                    .map(|(_, tag, desc)| quote! { (#tag, #desc) });
+                let allowed = text_variants
+                    .iter()
+                    .map(|(_, tag, _)| tag.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let described = text_variants
+                    .iter()
+                    .filter(|(_, _, desc)| !desc.is_empty())
+                    .map(|(_, tag, desc)| quote! { (#tag, #desc) })
+                    .collect::<Vec<_>>();
+
+                let text_example = match text_variants.first() {
+                    Some((_, tag, _)) => quote! {
+                        fn example_content(_depth: usize) -> Option<String> {
+                            Some(#tag.to_string())
+                        }
+                    },
+                    None => quote! {},
+                };
+
+                // Let serde read the raw name and match it against the variants ourselves
+                e.attrs
+                    .push(parse_quote! { #[serde(try_from = "::llm_xml_caster::TextEnumValue")] });
+
+                extra_impls.push(quote! {
+                    impl ::std::convert::TryFrom<::llm_xml_caster::TextEnumValue> for #name {
+                        type Error = String;
+
+                        fn try_from(value: ::llm_xml_caster::TextEnumValue) -> ::core::result::Result<Self, Self::Error> {
+                            let text = value.0.trim();
+                            #(
+                                if text.eq_ignore_ascii_case(#tags) {
+                                    return Ok(Self::#idents);
+                                }
+                            )*
+                            Err(format!("can not parse '{}' as a {} value, it must be one of: {}", text, #item_name, #allowed))
+                        }
                     }
-                    fn root_name() -> &'static str { "" }
-                    const IS_ENUM: bool = true;
-                }
-            });
+
+                    impl ::llm_xml_caster::LlmPrompt for #name {
+                        fn get_prompt_schema() -> &'static str {
+                            ::llm_xml_caster::Cache::<Self>::get().prompt_schema.get_or_init(|| {
+                                ::llm_xml_caster::text_enum_schema(#allowed, &[#(#described),*])
+                            })
+                        }
+                        fn root_name() -> &'static str { "" }
+                        #text_example
+                        fn schema_node() -> ::llm_xml_caster::SchemaNode {
+                            ::llm_xml_caster::SchemaNode::leaf::<Self>(
+                                ::llm_xml_caster::LeafKind::OneOf(&[#(#leaf_names),*]),
+                            )
+                        }
+                        const IS_ENUM: bool = true;
+                    }
+                });
+            } else {
+                let enum_example = match variant_examples.first() {
+                    Some((first, _)) => {
+                        // once the depth runs out, the variant with the fewest fields ends a
+                        // recursive enum
+                        let (smallest, _) = variant_examples
+                            .iter()
+                            .min_by_key(|(_, fields)| *fields)
+                            .expect("there is a first variant");
+                        quote! {
+                            fn example_content(depth: usize) -> Option<String> {
+                                Some(if depth == 0 { #smallest } else { #first })
+                            }
+                        }
+                    }
+                    None => quote! {},
+                };
+                let every_example = variant_examples.iter().map(|(example, _)| example);
+                extra_impls.push(quote! {
+                    impl ::llm_xml_caster::LlmPrompt for #name {
+                        fn get_prompt_schema() -> &'static str {
+                            ::llm_xml_caster::Cache::<Self>::get().prompt_schema.get_or_init(|| {
+                                ::llm_xml_caster::Cache::<Self>::build_schema(|| Self::schema_node().render())
+                            })
+                        }
+                        fn root_name() -> &'static str { "" }
+                        fn variant_names() -> &'static [&'static str] {
+                            &[#(#variant_tags),*]
+                        }
+                        #enum_example
+                        fn example_variants(depth: usize) -> Vec<String> {
+                            let _ = depth;
+                            vec![#(#every_example),*]
+                        }
+                        fn schema_node() -> ::llm_xml_caster::SchemaNode {
+                            ::llm_xml_caster::SchemaNode::Enum {
+                                name: #item_name,
+                                variants: vec![#(#variant_nodes),*],
+                            }
+                        }
+                        const IS_ENUM: bool = true;
+                    }
+                });
+            }
         }
         _ => return quote! { compile_error!("llm_prompt only supports Struct and Enum"); }.into(),
     }
@@ -153,7 +385,7 @@ pub fn llm_prompt(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut weak_shadow = quote! {};
     if weak {
         let weak_name = format_ident!("{}Weak", item_ident);
-        let item_name_str = item_ident.to_string();
+        let item_name_str = root.unwrap_or_else(|| item_ident.to_string());
         let prompt_schema = format!(
             "<{}>Referencing the types above.</{}>",
             item_name_str, item_name_str
@@ -186,6 +418,21 @@ pub fn llm_prompt(attr: TokenStream, item: TokenStream) -> TokenStream {
                 fn root_name() -> &'static str {
                     <#item_ident as ::llm_xml_caster::LlmPrompt>::root_name()
                 }
+                fn variant_names() -> &'static [&'static str] {
+                    <#item_ident as ::llm_xml_caster::LlmPrompt>::variant_names()
+                }
+                fn example_content(depth: usize) -> Option<String> {
+                    <#item_ident as ::llm_xml_caster::LlmPrompt>::example_content(depth)
+                }
+                fn example_item(depth: usize) -> Option<String> {
+                    <#item_ident as ::llm_xml_caster::LlmPrompt>::example_item(depth)
+                }
+                fn schema_node() -> ::llm_xml_caster::SchemaNode {
+                    ::llm_xml_caster::SchemaNode::Reference {
+                        schema: #prompt_schema,
+                        node: <#item_ident as ::llm_xml_caster::LlmPrompt>::schema_node,
+                    }
+                }
                 const IS_ENUM: bool = <#item_ident as ::llm_xml_caster::LlmPrompt>::IS_ENUM;
             }
         };
@@ -204,27 +451,128 @@ fn process_field(
     item_name: &str,
     variant_name: Option<&str>,
     field: &mut Field,
-    generators: &mut Vec<proc_macro2::TokenStream>,
+    strict: bool,
+    rename_all: Option<RenameRule>,
+    tokens: &mut FieldTokens,
 ) -> proc_macro2::TokenStream {
     let field_ident = field.ident.as_ref().expect("Only support named fields");
     let field_name = field_ident.to_string();
+    // The tag quick_xml expects for this field, following serde's renaming
+    let field_tag = serde_rename(&field.attrs).unwrap_or_else(|| {
+        let unraw = field_name.trim_start_matches("r#");
+        rename_all
+            .map(|rule| rule.apply_to_field(unraw))
+            .unwrap_or_else(|| unraw.to_string())
+    });
     let field_type = &field.ty;
     let mut extra_functions = Vec::new();
 
-    // Extract #[prompt("...")]
-    let mut user_description = None;
-    for attr in &field.attrs {
-        if attr.path().is_ident("prompt")
-            && let Ok(lit) = attr.parse_args::<syn::LitStr>()
-        {
-            user_description = Some(lit);
+    // Extract #[prompt("...", ...)]
+    let prompt_args = match parse_prompt_args(&field.attrs) {
+        Ok(args) => args,
+        Err(e) => {
+            field.attrs.retain(|attr| !attr.path().is_ident("prompt"));
+            return e.to_compile_error();
         }
-    }
+    };
+
+    let constraints = field_constraints(&prompt_args, field_type);
+    let parser_options = ParserOptions {
+        unique_keys: prompt_args
+            .on_duplicate
+            .as_ref()
+            .is_some_and(|policy| policy.value() == "error"),
+        keep_empty: prompt_args.keep_empty,
+        strict_seq: prompt_args.strict_seq,
+        coerce_float: prompt_args.coerce_float,
+        percent: prompt_args.percent,
+        strip_suffix: prompt_args.strip_suffix,
+        preserve_whitespace: prompt_args.preserve_whitespace,
+        clean: prompt_args.clean,
+        decode_entities: prompt_args.decode_entities,
+    };
+    let unit_note = match &prompt_args.unit {
+        Some(unit) => format!(
+            "a number in {unit}, the unit may follow it, e.g. 3.2 {unit}",
+            unit = unit.value()
+        ),
+        None => "a unit may follow the number, e.g. 3.2 points".to_string(),
+    };
+    let notes = constraints
+        .iter()
+        .map(|c| c.note.as_str())
+        .chain(
+            parser_options
+                .unique_keys
+                .then_some("every key must be unique"),
+        )
+        .chain(
+            parser_options
+                .percent
+                .then_some("may be written as a percentage, e.g. 85% for 0.85"),
+        )
+        .chain(parser_options.strip_suffix.then_some(unit_note.as_str()))
+        .chain(
+            parser_options
+                .preserve_whitespace
+                .then_some("whitespace inside the CDATA is preserved verbatim"),
+        )
+        .collect::<Vec<_>>()
+        .join("; ");
 
-    let user_description_quote = match user_description {
-        Some(desc) => quote! { #desc },
-        None => quote! { "" }, // Should probably be a compile error if we want strictness
+    let user_description_quote = match &prompt_args.description {
+        Some(desc) if notes.is_empty() => quote! { #desc },
+        Some(desc) => {
+            let desc = format!("{} ({})", desc.value(), notes);
+            quote! { #desc }
+        }
+        None if !notes.is_empty() => quote! { #notes },
+        None if strict && !prompt_args.flatten => {
+            let owner = match variant_name {
+                Some(v) => format!("{}::{}", item_name, v),
+                None => item_name.to_string(),
+            };
+            let message = format!(
+                "field `{}` of `{}` is missing a #[prompt(\"...\")] description, which is required by #[llm_prompt(strict)]",
+                field_name, owner
+            );
+            let error = syn::Error::new_spanned(field_ident, message).to_compile_error();
+            extra_functions.push(error);
+            quote! { "" }
+        }
+        None => quote! { "" },
     };
+    let description = match &prompt_args.description {
+        Some(desc) if notes.is_empty() => desc.value(),
+        Some(desc) => format!("{} ({})", desc.value(), notes),
+        None => notes.clone(),
+    };
+
+    if prompt_args.flatten {
+        // The child's fields are spliced in place, so serde has to look for them in the parent too
+        field.attrs.push(parse_quote! { #[serde(flatten)] });
+        field.attrs.retain(|attr| !attr.path().is_ident("prompt"));
+
+        tokens.examples.push(quote! {
+            <#field_type as ::llm_xml_caster::LlmPrompt>::example_content(depth)
+        });
+        tokens.leaves.push(None);
+        tokens.nodes.push(quote! {
+            fields.push(::llm_xml_caster::SchemaField {
+                tag: "",
+                description: #user_description_quote,
+                required: false,
+                flatten: true,
+                node: <#field_type as ::llm_xml_caster::LlmPrompt>::schema_node,
+                check: None,
+                constraints: ::std::default::Default::default(),
+            });
+        });
+
+        return quote! {
+            #(#extra_functions)*
+        };
+    }
 
     // Auto-generate #[serde(deserialize_with = "...")]
     let inner_field_name = if let Some(v) = variant_name {
@@ -232,27 +580,97 @@ fn process_field(
     } else {
         format!("{}_{}", item_name, field_name)
     };
-    if let (code, Some(parser_path)) = get_custom_parser(&inner_field_name, field_type) {
+    let mut field_parser = None;
+    if let (code, Some(mut parser_path)) =
+        get_custom_parser(&inner_field_name, field_type, parser_options)
+    {
+        extra_functions.push(code);
+
+        // Validate the parsed value so constraint violations reach the retry loop as errors
+        if !constraints.is_empty() {
+            let base_parser: syn::Path =
+                syn::parse_str(&parser_path).expect("generated parser path is valid");
+            let checked_ident = format_ident!("{}_checked", inner_field_name);
+            let checks = constraints.iter().map(|c| {
+                let check = &c.check;
+                if is_option(field_type) {
+                    quote! {
+                        let value = match value {
+                            Some(value) => Some(#check.map_err(<D::Error as serde::de::Error>::custom)?),
+                            None => None,
+                        };
+                    }
+                } else {
+                    quote! {
+                        let value = #check.map_err(<D::Error as serde::de::Error>::custom)?;
+                    }
+                }
+            });
+            extra_functions.push(quote! {
+                pub fn #checked_ident<'de, D>(deserializer: D) -> ::core::result::Result<#field_type, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    let value = #base_parser(deserializer)?;
+                    #(#checks)*
+                    Ok(value)
+                }
+            });
+            parser_path = checked_ident.to_string();
+        }
+        field_parser = Some(parser_path.clone());
+
         let attr: syn::Attribute = if is_option(field_type) {
             parse_quote! { #[serde(deserialize_with = #parser_path, default)] }
         } else {
             parse_quote! { #[serde(deserialize_with = #parser_path)] }
         };
         field.attrs.push(attr);
-        extra_functions.push(code);
     }
 
-    generators.push(quote! {
-        {
-            let sub_schema = <#field_type as ::llm_xml_caster::LlmPrompt>::get_prompt_schema();
-            let description = #user_description_quote;
-            let indented_schema = sub_schema.lines()
-                .map(|line| format!("  {}", line))
-                .collect::<Vec<_>>()
-                .join("\n");
-            format!("<{name}>\n{schema}\n</{name}> <!-- {desc} -->",
-                name = #field_name, schema = indented_schema, desc = description)
+    // The validator reads the field with its own parser, constraints included
+    let check = match field_parser {
+        Some(parser_path) => {
+            let parser: syn::Path =
+                syn::parse_str(&parser_path).expect("generated parser path is valid");
+            quote! {
+                Some((|xml: &str| ::llm_xml_caster::check_field(xml, |de| #parser(de).map(drop)))
+                    as ::llm_xml_caster::ElementCheck)
+            }
         }
+        None => quote! { None },
+    };
+    let required = !is_option(field_type) && !serde_flag(&field.attrs, "default");
+    let schema_constraints = schema_constraints(&prompt_args);
+    tokens.leaves.push(Some(LeafField {
+        tag: field_tag.clone(),
+        description,
+        ty: field_type.clone(),
+    }));
+    tokens.nodes.push(quote! {
+        fields.push(::llm_xml_caster::SchemaField {
+            tag: #field_tag,
+            description: #user_description_quote,
+            required: #required,
+            flatten: false,
+            node: <#field_type as ::llm_xml_caster::LlmPrompt>::schema_node,
+            check: #check,
+            constraints: #schema_constraints,
+        });
+    });
+
+    // A constrained field gets a placeholder that satisfies the constraint
+    let example = if let Some(allowed) = prompt_args.one_of.first() {
+        let cdata = format!("<![CDATA[{}]]>", allowed.value());
+        quote! { Some(#cdata.to_string()) }
+    } else if let Some(bound) = prompt_args.min.as_ref().or(prompt_args.max.as_ref()) {
+        let bound = expr_text(bound);
+        quote! { Some(#bound.to_string()) }
+    } else {
+        quote! { <#field_type as ::llm_xml_caster::LlmPrompt>::example_content(depth.saturating_sub(1)) }
+    };
+    tokens.examples.push(quote! {
+        #example.map(|content| ::llm_xml_caster::example_element(#field_tag, Some(content)))
     });
 
     // Remove #[prompt] from the field attributes so it doesn't cause a compile error
@@ -263,138 +681,1321 @@ fn process_field(
     }
 }
 
-fn is_option(ty: &Type) -> bool {
-    if let Type::Path(p) = ty
-        && let Some(segment) = p.path.segments.last()
-    {
-        let ident = segment.ident.to_string();
-        return ident == "Option";
-    }
+/// What `process_field` generates for the fields of a struct or variant, in order.
+#[derive(Default)]
+struct FieldTokens {
+    /// The example of each field, `None` when it is left out.
+    examples: Vec<proc_macro2::TokenStream>,
+    /// Pushes the `SchemaField` of each field.
+    nodes: Vec<proc_macro2::TokenStream>,
+    /// Each field as written in the schema, `None` for a flattened one.
+    leaves: Vec<Option<LeafField>>,
+}
 
-    false
+/// A field element of a struct, which the schema writes as the schema of its type.
+struct LeafField {
+    tag: String,
+    description: String,
+    ty: Type,
 }
 
-fn get_custom_parser(name: &str, ty: &Type) -> (proc_macro2::TokenStream, Option<String>) {
-    let tp = if let Type::Path(p) = ty {
-        p
-    } else {
-        return (quote! {}, None);
-    };
-    let segment = if let Some(segment) = tp.path.segments.last() {
-        segment
-    } else {
-        return (quote! {}, None);
-    };
+/// Consts holding the English prompt and fields schemas of a struct, assembled at compile time
+/// as `SchemaNode::render` and `render_fields` write them, so that a struct of strings and
+/// numbers renders nothing at runtime.
+///
+/// Only the compiler knows which types the fields are, so `__LLM_LEAF_FIELDS` tells whether
+/// they all have an `ENGLISH_LEAF_SCHEMA`, without which the schemas aren't used. `None` with a
+/// flattened field, whose fields are spliced in.
+fn english_schemas(
+    name: &Ident,
+    root_tag: &str,
+    leaves: &[Option<LeafField>],
+) -> Option<proc_macro2::TokenStream> {
+    let leaves = leaves
+        .iter()
+        .map(Option::as_ref)
+        .collect::<Option<Vec<_>>>()?;
+    let types = leaves.iter().map(|leaf| &leaf.ty).collect::<Vec<_>>();
+    let parts = leaves.iter().enumerate().map(|(i, leaf)| {
+        let separator = if i == 0 { "" } else { "\n  " };
+        let open = format!("{}<{}>\n  ", separator, leaf.tag);
+        let close = format!(
+            "\n</{}> <!-- {} -->",
+            leaf.tag,
+            comment_text(&leaf.description)
+        );
+        let ty = &leaf.ty;
+        quote! {
+            #open,
+            match <#ty as ::llm_xml_caster::LlmPrompt>::ENGLISH_LEAF_SCHEMA {
+                Some(schema) => schema,
+                None => "",
+            },
+            #close,
+        }
+    });
+    let open = format!("<{}>\n  ", root_tag);
+    let close = format!("\n</{}>", root_tag);
+    Some(quote! {
+        impl #name {
+            #[doc(hidden)]
+            const __LLM_LEAF_FIELDS: bool =
+                true #(&& <#types as ::llm_xml_caster::LlmPrompt>::ENGLISH_LEAF_SCHEMA.is_some())*;
+            #[doc(hidden)]
+            const __LLM_ENGLISH_FIELDS_SCHEMA: &'static str =
+                ::llm_xml_caster::const_format::concatcp!(#(#parts)*);
+            #[doc(hidden)]
+            const __LLM_ENGLISH_SCHEMA: &'static str = ::llm_xml_caster::const_format::concatcp!(
+                #open,
+                #name::__LLM_ENGLISH_FIELDS_SCHEMA,
+                #close,
+            );
+        }
+    })
+}
 
-    let mut extra_functions = Vec::new();
-    let mut ret_function_name = None;
+/// Returns the English schema const `konst` of the struct `name`, when its fields are all leaves
+/// and the schema is rendered in English.
+fn english_return(name: &Ident, konst: &str) -> proc_macro2::TokenStream {
+    let konst = format_ident!("{}", konst);
+    quote! {
+        if #name::__LLM_LEAF_FIELDS
+            && ::llm_xml_caster::SchemaText::current().language
+                == ::llm_xml_caster::SchemaText::ENGLISH.language
+        {
+            return #name::#konst;
+        }
+    }
+}
 
-    let type_str = quote! { #ty }.to_string();
-    let mut hasher = DefaultHasher::new();
-    type_str.hash(&mut hasher);
-    let type_hash = hasher.finish();
+/// `description` as the text of an XML comment, as the schema writes it: `a --> b <c>` becomes
+/// `a - -&gt; b &lt;c&gt;`.
+fn comment_text(description: &str) -> String {
+    let mut text = description.replace('<', "&lt;").replace('>', "&gt;");
+    // `---` takes two rounds
+    while text.contains("--") {
+        text = text.replace("--", "- -");
+    }
+    text
+}
 
-    match &segment.arguments {
-        PathArguments::None => {
-            ret_function_name = match segment.ident.to_string().as_str() {
-                "i8" | "i16" | "i32" | "i64" | "i128" | "u8" | "u16" | "u32" | "u64" | "u128"
-                | "f32" | "f64" | "bool" => {
-                    Some(format!("::llm_xml_caster::custom_{}_parser", segment.ident))
-                }
-                "String" => Some(format!("::llm_xml_caster::custom_string_parser")),
-                _ => None,
-            };
+/// The `example_content` of a struct, joining the examples of its fields.
+fn fields_example(examples: &[proc_macro2::TokenStream]) -> proc_macro2::TokenStream {
+    if examples.is_empty() {
+        return quote! {
+            fn example_content(_depth: usize) -> Option<String> {
+                Some(String::new())
+            }
+        };
+    }
+    quote! {
+        fn example_content(depth: usize) -> Option<String> {
+            let fields: Vec<Option<String>> = vec![#(#examples),*];
+            Some(fields.into_iter().flatten().collect())
         }
-        PathArguments::AngleBracketed(path) => match path.args.len() {
-            1 => match segment.ident.to_string().as_str() {
-                #[cfg(feature = "ordered_float")]
-                "OrderedFloat" => {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments
-                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
-                    {
-                        let inner_name = format!("_{}_{}_inner", type_hash, name);
-                        let (inner_tokens, _) = get_custom_parser(&inner_name, inner_ty);
+    }
+}
 
-                        let parser_call = quote! { ::llm_xml_caster::OrderedFloatParser::<#inner_ty>::custom_ordered_float_parser };
+/// Arguments accepted by `#[llm_prompt(...)]` on a struct or enum.
+#[derive(Default)]
+struct LlmPromptArgs {
+    weak: bool,
+    strict: bool,
+    text_enum: bool,
+    root: Option<String>,
+}
 
-                        let func_ident = format_ident!("{}", name);
+fn parse_llm_prompt_args(input: ParseStream) -> syn::Result<LlmPromptArgs> {
+    let mut args = LlmPromptArgs::default();
+    while !input.is_empty() {
+        let ident: Ident = input.parse()?;
+        let flag = matches!(ident.to_string().as_str(), "strict" | "text_enum");
+        if flag && !input.is_empty() && !input.peek(syn::Token![,]) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("`{}` takes no value, write it alone", ident),
+            ));
+        }
+        match ident.to_string().as_str() {
+            "strict" => args.strict = true,
+            "text_enum" => args.text_enum = true,
+            "weak" => {
+                input.parse::<syn::Token![=]>()?;
+                args.weak = input.parse::<LitBool>()?.value;
+            }
+            "root" => {
+                input.parse::<syn::Token![=]>()?;
+                let root: LitStr = input.parse()?;
+                if root.value().is_empty() {
+                    return Err(syn::Error::new(root.span(), "the root name can't be empty"));
+                }
+                args.root = Some(root.value());
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "unknown llm_prompt argument `{}`, expected `root`, `strict`, `text_enum` or `weak`",
+                        ident
+                    ),
+                ));
+            }
+        }
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+        }
+    }
+    Ok(args)
+}
 
-                        let wrapper_function = quote! {
-                            pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-                            where
-                                D: serde::Deserializer<'de>,
-                            {
-                                #parser_call(deserializer)
+/// Arguments accepted by `#[prompt(...)]` on a field.
+#[derive(Default)]
+struct PromptArgs {
+    description: Option<LitStr>,
+    flatten: bool,
+    keep_empty: bool,
+    strict_seq: bool,
+    coerce_float: bool,
+    percent: bool,
+    strip_suffix: bool,
+    unit: Option<LitStr>,
+    preserve_whitespace: bool,
+    clean: bool,
+    decode_entities: bool,
+    one_of: Vec<LitStr>,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    pattern: Option<LitStr>,
+    pattern_desc: Option<LitStr>,
+    min_items: Option<LitInt>,
+    max_items: Option<LitInt>,
+    on_duplicate: Option<LitStr>,
+}
+
+fn parse_prompt_args(attrs: &[Attribute]) -> syn::Result<PromptArgs> {
+    let mut args = PromptArgs::default();
+    for attr in attrs {
+        if !attr.path().is_ident("prompt") {
+            continue;
+        }
+        attr.parse_args_with(|input: ParseStream| {
+            while !input.is_empty() {
+                if input.peek(LitStr) {
+                    let description = input.parse()?;
+                    check_description(&description)?;
+                    args.description = Some(description);
+                } else {
+                    let ident: Ident = input.parse()?;
+                    match ident.to_string().as_str() {
+                        "flatten" => args.flatten = true,
+                        "keep_empty" => args.keep_empty = true,
+                        "strict_seq" => args.strict_seq = true,
+                        "coerce_float" => args.coerce_float = true,
+                        "percent" => args.percent = true,
+                        "preserve_whitespace" => args.preserve_whitespace = true,
+                        "clean" => args.clean = true,
+                        "decode_entities" => args.decode_entities = true,
+                        "strip_suffix" => {
+                            args.strip_suffix = true;
+                            if input.peek(syn::Token![=]) {
+                                input.parse::<syn::Token![=]>()?;
+                                args.unit = Some(input.parse()?);
                             }
-                        };
-                        extra_functions.push(quote! {
-                            #inner_tokens
-                            #wrapper_function
-                        });
-                        ret_function_name = Some(func_ident.to_string());
+                        }
+                        "one_of" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            args.one_of = content
+                                .parse_terminated(|input| input.parse::<LitStr>(), syn::Token![,])?
+                                .into_iter()
+                                .collect();
+                        }
+                        "min" => {
+                            input.parse::<syn::Token![=]>()?;
+                            args.min = Some(input.parse()?);
+                        }
+                        "max" => {
+                            input.parse::<syn::Token![=]>()?;
+                            args.max = Some(input.parse()?);
+                        }
+                        "pattern" => {
+                            input.parse::<syn::Token![=]>()?;
+                            args.pattern = Some(input.parse()?);
+                        }
+                        "desc" => {
+                            input.parse::<syn::Token![=]>()?;
+                            args.pattern_desc = Some(input.parse()?);
+                        }
+                        "min_items" => {
+                            input.parse::<syn::Token![=]>()?;
+                            args.min_items = Some(input.parse()?);
+                        }
+                        "max_items" => {
+                            input.parse::<syn::Token![=]>()?;
+                            args.max_items = Some(input.parse()?);
+                        }
+                        "on_duplicate" => {
+                            input.parse::<syn::Token![=]>()?;
+                            let policy: LitStr = input.parse()?;
+                            if !matches!(policy.value().as_str(), "last" | "error") {
+                                return Err(syn::Error::new(
+                                    policy.span(),
+                                    "expected `on_duplicate = \"last\"` or `on_duplicate = \"error\"`",
+                                ));
+                            }
+                            args.on_duplicate = Some(policy);
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                ident.span(),
+                                format!("unknown prompt argument `{}`", ident),
+                            ));
+                        }
                     }
                 }
-                "Vec" => {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments
-                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
-                    {
-                        let inner_name = format!("_{}_{}_inner", type_hash, name);
-                        let (inner_tokens, _) = get_custom_parser(&inner_name, inner_ty);
+                if !input.is_empty() {
+                    input.parse::<syn::Token![,]>()?;
+                }
+            }
+            Ok(())
+        })?;
+        if args.clean && args.preserve_whitespace {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`clean` trims the string, so it can't be combined with `preserve_whitespace`",
+            ));
+        }
+    }
+    Ok(args)
+}
 
-                        let parser_call = quote! { ::llm_xml_caster::VecParser::<#inner_ty>::custom_vector_parser };
+/// Rejects a description that can't be written in the XML comment it is rendered into.
+///
+/// `-->`, `<` and `>` are escaped when rendering, but XML has no way at all to write most
+/// control characters.
+fn check_description(description: &LitStr) -> syn::Result<()> {
+    let text = description.value();
+    match text
+        .chars()
+        .find(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+    {
+        Some(c) => Err(syn::Error::new(
+            description.span(),
+            format!(
+                "the description contains the control character U+{:04X}, which can't be written in XML",
+                c as u32
+            ),
+        )),
+        None => Ok(()),
+    }
+}
 
-                        let func_ident = format_ident!("{}", name);
+/// A constraint from `#[prompt(...)]`: a note appended to the field description and a check
+/// applied to the parsed `value`, evaluating to `Result<_, String>`.
+struct Constraint {
+    note: String,
+    check: proc_macro2::TokenStream,
+}
 
-                        let wrapper_function = quote! {
-                            pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-                            where
-                                D: serde::Deserializer<'de>,
-                            {
-                                #parser_call(deserializer)
-                            }
-                        };
-                        extra_functions.push(quote! {
-                            #inner_tokens
-                            #wrapper_function
-                        });
-                        ret_function_name = Some(func_ident.to_string());
-                    }
-                }
-                "Option" => {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments
-                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
-                    {
-                        let inner_name = format!("_{}_{}_inner", type_hash, name);
-                        let (inner_tokens, inner_parser) = get_custom_parser(&inner_name, inner_ty);
+fn field_constraints(args: &PromptArgs, ty: &Type) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+    // Constraints apply to the value inside an `Option`
+    let value_ty = option_inner(ty).unwrap_or(ty);
 
-                        let func_ident = format_ident!("{}", name);
+    if !args.one_of.is_empty() {
+        let allowed = &args.one_of;
+        let note = format!(
+            "must be exactly one of: {}",
+            allowed
+                .iter()
+                .map(|lit| lit.value())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        constraints.push(Constraint {
+            note,
+            check: quote! { ::llm_xml_caster::check_one_of(value, &[#(#allowed),*]) },
+        });
+    }
 
-                        let wrapper_function = if let Some(inner_parser_path) = inner_parser {
-                            quote! {
-                                pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-                                where
-                                    D: serde::Deserializer<'de>,
-                                {
-                                    #[derive(serde::Deserialize)]
-                                    struct OptionWrapper(#[serde(deserialize_with = #inner_parser_path)] #inner_ty);
-
-                                    match OptionWrapper::deserialize(deserializer) {
-                                        Ok(wrapper) => Ok(Some(wrapper.0)),
-                                        Err(_) => Ok(None),
-                                    }
-                                }
-                            }
-                        } else {
-                            quote! {
-                                pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-                                where
-                                    D: serde::Deserializer<'de>,
-                                {
-                                    ::llm_xml_caster::OptionParser::<#inner_ty>::custom_option_parser(deserializer)
-                                }
+    if let Some(pattern) = &args.pattern {
+        let note = match &args.pattern_desc {
+            Some(desc) => format!(
+                "{}, matching the regular expression {}",
+                desc.value(),
+                pattern.value()
+            ),
+            None => format!("matching the regular expression {}", pattern.value()),
+        };
+        let desc = match &args.pattern_desc {
+            Some(desc) => quote! { Some(#desc) },
+            None => quote! { None },
+        };
+        constraints.push(Constraint {
+            note,
+            check: quote! {{
+                static PATTERN: ::std::sync::OnceLock<::llm_xml_caster::regex::Regex> =
+                    ::std::sync::OnceLock::new();
+                ::llm_xml_caster::check_pattern(value, &PATTERN, #pattern, #desc)
+            }},
+        });
+    }
+
+    if args.min.is_some() || args.max.is_some() {
+        let number = number_kind(value_ty);
+        let kind = match number {
+            NumberKind::Integer => "an integer",
+            NumberKind::Float => "a float",
+            NumberKind::Other => "a number",
+        };
+        let note = match (&args.min, &args.max) {
+            (Some(min), Some(max)) => format!(
+                "{} between {} and {} inclusive",
+                kind,
+                expr_text(min),
+                expr_text(max)
+            ),
+            (Some(min), None) => format!("{} greater than or equal to {}", kind, expr_text(min)),
+            (None, Some(max)) => format!("{} less than or equal to {}", kind, expr_text(max)),
+            (None, None) => unreachable!(),
+        };
+        let bound = |name: &str, expr: &Option<Expr>| match expr {
+            Some(expr) => range_bound(name, expr, value_ty, number),
+            None => Ok(quote! { None }),
+        };
+        let check = match (bound("min", &args.min), bound("max", &args.max)) {
+            (Ok(min), Ok(max)) => quote! { ::llm_xml_caster::check_range(value, #min, #max) },
+            (Err(e), _) | (_, Err(e)) => e.to_compile_error(),
+        };
+        constraints.push(Constraint { note, check });
+    }
+
+    if args.min_items.is_some() || args.max_items.is_some() {
+        let note = match (&args.min_items, &args.max_items) {
+            (Some(min), Some(max)) => format!("between {} and {} items", min, max),
+            (Some(min), None) => format!("at least {} items", min),
+            (None, Some(max)) => format!("at most {} items", max),
+            (None, None) => unreachable!(),
+        };
+        let bound = |lit: &Option<LitInt>| match lit {
+            Some(lit) => quote! { Some(#lit) },
+            None => quote! { None },
+        };
+        let (min, max) = (bound(&args.min_items), bound(&args.max_items));
+        constraints.push(Constraint {
+            note,
+            check: quote! { ::llm_xml_caster::check_items(value, #min, #max) },
+        });
+    }
+
+    constraints
+}
+
+/// What kind of number a field holds, from the name of its type.
+#[derive(Clone, Copy)]
+enum NumberKind {
+    Integer,
+    /// `f32`, `f64` or a float wrapper such as `OrderedFloat<f64>`.
+    Float,
+    /// An alias or a type such as `Decimal`, whose bounds are converted with `TryFrom`.
+    Other,
+}
+
+fn number_kind(ty: &Type) -> NumberKind {
+    let Type::Path(tp) = ty else {
+        return NumberKind::Other;
+    };
+    let Some(segment) = tp.path.segments.last() else {
+        return NumberKind::Other;
+    };
+    match segment.ident.to_string().as_str() {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" => NumberKind::Integer,
+        "f32" | "f64" | "OrderedFloat" | "NotNan" => NumberKind::Float,
+        _ => NumberKind::Other,
+    }
+}
+
+/// The literal of a bound such as `5` or `-0.5`, and whether it is negated.
+fn bound_literal(expr: &Expr) -> Option<(bool, &syn::Lit)> {
+    match expr {
+        Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Neg(_)) => {
+            bound_literal(&unary.expr).map(|(negated, lit)| (!negated, lit))
+        }
+        Expr::Lit(lit) => Some((false, &lit.lit)),
+        _ => None,
+    }
+}
+
+/// The `Option` holding the `min` or `max` bound `expr` as a `ty`, a field of the `number`
+/// kind.
+///
+/// A literal of a primitive field is written with the type of the field, so a bound out of its
+/// range fails to compile, and a literal with a fraction is rejected for an integer field. Any
+/// other bound is converted with `TryFrom`, failing on the first check if it doesn't fit.
+fn range_bound(
+    name: &str,
+    expr: &Expr,
+    ty: &Type,
+    number: NumberKind,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let primitive = match ty {
+        Type::Path(tp) => tp.path.get_ident().filter(|_| tp.qself.is_none()),
+        _ => None,
+    };
+    let literal = match (bound_literal(expr), number) {
+        (Some((_, syn::Lit::Float(_))), NumberKind::Integer) => {
+            return Err(syn::Error::new_spanned(
+                expr,
+                format!(
+                    "`{} = {}` is not a whole number, but `{}` is an integer type",
+                    name,
+                    expr_text(expr),
+                    quote! { #ty }
+                ),
+            ));
+        }
+        (Some((negated, syn::Lit::Int(lit))), NumberKind::Integer) => {
+            Some((negated, lit.base10_digits().to_string(), true))
+        }
+        (Some((negated, syn::Lit::Int(lit))), NumberKind::Float) => {
+            Some((negated, format!("{}.0", lit.base10_digits()), false))
+        }
+        (Some((negated, syn::Lit::Float(lit))), NumberKind::Float) => {
+            Some((negated, lit.base10_digits().to_string(), false))
+        }
+        _ => None,
+    };
+    let sign = |negated: bool| negated.then(|| quote! { - });
+    match (literal, primitive) {
+        (Some((negated, digits, integer)), Some(ident)) => {
+            let suffixed = format!("{}{}", digits, ident);
+            let lit = match integer {
+                true => syn::Lit::Int(LitInt::new(&suffixed, expr_span(expr))),
+                false => syn::Lit::Float(syn::LitFloat::new(&suffixed, expr_span(expr))),
+            };
+            let sign = sign(negated);
+            Ok(quote! { Some(#sign #lit) })
+        }
+        (literal, _) => {
+            let value = match literal {
+                Some((negated, digits, integer)) => {
+                    let lit = match integer {
+                        true => syn::Lit::Int(LitInt::new(&digits, expr_span(expr))),
+                        false => syn::Lit::Float(syn::LitFloat::new(&digits, expr_span(expr))),
+                    };
+                    let sign = sign(negated);
+                    quote! { #sign #lit }
+                }
+                None => quote! { #expr },
+            };
+            let message = format!(
+                "`{} = {}` doesn't fit in `{}`",
+                name,
+                expr_text(expr),
+                quote! { #ty }
+            );
+            Ok(quote! {
+                match <#ty as ::core::convert::TryFrom<_>>::try_from(#value) {
+                    ::core::result::Result::Ok(bound) => Some(bound),
+                    ::core::result::Result::Err(_) => panic!(#message),
+                }
+            })
+        }
+    }
+}
+
+fn expr_span(expr: &Expr) -> proc_macro2::Span {
+    syn::spanned::Spanned::span(expr)
+}
+
+/// The constraints of a field as a `FieldConstraints` of its schema node.
+fn schema_constraints(args: &PromptArgs) -> proc_macro2::TokenStream {
+    let one_of = &args.one_of;
+    let optional = |value: Option<proc_macro2::TokenStream>| match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    };
+    let pattern = optional(args.pattern.as_ref().map(|p| quote! { #p }));
+    let min = optional(args.min.as_ref().map(schema_bound));
+    let max = optional(args.max.as_ref().map(schema_bound));
+    let min_items = optional(args.min_items.as_ref().map(|n| quote! { #n }));
+    let max_items = optional(args.max_items.as_ref().map(|n| quote! { #n }));
+    quote! {
+        ::llm_xml_caster::FieldConstraints {
+            one_of: &[#(#one_of),*],
+            pattern: #pattern,
+            min: #min,
+            max: #max,
+            min_items: #min_items,
+            max_items: #max_items,
+        }
+    }
+}
+
+/// A `min` or `max` bound as the `f64` of the schema: a literal is read here, whatever the
+/// type of the field, and only a named constant is cast.
+fn schema_bound(expr: &Expr) -> proc_macro2::TokenStream {
+    let value = match bound_literal(expr) {
+        Some((negated, syn::Lit::Int(lit))) => lit.base10_parse::<f64>().ok().map(|v| (negated, v)),
+        Some((negated, syn::Lit::Float(lit))) => {
+            lit.base10_parse::<f64>().ok().map(|v| (negated, v))
+        }
+        _ => None,
+    };
+    match value {
+        Some((negated, value)) => {
+            let value = proc_macro2::Literal::f64_suffixed(if negated { -value } else { value });
+            quote! { #value }
+        }
+        None => quote! { (#expr) as f64 },
+    }
+}
+
+/// The source text of a literal bound such as `-5` or `0.5`, as written by the user.
+fn expr_text(expr: &Expr) -> String {
+    match expr {
+        Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Neg(_)) => {
+            format!("-{}", expr_text(&unary.expr))
+        }
+        Expr::Lit(lit) => {
+            let lit = &lit.lit;
+            quote! { #lit }.to_string()
+        }
+        _ => quote! { #expr }.to_string(),
+    }
+}
+
+/// Serde's `rename_all` rules, applied the same way serde applies them.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lowercase" => Some(Self::Lower),
+            "UPPERCASE" => Some(Self::Upper),
+            "PascalCase" => Some(Self::Pascal),
+            "camelCase" => Some(Self::Camel),
+            "snake_case" => Some(Self::Snake),
+            "SCREAMING_SNAKE_CASE" => Some(Self::ScreamingSnake),
+            "kebab-case" => Some(Self::Kebab),
+            "SCREAMING-KEBAB-CASE" => Some(Self::ScreamingKebab),
+            _ => None,
+        }
+    }
+
+    /// Renames a variant, which is assumed to be in PascalCase.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            Self::Pascal => variant.to_string(),
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Camel => variant[..1].to_ascii_lowercase() + &variant[1..],
+            Self::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            Self::ScreamingSnake => Self::Snake.apply_to_variant(variant).to_ascii_uppercase(),
+            Self::Kebab => Self::Snake.apply_to_variant(variant).replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Renames a field, which is assumed to be in snake_case.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            Self::Camel => {
+                let pascal = Self::Pascal.apply_to_field(field);
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// Reads a string-valued serde argument such as `rename = "..."`, also accepting the
+/// `rename(deserialize = "...")` form since only deserialization matters here.
+fn serde_string_arg(attrs: &[Attribute], key: &str) -> Option<String> {
+    let mut found = None;
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        // Malformed attributes are left for serde itself to report
+        let _ = attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident(key) {
+                return skip_serde_meta(&meta);
+            }
+            if meta.input.peek(syn::Token![=]) {
+                found = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                meta.parse_nested_meta(|inner| {
+                    if inner.path.is_ident("deserialize") {
+                        found = Some(inner.value()?.parse::<LitStr>()?.value());
+                        Ok(())
+                    } else {
+                        skip_serde_meta(&inner)
+                    }
+                })?;
+            }
+            Ok(())
+        });
+    }
+    found
+}
+
+/// Whether a serde flag such as `default` is set, with or without a value.
+fn serde_flag(attrs: &[Attribute], key: &str) -> bool {
+    let mut found = false;
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        let _ = attr.parse_nested_meta(|meta| {
+            found |= meta.path.is_ident(key);
+            skip_serde_meta(&meta)
+        });
+    }
+    found
+}
+
+fn skip_serde_meta(meta: &syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| skip_serde_meta(&inner))?;
+    }
+    Ok(())
+}
+
+fn serde_rename(attrs: &[Attribute]) -> Option<String> {
+    serde_string_arg(attrs, "rename")
+}
+
+fn serde_rename_all(attrs: &[Attribute], key: &str) -> Option<RenameRule> {
+    serde_string_arg(attrs, key).and_then(|name| RenameRule::from_name(&name))
+}
+
+/// Whether `tp` is `std::time::Duration` or `core::time::Duration`.
+fn is_std_duration_path(tp: &syn::TypePath) -> bool {
+    let names: Vec<String> = tp
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    match names.as_slice() {
+        [.., krate, module, _] => (krate == "std" || krate == "core") && module == "time",
+        _ => false,
+    }
+}
+
+/// Whether `tp` is written with one of `krates` before its name, e.g. `time::Date` or
+/// `serde_json::Value`.
+#[cfg(any(
+    feature = "json",
+    feature = "decimal",
+    feature = "url",
+    feature = "uuid",
+    feature = "time"
+))]
+fn is_crate_path(tp: &syn::TypePath, krates: &[&str]) -> bool {
+    let segments = &tp.path.segments;
+    segments.len() >= 2
+        && krates
+            .iter()
+            .any(|krate| segments[segments.len() - 2].ident == krate)
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    if let Type::Path(p) = ty
+        && let Some(segment) = p.path.segments.last()
+        && segment.ident == "Option"
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+    {
+        return Some(inner_ty);
+    }
+
+    None
+}
+
+fn is_option(ty: &Type) -> bool {
+    if let Type::Path(p) = ty
+        && let Some(segment) = p.path.segments.last()
+    {
+        let ident = segment.ident.to_string();
+        return ident == "Option";
+    }
+
+    false
+}
+
+fn get_custom_parser(
+    name: &str,
+    ty: &Type,
+    opts: ParserOptions,
+) -> (proc_macro2::TokenStream, Option<String>) {
+    match known_parser(name, ty, opts) {
+        (tokens, None) if matches!(ty, Type::Path(_)) => {
+            let func_ident = format_ident!("{}", name);
+            let wrapper_function = quote! {
+                pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    ::llm_xml_caster::EnumParser::<#ty>::custom_enum_parser(deserializer)
+                }
+            };
+            (
+                quote! {
+                    #tokens
+                    #wrapper_function
+                },
+                Some(func_ident.to_string()),
+            )
+        }
+        parser => parser,
+    }
+}
+
+/// Generates the type a container reads each of its `<item>`, `<key>` or `<value>` elements as.
+///
+/// The wrapper hands the whole element to the parser picked for `ty`, so a nested container
+/// keeps its own grammar and leaves keep trimming and number coercion at any depth.
+fn element_wrapper(
+    name: &str,
+    ty: &Type,
+    opts: ParserOptions,
+) -> (proc_macro2::TokenStream, proc_macro2::Ident) {
+    let (parser_tokens, parser_path) = element_parser(&format!("{}_parser", name), ty, opts);
+
+    let elem_ident = format_ident!("{}_element", name);
+    let tokens = quote! {
+        #parser_tokens
+
+        #[allow(non_camel_case_types)]
+        #[derive(serde::Deserialize)]
+        #[serde(transparent)]
+        struct #elem_ident(#[serde(deserialize_with = #parser_path)] #ty);
+    };
+    (tokens, elem_ident)
+}
+
+/// The parser for `ty` written as a container element.
+///
+/// Structs and enums have no parser of their own and are read from the element content, e.g.
+/// `<item><Person>..</Person></item>`, and so are the `Box`, `Rc` and `Arc` pointers to them.
+fn element_parser(
+    name: &str,
+    ty: &Type,
+    opts: ParserOptions,
+) -> (proc_macro2::TokenStream, String) {
+    let func_ident = format_ident!("{}", name);
+
+    if let Type::Path(tp) = ty
+        && let Some(segment) = tp.path.segments.last()
+        && matches!(segment.ident.to_string().as_str(), "Box" | "Rc" | "Arc")
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+    {
+        let (inner_tokens, inner_parser) =
+            element_parser(&format!("{}_inner", name), inner_ty, opts);
+        let inner_parser: syn::Path =
+            syn::parse_str(&inner_parser).expect("parser paths are generated by this macro");
+        let tokens = quote! {
+            #inner_tokens
+
+            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                #inner_parser(deserializer).map(<#ty>::from)
+            }
+        };
+        return (tokens, func_ident.to_string());
+    }
+
+    if let Type::Path(tp) = ty
+        && let Some(segment) = tp.path.segments.last()
+        && segment.ident == "Option"
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+    {
+        // an absent value still has its element, left empty
+        let (inner_tokens, parser_call) = match content_type(inner_ty) {
+            _ if opts.keep_empty => {
+                let (inner_tokens, inner_parser) =
+                    element_parser(&format!("{}_some", name), inner_ty, opts);
+                let inner_parser: syn::Path = syn::parse_str(&inner_parser)
+                    .expect("parser paths are generated by this macro");
+                (
+                    inner_tokens,
+                    quote! { #inner_parser(deserializer).map(Some) },
+                )
+            }
+            Some(content_ty) => (
+                quote! {},
+                quote! {
+                    ::llm_xml_caster::OptionItemParser::<#content_ty>::custom_option_value_parser(deserializer)
+                        .map(|value| value.map(<#inner_ty>::from))
+                },
+            ),
+            None => {
+                let (elem_tokens, elem_ident) =
+                    element_wrapper(&format!("{}_some", name), inner_ty, opts);
+                (
+                    elem_tokens,
+                    quote! {
+                        ::llm_xml_caster::OptionItemParser::<#elem_ident>::custom_option_item_parser(deserializer)
+                            .map(|value| value.map(|elem| elem.0))
+                    },
+                )
+            }
+        };
+        let tokens = quote! {
+            #inner_tokens
+
+            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                #parser_call
+            }
+        };
+        return (tokens, func_ident.to_string());
+    }
+
+    match known_parser(name, ty, opts) {
+        (tokens, Some(path)) => (tokens, path),
+        _ => {
+            let wrapper_function = quote! {
+                pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    ::llm_xml_caster::ItemParser::<#ty>::custom_item_parser(deserializer)
+                }
+            };
+            (wrapper_function, func_ident.to_string())
+        }
+    }
+}
+
+/// The struct or enum a container element of type `ty` holds inside it, looking through `Box`,
+/// `Rc` and `Arc`, or `None` if `ty` has a parser of its own.
+fn content_type(ty: &Type) -> Option<&Type> {
+    if let Type::Path(tp) = ty
+        && let Some(segment) = tp.path.segments.last()
+        && matches!(segment.ident.to_string().as_str(), "Box" | "Rc" | "Arc")
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+    {
+        return content_type(inner_ty);
+    }
+
+    match known_parser("content", ty, ParserOptions::default()) {
+        (_, Some(_)) => None,
+        _ => Some(ty),
+    }
+}
+
+/// A parser for a `Vec`, `VecDeque` or set of `inner_ty`, reading every `<item>` with the
+/// parser of `inner_ty` and collecting the results into `ty`.
+fn sequence_parser(
+    name: &str,
+    inner_name: &str,
+    ty: &Type,
+    inner_ty: &Type,
+    opts: ParserOptions,
+) -> proc_macro2::TokenStream {
+    let (elem_tokens, elem_ident) = element_wrapper(inner_name, inner_ty, opts);
+    let func_ident = format_ident!("{}", name);
+    let parser_call = if opts.strict_seq {
+        quote! { ::llm_xml_caster::VecParser::<#elem_ident>::custom_strict_item_vector_parser }
+    } else {
+        quote! { ::llm_xml_caster::VecParser::<#elem_ident>::custom_item_vector_parser }
+    };
+
+    quote! {
+        #elem_tokens
+
+        pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            #parser_call(deserializer)
+                .map(|items| items.into_iter().map(|item| item.0).collect())
+        }
+    }
+}
+
+/// A parser for a map type `ty`, reading every `<key>` and `<value>` with the parsers of
+/// `key_ty` and `val_ty` and collecting the pairs into `ty`.
+fn map_parser(
+    name: &str,
+    entry_name: &str,
+    ty: &Type,
+    key_ty: &Type,
+    val_ty: &Type,
+    opts: ParserOptions,
+) -> proc_macro2::TokenStream {
+    let (key_tokens, key_ident) = element_wrapper(&format!("{}_key", entry_name), key_ty, opts);
+    let (val_tokens, val_ident) = element_wrapper(&format!("{}_val", entry_name), val_ty, opts);
+    let func_ident = format_ident!("{}", name);
+    let parser_call = if opts.strict_seq {
+        quote! { ::llm_xml_caster::MapEntriesParser::<#key_ident, #val_ident>::custom_strict_map_entries_parser }
+    } else {
+        quote! { ::llm_xml_caster::MapEntriesParser::<#key_ident, #val_ident>::custom_map_entries_parser }
+    };
+
+    // without the check, a repeated key silently replaces the earlier entry
+    let unique_check = if opts.unique_keys {
+        let check = if is_display_key(key_ty) {
+            quote! { ::llm_xml_caster::check_unique_keys }
+        } else {
+            quote! { ::llm_xml_caster::check_unique_key_entries }
+        };
+        quote! {
+            #check(&entries).map_err(<D::Error as serde::de::Error>::custom)?;
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #key_tokens
+        #val_tokens
+
+        pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let entries = #parser_call(deserializer)?
+                .into_iter()
+                .map(|(key, value)| (key.0, value.0))
+                .collect::<Vec<_>>();
+            #unique_check
+            Ok(entries.into_iter().collect())
+        }
+    }
+}
+
+/// Whether map keys of type `ty` can be quoted in an error message.
+fn is_display_key(ty: &Type) -> bool {
+    let Type::Path(tp) = ty else {
+        return false;
+    };
+    let Some(segment) = tp.path.segments.last() else {
+        return false;
+    };
+    if let PathArguments::AngleBracketed(args) = &segment.arguments {
+        // `Rc<str>`, `Arc<String>`, `Box<i32>` display like what they point to
+        return matches!(segment.ident.to_string().as_str(), "Box" | "Rc" | "Arc")
+            && matches!(args.args.first(), Some(GenericArgument::Type(inner)) if is_display_key(inner));
+    }
+    matches!(
+        segment.ident.to_string().as_str(),
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "bool"
+            | "char"
+            | "String"
+            | "IpAddr"
+            | "Ipv4Addr"
+            | "Ipv6Addr"
+            | "SocketAddr"
+            | "Uuid"
+            | "Url"
+            | "Decimal"
+    ) || segment.ident.to_string().starts_with("NonZero")
+}
+
+/// Settings from the field's `#[prompt(...)]` that change how its value is parsed.
+#[derive(Clone, Copy, Default)]
+struct ParserOptions {
+    /// Reject maps that repeat a key, from `on_duplicate = "error"`.
+    unique_keys: bool,
+    /// Read an empty element of an `Option` as `Some`, from `keep_empty`.
+    keep_empty: bool,
+    /// Only accept `<item>` children in sequences and `<entry>` children in maps, from
+    /// `strict_seq`.
+    strict_seq: bool,
+    /// Accept integers written as whole floats such as `7.0`, from `coerce_float`.
+    coerce_float: bool,
+    /// Read a trailing `%` as a percentage, from `percent`.
+    percent: bool,
+    /// Ignore a unit after the number, from `strip_suffix`.
+    strip_suffix: bool,
+    /// Keep the whitespace around strings, from `preserve_whitespace`.
+    preserve_whitespace: bool,
+    /// Unwrap strings from quotes and emphasis, from `clean`.
+    clean: bool,
+    /// Decode HTML entities in strings, from `decode_entities`.
+    decode_entities: bool,
+}
+
+impl ParserOptions {
+    /// Whether numbers need a parser with a non-default `NumberFormat`.
+    fn number_format(&self) -> bool {
+        self.coerce_float || self.percent || self.strip_suffix
+    }
+
+    /// Whether strings need a parser with a non-default `StringFormat`.
+    fn string_format(&self) -> bool {
+        self.preserve_whitespace || self.clean || self.decode_entities
+    }
+}
+
+/// A parser for the string type `ty` that calls `format_parser` with the field's `StringFormat`.
+fn string_format_parser(
+    name: &str,
+    ty: &Type,
+    format_parser: &str,
+    opts: ParserOptions,
+) -> proc_macro2::TokenStream {
+    let func_ident = format_ident!("{}", name);
+    let format_parser = format_ident!("{}", format_parser);
+    let ParserOptions {
+        preserve_whitespace,
+        clean,
+        decode_entities,
+        ..
+    } = opts;
+    quote! {
+        pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let format = ::llm_xml_caster::StringFormat {
+                preserve_whitespace: #preserve_whitespace,
+                clean: #clean,
+                decode_entities: #decode_entities,
+            };
+            ::llm_xml_caster::#format_parser(deserializer, format)
+        }
+    }
+}
+
+/// The parser for `ty` if it is one of the types this crate knows how to read, `None` otherwise.
+fn known_parser(
+    name: &str,
+    ty: &Type,
+    opts: ParserOptions,
+) -> (proc_macro2::TokenStream, Option<String>) {
+    if let Type::Tuple(tuple) = ty
+        && (2..=4).contains(&tuple.elems.len())
+    {
+        let func_ident = format_ident!("{}", name);
+        let wrapper_function = quote! {
+            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                ::llm_xml_caster::TupleParser::<#ty>::custom_tuple_parser(deserializer)
+            }
+        };
+        return (wrapper_function, Some(func_ident.to_string()));
+    }
+
+    let tp = if let Type::Path(p) = ty {
+        p
+    } else {
+        return (quote! {}, None);
+    };
+    let segment = if let Some(segment) = tp.path.segments.last() {
+        segment
+    } else {
+        return (quote! {}, None);
+    };
+
+    let mut extra_functions = Vec::new();
+    let mut ret_function_name = None;
+
+    let type_str = quote! { #ty }.to_string();
+    let mut hasher = DefaultHasher::new();
+    type_str.hash(&mut hasher);
+    let type_hash = hasher.finish();
+
+    match &segment.arguments {
+        PathArguments::None => {
+            ret_function_name = match segment.ident.to_string().as_str() {
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                | "u128" | "usize" | "f32" | "f64"
+                    if opts.number_format() =>
+                {
+                    let func_ident = format_ident!("{}", name);
+                    let parser = format_ident!("custom_{}_format_parser", segment.ident);
+                    let ParserOptions {
+                        coerce_float,
+                        percent,
+                        strip_suffix,
+                        ..
+                    } = opts;
+                    extra_functions.push(quote! {
+                        pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                        where
+                            D: serde::Deserializer<'de>,
+                        {
+                            let format = ::llm_xml_caster::NumberFormat {
+                                coerce_float: #coerce_float,
+                                percent: #percent,
+                                strip_suffix: #strip_suffix,
+                            };
+                            ::llm_xml_caster::#parser(deserializer, format)
+                        }
+                    });
+                    Some(func_ident.to_string())
+                }
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                | "u128" | "usize" | "f32" | "f64" | "bool" | "char" => {
+                    Some(format!("::llm_xml_caster::custom_{}_parser", segment.ident))
+                }
+                "String" if opts.string_format() => {
+                    extra_functions.push(string_format_parser(
+                        name,
+                        ty,
+                        "custom_string_format_parser",
+                        opts,
+                    ));
+                    Some(name.to_string())
+                }
+                "String" => Some("::llm_xml_caster::custom_string_parser".to_string()),
+                "PathBuf" => Some("::llm_xml_caster::custom_path_buf_parser".to_string()),
+                "Base64Bytes" => Some("::llm_xml_caster::custom_base64_bytes_parser".to_string()),
+                // a bare `Duration` may as well be `chrono::Duration`, whose parser can't be told
+                // apart from here
+                "Duration" if tp.path.segments.len() == 1 => {
+                    extra_functions.push(
+                        syn::Error::new_spanned(
+                            ty,
+                            "write the path of `Duration`, e.g. `std::time::Duration`, a bare `Duration` may be `chrono::Duration` or `time::Duration`",
+                        )
+                        .to_compile_error(),
+                    );
+                    Some("::llm_xml_caster::custom_duration_parser".to_string())
+                }
+                // leave `chrono::Duration` and `time::Duration` alone
+                "Duration" if is_std_duration_path(tp) => {
+                    Some("::llm_xml_caster::custom_duration_parser".to_string())
+                }
+                "IpAddr" => Some("::llm_xml_caster::custom_ip_addr_parser".to_string()),
+                "Ipv4Addr" => Some("::llm_xml_caster::custom_ipv4_addr_parser".to_string()),
+                "Ipv6Addr" => Some("::llm_xml_caster::custom_ipv6_addr_parser".to_string()),
+                "SocketAddr" => Some("::llm_xml_caster::custom_socket_addr_parser".to_string()),
+                // `Value`, `Decimal`, `Url` and `Uuid` are too common as names to claim unless
+                // written with their crate, as in `serde_json::Value`
+                #[cfg(feature = "json")]
+                "Value" if is_crate_path(tp, &["serde_json", "json"]) => {
+                    Some("::llm_xml_caster::custom_json_value_parser".to_string())
+                }
+                #[cfg(feature = "decimal")]
+                "Decimal" if is_crate_path(tp, &["rust_decimal"]) => {
+                    Some("::llm_xml_caster::custom_decimal_parser".to_string())
+                }
+                #[cfg(feature = "url")]
+                "Url" if is_crate_path(tp, &["url"]) => {
+                    Some("::llm_xml_caster::custom_url_parser".to_string())
+                }
+                #[cfg(feature = "uuid")]
+                "Uuid" if is_crate_path(tp, &["uuid"]) => {
+                    Some("::llm_xml_caster::custom_uuid_parser".to_string())
+                }
+                #[cfg(feature = "time")]
+                "OffsetDateTime" => {
+                    Some("::llm_xml_caster::custom_offset_date_time_parser".to_string())
+                }
+                // `Date` and `Time` are too common as names to claim unless written as `time::Date`
+                #[cfg(feature = "time")]
+                "Date" | "Time" if is_crate_path(tp, &["time"]) => Some(format!(
+                    "::llm_xml_caster::custom_{}_parser",
+                    segment.ident.to_string().to_lowercase()
+                )),
+                #[cfg(feature = "chrono")]
+                "NaiveDate" => Some("::llm_xml_caster::custom_naive_date_parser".to_string()),
+                #[cfg(feature = "chrono")]
+                "NaiveDateTime" => {
+                    Some("::llm_xml_caster::custom_naive_date_time_parser".to_string())
+                }
+                "NonZeroI8" | "NonZeroI16" | "NonZeroI32" | "NonZeroI64" | "NonZeroI128"
+                | "NonZeroIsize" | "NonZeroU8" | "NonZeroU16" | "NonZeroU32" | "NonZeroU64"
+                | "NonZeroU128" | "NonZeroUsize" => Some(format!(
+                    "::llm_xml_caster::custom_non_zero_{}_parser",
+                    &segment.ident.to_string()["NonZero".len()..].to_lowercase()
+                )),
+                _ => None,
+            };
+        }
+        PathArguments::AngleBracketed(path) => match path.args.len() {
+            1 => match segment.ident.to_string().as_str() {
+                #[cfg(feature = "ordered_float")]
+                "OrderedFloat" => {
+                    if let PathArguments::AngleBracketed(args) = &segment.arguments
+                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+                    {
+                        let inner_name = format!("_{}_{}_inner", type_hash, name);
+                        let (inner_tokens, inner_parser) =
+                            get_custom_parser(&inner_name, inner_ty, opts);
+                        let inner_parser: syn::Path = syn::parse_str(
+                            &inner_parser.expect("the fallback always provides a parser"),
+                        )
+                        .expect("parser paths are generated by this macro");
+
+                        let func_ident = format_ident!("{}", name);
+
+                        let wrapper_function = quote! {
+                            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                            where
+                                D: serde::Deserializer<'de>,
+                            {
+                                #inner_parser(deserializer).map(<#ty>::from)
                             }
                         };
-
                         extra_functions.push(quote! {
                             #inner_tokens
                             #wrapper_function
@@ -402,77 +2003,243 @@ fn get_custom_parser(name: &str, ty: &Type) -> (proc_macro2::TokenStream, Option
                         ret_function_name = Some(func_ident.to_string());
                     }
                 }
-                _ => {}
-            },
-            2 => match segment.ident.to_string().as_str() {
-                "BTreeMap" => {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                        let mut args_iter = args.args.iter();
-                        if let (
-                            Some(GenericArgument::Type(key_ty)),
-                            Some(GenericArgument::Type(val_ty)),
-                        ) = (args_iter.next(), args_iter.next())
-                        {
-                            let key_name = format!("_{}_{}_key", type_hash, name);
-                            let val_name = format!("_{}_{}_val", type_hash, name);
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => {
+                    if let Some(GenericArgument::Type(inner_ty)) = path.args.first() {
+                        let inner_name = format!("_{}_{}_item", type_hash, name);
+                        extra_functions.push(sequence_parser(
+                            name,
+                            &inner_name,
+                            ty,
+                            inner_ty,
+                            opts,
+                        ));
+                        ret_function_name = Some(name.to_string());
+                    }
+                }
+                #[cfg(feature = "indexmap")]
+                "IndexSet" => {
+                    if let Some(GenericArgument::Type(inner_ty)) = path.args.first() {
+                        let inner_name = format!("_{}_{}_item", type_hash, name);
+                        extra_functions.push(sequence_parser(
+                            name,
+                            &inner_name,
+                            ty,
+                            inner_ty,
+                            opts,
+                        ));
+                        ret_function_name = Some(name.to_string());
+                    }
+                }
+                "Between" => {
+                    if let PathArguments::AngleBracketed(args) = &segment.arguments
+                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+                    {
+                        let parser_call = quote! { ::llm_xml_caster::BetweenParser::<#inner_ty>::custom_between_parser };
 
-                            let (key_tokens, _) = get_custom_parser(&key_name, key_ty);
-                            let (val_tokens, _) = get_custom_parser(&val_name, val_ty);
+                        let func_ident = format_ident!("{}", name);
 
-                            let parser_call = quote! { ::llm_xml_caster::BTreeMapParser::<#key_ty, #val_ty>::custom_btreemap_parser };
+                        extra_functions.push(quote! {
+                            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                            where
+                                D: serde::Deserializer<'de>,
+                            {
+                                #parser_call(deserializer)
+                            }
+                        });
+                        ret_function_name = Some(func_ident.to_string());
+                    }
+                }
+                "Box" => {
+                    if let PathArguments::AngleBracketed(args) = &segment.arguments
+                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+                    {
+                        let parser_call =
+                            quote! { ::llm_xml_caster::BoxParser::<#inner_ty>::custom_box_parser };
 
-                            let func_ident = format_ident!("{}", name);
+                        let func_ident = format_ident!("{}", name);
 
-                            let wrapper_function = quote! {
-                                pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-                                where
-                                    D: serde::Deserializer<'de>,
-                                {
-                                    #parser_call(deserializer)
-                                }
-                            };
-                            extra_functions.push(quote! {
-                                #key_tokens
-                                #val_tokens
-                                #wrapper_function
-                            });
-                            ret_function_name = Some(func_ident.to_string());
-                        }
+                        extra_functions.push(quote! {
+                            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                            where
+                                D: serde::Deserializer<'de>,
+                            {
+                                #parser_call(deserializer)
+                            }
+                        });
+                        ret_function_name = Some(func_ident.to_string());
                     }
                 }
-                "HashMap" => {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                        let mut args_iter = args.args.iter();
-                        if let (
-                            Some(GenericArgument::Type(key_ty)),
-                            Some(GenericArgument::Type(val_ty)),
-                        ) = (args_iter.next(), args_iter.next())
-                        {
-                            let key_name = format!("_{}_{}_key", type_hash, name);
-                            let val_name = format!("_{}_{}_val", type_hash, name);
+                #[cfg(feature = "chrono")]
+                "DateTime" => {
+                    if let Some(GenericArgument::Type(Type::Path(tz))) = path.args.first()
+                        && let Some(tz) = tz.path.segments.last()
+                    {
+                        ret_function_name = match tz.ident.to_string().as_str() {
+                            "Utc" => {
+                                Some("::llm_xml_caster::custom_datetime_utc_parser".to_string())
+                            }
+                            "FixedOffset" => Some(
+                                "::llm_xml_caster::custom_datetime_fixed_offset_parser".to_string(),
+                            ),
+                            _ => None,
+                        };
+                    }
+                }
+                "NonZero" => {
+                    // `NonZero<u32>` is the same type as `NonZeroU32`
+                    if let Some(GenericArgument::Type(Type::Path(inner))) = path.args.first()
+                        && let Some(inner) = inner.path.get_ident()
+                    {
+                        ret_function_name = Some(format!(
+                            "::llm_xml_caster::custom_non_zero_{}_parser",
+                            inner
+                        ));
+                    }
+                }
+                "Rc" | "Arc" => {
+                    if let PathArguments::AngleBracketed(args) = &segment.arguments
+                        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+                    {
+                        let inner_name = format!("_{}_{}_inner", type_hash, name);
+                        let (inner_tokens, inner_parser) =
+                            get_custom_parser(&inner_name, inner_ty, opts);
+                        let inner_parser: syn::Path = syn::parse_str(
+                            &inner_parser.expect("the fallback always provides a parser"),
+                        )
+                        .expect("parser paths are generated by this macro");
 
-                            let (key_tokens, _) = get_custom_parser(&key_name, key_ty);
-                            let (val_tokens, _) = get_custom_parser(&val_name, val_ty);
+                        let func_ident = format_ident!("{}", name);
 
-                            let parser_call = quote! { ::llm_xml_caster::HashMapParser::<#key_ty, #val_ty>::custom_hashmap_parser };
+                        // Parse exactly like a plain `T` field, then share it
+                        let wrapper_function = quote! {
+                            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                            where
+                                D: serde::Deserializer<'de>,
+                            {
+                                #inner_parser(deserializer).map(<#ty>::from)
+                            }
+                        };
+                        extra_functions.push(quote! {
+                            #inner_tokens
+                            #wrapper_function
+                        });
+                        ret_function_name = Some(func_ident.to_string());
+                    }
+                }
+                "Option" => {
+                    if let Some(GenericArgument::Type(inner_ty)) = path.args.first() {
+                        let inner_name = format!("_{}_{}_inner", type_hash, name);
+                        let func_ident = format_ident!("{}", name);
 
-                            let func_ident = format_ident!("{}", name);
+                        let (inner_tokens, inner_parser) =
+                            get_custom_parser(&inner_name, inner_ty, opts);
 
-                            let wrapper_function = quote! {
-                                pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-                                where
-                                    D: serde::Deserializer<'de>,
-                                {
-                                    #parser_call(deserializer)
+                        // an empty element is `None`, anything else must parse as the inner type
+                        let (inner_tokens, parser_call) = match inner_parser {
+                            None => (
+                                quote! {},
+                                quote! {
+                                    ::llm_xml_caster::OptionParser::<#inner_ty>::custom_option_parser(deserializer)
+                                },
+                            ),
+                            Some(inner_parser) if opts.keep_empty => {
+                                let inner_parser: syn::Path = syn::parse_str(&inner_parser)
+                                    .expect("parser paths are generated by this macro");
+                                (
+                                    inner_tokens,
+                                    quote! { #inner_parser(deserializer).map(Some) },
+                                )
+                            }
+                            Some(_) => match content_type(inner_ty) {
+                                Some(content_ty) => (
+                                    quote! {},
+                                    quote! {
+                                        ::llm_xml_caster::OptionItemParser::<#content_ty>::custom_option_field_parser(deserializer)
+                                            .map(|value| value.map(<#inner_ty>::from))
+                                    },
+                                ),
+                                None => {
+                                    let (elem_tokens, elem_ident) =
+                                        element_wrapper(&inner_name, inner_ty, opts);
+                                    (
+                                        elem_tokens,
+                                        quote! {
+                                            ::llm_xml_caster::OptionItemParser::<#elem_ident>::custom_option_item_parser(deserializer)
+                                                .map(|value| value.map(|elem| elem.0))
+                                        },
+                                    )
                                 }
-                            };
-                            extra_functions.push(quote! {
-                                #key_tokens
-                                #val_tokens
-                                #wrapper_function
-                            });
-                            ret_function_name = Some(func_ident.to_string());
-                        }
+                            },
+                        };
+
+                        extra_functions.push(quote! {
+                            #inner_tokens
+
+                            pub fn #func_ident<'de, D>(deserializer: D) -> ::core::result::Result<#ty, D::Error>
+                            where
+                                D: serde::Deserializer<'de>,
+                            {
+                                #parser_call
+                            }
+                        });
+                        ret_function_name = Some(func_ident.to_string());
+                    }
+                }
+                _ => {}
+            },
+            2 => match segment.ident.to_string().as_str() {
+                "Cow" => {
+                    if let Some(GenericArgument::Type(Type::Path(inner))) = path.args.last()
+                        && inner.path.is_ident("str")
+                    {
+                        ret_function_name = if opts.string_format() {
+                            extra_functions.push(string_format_parser(
+                                name,
+                                ty,
+                                "custom_cow_str_format_parser",
+                                opts,
+                            ));
+                            Some(name.to_string())
+                        } else {
+                            Some("::llm_xml_caster::custom_cow_str_parser".to_string())
+                        };
+                    }
+                }
+                "HashMap" | "BTreeMap" => {
+                    if let (
+                        Some(GenericArgument::Type(key_ty)),
+                        Some(GenericArgument::Type(val_ty)),
+                    ) = (path.args.first(), path.args.last())
+                    {
+                        let entry_name = format!("_{}_{}", type_hash, name);
+                        extra_functions.push(map_parser(
+                            name,
+                            &entry_name,
+                            ty,
+                            key_ty,
+                            val_ty,
+                            opts,
+                        ));
+                        ret_function_name = Some(name.to_string());
+                    }
+                }
+                #[cfg(feature = "indexmap")]
+                "IndexMap" => {
+                    if let (
+                        Some(GenericArgument::Type(key_ty)),
+                        Some(GenericArgument::Type(val_ty)),
+                    ) = (path.args.first(), path.args.last())
+                    {
+                        let entry_name = format!("_{}_{}", type_hash, name);
+                        extra_functions.push(map_parser(
+                            name,
+                            &entry_name,
+                            ty,
+                            key_ty,
+                            val_ty,
+                            opts,
+                        ));
+                        ret_function_name = Some(name.to_string());
                     }
                 }
                 _ => {}
@@ -482,20 +2249,6 @@ fn get_custom_parser(name: &str, ty: &Type) -> (proc_macro2::TokenStream, Option
         _ => {}
     }
 
-    if ret_function_name.is_none() {
-        let func_ident = format_ident!("{}", name);
-        let wrapper_function = quote! {
-            pub fn #func_ident<'de, D>(deserializer: D) -> Result<#ty, D::Error>
-            where
-                D: serde::Deserializer<'de>,
-            {
-                ::llm_xml_caster::EnumParser::<#ty>::custom_enum_parser(deserializer)
-            }
-        };
-        extra_functions.push(wrapper_function);
-        ret_function_name = Some(func_ident.to_string());
-    }
-
     (
         quote! {
             #(#extra_functions)*
@@ -0,0 +1,157 @@
+//! The abstraction over the client that sends requests to the model, so that clients other than
+//! `genai`'s can drive the generation functions.
+
+use crate::Result;
+use genai::{
+    Client,
+    chat::{ChatOptions, ChatRequest, StopReason, Usage},
+};
+use std::fmt;
+use std::future::Future;
+
+/// What a backend got back from the model for one request.
+#[derive(Clone, Debug, Default)]
+pub struct BackendResponse {
+    /// The text of the response, or `None` if the model returned no text.
+    pub text: Option<String>,
+    /// Why the model stopped writing, if the backend reports it. A `StopReason::MaxTokens`
+    /// lets a truncated document be completed when `CastOptions::complete_truncated` is set.
+    pub stop_reason: Option<StopReason>,
+    /// The token usage of the request, left at its default if the backend doesn't report it.
+    pub usage: Usage,
+    /// The name of the model that wrote the response, if the backend reports it.
+    pub model_name: Option<String>,
+    /// The arguments of the first tool call of the response, if the model called a tool, as in
+    /// [tool mode](crate::GenerateOptions::with_tool_mode).
+    #[cfg(feature = "json")]
+    pub tool_arguments: Option<serde_json::Value>,
+}
+
+impl BackendResponse {
+    /// A response of `text` alone.
+    pub fn from_text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+
+    /// A response of the text `parts` joined in order, as some providers split a long answer
+    /// across several content parts. Without any part, the response has no text.
+    pub fn from_texts<S: AsRef<str>>(parts: impl IntoIterator<Item = S>) -> Self {
+        let mut parts = parts.into_iter().peekable();
+        Self {
+            text: parts
+                .peek()
+                .is_some()
+                .then(|| parts.map(|part| part.as_ref().to_string()).collect()),
+            ..Self::default()
+        }
+    }
+
+    /// A response of a tool call with `arguments` alone.
+    #[cfg(feature = "json")]
+    pub fn from_tool_arguments(arguments: serde_json::Value) -> Self {
+        Self {
+            tool_arguments: Some(arguments),
+            ..Self::default()
+        }
+    }
+}
+
+/// An error status a provider answered a request with, for a backend to return in
+/// `Error::Backend`, e.g. `Error::Backend(Box::new(HttpStatusError::new(429)))`.
+///
+/// [`Error::status_code`](crate::Error::status_code) and
+/// [`Error::retry_after`](crate::Error::retry_after) read it, so that the retries wait as long as
+/// the provider asked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpStatusError {
+    /// The HTTP status, e.g. 429.
+    pub status: u16,
+    /// The value of the `Retry-After` header, if any: a number of seconds, or an HTTP date such
+    /// as `Wed, 21 Oct 2015 07:28:00 GMT`.
+    pub retry_after: Option<String>,
+    /// The body of the response, e.g. the provider's error message.
+    pub body: String,
+}
+
+impl HttpStatusError {
+    /// An error of `status`, without a `Retry-After` header or a body.
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the value of the `Retry-After` header.
+    pub fn with_retry_after(mut self, retry_after: impl Into<String>) -> Self {
+        self.retry_after = Some(retry_after.into());
+        self
+    }
+
+    /// Sets the body of the response.
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.body.is_empty() {
+            true => write!(f, "the provider answered with status {}", self.status),
+            false => write!(
+                f,
+                "the provider answered with status {}: {}",
+                self.status, self.body
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Sends chat requests to a model, e.g. through an SDK of its own.
+///
+/// Implemented for `genai::Client`. A request that fails to reach the model is returned as an
+/// error, which [`GenerateOptions::with_transport_retries`](crate::GenerateOptions::with_transport_retries)
+/// retries; an error of another client fits in `Error::Backend`, as an [`HttpStatusError`] if
+/// the provider answered with an error status.
+pub trait LlmBackend {
+    /// Sends `request` to the model named `model_name` with `options`.
+    fn chat(
+        &self,
+        model_name: &str,
+        request: &ChatRequest,
+        options: &ChatOptions,
+    ) -> impl Future<Output = Result<BackendResponse>> + Send;
+}
+
+impl LlmBackend for Client {
+    async fn chat(
+        &self,
+        model_name: &str,
+        request: &ChatRequest,
+        options: &ChatOptions,
+    ) -> Result<BackendResponse> {
+        let res = self
+            .exec_chat(model_name, request.clone(), Some(options))
+            .await?;
+        let response = BackendResponse {
+            #[cfg(feature = "json")]
+            tool_arguments: res
+                .tool_calls()
+                .first()
+                .map(|call| call.fn_arguments.clone()),
+            ..BackendResponse::from_texts(res.texts())
+        };
+        Ok(BackendResponse {
+            stop_reason: res.stop_reason,
+            usage: res.usage,
+            model_name: Some(res.model_iden.model_name.to_string()),
+            ..response
+        })
+    }
+}
@@ -1,10 +1,782 @@
-use crate::{Error, LlmPrompt, Result};
-use genai::{
-    Client,
-    chat::{ChatMessage, ChatRequest},
+#[cfg(feature = "json")]
+use crate::cast::cast_json;
+use crate::cast::{CastFailure, CastOptions, cast_text, document_root, document_schema};
+use crate::r#type::{ContainerMistake, EXAMPLE_DEPTH, fill};
+use crate::{
+    AttemptFailure, BackendResponse, DeserializationError, Error, ExtractionError, LlmBackend,
+    LlmPrompt, Result, SchemaStyle, SchemaText, estimate_tokens, to_llm_xml,
 };
-use quick_xml::de::from_str;
-use serde::de::DeserializeOwned;
+use futures::stream::{self, Stream, StreamExt};
+#[cfg(feature = "json")]
+use genai::chat::Tool;
+use genai::chat::{ChatMessage, ChatOptions, ChatRequest, StopReason, Usage};
+use quick_xml::DeError;
+use serde::{Serialize, de::DeserializeOwned};
+#[cfg(feature = "json")]
+use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What a generation attempt got back from the model, passed to the
+/// [`on_attempt`](GenerateOptions::with_on_attempt) callback.
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    /// The number of the attempt, counting from 1.
+    pub number: usize,
+    /// The raw text of the response, or the JSON arguments of its tool call in
+    /// [tool mode](GenerateOptions::with_tool_mode).
+    pub text: &'a str,
+    /// The XML the value was read from, or that failed to deserialize.
+    pub xml: Option<&'a str>,
+    /// How casting the response went.
+    pub outcome: AttemptOutcome<'a>,
+    /// The number of the oldest corrections left out of the request so far, to keep it within
+    /// the [token limit](GenerateOptions::with_max_prompt_tokens).
+    pub trimmed: usize,
+}
+
+/// How casting the response of an [`Attempt`] went.
+#[derive(Debug)]
+pub enum AttemptOutcome<'a> {
+    /// The response was cast.
+    Ok,
+    /// No XML for the type was found, for the given reason.
+    Extraction(&'a ExtractionError),
+    /// The XML didn't deserialize, with where in it the error occurred.
+    Deserialization(&'a DeserializationError),
+    /// The value was rejected by the validator, with the given message.
+    Validation(&'a str),
+}
+
+/// A value generated by [`generate_as_detailed`], with the response it was read from.
+#[derive(Debug)]
+pub struct GenerationResult<T> {
+    /// The generated value.
+    pub value: T,
+    /// The raw text of the response the value was read from, joined with its continuations,
+    /// or the JSON arguments of its tool call in [tool mode](GenerateOptions::with_tool_mode).
+    pub text: String,
+    /// The XML the value was deserialized from, written from the arguments of a tool call, or
+    /// the JSON object with `CastOptions::json_fallback`.
+    pub xml: String,
+    /// The number of attempts used, counting the successful one.
+    pub attempts: usize,
+    /// The errors of the failed attempts before it, in order.
+    pub errors: Vec<Error>,
+    /// The token usage of the successful response, of its last part if it was continued.
+    pub usage: Usage,
+    /// The name of the model that wrote the successful response, if the backend reported it.
+    pub model_name: Option<String>,
+    /// The model the successful request was sent to, which differs from the one passed in
+    /// after switching to a [fallback model](GenerateOptions::with_fallback_models).
+    pub requested_model: String,
+    /// The temperature the successful request was sent with, if any.
+    pub temperature: Option<f64>,
+    /// The number of the oldest corrections left out of the successful request, to keep it
+    /// within the [token limit](GenerateOptions::with_max_prompt_tokens).
+    pub trimmed: usize,
+}
+
+/// What a correction message is written from, passed to the
+/// [`correction_message`](GenerateOptions::with_correction_message) callback.
+#[derive(Debug)]
+pub struct Correction<'a> {
+    /// Why the response didn't cast.
+    pub error: &'a str,
+    /// The elements from the root down to the one the XML failed to deserialize in, e.g.
+    /// `["NestedStruct", "person", "age"]`, if known.
+    pub path: &'a [String],
+    /// The line and column in the XML where it failed to deserialize, if known.
+    pub position: Option<(usize, usize)>,
+    /// The offending XML, cut off after the quote limit, if XML for the type was found.
+    pub xml: Option<&'a str>,
+    /// The schema of the type, if the [`CorrectionPolicy`] resends it this time.
+    pub schema: Option<&'a str>,
+    /// The valid example, cut off after the quote limit, if it is resent this time.
+    pub example: Option<&'a str>,
+    /// The built-in mistake the response most likely made, if
+    /// [built-in bad examples](GenerateOptions::with_built_in_bad_examples) are on.
+    pub bad_example: Option<&'a BadExample>,
+}
+
+/// A mistake to warn the model about, see [`GenerateOptions::with_bad_examples`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BadExample {
+    /// The XML the model shouldn't write.
+    pub incorrect: Cow<'static, str>,
+    /// Why it is incorrect.
+    pub explanation: Cow<'static, str>,
+    /// The XML to write instead.
+    pub correct: Cow<'static, str>,
+}
+
+impl BadExample {
+    /// The items of a sequence written without their `<item>` elements.
+    pub const MISSING_ITEM: BadExample = BadExample {
+        incorrect: Cow::Borrowed(
+            "<people><Person><name><![CDATA[Ann]]></name></Person><Person><name><![CDATA[Bo]]></name></Person></people>",
+        ),
+        explanation: Cow::Borrowed(
+            "every element of a sequence must be wrapped in an <item> element of its own, even a single one",
+        ),
+        correct: Cow::Borrowed(
+            "<people><item><Person><name><![CDATA[Ann]]></name></Person></item><item><Person><name><![CDATA[Bo]]></name></Person></item></people>",
+        ),
+    };
+    /// Text escaped with entities, or not at all, instead of written inside CDATA.
+    pub const ESCAPED_TEXT: BadExample = BadExample {
+        incorrect: Cow::Borrowed("<title>Tom &amp; Jerry</title>"),
+        explanation: Cow::Borrowed(
+            "a string must be written inside <![CDATA[...]]> exactly as it is, without escaping",
+        ),
+        correct: Cow::Borrowed("<title><![CDATA[Tom & Jerry]]></title>"),
+    };
+    /// The entries of a map written as elements named after their keys.
+    pub const MISSING_ENTRY: BadExample = BadExample {
+        incorrect: Cow::Borrowed("<scores><Ann>3</Ann></scores>"),
+        explanation: Cow::Borrowed(
+            "a map must be a sequence of <entry> elements, each holding a <key> and a <value>",
+        ),
+        correct: Cow::Borrowed(
+            "<scores><entry><key><![CDATA[Ann]]></key><value>3</value></entry></scores>",
+        ),
+    };
+    /// The mistakes models make most often with the grammar of this crate, see
+    /// [`GenerateOptions::with_built_in_bad_examples`].
+    pub const BUILT_IN: &'static [BadExample] = &[
+        BadExample::MISSING_ITEM,
+        BadExample::ESCAPED_TEXT,
+        BadExample::MISSING_ENTRY,
+    ];
+
+    /// A mistake to warn about, from the incorrect XML, why it is incorrect and the correct XML.
+    pub fn new(
+        incorrect: impl Into<Cow<'static, str>>,
+        explanation: impl Into<Cow<'static, str>>,
+        correct: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self {
+            incorrect: incorrect.into(),
+            explanation: explanation.into(),
+            correct: correct.into(),
+        }
+    }
+
+    /// The built-in mistake the response that failed with `failure` most likely made, if any.
+    fn matching(failure: &CastFailure) -> Option<&'static BadExample> {
+        let CastFailure::Deserialization(_, e, mistake) = failure else {
+            return None;
+        };
+        match (&e.error, mistake) {
+            // an unescaped `&` or `<` in text that should have been CDATA
+            (DeError::InvalidXml(_), _) => Some(&BadExample::ESCAPED_TEXT),
+            (_, Some(ContainerMistake::MissingItem)) => Some(&BadExample::MISSING_ITEM),
+            (_, Some(ContainerMistake::MissingEntry)) => Some(&BadExample::MISSING_ENTRY),
+            (_, None) => None,
+        }
+    }
+
+    /// The mistake written with the `bad_example` sentence of `text`.
+    fn render(&self, text: &SchemaText) -> String {
+        fill(
+            text.bad_example,
+            &[&self.incorrect, &self.explanation, &self.correct],
+        )
+    }
+}
+
+/// The role correction messages are sent with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CorrectionRole {
+    /// As the model's own words, which some providers take for something it already said.
+    #[default]
+    Assistant,
+    /// As the user asking for a fix.
+    User,
+    /// As a system instruction.
+    System,
+}
+
+impl CorrectionRole {
+    fn message(self, content: String) -> ChatMessage {
+        match self {
+            CorrectionRole::Assistant => ChatMessage::assistant(content),
+            CorrectionRole::User => ChatMessage::user(content),
+            CorrectionRole::System => ChatMessage::system(content),
+        }
+    }
+}
+
+type AttemptCallback = dyn Fn(&Attempt<'_>) -> ControlFlow<()> + Send + Sync;
+type CorrectionTemplate = dyn Fn(&Correction<'_>) -> String + Send + Sync;
+
+/// The `on_attempt` callback, wrapped so that the options stay `Clone` and `Debug`.
+#[derive(Clone)]
+struct Observer(Arc<AttemptCallback>);
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// The `correction_message` callback, wrapped like [`Observer`].
+#[derive(Clone)]
+struct Template(Arc<CorrectionTemplate>);
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Template")
+    }
+}
+
+/// How long to wait before sending a request again after it failed to reach the model.
+///
+/// A response that doesn't parse is retried at once, since the model isn't overloaded by it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backoff {
+    /// Retry at once.
+    #[default]
+    None,
+    /// Wait the same time before every retry.
+    Fixed(Duration),
+    /// Double the wait after every retry, starting at `initial` and capped at `max`. A random
+    /// part of up to half of it is left out, so that many clients don't retry all at once.
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// The wait before retry number `retry`, counting from 0.
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let delay = initial
+                    .checked_mul(2u32.saturating_pow(retry))
+                    .map_or(max, |delay| delay.min(max));
+                jitter(delay)
+            }
+        }
+    }
+}
+
+/// `delay` less a random part of up to half of it.
+fn jitter(delay: Duration) -> Duration {
+    use std::hash::{BuildHasher, RandomState};
+    // a fresh `RandomState` is randomly keyed, which is all the randomness needed here
+    let random = RandomState::new().hash_one(delay) % 1000;
+    delay - delay.mul_f64(random as f64 / 2000.0)
+}
+
+/// Whether a generation asks for the value as the arguments of a tool call, which the native
+/// function calling of OpenAI, Anthropic and Gemini models fills in far more reliably than XML,
+/// see [`GenerateOptions::with_tool_mode`].
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToolMode {
+    /// Ask for XML only.
+    #[default]
+    Off,
+    /// Ask for a tool call when the model is one of OpenAI, Anthropic or Google, i.e. its name
+    /// starts with `gpt-`, `chatgpt-`, `o1`, `o3`, `o4`, `claude-` or `gemini-`, after a
+    /// namespace such as `openai::` if any.
+    Auto,
+    /// Ask for a tool call whatever the model.
+    Always,
+}
+
+#[cfg(feature = "json")]
+impl ToolMode {
+    /// Whether a request to `model_name` asks for a tool call in this mode.
+    pub fn applies_to(self, model_name: &str) -> bool {
+        const PREFIXES: [&str; 7] = ["gpt-", "chatgpt-", "o1", "o3", "o4", "claude-", "gemini-"];
+        let name = model_name.rsplit("::").next().unwrap_or(model_name);
+        match self {
+            ToolMode::Off => false,
+            ToolMode::Auto => PREFIXES.iter().any(|prefix| name.starts_with(prefix)),
+            ToolMode::Always => true,
+        }
+    }
+}
+
+/// What the message asking the model to correct a failed response holds besides the error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CorrectionPolicy {
+    /// The error and the offending XML, along with the schema and the valid example only on the
+    /// first correction, or when the response missed the structure altogether: no root element,
+    /// or a missing or unknown field or variant. Keeps the context small over many retries.
+    #[default]
+    Concise,
+    /// The schema and the valid example with every correction.
+    AlwaysResend,
+}
+
+/// How [`generate_as_with_options`] talks to the model and reads its response.
+///
+/// The default sends the request without any chat options, leaving sampling to the provider,
+/// and casts the response with the default [`CastOptions`].
+#[derive(Clone, Debug)]
+pub struct GenerateOptions {
+    chat: ChatOptions,
+    cast: CastOptions,
+    transport_retries: usize,
+    backoff: Backoff,
+    respect_retry_after: bool,
+    on_attempt: Option<Observer>,
+    correction: CorrectionPolicy,
+    correction_role: CorrectionRole,
+    correction_message: Option<Template>,
+    quote_limit: usize,
+    attempt_temperatures: Vec<f64>,
+    fallback_models: Vec<String>,
+    max_continuations: usize,
+    schema_style: SchemaStyle,
+    schema_text: Option<&'static SchemaText>,
+    schema_max_depth: Option<usize>,
+    max_prompt_tokens: Option<usize>,
+    few_shot: Vec<String>,
+    variant_examples: bool,
+    bad_examples: Vec<BadExample>,
+    built_in_bad_examples: bool,
+    #[cfg(feature = "json")]
+    tool_mode: ToolMode,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            chat: ChatOptions::default(),
+            cast: CastOptions::default(),
+            transport_retries: 0,
+            backoff: Backoff::None,
+            respect_retry_after: true,
+            on_attempt: None,
+            correction: CorrectionPolicy::Concise,
+            correction_role: CorrectionRole::Assistant,
+            correction_message: None,
+            quote_limit: 2000,
+            attempt_temperatures: Vec::new(),
+            fallback_models: Vec::new(),
+            max_continuations: 0,
+            schema_style: SchemaStyle::Full,
+            schema_text: None,
+            schema_max_depth: None,
+            max_prompt_tokens: None,
+            few_shot: Vec::new(),
+            variant_examples: false,
+            bad_examples: Vec::new(),
+            built_in_bad_examples: false,
+            #[cfg(feature = "json")]
+            tool_mode: ToolMode::Off,
+        }
+    }
+}
+
+impl GenerateOptions {
+    /// Options with the default behavior of [`generate_as`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sampling temperature. Some providers reject it, so it is unset by default.
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.chat = self.chat.with_temperature(temperature);
+        self
+    }
+
+    /// Sets the maximum number of tokens of a response.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.chat = self.chat.with_max_tokens(max_tokens);
+        self
+    }
+
+    /// Sets the nucleus sampling probability.
+    pub fn with_top_p(mut self, top_p: f64) -> Self {
+        self.chat = self.chat.with_top_p(top_p);
+        self
+    }
+
+    /// Sets the sequences that end a response.
+    pub fn with_stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.chat = self.chat.with_stop_sequences(stop_sequences);
+        self
+    }
+
+    /// Replaces the chat options with `chat`, for the settings without a method of their own.
+    ///
+    /// The setters above apply on top of it, so call this first.
+    pub fn with_chat_options(mut self, chat: ChatOptions) -> Self {
+        self.chat = chat;
+        self
+    }
+
+    /// Sets how the response is extracted and cast.
+    pub fn with_cast_options(mut self, cast: CastOptions) -> Self {
+        self.cast = cast;
+        self
+    }
+
+    /// Sets how many times a request that failed to reach the model, e.g. on a 429 or a dropped
+    /// connection, is sent again. These retries are counted apart from the ones for responses
+    /// that don't parse. None by default, so such a failure is returned at once.
+    pub fn with_transport_retries(mut self, transport_retries: usize) -> Self {
+        self.transport_retries = transport_retries;
+        self
+    }
+
+    /// Sets the wait before each retry of a request that failed to reach the model.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets whether to wait as long as the `Retry-After` header of a failed request asks, when
+    /// that is longer than the backoff. On by default.
+    pub fn with_respect_retry_after(mut self, respect_retry_after: bool) -> Self {
+        self.respect_retry_after = respect_retry_after;
+        self
+    }
+
+    /// Sets a callback run after every attempt that got a text response, with what the model
+    /// returned and how casting it went, e.g. to log failed responses.
+    ///
+    /// Returning [`ControlFlow::Break`] from a failed attempt stops without retrying, with
+    /// `Error::RetryLimitExceeded` holding the errors so far.
+    pub fn with_on_attempt(
+        mut self,
+        on_attempt: impl Fn(&Attempt<'_>) -> ControlFlow<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_attempt = Some(Observer(Arc::new(on_attempt)));
+        self
+    }
+
+    /// Sets what the correction messages hold besides the error.
+    pub fn with_correction(mut self, correction: CorrectionPolicy) -> Self {
+        self.correction = correction;
+        self
+    }
+
+    /// Sets the role correction messages are sent with, the assistant by default.
+    pub fn with_correction_role(mut self, role: CorrectionRole) -> Self {
+        self.correction_role = role;
+        self
+    }
+
+    /// Sets a callback writing the correction message, e.g. in the language of the system
+    /// prompt. It replaces the default English messages, which send the valid example in a
+    /// message of its own, with the single message it returns.
+    pub fn with_correction_message(
+        mut self,
+        correction_message: impl Fn(&Correction<'_>) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.correction_message = Some(Template(Arc::new(correction_message)));
+        self
+    }
+
+    /// Sets how many characters of the offending XML and of the valid example a correction
+    /// message quotes, cutting off the rest. 2000 by default.
+    pub fn with_quote_limit(mut self, quote_limit: usize) -> Self {
+        self.quote_limit = quote_limit;
+        self
+    }
+
+    /// Sets the temperature of each attempt in turn, e.g. `[0.1, 0.4, 0.7]`, so that a retry
+    /// doesn't reproduce the same malformed response. Attempts past the end keep the last one.
+    pub fn with_attempt_temperatures(mut self, temperatures: Vec<f64>) -> Self {
+        self.attempt_temperatures = temperatures;
+        self
+    }
+
+    /// Sets the models to switch to as attempts fail: the first attempt goes to the model passed
+    /// to the function, attempt `n` to `models[n - 2]`, and attempts past the end to the last
+    /// one. Repeat the first model to switch later, e.g. only on the third attempt.
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.fallback_models = models;
+        self
+    }
+
+    /// Sets how many times a response that stopped at the token limit is continued: the model
+    /// is asked to go on exactly where it left off, and the parts are joined before the XML is
+    /// looked for, instead of regenerating the whole document. None by default.
+    ///
+    /// Continuations aren't counted as attempts, and are tried before
+    /// `CastOptions::complete_truncated` closes what is still left open.
+    pub fn with_max_continuations(mut self, max_continuations: usize) -> Self {
+        self.max_continuations = max_continuations;
+        self
+    }
+
+    /// Sets how the schema is written in the system prompt and in correction messages, e.g.
+    /// [`SchemaStyle::Compact`] to save tokens on every request and retry, or
+    /// [`SchemaStyle::Legend`] for a model that copies the description comments into its
+    /// response. The full schema by default.
+    pub fn with_schema_style(mut self, schema_style: SchemaStyle) -> Self {
+        self.schema_style = schema_style;
+        self
+    }
+
+    /// Sets how many levels of nested structs and enums the schema expands, for a type too deep
+    /// to be written out whole. No limit by default, see
+    /// [`SchemaNode::render_with_max_depth`](crate::SchemaNode::render_with_max_depth).
+    pub fn with_schema_max_depth(mut self, max_depth: usize) -> Self {
+        self.schema_max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets the sentences the schema and the request for it are written with, e.g.
+    /// [`SchemaText::SIMPLIFIED_CHINESE`] for a prompt in Chinese, in place of the default
+    /// [`SchemaText::current`].
+    pub fn with_schema_text(mut self, schema_text: &'static SchemaText) -> Self {
+        self.schema_text = Some(schema_text);
+        self
+    }
+
+    /// Sets examples of valid responses, written into the system message as numbered examples
+    /// with a note to copy their structure but not their values. Two or three different ones
+    /// help the model more than the single valid example sent with corrections, e.g. for an
+    /// enum with many variants.
+    pub fn with_few_shot_xml(mut self, examples: Vec<String>) -> Self {
+        self.few_shot = examples;
+        self
+    }
+
+    /// Like [`with_few_shot_xml`](Self::with_few_shot_xml), with the examples serialized from
+    /// `examples` by [`to_llm_xml`](crate::to_llm_xml).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::XmlSerialization` if one of them can't be written as XML.
+    pub fn with_few_shot<'a, T: Serialize + LlmPrompt + 'a>(
+        mut self,
+        examples: impl IntoIterator<Item = &'a T>,
+    ) -> Result<Self> {
+        self.few_shot = examples
+            .into_iter()
+            .map(to_llm_xml)
+            .collect::<Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Sets whether the system message also shows the placeholder example of each variant when
+    /// the type is an enum, after the few-shot examples, see [`LlmPrompt::example_variants`].
+    /// Off by default.
+    pub fn with_variant_examples(mut self, variant_examples: bool) -> Self {
+        self.variant_examples = variant_examples;
+        self
+    }
+
+    /// Sets mistakes to warn the model about in the system message, each written as the
+    /// incorrect XML, why it is incorrect, and the correct XML.
+    pub fn with_bad_examples(mut self, bad_examples: Vec<BadExample>) -> Self {
+        self.bad_examples = bad_examples;
+        self
+    }
+
+    /// Sets whether the system message also warns about the mistakes of
+    /// [`BadExample::BUILT_IN`], after those of `with_bad_examples`, and a correction quotes the
+    /// one its error most likely came from. Off by default.
+    pub fn with_built_in_bad_examples(mut self, built_in_bad_examples: bool) -> Self {
+        self.built_in_bad_examples = built_in_bad_examples;
+        self
+    }
+
+    /// Sets the most tokens a request may take, as [`estimate_tokens`] counts them, to stay
+    /// within the context window of the model. No limit by default.
+    ///
+    /// Once the corrections of the retries would take a request over it, the oldest ones are
+    /// left out, though never the prompt, the system message asking for the XML, or the newest
+    /// correction. A request still over it fails with `Error::PromptTooLarge` before it is sent.
+    pub fn with_max_prompt_tokens(mut self, max_prompt_tokens: usize) -> Self {
+        self.max_prompt_tokens = Some(max_prompt_tokens);
+        self
+    }
+
+    /// Sets whether the first attempt asks for the value as the arguments of a tool call
+    /// instead of XML. Off by default.
+    ///
+    /// For the models `tool_mode` applies to, the request carries a tool whose parameters are
+    /// [`LlmPrompt::json_schema`], wrapped in an object of a single `value` for a type that
+    /// isn't one, and the arguments are read with [`cast_from_json`](crate::cast_from_json).
+    /// When the provider rejects the request, the model answers without calling the tool, or
+    /// its arguments don't cast, the same attempt goes on to ask for XML, so the tool call
+    /// doesn't take one of the retries. A rejected tool isn't sent again by the transport
+    /// retries.
+    #[cfg(feature = "json")]
+    pub fn with_tool_mode(mut self, tool_mode: ToolMode) -> Self {
+        self.tool_mode = tool_mode;
+        self
+    }
+
+    /// The chat options sent with each request.
+    pub fn chat_options(&self) -> &ChatOptions {
+        &self.chat
+    }
+
+    /// The chat options of attempt `attempt`, counting from 1, with its temperature if any.
+    fn attempt_chat_options(&self, attempt: usize) -> Cow<'_, ChatOptions> {
+        let temperatures = &self.attempt_temperatures;
+        match temperatures.get(attempt - 1).or(temperatures.last()) {
+            Some(&temperature) => Cow::Owned(self.chat.clone().with_temperature(temperature)),
+            None => Cow::Borrowed(&self.chat),
+        }
+    }
+
+    /// The model attempt `attempt` goes to, `model_name` or one of the fallback models.
+    fn attempt_model<'a>(&'a self, attempt: usize, model_name: &'a str) -> &'a str {
+        match attempt {
+            1 => model_name,
+            n => self
+                .fallback_models
+                .get(n - 2)
+                .or(self.fallback_models.last())
+                .map_or(model_name, String::as_str),
+        }
+    }
+
+    /// The options each response is cast with.
+    pub fn cast_options(&self) -> CastOptions {
+        self.cast
+    }
+
+    /// The number of retries of a request that failed to reach the model.
+    pub fn transport_retries(&self) -> usize {
+        self.transport_retries
+    }
+
+    /// Runs the `on_attempt` callback, if any.
+    fn observe(&self, attempt: &Attempt<'_>) -> ControlFlow<()> {
+        self.on_attempt
+            .as_ref()
+            .map_or(ControlFlow::Continue(()), |observer| (observer.0)(attempt))
+    }
+
+    /// The failure of attempt `number`, whose response `text` didn't cast with `failure`.
+    fn attempt_failure(&self, number: usize, text: &str, failure: CastFailure) -> AttemptFailure {
+        let xml = match &failure {
+            CastFailure::Extraction(_) => None,
+            CastFailure::Deserialization(xml, ..) | CastFailure::Validation(xml, _) => {
+                Some(self.quote(xml).into_owned())
+            }
+        };
+        AttemptFailure {
+            attempt: number,
+            error: failure.into(),
+            text: Some(self.quote(text).into_owned()),
+            xml,
+        }
+    }
+
+    /// `text` cut off after the quote limit, noting how much was left out.
+    fn quote<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match text.char_indices().nth(self.quote_limit) {
+            Some((end, _)) => format!(
+                "{}... ({} more characters)",
+                &text[..end],
+                text[end..].chars().count()
+            )
+            .into(),
+            None => text.into(),
+        }
+    }
+
+    /// Runs `render` with the sentences set by `with_schema_text`, if any.
+    fn in_schema_text<R>(&self, render: impl FnOnce() -> R) -> R {
+        match self.schema_text {
+            Some(text) => text.scope(render),
+            None => render(),
+        }
+    }
+
+    /// Checks that `request` takes no more tokens than the limit, if any.
+    fn check_prompt_tokens(&self, request: &ChatRequest) -> Result<()> {
+        let Some(limit) = self.max_prompt_tokens else {
+            return Ok(());
+        };
+        match request_tokens(request) {
+            estimated if estimated > limit => Err(Error::PromptTooLarge { estimated, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The wait before retry number `retry` of a request that failed with `err`.
+    fn transport_delay(&self, retry: u32, err: &Error) -> Duration {
+        let delay = self.backoff.delay(retry);
+        match err.retry_after() {
+            Some(asked) if self.respect_retry_after => delay.max(asked),
+            _ => delay,
+        }
+    }
+}
+
+impl From<ChatOptions> for GenerateOptions {
+    fn from(chat: ChatOptions) -> Self {
+        Self::new().with_chat_options(chat)
+    }
+}
+
+impl From<CastOptions> for GenerateOptions {
+    fn from(cast: CastOptions) -> Self {
+        Self::new().with_cast_options(cast)
+    }
+}
+
+/// The messages a generation starts from, to which the system message asking for the XML of
+/// the type is appended.
+///
+/// Implemented for an owned `Vec<ChatMessage>`, for a borrowed slice of messages, which is cloned
+/// so that the same base conversation can be reused across calls, for a single `ChatMessage`,
+/// and for a `ChatRequest`, which keeps its own system prompt.
+pub trait IntoPrompt {
+    /// The request holding the messages.
+    fn into_request(self) -> ChatRequest;
+}
+
+impl IntoPrompt for ChatRequest {
+    fn into_request(self) -> ChatRequest {
+        self
+    }
+}
+
+impl IntoPrompt for &ChatRequest {
+    fn into_request(self) -> ChatRequest {
+        self.clone()
+    }
+}
+
+impl IntoPrompt for Vec<ChatMessage> {
+    fn into_request(self) -> ChatRequest {
+        ChatRequest::new(self)
+    }
+}
+
+impl IntoPrompt for &Vec<ChatMessage> {
+    fn into_request(self) -> ChatRequest {
+        ChatRequest::new(self.clone())
+    }
+}
+
+impl IntoPrompt for &[ChatMessage] {
+    fn into_request(self) -> ChatRequest {
+        ChatRequest::new(self.to_vec())
+    }
+}
+
+impl<const N: usize> IntoPrompt for [ChatMessage; N] {
+    fn into_request(self) -> ChatRequest {
+        ChatRequest::new(self.into())
+    }
+}
+
+impl IntoPrompt for ChatMessage {
+    fn into_request(self) -> ChatRequest {
+        ChatRequest::new(vec![self])
+    }
+}
 
 /// Attempts to generate structured data of type `T` from an LLM response.
 ///
@@ -13,19 +785,54 @@ use serde::de::DeserializeOwned;
 ///
 /// # Arguments
 ///
-/// * `client` - The `genai::Client` used for the API request.
+/// * `client` - The backend used for the API request, e.g. a `genai::Client`.
 /// * `model_name` - The name of the model to use (e.g., "gemini-2.5-flash").
-/// * `prompt` - The initial user prompt messages.
-/// * `valid_example` - A valid XML example string to guide the LLM.
+/// * `prompt` - The initial prompt messages, e.g. a `Vec<ChatMessage>` or a borrowed slice of
+///   them, see [`IntoPrompt`].
+/// * `valid_example` - A valid XML example string to guide the LLM, or `""` for
+///   [`LlmPrompt::example_xml`].
 pub async fn generate_as<T: DeserializeOwned + LlmPrompt>(
-    client: &Client,
+    client: &impl LlmBackend,
     model_name: &str,
-    prompt: Vec<ChatMessage>,
+    prompt: impl IntoPrompt,
     valid_example: &str,
 ) -> Result<T> {
     generate_as_with_retries(client, model_name, prompt, valid_example, 3).await
 }
 
+/// Like [`generate_as`], with a prompt of the single user message `user_text`.
+pub async fn generate_from_text<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    user_text: &str,
+    valid_example: &str,
+) -> Result<T> {
+    generate_as(
+        client,
+        model_name,
+        ChatMessage::user(user_text),
+        valid_example,
+    )
+    .await
+}
+
+/// Like [`generate_as`], with the valid example serialized from `example` by
+/// [`to_llm_xml`](crate::to_llm_xml), so that it can't drift out of sync with `T`.
+///
+/// # Errors
+///
+/// Returns `Error::XmlSerialization` if `example` can't be written as XML, and the errors of
+/// [`generate_as`] otherwise.
+pub async fn generate_as_with_example_value<T: DeserializeOwned + Serialize + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    example: &T,
+) -> Result<T> {
+    let valid_example = to_llm_xml(example)?;
+    generate_as(client, model_name, prompt, &valid_example).await
+}
+
 /// Attempts to generate structured data of type `T` from an LLM response with a specified number of retries.
 ///
 /// If the LLM response does not contain valid XML or the XML cannot be deserialized,
@@ -34,74 +841,931 @@ pub async fn generate_as<T: DeserializeOwned + LlmPrompt>(
 ///
 /// # Arguments
 ///
-/// * `client` - The `genai::Client` used for the API request.
+/// * `client` - The backend used for the API request, e.g. a `genai::Client`.
 /// * `model_name` - The name of the model to use (e.g., "gemini-2.5-flash").
-/// * `prompt` - The initial user prompt messages.
-/// * `valid_example` - A valid XML example string to guide the LLM.
+/// * `prompt` - The initial prompt messages, e.g. a `Vec<ChatMessage>` or a borrowed slice of
+///   them, see [`IntoPrompt`].
+/// * `valid_example` - A valid XML example string to guide the LLM, or `""` for
+///   [`LlmPrompt::example_xml`].
 /// * `retries` - The maximum number of attempts to correct and regenerate the output.
 ///
 /// # Errors
 ///
-/// Returns `Error::RetryLimitExceeded` if the XML output remains invalid after all retry attempts.
+/// Returns `Error::RetryLimitExceeded` if the XML output remains invalid after all retry attempts,
+/// or the error of the backend, e.g. `Error::ChatRequest`, if a request fails to reach the model.
 pub async fn generate_as_with_retries<T: DeserializeOwned + LlmPrompt>(
-    client: &Client,
+    client: &impl LlmBackend,
     model_name: &str,
-    prompt: Vec<ChatMessage>,
+    prompt: impl IntoPrompt,
     valid_example: &str,
     retries: usize,
 ) -> Result<T> {
-    let chat_req = ChatRequest::new(prompt);
-    let mut chat_req = chat_req.append_message(
-        ChatMessage::system(format!("You must respond with a valid XML document(root name is {}) that adheres to the following schema: {}", T::root_name(), T::get_prompt_schema()))
-    );
+    generate_as_with_options(
+        client,
+        model_name,
+        prompt,
+        valid_example,
+        retries,
+        GenerateOptions::default(),
+    )
+    .await
+}
+
+/// Like [`generate_as_with_retries`], with the chat and cast settings of `options`.
+///
+/// `options` is a [`GenerateOptions`], or a [`ChatOptions`] or [`CastOptions`] on its own. With
+/// repair enabled in the cast options, XML that only needs a targeted fix (an unescaped `&`,
+/// say) is repaired instead of spending a retry on it.
+pub async fn generate_as_with_options<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+) -> Result<T> {
+    generate_as_detailed(client, model_name, prompt, valid_example, retries, options)
+        .await
+        .map(|result| result.value)
+}
+
+/// Like [`generate_as_with_options`], retrying as well when `validator` rejects a value that
+/// cast, e.g. one whose end date lies before its start date.
+///
+/// The message `validator` returns is sent to the model as the correction, and the attempt
+/// counts against `retries`.
+///
+/// # Errors
+///
+/// Returns `Error::ValidationLimitExceeded` if some responses cast but none passed `validator`
+/// within the retries, and `Error::RetryLimitExceeded` if none cast at all.
+pub async fn generate_as_with_validator<T, F>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+    validator: F,
+) -> Result<T>
+where
+    T: DeserializeOwned + LlmPrompt,
+    F: Fn(&T) -> std::result::Result<(), String>,
+{
+    let options = options.into();
+    generate_validated(
+        client,
+        model_name,
+        prompt,
+        None,
+        valid_example,
+        retries,
+        options,
+        &validator,
+    )
+    .await
+    .map(|result| result.value)
+}
+
+/// Like [`generate_as_with_options`], returning the response the value was read from along with
+/// it: the raw text and XML, the attempts used, and the usage reported by the model.
+pub async fn generate_as_detailed<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+) -> Result<GenerationResult<T>> {
+    let options = options.into();
+    let accept = |_: &T| Ok(());
+    generate_validated(
+        client,
+        model_name,
+        prompt,
+        None,
+        valid_example,
+        retries,
+        options,
+        &accept,
+    )
+    .await
+}
+
+/// The retry loop of [`generate_as_detailed`], taking the values `validator` accepts.
+///
+/// `request` is the [`schema_request`] of `prompt` if the caller built it already.
+#[allow(clippy::too_many_arguments)]
+async fn generate_validated<T, F>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    request: Option<ChatRequest>,
+    valid_example: &str,
+    retries: usize,
+    options: GenerateOptions,
+    validator: &F,
+) -> Result<GenerationResult<T>>
+where
+    T: DeserializeOwned + LlmPrompt,
+    F: Fn(&T) -> std::result::Result<(), String>,
+{
+    let prompt = prompt.into_request();
+    #[cfg(feature = "json")]
+    let mut tool_request = options
+        .tool_mode
+        .applies_to(model_name)
+        .then(|| tool_request::<T>(&options, &prompt));
+    let request = request.unwrap_or_else(|| schema_request::<T>(&options, prompt));
+    let mut history = History::new(request);
+    let valid_example = match valid_example {
+        "" => T::example_xml(),
+        example => example.to_string(),
+    };
 
     let mut errs = Vec::new();
+    let mut transport_failures = 0;
+    let mut corrected = false;
 
-    for _attempt in 1..=retries {
-        let res = client.exec_chat(model_name, chat_req.clone(), None).await?;
-        if let Some(text) = res.first_text() {
-            let root_name = T::root_name();
-            let start_tag = format!("<{}>", root_name);
-            let end_tag = format!("</{}>", root_name);
+    for attempt in 1..=retries {
+        let chat_req = history.request(options.max_prompt_tokens);
+        let requested_model = options.attempt_model(attempt, model_name);
+        let chat_options = options.attempt_chat_options(attempt);
+        let mut transport = Transport {
+            client,
+            model_name: requested_model,
+            chat_options: &chat_options,
+            options: &options,
+            attempt,
+            failures: &mut transport_failures,
+            errs: &mut errs,
+        };
+        // the XML is asked for in the same attempt when the tool call fails, so that it doesn't
+        // take one of the retries
+        #[cfg(feature = "json")]
+        if let Some((request, wrapped)) = tool_request.take() {
+            let res = match transport.send(&request).await {
+                Ok(res) => Some(res),
+                // e.g. a provider without tools
+                Err(e @ (Error::ChatRequest(_) | Error::Backend(_))) => {
+                    transport.errs.push(AttemptFailure::new(attempt, e));
+                    None
+                }
+                // the XML request still gets its go, with the transport retries used up
+                Err(Error::RetryLimitExceeded(failures)) => {
+                    *transport.errs = failures;
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+            match res.as_ref().map(|res| &res.tool_arguments) {
+                None => {}
+                Some(None) => transport
+                    .errs
+                    .push(AttemptFailure::new(attempt, Error::MissingToolCall)),
+                Some(Some(arguments)) => {
+                    let value = match wrapped {
+                        true => &arguments["value"],
+                        false => arguments,
+                    };
+                    let text = arguments.to_string();
+                    let result = validated(cast_json::<T>(value), validator);
+                    let flow = observe_cast(&options, attempt, history.trimmed, &text, &result);
+                    match result {
+                        Ok((value, xml)) => {
+                            let res = res.expect("a response with tool arguments");
+                            return Ok(GenerationResult {
+                                value,
+                                text,
+                                xml,
+                                attempts: attempt,
+                                errors: std::mem::take(transport.errs)
+                                    .into_iter()
+                                    .map(|failure| failure.error)
+                                    .collect(),
+                                usage: res.usage,
+                                model_name: res.model_name,
+                                requested_model: requested_model.to_string(),
+                                temperature: chat_options.temperature,
+                                trimmed: history.trimmed,
+                            });
+                        }
+                        Err(failure) => {
+                            transport
+                                .errs
+                                .push(options.attempt_failure(attempt, &text, failure));
+                            if flow.is_break() {
+                                return Err(exhausted(std::mem::take(transport.errs)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let res = transport.send(&chat_req).await?;
+        let res = transport.continue_truncated(&chat_req, res).await?;
+        let Some(text) = res.text.as_deref() else {
+            // nothing to correct, so the request is sent again as it is
+            errs.push(AttemptFailure::new(attempt, Error::EmptyResponse));
+            continue;
+        };
+        let (result, flow) =
+            cast_response(&options, attempt, history.trimmed, text, &res, validator);
+        let (data, xml): (T, String) = match result {
+            Ok(v) => v,
+            Err(failure) if flow.is_break() => {
+                errs.push(options.attempt_failure(attempt, text, failure));
+                return Err(exhausted(errs));
+            }
+            Err(failure) => {
+                let resend = match options.correction {
+                    CorrectionPolicy::AlwaysResend => true,
+                    CorrectionPolicy::Concise => !corrected || failure.is_structural(),
+                };
+                corrected = true;
+                history.push(correction_messages::<T>(
+                    &options,
+                    &failure,
+                    resend,
+                    &valid_example,
+                ));
+                errs.push(options.attempt_failure(attempt, text, failure));
+                continue;
+            }
+        };
+
+        return Ok(GenerationResult {
+            value: data,
+            text: text.to_string(),
+            xml,
+            attempts: attempt,
+            errors: errs.into_iter().map(|failure| failure.error).collect(),
+            usage: res.usage,
+            model_name: res.model_name,
+            requested_model: requested_model.to_string(),
+            temperature: chat_options.temperature,
+            trimmed: history.trimmed,
+        });
+    }
+
+    Err(exhausted(errs))
+}
+
+/// The request of a generation, with the corrections of its retries so far.
+struct History {
+    /// The prompt with the system message asking for the XML.
+    base: ChatRequest,
+    base_tokens: usize,
+    /// The messages of each correction, oldest first, with the tokens they take.
+    corrections: VecDeque<(Vec<ChatMessage>, usize)>,
+    /// The number of corrections left out to fit the token limit.
+    trimmed: usize,
+}
 
-            let xml_content: &str;
-            let data: T;
+impl History {
+    fn new(base: ChatRequest) -> Self {
+        Self {
+            base_tokens: request_tokens(&base),
+            base,
+            corrections: VecDeque::new(),
+            trimmed: 0,
+        }
+    }
 
-            if let (Some(xml_start), Some(xml_end_tag_start)) =
-                (text.find(&start_tag), text.rfind(&end_tag))
+    fn push(&mut self, messages: Vec<ChatMessage>) {
+        let tokens = messages.iter().map(message_tokens).sum();
+        self.corrections.push_back((messages, tokens));
+    }
+
+    /// The request of the next attempt, leaving out the oldest corrections but the newest while
+    /// it would take more than `max_tokens`.
+    fn request(&mut self, max_tokens: Option<usize>) -> ChatRequest {
+        if let Some(limit) = max_tokens {
+            let mut tokens = self.base_tokens + self.corrections.iter().map(|c| c.1).sum::<usize>();
+            while tokens > limit && self.corrections.len() > 1 {
+                let (_, oldest) = self
+                    .corrections
+                    .pop_front()
+                    .expect("more than one correction");
+                tokens -= oldest;
+                self.trimmed += 1;
+            }
+        }
+        self.corrections
+            .iter()
+            .flat_map(|(messages, _)| messages.iter().cloned())
+            .fold(self.base.clone(), |request, message| {
+                request.append_message(message)
+            })
+    }
+}
+
+/// Sends the requests of one attempt, retrying those that fail to reach the model.
+struct Transport<'a, B> {
+    client: &'a B,
+    model_name: &'a str,
+    chat_options: &'a ChatOptions,
+    options: &'a GenerateOptions,
+    /// The number of the attempt the requests are sent for.
+    attempt: usize,
+    /// The failed requests so far, counted across the attempts.
+    failures: &'a mut usize,
+    errs: &'a mut Vec<AttemptFailure>,
+}
+
+impl<B: LlmBackend> Transport<'_, B> {
+    /// Sends `request`, with the transport retries left.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::PromptTooLarge` if `request` is over the token limit, the error of the
+    /// request without transport retries or when [sending it again](Error::is_retryable) can't
+    /// help, and `Error::RetryLimitExceeded` with the errors so far once they ran out.
+    async fn send(&mut self, request: &ChatRequest) -> Result<BackendResponse> {
+        self.options.check_prompt_tokens(request)?;
+        loop {
+            match self
+                .client
+                .chat(self.model_name, request, self.chat_options)
+                .await
             {
-                let xml_end = xml_end_tag_start + end_tag.len();
-                xml_content = &text[xml_start..xml_end];
-                data = match from_str(xml_content) {
-                    Ok(v) => v,
+                Ok(res) => return Ok(res),
+                // e.g. a 400, which fails the same way however often it is sent
+                Err(e) if self.options.transport_retries == 0 || !e.is_retryable() => {
+                    return Err(e);
+                }
+                Err(e) if *self.failures < self.options.transport_retries => {
+                    let delay = self.options.transport_delay(*self.failures as u32, &e);
+                    *self.failures += 1;
+                    self.errs.push(AttemptFailure::new(self.attempt, e));
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    self.errs.push(AttemptFailure::new(self.attempt, e));
+                    return Err(Error::RetryLimitExceeded(std::mem::take(self.errs)));
+                }
+            }
+        }
+    }
+
+    /// `res` joined with the continuations of it, while it stopped at the token limit and the
+    /// [continuations](GenerateOptions::with_max_continuations) last. The usage and stop reason
+    /// are those of the last part.
+    async fn continue_truncated(
+        &mut self,
+        request: &ChatRequest,
+        mut res: BackendResponse,
+    ) -> Result<BackendResponse> {
+        for _ in 0..self.options.max_continuations {
+            if !matches!(res.stop_reason, Some(StopReason::MaxTokens(_))) {
+                break;
+            }
+            let Some(partial) = res.text.take() else {
+                break;
+            };
+            let tail = match partial.char_indices().nth_back(CONTINUATION_TAIL - 1) {
+                Some((start, _)) => &partial[start..],
+                None => &partial[..],
+            };
+            let continuation = request
+                .clone()
+                .append_message(ChatMessage::assistant(partial.clone()))
+                .append_message(ChatMessage::user(format!(
+                    "Your response was cut off at the length limit. It ended with:\n{}\nContinue exactly where you left off, without repeating anything already written.",
+                    tail
+                )));
+            let next = self.send(&continuation).await?;
+            let text = match next.text.as_deref() {
+                Some(rest) => stitch(&partial, rest),
+                // nothing to add, so the truncated response is cast as it is
+                None => partial,
+            };
+            res = BackendResponse {
+                text: Some(text),
+                ..next
+            };
+        }
+        Ok(res)
+    }
+}
+
+/// The characters at the end of a truncated response quoted in the request to continue it.
+const CONTINUATION_TAIL: usize = 200;
+
+/// `partial` followed by its continuation `rest`, leaving out the start of `rest` when the model
+/// repeated the end of `partial` despite the instructions. Repeats shorter than a few characters
+/// are kept, as they are as likely to be a part of the document.
+fn stitch(partial: &str, rest: &str) -> String {
+    const MIN_REPEAT: usize = 8;
+    let repeat = (MIN_REPEAT..=partial.len().min(rest.len()))
+        .rev()
+        .find(|&len| rest.is_char_boundary(len) && partial.ends_with(&rest[..len]))
+        .unwrap_or(0);
+    format!("{}{}", partial, &rest[repeat..])
+}
+
+/// The error once the retries ran out with `errs`, telling whether any response cast.
+fn exhausted(errs: Vec<AttemptFailure>) -> Error {
+    if errs.iter().any(|f| matches!(f.error, Error::Validation(_))) {
+        Error::ValidationLimitExceeded(errs.into_iter().map(|f| f.error).collect())
+    } else {
+        Error::RetryLimitExceeded(errs)
+    }
+}
+
+/// Generates a `T` from each of `prompts`, running at most `concurrency` of them at once, and
+/// returns the results in the order of the prompts.
+///
+/// Each prompt gets the retries of [`generate_as_with_retries`], and a prompt that fails doesn't
+/// stop the others.
+pub async fn generate_many_as<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompts: impl IntoIterator<Item = impl IntoPrompt>,
+    valid_example: &str,
+    concurrency: usize,
+    retries: usize,
+) -> Vec<Result<T>> {
+    generate_many_as_with_options(
+        client,
+        model_name,
+        prompts,
+        valid_example,
+        concurrency,
+        retries,
+        GenerateOptions::default(),
+    )
+    .await
+}
+
+/// Like [`generate_many_as`], with the chat and cast settings of `options`.
+pub async fn generate_many_as_with_options<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompts: impl IntoIterator<Item = impl IntoPrompt>,
+    valid_example: &str,
+    concurrency: usize,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+) -> Vec<Result<T>> {
+    let prompts: Vec<ChatRequest> = prompts.into_iter().map(IntoPrompt::into_request).collect();
+    let mut results: Vec<Option<Result<T>>> = prompts.iter().map(|_| None).collect();
+    let stream = generate_many_as_stream(
+        client,
+        model_name,
+        prompts,
+        valid_example,
+        concurrency,
+        retries,
+        options,
+    );
+    let mut stream = std::pin::pin!(stream);
+    while let Some((index, result)) = stream.next().await {
+        results[index] = Some(result);
+    }
+    results
+        .into_iter()
+        .map(|result| result.expect("every prompt yields a result"))
+        .collect()
+}
+
+/// Like [`generate_many_as_with_options`], yielding each result with the index of its prompt as
+/// soon as it is ready, e.g. to report progress or to store results without waiting for the
+/// whole batch.
+pub fn generate_many_as_stream<'a, T: DeserializeOwned + LlmPrompt + 'a>(
+    client: &'a impl LlmBackend,
+    model_name: &'a str,
+    prompts: impl IntoIterator<Item = impl IntoPrompt>,
+    valid_example: &'a str,
+    concurrency: usize,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+) -> impl Stream<Item = (usize, Result<T>)> + 'a {
+    let options = options.into();
+    let prompts: Vec<ChatRequest> = prompts.into_iter().map(IntoPrompt::into_request).collect();
+    stream::iter(prompts.into_iter().enumerate())
+        .map(move |(index, prompt)| {
+            let options = options.clone();
+            async move {
+                let result = generate_as_with_options(
+                    client,
+                    model_name,
+                    prompt,
+                    valid_example,
+                    retries,
+                    options,
+                )
+                .await;
+                (index, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+}
+
+/// Generates `n` candidates of `T` at once and returns the first that casts, using the default
+/// retry limit of 3 attempts if none does.
+///
+/// This trades the cost of `n` requests for the latency of the corrective retries, which is
+/// worth it for types the model often gets wrong.
+///
+/// # Errors
+///
+/// Returns `Error::RetryLimitExceeded` with the errors of every candidate and retry if none of
+/// them casts.
+pub async fn generate_as_best_of<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    n: usize,
+) -> Result<T> {
+    generate_as_best_of_with_options(
+        client,
+        model_name,
+        prompt,
+        valid_example,
+        n,
+        3,
+        GenerateOptions::default(),
+    )
+    .await
+}
+
+/// Like [`generate_as_best_of`], with `retries` corrective attempts once all `n` candidates
+/// failed, and the chat and cast settings of `options`.
+///
+/// At most `n` requests are in flight at once. The candidates still running when one casts are
+/// dropped, which cancels their requests. Each candidate has the transport retries of `options`
+/// to itself, and one whose request still fails counts as a failed candidate.
+pub async fn generate_as_best_of_with_options<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    n: usize,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+) -> Result<T> {
+    let options = options.into();
+    let prompt = prompt.into_request();
+    let chat_req = schema_request::<T>(&options, prompt.clone());
+    options.check_prompt_tokens(&chat_req)?;
+    let mut errs = Vec::new();
+
+    let mut candidates = stream::iter(1..=n)
+        .map(|candidate| {
+            let (options, chat_req) = (&options, &chat_req);
+            async move {
+                let mut failures = 0;
+                let mut errs = Vec::new();
+                let mut transport = Transport {
+                    client,
+                    model_name,
+                    chat_options: options.chat_options(),
+                    options,
+                    attempt: candidate,
+                    failures: &mut failures,
+                    errs: &mut errs,
+                };
+                let res = match transport.send(chat_req).await {
+                    // the failures are those the transport retries collected so far
+                    Err(Error::RetryLimitExceeded(failures)) => return (candidate, None, failures),
                     Err(e) => {
-                        chat_req = chat_req.append_message(
-                            ChatMessage::assistant(format!("The last time you responded, the XML content was: {}\nThe error was: {}\nPlease ensure your response strictly follows the required XML format.\nThe format body is: {}", xml_content, e,T::get_prompt_schema()))
-                        );
-                        chat_req = chat_req.append_message(ChatMessage::assistant(format!(
-                            "Here is a valid example for your reference:\n{}",
-                            valid_example
-                        )));
-                        errs.push(Error::XmlDeserialization(e));
-                        continue;
+                        errs.push(AttemptFailure::new(candidate, e));
+                        None
                     }
+                    Ok(res) => Some(res),
                 };
-            } else {
-                errs.push(Error::XmlExtraction(format!(
-                    "cannot find the root {} of the structure",
-                    T::root_name()
-                )));
-                chat_req = chat_req.append_message(ChatMessage::assistant(format!("The error was: cannot find the root {} of the structure\nPlease ensure your response strictly follows the required XML format.\n The format body is: {}", T::root_name(), T::get_prompt_schema())));
-                chat_req = chat_req.append_message(ChatMessage::assistant(format!(
-                    "Here is a valid example for your reference:\n{}",
-                    valid_example
-                )));
-                continue;
-            };
+                (candidate, res, errs)
+            }
+        })
+        .buffer_unordered(n.max(1));
+    while let Some((candidate, res, failures)) = candidates.next().await {
+        errs.extend(failures);
+        let Some(res) = res else {
+            continue;
+        };
+        let Some(text) = res.text.as_deref() else {
+            errs.push(AttemptFailure::new(candidate, Error::EmptyResponse));
+            continue;
+        };
+        match cast_response::<T, _>(&options, candidate, 0, text, &res, &|_| Ok(())) {
+            (Ok((data, _)), _) => return Ok(data),
+            (Err(failure), flow) => {
+                errs.push(options.attempt_failure(candidate, text, failure));
+                if flow.is_break() {
+                    return Err(Error::RetryLimitExceeded(errs));
+                }
+            }
+        }
+    }
+    drop(candidates);
 
-            return Ok(data);
+    // the retries are numbered after the candidates, and send the request of the candidates
+    let accept = |_: &T| Ok(());
+    let fallback = generate_validated(
+        client,
+        model_name,
+        prompt,
+        Some(chat_req),
+        valid_example,
+        retries,
+        options,
+        &accept,
+    );
+    match fallback.await {
+        Ok(result) => Ok(result.value),
+        Err(Error::RetryLimitExceeded(more)) => {
+            errs.extend(more.into_iter().map(|failure| AttemptFailure {
+                attempt: n + failure.attempt,
+                ..failure
+            }));
+            Err(Error::RetryLimitExceeded(errs))
+        }
+        Err(e) => {
+            errs.push(AttemptFailure::new(n + 1, e));
+            Err(Error::RetryLimitExceeded(errs))
         }
     }
+}
+
+/// The tokens a message takes besides its text, for its role and the separators around it.
+const MESSAGE_TOKENS: usize = 4;
 
-    Err(Error::RetryLimitExceeded(errs))
+/// An estimate of the tokens `request` takes: its system prompt and its messages.
+fn request_tokens(request: &ChatRequest) -> usize {
+    let system = request.system.as_deref().map_or(0, estimate_tokens);
+    system + request.messages.iter().map(message_tokens).sum::<usize>()
+}
+
+/// An estimate of the tokens `message` takes, its text and the separators around it.
+fn message_tokens(message: &ChatMessage) -> usize {
+    let text: usize = message
+        .content
+        .texts()
+        .into_iter()
+        .map(estimate_tokens)
+        .sum();
+    MESSAGE_TOKENS + text
+}
+
+/// The request for `prompt`, with the system message asking for the XML of `T` in the schema
+/// style and sentences of `options`, followed by the examples of `options` if any.
+fn schema_request<T: LlmPrompt>(options: &GenerateOptions, prompt: ChatRequest) -> ChatRequest {
+    let message = options.in_schema_text(|| {
+        let text = SchemaText::current();
+        let schema = document_schema::<T>(options.schema_style, options.schema_max_depth);
+        let mut message = fill(text.request, &[document_root::<T>(), &schema]);
+        let variants = match options.variant_examples {
+            true => T::example_variants(EXAMPLE_DEPTH),
+            false => Vec::new(),
+        };
+        let examples = options.few_shot.iter().chain(&variants);
+        for (number, example) in examples.enumerate() {
+            if number == 0 {
+                message.push('\n');
+                message.push_str(text.examples);
+            }
+            message.push('\n');
+            message.push_str(&fill(text.example, &[&(number + 1).to_string(), example]));
+        }
+        let built_in = match options.built_in_bad_examples {
+            true => BadExample::BUILT_IN,
+            false => &[],
+        };
+        for (number, bad_example) in options.bad_examples.iter().chain(built_in).enumerate() {
+            if number == 0 {
+                message.push('\n');
+                message.push_str(text.bad_examples);
+            }
+            message.push('\n');
+            message.push_str(&bad_example.render(text));
+        }
+        message
+    });
+    prompt.append_message(ChatMessage::system(message))
+}
+
+/// The request of the first attempt in tool mode: `prompt` with a tool taking the value of `T`
+/// and a system message asking to call it, and whether the value is wrapped in a `value`
+/// parameter, as the parameters of a tool are an object.
+#[cfg(feature = "json")]
+fn tool_request<T: LlmPrompt>(
+    options: &GenerateOptions,
+    prompt: &ChatRequest,
+) -> (ChatRequest, bool) {
+    let name = document_root::<T>();
+    let mut schema = T::json_schema();
+    let wrapped = schema.get("type").and_then(Value::as_str) != Some("object");
+    if wrapped {
+        let defs = schema
+            .as_object_mut()
+            .and_then(|object| object.remove("$defs"));
+        // a reference to the document now points to the `value` inside it
+        refer_to_value(&mut schema);
+        schema = json!({
+            "type": "object",
+            "properties": { "value": schema },
+            "required": ["value"],
+            "additionalProperties": false,
+        });
+        if let Some(defs) = defs {
+            schema["$defs"] = defs;
+        }
+    }
+    let (message, description) = options.in_schema_text(|| {
+        let text = SchemaText::current();
+        (fill(text.tool_request, &[name]), text.tool_description)
+    });
+    let tool = Tool::new(name)
+        .with_description(description)
+        .with_schema(schema);
+    let request = prompt
+        .clone()
+        .append_tool(tool)
+        .append_message(ChatMessage::system(message));
+    (request, wrapped)
+}
+
+/// Points the `{"$ref": "#"}` references in `schema` to `#/properties/value`.
+#[cfg(feature = "json")]
+fn refer_to_value(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match key == "$ref" && *value == "#" {
+                    true => *value = Value::from("#/properties/value"),
+                    false => refer_to_value(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(refer_to_value),
+        _ => {}
+    }
+}
+
+/// The messages asking the model to correct the response that failed with `failure`, along
+/// with the schema and the valid example if `resend` is set.
+fn correction_messages<T: LlmPrompt>(
+    options: &GenerateOptions,
+    failure: &CastFailure,
+    resend: bool,
+    valid_example: &str,
+) -> Vec<ChatMessage> {
+    let (error, xml) = match failure {
+        CastFailure::Extraction(reason) => (reason.to_string(), None),
+        // without the location, which the message points at on a line of its own
+        CastFailure::Deserialization(xml, e, _) => (e.error.to_string(), Some(options.quote(xml))),
+        CastFailure::Validation(xml, message) => (message.clone(), Some(options.quote(xml))),
+    };
+    let (path, position) = match failure {
+        CastFailure::Deserialization(_, e, _) => (&e.path[..], e.position),
+        _ => (&[][..], None),
+    };
+    let example = resend.then(|| options.quote(valid_example));
+    let schema = resend.then(|| {
+        options.in_schema_text(|| {
+            document_schema::<T>(options.schema_style, options.schema_max_depth).into_owned()
+        })
+    });
+    let bad_example = BadExample::matching(failure).filter(|_| options.built_in_bad_examples);
+    let correction = Correction {
+        error: &error,
+        path,
+        position,
+        xml: xml.as_deref(),
+        schema: schema.as_deref(),
+        example: example.as_deref(),
+        bad_example,
+    };
+    let role = options.correction_role;
+    if let Some(template) = &options.correction_message {
+        return vec![role.message((template.0)(&correction))];
+    }
+
+    let mut message = match correction.xml {
+        Some(xml) => format!(
+            "The last time you responded, the XML content was: {}\nThe error was: {}",
+            xml, correction.error
+        ),
+        None => format!("The error was: {}", correction.error),
+    };
+    if !correction.path.is_empty() {
+        let elements = correction
+            .path
+            .iter()
+            .map(|element| format!("<{}>", element))
+            .collect::<Vec<_>>()
+            .join(" → ");
+        message.push_str(&format!("\nThe problem is inside {}", elements));
+        if let Some((line, column)) = correction.position {
+            message.push_str(&format!(
+                ", near line {}, column {} of your XML",
+                line, column
+            ));
+        }
+        message.push('.');
+    }
+    let instruction = match failure {
+        CastFailure::Validation(..) => Cow::Borrowed("Please correct the values accordingly."),
+        CastFailure::Extraction(ExtractionError::NoXmlFound { .. }) => Cow::Borrowed(
+            "Your response must be the XML document itself, in the required format, rather than prose about it.",
+        ),
+        CastFailure::Extraction(ExtractionError::RootNotFound { expected }) => Cow::Owned(format!(
+            "Write the whole document inside the <{}> root element, as the required format shows.",
+            expected
+        )),
+        CastFailure::Extraction(ExtractionError::VariantNotFound { .. }) => Cow::Borrowed(
+            "Write exactly one of the variant elements of the required format as the whole document.",
+        ),
+        CastFailure::Extraction(ExtractionError::UnclosedRoot { root, .. }) => Cow::Owned(format!(
+            "Your response stopped before the document was finished. Write the complete document, ending with </{}>, and keep the values short if needed.",
+            root
+        )),
+        _ => Cow::Borrowed("Please ensure your response strictly follows the required XML format."),
+    };
+    message.push('\n');
+    message.push_str(&instruction);
+    if let Some(bad_example) = correction.bad_example {
+        message.push('\n');
+        message.push_str(&bad_example.render(SchemaText::ENGLISH));
+    }
+    if let Some(schema) = correction.schema {
+        message.push_str(&format!("\nThe format body is: {}", schema));
+    }
+    let mut messages = vec![role.message(message)];
+    if let Some(example) = correction.example {
+        messages.push(role.message(format!(
+            "Here is a valid example for your reference:\n{}",
+            example
+        )));
+    }
+    messages
+}
+
+/// Casts `text`, the text of `res`, checks the value with `validator`, and reports the attempt
+/// to the `on_attempt` callback, with `trimmed` corrections left out of its request.
+fn cast_response<T, F>(
+    options: &GenerateOptions,
+    number: usize,
+    trimmed: usize,
+    text: &str,
+    res: &BackendResponse,
+    validator: &F,
+) -> (
+    std::result::Result<(T, String), CastFailure>,
+    ControlFlow<()>,
+)
+where
+    T: DeserializeOwned + LlmPrompt,
+    F: Fn(&T) -> std::result::Result<(), String>,
+{
+    let truncated = matches!(res.stop_reason, Some(StopReason::MaxTokens(_)));
+    let result = validated(
+        cast_text(text, options.cast_options(), truncated),
+        validator,
+    );
+    let flow = observe_cast(options, number, trimmed, text, &result);
+    (result, flow)
+}
+
+/// `result`, or its failure if `validator` rejects the value cast.
+fn validated<T, F>(
+    result: std::result::Result<(T, String), CastFailure>,
+    validator: &F,
+) -> std::result::Result<(T, String), CastFailure>
+where
+    F: Fn(&T) -> std::result::Result<(), String>,
+{
+    result.and_then(|(data, xml)| match validator(&data) {
+        Ok(()) => Ok((data, xml)),
+        Err(message) => Err(CastFailure::Validation(xml, message)),
+    })
+}
+
+/// Runs the `on_attempt` callback for attempt `number`, whose response `text` cast to `result`.
+fn observe_cast<T>(
+    options: &GenerateOptions,
+    number: usize,
+    trimmed: usize,
+    text: &str,
+    result: &std::result::Result<(T, String), CastFailure>,
+) -> ControlFlow<()> {
+    let (xml, outcome) = match result {
+        Ok((_, xml)) => (Some(xml.as_str()), AttemptOutcome::Ok),
+        Err(CastFailure::Extraction(reason)) => (None, AttemptOutcome::Extraction(reason)),
+        Err(CastFailure::Deserialization(xml, e, _)) => {
+            (Some(xml.as_str()), AttemptOutcome::Deserialization(e))
+        }
+        Err(CastFailure::Validation(xml, message)) => {
+            (Some(xml.as_str()), AttemptOutcome::Validation(message))
+        }
+    };
+    options.observe(&Attempt {
+        number,
+        text,
+        xml,
+        outcome,
+        trimmed,
+    })
 }
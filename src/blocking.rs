@@ -0,0 +1,86 @@
+//! Blocking versions of the generation functions, for callers without an async runtime.
+//!
+//! Each call runs the async function to completion on a current-thread tokio runtime of its own,
+//! like `reqwest::blocking`. Enabled by the `blocking` feature.
+
+use crate::{GenerateOptions, IntoPrompt, LlmBackend, LlmPrompt, Result};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+
+/// Blocking version of [`generate_as`](crate::generate_as).
+///
+/// # Panics
+///
+/// Panics when called from within an async runtime, where it would block the runtime's thread;
+/// await [`generate_as`](crate::generate_as) there instead.
+pub fn generate_as_blocking<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+) -> Result<T> {
+    block_on(crate::generate_as(
+        client,
+        model_name,
+        prompt,
+        valid_example,
+    ))
+}
+
+/// Blocking version of [`generate_as_with_retries`](crate::generate_as_with_retries).
+///
+/// # Panics
+///
+/// Panics when called from within an async runtime, like [`generate_as_blocking`].
+pub fn generate_as_with_retries_blocking<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    retries: usize,
+) -> Result<T> {
+    block_on(crate::generate_as_with_retries(
+        client,
+        model_name,
+        prompt,
+        valid_example,
+        retries,
+    ))
+}
+
+/// Blocking version of [`generate_as_with_options`](crate::generate_as_with_options).
+///
+/// # Panics
+///
+/// Panics when called from within an async runtime, like [`generate_as_blocking`].
+pub fn generate_as_with_options_blocking<T: DeserializeOwned + LlmPrompt>(
+    client: &impl LlmBackend,
+    model_name: &str,
+    prompt: impl IntoPrompt,
+    valid_example: &str,
+    retries: usize,
+    options: impl Into<GenerateOptions>,
+) -> Result<T> {
+    block_on(crate::generate_as_with_options(
+        client,
+        model_name,
+        prompt,
+        valid_example,
+        retries,
+        options,
+    ))
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        panic!(
+            "llm_xml_caster's blocking functions can't be called from within an async runtime, \
+             await the async version instead"
+        );
+    }
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build the tokio runtime of llm_xml_caster's blocking functions")
+        .block_on(future)
+}
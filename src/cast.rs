@@ -0,0 +1,736 @@
+#[cfg(feature = "json")]
+use crate::json_document::json_document;
+use crate::repair::{complete_truncated, repair_xml};
+use crate::r#type::xml::XmlResponse;
+use crate::r#type::{ContainerMistake, take_container_mistake};
+use crate::{DeserializationError, Error, ExtractionError, LlmPrompt, Result, SchemaStyle};
+use quick_xml::events::Event;
+use quick_xml::{DeError, Reader, de::Deserializer};
+use serde::de::DeserializeOwned;
+use serde_path_to_error::{Segment, Track};
+use std::borrow::Cow;
+use std::io::{self, BufRead, Cursor, Read};
+use std::sync::Arc;
+
+/// The tags of the reasoning blocks removed from a response before its XML is looked for.
+pub const REASONING_TAGS: &[&str] = &["think", "thinking", "reasoning"];
+
+/// The root element of a document of a type without one of its own, e.g. a `Vec<T>`.
+pub(crate) const RESPONSE_ROOT: &str = "response";
+
+/// Which XML document to use when a response holds several, e.g. a "wrong" and a "corrected"
+/// version of the answer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Extraction {
+    /// The first complete document.
+    First,
+    /// The last complete document.
+    Last,
+    /// Each complete document in turn, until one deserializes.
+    #[default]
+    FirstThatParses,
+}
+
+/// How the XML of a response is found and read.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CastOptions {
+    /// Which document to use when the response holds several.
+    pub extraction: Extraction,
+    /// Fix an unescaped `&` or `<` and close tags in the wrong case when the XML doesn't parse,
+    /// before giving up on it. Off by default.
+    pub repair: bool,
+    /// Close the elements left open by a response cut off at the token limit, so that what was
+    /// written still deserializes and the missing fields fall back to their `Option` or default.
+    /// Off by default, since the result is incomplete data.
+    ///
+    /// [`generate_as_with_options`](crate::generate_as_with_options) only completes responses
+    /// that stopped at the token limit; [`cast_from_str_with`] can't tell, so it completes any
+    /// document left unfinished at the end of the text.
+    pub complete_truncated: bool,
+    /// Read `T` from a JSON object in the response when it holds no XML for `T` at all, for
+    /// models that answer in JSON despite the instructions. Off by default.
+    ///
+    /// The object is deserialized with `serde_json`, so its keys have to match the field names
+    /// of `T`, which works best with the default field names. A response with XML for `T` is
+    /// always read from the XML, and an object that doesn't match `T` falls through to the
+    /// extraction error, i.e. to a corrective retry.
+    #[cfg(feature = "json")]
+    pub json_fallback: bool,
+}
+
+/// Casts the raw text of an LLM response into `T`, without talking to an LLM.
+///
+/// The XML of `T` is located in `text` the same way [`generate_as`](crate::generate_as) does,
+/// so any prose or markdown around it is ignored, and then deserialized. Reasoning blocks such
+/// as `<think>...</think>` are removed first, since their drafts often hold the same root tag.
+/// When there are several documents, the first one that deserializes is used.
+///
+/// A type without a root element of its own, such as a `Vec<T>` or a `bool`, is read from
+/// inside a `<response>` element, e.g. `<response><item>..</item></response>`.
+///
+/// # Errors
+///
+/// Returns `Error::XmlExtraction` if `text` holds no XML for `T`, saying whether it holds no
+/// XML at all, other elements, or a document that is never closed, and
+/// `Error::XmlDeserialization` if the XML doesn't match `T`, with the path of the element the
+/// error occurred in and its line and column in the XML.
+pub fn cast_from_str<T: DeserializeOwned + LlmPrompt>(text: &str) -> Result<T> {
+    cast_from_str_with(text, CastOptions::default())
+}
+
+/// Like [`cast_from_str`], with the extraction and repair settings of `options`.
+pub fn cast_from_str_with<T: DeserializeOwned + LlmPrompt>(
+    text: &str,
+    options: CastOptions,
+) -> Result<T> {
+    cast_text(text, options, options.complete_truncated)
+        .map(|(data, _)| data)
+        .map_err(Error::from)
+}
+
+/// Casts `value`, a value of `T` in its JSON form such as the arguments of a tool call, e.g.
+/// `{"name": "Ada", "tags": ["a", "b"]}`.
+///
+/// `value` follows [`LlmPrompt::json_schema`] or [`LlmPrompt::strict_json_schema`]. It is
+/// written as the XML document of `T` and read like a response, so the lenient parsers of the
+/// fields and their `#[prompt(...)]` constraints apply, as they do to a response in XML.
+///
+/// # Errors
+///
+/// Returns `Error::XmlDeserialization` if `value` doesn't match `T`, with the path of the element
+/// the error occurred in.
+#[cfg(feature = "json")]
+pub fn cast_from_json<T: DeserializeOwned + LlmPrompt>(value: &serde_json::Value) -> Result<T> {
+    cast_json(value).map(|(data, _)| data).map_err(Error::from)
+}
+
+/// Casts `value` like [`cast_from_json`], and returns the XML it was written as.
+#[cfg(feature = "json")]
+pub(crate) fn cast_json<T: DeserializeOwned + LlmPrompt>(
+    value: &serde_json::Value,
+) -> std::result::Result<(T, String), CastFailure> {
+    let xml = json_document::<T>(value);
+    match read_document(&xml) {
+        Ok(data) => Ok((data, xml)),
+        Err((e, mistake)) => Err(CastFailure::Deserialization(xml, e, mistake)),
+    }
+}
+
+/// Casts a document of `T` read from `reader`, e.g. a large response saved to a file, which is
+/// parsed as it is read rather than held in memory whole.
+///
+/// `reader` holds the document alone, as [`extract_candidates`] returns it, with the
+/// `<response>` root of a type without one of its own; [`extract_from_reader`] reads a whole
+/// response. The XML is read as it is written, without repairs or completing a document cut off
+/// at its end.
+///
+/// # Errors
+///
+/// Returns `Error::XmlDeserialization` if the XML doesn't match `T` or `reader` fails, with the
+/// path of the element the error occurred in. Its line and column are unknown, since the text
+/// before it is gone.
+pub fn cast_from_reader<T: DeserializeOwned + LlmPrompt, R: BufRead>(reader: R) -> Result<T> {
+    let mut de = Deserializer::from_reader(reader);
+    let mut track = Track::new();
+    let tracked = serde_path_to_error::Deserializer::new(&mut de, &mut track);
+    deserialize_document(tracked).map_err(|error| {
+        Error::XmlDeserialization(DeserializationError {
+            error,
+            path: element_path::<T>(&track.path()),
+            position: None,
+        })
+    })
+}
+
+/// Like [`cast_from_reader`], for a whole response read from `reader`: the text before the
+/// document of `T` is skipped as it is read, along with reasoning blocks such as
+/// `<think>...</think>`, and only the first document is read.
+///
+/// Unlike [`cast_from_str`], reasoning whose opening tag is missing isn't recognized, as the text
+/// before its closing tag is gone by the time it is read.
+///
+/// # Errors
+///
+/// Returns `Error::XmlExtraction` if `reader` holds no start tag of a document of `T`, and the
+/// errors of [`cast_from_reader`] otherwise.
+pub fn extract_from_reader<T: DeserializeOwned + LlmPrompt, R: BufRead>(
+    mut reader: R,
+) -> Result<T> {
+    match skip_to_document::<T>(&mut reader) {
+        Ok(Ok(start)) => cast_from_reader(Cursor::new(start).chain(reader)),
+        Ok(Err(missing)) => Err(Error::XmlExtraction(missing)),
+        Err(e) => Err(DeError::InvalidXml(quick_xml::Error::Io(Arc::new(e))).into()),
+    }
+}
+
+/// Reads `reader` up to the first start tag of a document of `T` outside reasoning blocks and
+/// backticks, and returns the bytes read from that tag on, or why there is none.
+///
+/// Only the bytes that may still begin a tag are kept between reads.
+fn skip_to_document<T: LlmPrompt>(
+    reader: &mut impl BufRead,
+) -> io::Result<std::result::Result<Vec<u8>, ExtractionError>> {
+    let root_name = [document_root::<T>()];
+    let names = if T::IS_ENUM {
+        T::variant_names()
+    } else {
+        &root_name[..]
+    };
+    // enough to tell a start tag of any of them, `<` and the byte after the name included
+    let longest = names
+        .iter()
+        .chain(REASONING_TAGS)
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+
+    let mut window = Vec::new();
+    let mut holds_xml = false;
+    // the end tag of the reasoning block being skipped
+    let mut reasoning: Option<String> = None;
+    // whether the byte before the window is a backtick
+    let mut after_backtick = false;
+    loop {
+        let chunk = reader.fill_buf()?;
+        let at_end = chunk.is_empty();
+        window.extend_from_slice(chunk);
+        let read = chunk.len();
+        reader.consume(read);
+
+        // the bytes before `done` can't begin a tag anymore
+        let mut done = 0;
+        loop {
+            if let Some(close) = &reasoning {
+                match find_bytes(&window[done..], close.as_bytes()) {
+                    Some(offset) => {
+                        done += offset + close.len();
+                        reasoning = None;
+                    }
+                    None => {
+                        done = done.max(window.len().saturating_sub(close.len() - 1));
+                        break;
+                    }
+                }
+                continue;
+            }
+            let Some(offset) = window[done..].iter().position(|&b| b == b'<') else {
+                done = window.len();
+                break;
+            };
+            let start = done + offset;
+            let rest = &window[start..];
+            if rest.len() < longest && !at_end {
+                done = start;
+                break;
+            }
+            let quoted = match start {
+                0 => after_backtick,
+                _ => window[start - 1] == b'`',
+            };
+            if !quoted && names.iter().any(|name| is_start_tag_bytes(rest, name)) {
+                window.drain(..start);
+                return Ok(Ok(window));
+            }
+            match REASONING_TAGS
+                .iter()
+                .find(|tag| rest.starts_with(format!("<{}>", tag).as_bytes()))
+            {
+                Some(tag) => {
+                    reasoning = Some(format!("</{}>", tag));
+                    done = start + tag.len() + 2;
+                }
+                None => {
+                    // a `<` followed by a name, so that "7 < 10" in the prose isn't taken for XML
+                    holds_xml |= rest
+                        .get(1)
+                        .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'_' || !b.is_ascii());
+                    done = start + 1;
+                }
+            }
+        }
+        if at_end {
+            return Ok(Err(no_document::<T>(holds_xml)));
+        }
+        if done > 0 {
+            after_backtick = window[done - 1] == b'`';
+            window.drain(..done);
+        }
+    }
+}
+
+/// The offset of the first `needle` in `haystack`.
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Why a response couldn't be cast, keeping the XML that failed to deserialize for the retry
+/// message.
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub(crate) enum CastFailure {
+    Extraction(ExtractionError),
+    /// The XML that didn't deserialize, its error, and the mistake a container of it reported,
+    /// if any.
+    Deserialization(String, DeserializationError, Option<ContainerMistake>),
+    /// The value cast from the XML was rejected by a validator, with the given message.
+    Validation(String, String),
+}
+
+impl CastFailure {
+    /// Whether the response missed the structure of the type altogether, rather than a value:
+    /// no XML for it was found, or a field or variant is missing or unknown.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    pub(crate) fn is_structural(&self) -> bool {
+        match self {
+            CastFailure::Extraction(_) => true,
+            CastFailure::Deserialization(_, e, _) => {
+                let message = e.error.to_string();
+                ["missing field", "unknown field", "unknown variant"]
+                    .iter()
+                    .any(|structural| message.contains(structural))
+            }
+            CastFailure::Validation(..) => false,
+        }
+    }
+}
+
+impl From<CastFailure> for Error {
+    fn from(failure: CastFailure) -> Self {
+        match failure {
+            CastFailure::Extraction(reason) => Error::XmlExtraction(reason),
+            CastFailure::Deserialization(_, e, _) => Error::XmlDeserialization(e),
+            CastFailure::Validation(_, message) => Error::Validation(message),
+        }
+    }
+}
+
+/// Casts a response, completing a document cut off at its end when `truncated` is set along with
+/// `options.complete_truncated`, and returns the XML (or JSON object) the value was read from.
+pub(crate) fn cast_text<T: DeserializeOwned + LlmPrompt>(
+    text: &str,
+    options: CastOptions,
+    truncated: bool,
+) -> std::result::Result<(T, String), CastFailure> {
+    let text = strip_reasoning(text, REASONING_TAGS);
+    let spans = candidate_spans::<T>(&text);
+    let tail_start = spans.last().map_or(0, |(_, end)| *end);
+    let candidates: Vec<&str> = spans
+        .into_iter()
+        .map(|(start, end)| &text[start..end])
+        .collect();
+    let candidates: Vec<&str> = match options.extraction {
+        Extraction::First => candidates.into_iter().take(1).collect(),
+        Extraction::Last => candidates.into_iter().last().into_iter().collect(),
+        Extraction::FirstThatParses => candidates,
+    };
+
+    let mut failure = None;
+    for xml_content in candidates {
+        let (e, mistake) = match read_document(xml_content) {
+            Ok(data) => return Ok((data, xml_content.to_string())),
+            Err(e) => e,
+        };
+        // a failed repair still reports the error of the XML as the LLM wrote it
+        if options.repair
+            && let Some(repaired) = repair_xml(xml_content)
+            && let Ok(data) = read_document(&repaired)
+        {
+            return Ok((data, repaired));
+        }
+        // report the last document, which is usually the model's final answer
+        failure = Some(CastFailure::Deserialization(
+            xml_content.to_string(),
+            e,
+            mistake,
+        ));
+    }
+
+    if options.complete_truncated
+        && truncated
+        && let Some(completed) = truncated_document::<T>(&text[tail_start..])
+    {
+        match read_document(&completed) {
+            Ok(data) => return Ok((data, completed)),
+            // only the completed document was found, so its error is the one to report
+            Err((e, mistake)) if failure.is_none() => {
+                failure = Some(CastFailure::Deserialization(completed, e, mistake));
+            }
+            Err(_) => {}
+        }
+    }
+    #[cfg(feature = "json")]
+    if options.json_fallback
+        && failure.is_none()
+        && let Some(found) = json_object(&text)
+    {
+        return Ok(found);
+    }
+    Err(failure.unwrap_or_else(|| CastFailure::Extraction(missing_xml::<T>(&text))))
+}
+
+/// The first JSON object in `text` that deserializes as `T`, along with its JSON.
+#[cfg(feature = "json")]
+fn json_object<T: DeserializeOwned>(text: &str) -> Option<(T, String)> {
+    let mut from = 0;
+    while let Some(offset) = text[from..].find('{') {
+        let start = from + offset;
+        let mut values =
+            serde_json::Deserializer::from_str(&text[start..]).into_iter::<serde_json::Value>();
+        match values.next() {
+            Some(Ok(value)) => {
+                let end = start + values.byte_offset();
+                if let Ok(data) = T::deserialize(value) {
+                    return Some((data, text[start..end].to_string()));
+                }
+                // an object nested in one that doesn't match isn't tried on its own
+                from = end;
+            }
+            _ => from = start + 1,
+        }
+    }
+    None
+}
+
+/// The root element of a document of `T`: its own, or `<response>` for a type without one.
+pub(crate) fn document_root<T: LlmPrompt>() -> &'static str {
+    match T::HAS_ROOT {
+        true => T::root_name(),
+        false => RESPONSE_ROOT,
+    }
+}
+
+/// The schema of a document of `T` in `style`, down to `max_depth` if any, with the
+/// `<response>` root of a type without one of its own.
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub(crate) fn document_schema<T: LlmPrompt>(
+    style: SchemaStyle,
+    max_depth: Option<usize>,
+) -> Cow<'static, str> {
+    let item = match (style, max_depth) {
+        (SchemaStyle::Full, None) if T::HAS_ROOT => return Cow::Borrowed(T::get_prompt_schema()),
+        (SchemaStyle::Full, None) => Cow::Borrowed(T::get_item_schema()),
+        _ => Cow::Owned(T::schema_node().render_document(style, max_depth, !T::HAS_ROOT)),
+    };
+    if T::HAS_ROOT {
+        return item;
+    }
+    Cow::Owned(format!("<{root}>{}</{root}>", item, root = RESPONSE_ROOT))
+}
+
+/// Deserializes a document of `T`, reading the content of a `<response>` root with the crate's
+/// container and primitive grammar, and tracking the element an error occurs in and the mistake
+/// a container reported.
+fn read_document<T: DeserializeOwned + LlmPrompt>(
+    xml: &str,
+) -> std::result::Result<T, (DeserializationError, Option<ContainerMistake>)> {
+    let mut de = Deserializer::from_str(xml);
+    let mut track = Track::new();
+    let tracked = serde_path_to_error::Deserializer::new(&mut de, &mut track);
+    // left over from a document that failed to read through another function
+    take_container_mistake();
+    deserialize_document(tracked).map_err(|error| {
+        let reader = de.get_ref().get_ref();
+        let path = element_path::<T>(&track.path());
+        let offset = match error {
+            DeError::InvalidXml(_) => usize::try_from(reader.error_position()).ok(),
+            // the parser reads ahead, so it stopped past the element the error occurred in
+            _ => usize::try_from(reader.buffer_position())
+                .ok()
+                .and_then(|stop| element_start(xml, &path, stop)),
+        };
+        let e = DeserializationError {
+            position: offset
+                .filter(|&offset| xml.is_char_boundary(offset))
+                .map(|offset| line_column(&xml[..offset])),
+            path,
+            error,
+        };
+        (e, take_container_mistake())
+    })
+}
+
+/// Deserializes a document of `T` from `de`, reading the content of a `<response>` root with the
+/// crate's container and primitive grammar.
+fn deserialize_document<'de, T: DeserializeOwned + LlmPrompt, D: serde::Deserializer<'de>>(
+    de: D,
+) -> std::result::Result<T, D::Error> {
+    match T::HAS_ROOT {
+        true => T::deserialize(de),
+        false => T::deserialize(XmlResponse::element(de)),
+    }
+}
+
+/// The names of the elements along `path`, from the root of the document of `T`.
+fn element_path<T: LlmPrompt>(path: &serde_path_to_error::Path) -> Vec<String> {
+    // an enum is written as its variant element, which the path starts with
+    let root = (!T::IS_ENUM).then(|| document_root::<T>().to_string());
+    let elements = path.iter().filter_map(|segment| match segment {
+        // `$value` and `$text` are the content of the element before them, not elements
+        Segment::Map { key } if !key.starts_with('$') => Some(key.clone()),
+        Segment::Enum { variant } => Some(variant.clone()),
+        _ => None,
+    });
+    root.into_iter().chain(elements).collect()
+}
+
+/// The offset of the start tag of the last element along `path` that starts before `stop`.
+fn element_start(xml: &str, path: &[String], stop: usize) -> Option<usize> {
+    let mut reader = Reader::from_str(xml);
+    let mut open: Vec<Vec<u8>> = Vec::new();
+    let mut found = None;
+    loop {
+        let start = usize::try_from(reader.buffer_position()).ok()?;
+        if start >= stop {
+            return found;
+        }
+        let (name, empty) = match reader.read_event() {
+            Ok(Event::Start(tag)) => (tag.name().as_ref().to_vec(), false),
+            Ok(Event::Empty(tag)) => (tag.name().as_ref().to_vec(), true),
+            Ok(Event::End(_)) => {
+                open.pop();
+                continue;
+            }
+            Ok(Event::Eof) | Err(_) => return found,
+            Ok(_) => continue,
+        };
+        open.push(name);
+        let on_path = open.len() == path.len()
+            && open
+                .iter()
+                .zip(path)
+                .all(|(name, element)| name == element.as_bytes());
+        if on_path {
+            found = Some(start);
+        }
+        if empty {
+            open.pop();
+        }
+    }
+}
+
+/// The line and column, counting from 1, of the end of `before`.
+fn line_column(before: &str) -> (usize, usize) {
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// The document of `T` that `tail` starts but never finishes, with its open elements closed.
+fn truncated_document<T: LlmPrompt>(tail: &str) -> Option<String> {
+    let (start, _) = document_start::<T>(tail)?;
+    complete_truncated(&tail[start..])
+}
+
+/// The offset and name of the first start tag in `text` that begins a document of `T`: its
+/// root, or one of its variants for an enum.
+fn document_start<T: LlmPrompt>(text: &str) -> Option<(usize, &'static str)> {
+    let root_name = [document_root::<T>()];
+    let names = if T::IS_ENUM {
+        T::variant_names()
+    } else {
+        &root_name[..]
+    };
+    text.match_indices('<')
+        .filter(|&(i, _)| !text[..i].ends_with('`'))
+        .find_map(|(i, _)| {
+            names
+                .iter()
+                .find(|name| is_start_tag(&text[i..], &format!("<{}", name)))
+                .map(|name| (i, *name))
+        })
+}
+
+/// The XML documents for `T` in `text`, in order: the `<Root>` elements for structs, the
+/// variant elements for enums, which are written as that element alone, or the `<response>`
+/// elements for containers and primitives.
+///
+/// An element counts once its start and end tags balance, so an `<?xml ...?>` declaration, a
+/// DOCTYPE, a tag name mentioned in the prose or a document cut off at the end is passed over.
+/// Without any balanced element, a struct falls back to the span from the first `<Root>` to the
+/// last `</Root>`, so that the parser can report what is wrong with it.
+pub fn extract_candidates<T: LlmPrompt>(text: &str) -> Vec<&str> {
+    candidate_spans::<T>(text)
+        .into_iter()
+        .map(|(start, end)| &text[start..end])
+        .collect()
+}
+
+/// The spans of the documents listed by [`extract_candidates`].
+fn candidate_spans<T: LlmPrompt>(text: &str) -> Vec<(usize, usize)> {
+    let spans = if T::IS_ENUM {
+        match T::variant_names() {
+            // a hand-written impl without variant names, so take everything between the brackets
+            [] => first_element_start(text)
+                .zip(text.rfind('>').map(|end| end + 1))
+                .into_iter()
+                .collect(),
+            names => find_elements(text, names),
+        }
+    } else {
+        let root_name = document_root::<T>();
+        let spans = find_elements(text, &[root_name]);
+        if spans.is_empty() {
+            let start_tag = format!("<{}>", root_name);
+            let end_tag = format!("</{}>", root_name);
+            text.find(&start_tag)
+                .zip(text.rfind(&end_tag).map(|end| end + end_tag.len()))
+                .into_iter()
+                .collect()
+        } else {
+            spans
+        }
+    };
+
+    spans
+        .into_iter()
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+/// Removes the reasoning blocks of `tags`, e.g. `<think>...</think>`, from a response.
+///
+/// Reasoning models often leave out the opening tag, so everything before a lone closing tag is
+/// reasoning too. An opening tag that is never closed is dropped by itself, keeping the text
+/// after it, where the answer may still follow.
+pub fn strip_reasoning<'a>(text: &'a str, tags: &[&str]) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    for tag in tags {
+        let open = format!("<{}>", tag);
+        let close = format!("</{}>", tag);
+        loop {
+            let stripped = match (text.find(&open), text.find(&close)) {
+                (Some(start), Some(end)) if start < end => {
+                    format!("{}{}", &text[..start], &text[end + close.len()..])
+                }
+                (_, Some(end)) => text[end + close.len()..].to_string(),
+                (Some(start), None) => format!("{}{}", &text[..start], &text[start + open.len()..]),
+                (None, None) => break,
+            };
+            text = Cow::Owned(stripped);
+        }
+    }
+    text
+}
+
+/// Why `text`, from which no document of `T` was extracted, holds none.
+fn missing_xml<T: LlmPrompt>(text: &str) -> ExtractionError {
+    if let Some((found_at, root)) = document_start::<T>(text) {
+        return ExtractionError::UnclosedRoot {
+            root: root.to_string(),
+            found_at,
+        };
+    }
+    // a `<` followed by a name, so that "7 < 10" in the prose isn't taken for XML
+    let holds_xml = text
+        .match_indices('<')
+        .any(|(i, _)| text[i + 1..].starts_with(|c: char| c.is_alphabetic() || c == '_'));
+    no_document::<T>(holds_xml)
+}
+
+/// Why a response without a start tag of a document of `T` holds none, given whether it
+/// `holds_xml` of other elements.
+fn no_document<T: LlmPrompt>(holds_xml: bool) -> ExtractionError {
+    let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    match (holds_xml, T::IS_ENUM) {
+        (false, true) => ExtractionError::NoXmlFound {
+            expected: names(T::variant_names()),
+        },
+        (false, false) => ExtractionError::NoXmlFound {
+            expected: names(&[document_root::<T>()]),
+        },
+        (true, true) => ExtractionError::VariantNotFound {
+            expected: names(T::variant_names()),
+        },
+        (true, false) => ExtractionError::RootNotFound {
+            expected: document_root::<T>().to_string(),
+        },
+    }
+}
+
+/// The first `<` that opens an element, skipping declarations, DOCTYPEs and comments.
+fn first_element_start(text: &str) -> Option<usize> {
+    text.match_indices('<')
+        .map(|(i, _)| i)
+        .find(|&i| !text[i + 1..].starts_with(['?', '!']))
+}
+
+/// Whether `rest` starts with the start tag `open` (`<name`) of an element named exactly so.
+fn is_start_tag(rest: &str, open: &str) -> bool {
+    rest.strip_prefix(open)
+        .is_some_and(|after| after.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()))
+}
+
+/// Like [`is_start_tag`], for the bytes `rest` and the element `name`.
+fn is_start_tag_bytes(rest: &[u8], name: &str) -> bool {
+    rest.strip_prefix(b"<")
+        .and_then(|rest| rest.strip_prefix(name.as_bytes()))
+        .and_then(|after| after.first())
+        .is_some_and(|&b| b == b'>' || b == b'/' || b.is_ascii_whitespace())
+}
+
+/// The spans of the balanced elements named one of `names` in `text`, in order.
+///
+/// Elements nested in an earlier match are part of it and not listed again. Start tags quoted
+/// in backticks, as in "the `<name>` element", are not taken as an element.
+fn find_elements(text: &str, names: &[&str]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut from = 0;
+    while let Some(offset) = text[from..].find('<') {
+        let start = from + offset;
+        if text[..start].ends_with('`') {
+            from = start + 1;
+            continue;
+        }
+        let element = names
+            .iter()
+            .filter(|name| is_start_tag(&text[start..], &format!("<{}", name)))
+            .find_map(|name| balanced_end(text, start, name));
+        match element {
+            Some(end) => {
+                spans.push((start, end));
+                from = end;
+            }
+            None => from = start + 1,
+        }
+    }
+    spans
+}
+
+/// The end of the element whose start tag is at `start`, found by balancing its start and end
+/// tags while skipping CDATA sections, comments and processing instructions.
+fn balanced_end(text: &str, start: usize, name: &str) -> Option<usize> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut depth = 0usize;
+    let mut i = start;
+    while let Some(offset) = text[i..].find('<') {
+        i += offset;
+        let rest = &text[i..];
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            i += "<![CDATA[".len() + cdata.find("]]>")? + "]]>".len();
+        } else if let Some(comment) = rest.strip_prefix("<!--") {
+            i += "<!--".len() + comment.find("-->")? + "-->".len();
+        } else if let Some(pi) = rest.strip_prefix("<?") {
+            i += "<?".len() + pi.find("?>")? + "?>".len();
+        } else if rest.starts_with(&close) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return Some(i);
+            }
+        } else if is_start_tag(rest, &open) {
+            let tag_end = rest.find('>')?;
+            if !rest[..tag_end].ends_with('/') {
+                depth += 1;
+            } else if depth == 0 {
+                return Some(i + tag_end + 1);
+            }
+            i += tag_end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
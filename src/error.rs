@@ -1,19 +1,567 @@
+#[cfg(feature = "client")]
+use crate::HttpStatusError;
+use quick_xml::DeError;
+use serde::Serialize;
+use serde::ser::{SerializeMap, Serializer};
+use std::fmt;
+use std::time::Duration;
+#[cfg(feature = "client")]
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
+/// The characters of a text an error is serialized with, past which it is cut off.
+const SERIALIZED_TEXT_LIMIT: usize = 1000;
+
 /// Custom error types for the LLM request and deserialization process.
+///
+/// Serializes as an object with the `kind` of the error in snake case, e.g.
+/// `"xml_deserialization"`, its `message`, and the fields of the kind, e.g. the `path` of a
+/// deserialization error or the `failures` of `RetryLimitExceeded`, for structured logs. Texts
+/// are cut off after 1000 characters, so that a huge response doesn't flood the log.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum RequestError {
+    #[cfg(feature = "client")]
     #[error("Failed to send request: {0}")]
     ChatRequest(#[from] genai::Error),
 
-    #[error(
-        "Retry limit exceeded, the following errors occurred when trying to send requests: {0:?}"
-    )]
-    RetryLimitExceeded(Vec<RequestError>),
+    #[cfg(feature = "client")]
+    #[error("Backend request failed: {0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+
+    #[cfg(feature = "client")]
+    #[error("The model returned a response without any text")]
+    EmptyResponse,
+
+    #[cfg(feature = "client")]
+    #[error("The model answered without calling the tool it was given")]
+    MissingToolCall,
+
+    #[cfg(feature = "client")]
+    #[error("The prompt is estimated at {estimated} tokens, over the limit of {limit}")]
+    PromptTooLarge { estimated: usize, limit: usize },
+
+    #[error("Retry limit exceeded after {}", summarize(.0))]
+    RetryLimitExceeded(Vec<AttemptFailure>),
+
+    #[error("Retry limit exceeded, the responses were parsed but failed validation: {0:?}")]
+    ValidationLimitExceeded(Vec<RequestError>),
 
     #[error("XML deserialization error: {0}")]
-    XmlDeserialization(#[from] quick_xml::DeError),
+    XmlDeserialization(#[from] DeserializationError),
+
+    #[error("Validation error: {0}")]
+    Validation(String),
 
     #[error("XML extraction error: {0}")]
-    XmlExtraction(String),
+    XmlExtraction(#[from] ExtractionError),
+
+    #[error("XML serialization error: {0}")]
+    XmlSerialization(String),
+}
+
+impl RequestError {
+    /// Whether the request didn't get a response from the model: the client or the provider
+    /// failed, e.g. with a timeout or a rate limit. A `RetryLimitExceeded` is if any of its
+    /// attempts was.
+    pub fn is_transport(&self) -> bool {
+        match self {
+            #[cfg(feature = "client")]
+            RequestError::ChatRequest(_) | RequestError::Backend(_) => true,
+            RequestError::RetryLimitExceeded(failures) => {
+                failures.iter().any(|failure| failure.error.is_transport())
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the response held no XML for the type, or XML that doesn't match it. A
+    /// `RetryLimitExceeded` is if all of its attempts were, i.e. the model kept writing
+    /// something the type can't represent.
+    pub fn is_parse(&self) -> bool {
+        match self {
+            RequestError::XmlExtraction(_) | RequestError::XmlDeserialization(_) => true,
+            RequestError::RetryLimitExceeded(failures) => {
+                !failures.is_empty() && failures.iter().all(|failure| failure.error.is_parse())
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether sending the same request again later may well succeed: a request that timed
+    /// out, never connected, was rate limited (429) or failed on the provider's side (408 or
+    /// 5xx), or a response without any text. Parse and validation errors are not, as the
+    /// corrective retries already had their go at them. A `RetryLimitExceeded` is if any of its
+    /// attempts failed for such a transport reason.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(feature = "client")]
+            RequestError::ChatRequest(_) | RequestError::Backend(_) => match self.status_code() {
+                Some(status) => status == 408 || status == 429 || status >= 500,
+                // no response at all
+                None => true,
+            },
+            #[cfg(feature = "client")]
+            RequestError::EmptyResponse => true,
+            RequestError::RetryLimitExceeded(failures) => failures
+                .iter()
+                .any(|failure| failure.error.is_transport() && failure.error.is_retryable()),
+            _ => false,
+        }
+    }
+
+    /// The HTTP status of the response the provider failed the request with, if the wrapped
+    /// client error carries one. That of the last failed attempt with one for a
+    /// `RetryLimitExceeded`.
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            #[cfg(feature = "client")]
+            RequestError::ChatRequest(err) => failed_status(err)
+                .map(|(status, _)| status)
+                .or_else(|| http_status(&format!("{:?}", self))),
+            #[cfg(feature = "client")]
+            RequestError::Backend(err) => match err.downcast_ref::<HttpStatusError>() {
+                Some(err) => Some(err.status),
+                None => http_status(&format!("{:?}", self)),
+            },
+            RequestError::RetryLimitExceeded(failures) => failures
+                .iter()
+                .rev()
+                .find_map(|failure| failure.error.status_code()),
+            _ => None,
+        }
+    }
+
+    /// How long the provider asked to wait before sending the request again, from the
+    /// `Retry-After` header of its error response: a number of seconds, or an HTTP date, which
+    /// is zero once past. A `Backend` error carries the header as an [`HttpStatusError`]. That
+    /// of the last failed attempt with one for a `RetryLimitExceeded`.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            #[cfg(feature = "client")]
+            RequestError::ChatRequest(err) => retry_after_wait(failed_status(err)?.1?),
+            #[cfg(feature = "client")]
+            RequestError::Backend(err) => retry_after_wait(
+                err.downcast_ref::<HttpStatusError>()?
+                    .retry_after
+                    .as_deref()?,
+            ),
+            RequestError::RetryLimitExceeded(failures) => failures
+                .iter()
+                .rev()
+                .find_map(|failure| failure.error.retry_after()),
+            _ => None,
+        }
+    }
+
+    /// The failed attempts of a `RetryLimitExceeded`, in order, or none for other errors.
+    pub fn attempt_failures(&self) -> &[AttemptFailure] {
+        match self {
+            RequestError::RetryLimitExceeded(failures) => failures,
+            _ => &[],
+        }
+    }
+
+    /// The error of the last failed attempt of a `RetryLimitExceeded` or
+    /// `ValidationLimitExceeded`, which is usually the closest the model got.
+    pub fn last_error(&self) -> Option<&RequestError> {
+        match self {
+            RequestError::RetryLimitExceeded(failures) => failures.last().map(|f| &f.error),
+            RequestError::ValidationLimitExceeded(errors) => errors.last(),
+            _ => None,
+        }
+    }
+
+    /// The raw texts of the responses of the failed attempts of a `RetryLimitExceeded`, in
+    /// order, leaving out the attempts that got none.
+    pub fn raw_responses(&self) -> Vec<&str> {
+        self.attempt_failures()
+            .iter()
+            .filter_map(|failure| failure.text.as_deref())
+            .collect()
+    }
+}
+
+/// The HTTP status in the debug output of a client error, e.g. `status: 429`, which the errors
+/// of `genai` and most HTTP clients have.
+#[cfg(feature = "client")]
+fn http_status(debug: &str) -> Option<u16> {
+    let text = debug.to_ascii_lowercase();
+    text.match_indices("status").find_map(|(i, _)| {
+        let after = text[i + "status".len()..].trim_start_matches(['"', '\\', ':', '=', ' ', '(']);
+        let digits = after.split(|c: char| !c.is_ascii_digit()).next()?;
+        match digits.parse() {
+            Ok(status) if digits.len() == 3 && (100..600).contains(&status) => Some(status),
+            _ => None,
+        }
+    })
+}
+
+/// The status and the `Retry-After` header of the response `genai` failed a request with, if it
+/// got one.
+#[cfg(feature = "client")]
+fn failed_status(err: &genai::Error) -> Option<(u16, Option<&str>)> {
+    use genai::webc::Error as WebError;
+    match err {
+        genai::Error::WebModelCall { webc_error, .. }
+        | genai::Error::WebAdapterCall { webc_error, .. } => match webc_error {
+            WebError::ResponseFailedStatus {
+                status, headers, ..
+            } => Some((
+                status.as_u16(),
+                headers
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok()),
+            )),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The wait a `Retry-After` header asks for, in seconds or until an HTTP date.
+#[cfg(feature = "client")]
+fn retry_after_wait(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = http_date(value)?;
+    Some(
+        at.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// The time of an HTTP date in any of the forms of RFC 9110: `Sun, 06 Nov 1994 08:49:37 GMT`,
+/// the obsolete `Sunday, 06-Nov-94 08:49:37 GMT` and `Sun Nov  6 08:49:37 1994`. The day comes
+/// before the year in all of them, and the weekday is left unchecked.
+#[cfg(feature = "client")]
+fn http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let (mut day, mut month, mut year, mut time) = (None, None, None, None);
+    for token in value
+        .split([' ', ',', '-'])
+        .filter(|token| !token.is_empty())
+    {
+        if token.contains(':') {
+            time = Some(token);
+        } else if let Some(index) = MONTHS.iter().position(|m| token.eq_ignore_ascii_case(m)) {
+            month = Some(index as u64 + 1);
+        } else if let Ok(number) = token.parse::<u64>() {
+            match day {
+                None => day = Some(number),
+                Some(_) => year = Some(number),
+            }
+        }
+    }
+    let (day, month, mut year) = (day?, month?, year?);
+    // the two-digit years of the obsolete form, read as RFC 9110 asks for recent dates
+    if year < 100 {
+        year += if year < 70 { 2000 } else { 1900 };
+    }
+    let mut clock = time?.split(':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+    if clock.next().is_some() || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month, day)?;
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86400 + hour * 3600 + minute * 60 + second))
+}
+
+/// The days from 1970-01-01 to a date of the Gregorian calendar, or `None` before it.
+#[cfg(feature = "client")]
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    // counted in eras of 400 years from 0000-03-01, so that the leap day ends a year
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let (era, year_of_era) = (year / 400, year % 400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era).checked_sub(719_468)
+}
+
+/// A failed attempt of a generation, as listed by `Error::RetryLimitExceeded`.
+#[derive(Debug)]
+pub struct AttemptFailure {
+    /// The number of the attempt, counting from 1. The retries of
+    /// [`generate_as_best_of`](crate::generate_as_best_of) are numbered after its candidates,
+    /// and a request retried by the transport fails several times within one attempt.
+    pub attempt: usize,
+    /// Why the attempt failed.
+    pub error: RequestError,
+    /// The raw text of the response, cut off after the quote limit, if there was one.
+    pub text: Option<String>,
+    /// The XML that was read from the response but failed to deserialize or validate, cut off
+    /// after the quote limit, if any.
+    pub xml: Option<String>,
+}
+
+impl AttemptFailure {
+    /// The failure of attempt `attempt` with `error`, without a response.
+    pub fn new(attempt: usize, error: RequestError) -> Self {
+        AttemptFailure {
+            attempt,
+            error,
+            text: None,
+            xml: None,
+        }
+    }
+}
+
+/// How the attempts of `failures` failed, e.g. "3 attempts: 1 extraction failure,
+/// 2 deserialization failures; last error: ...".
+fn summarize(failures: &[AttemptFailure]) -> String {
+    let attempts = attempt_count(failures);
+    let mut kinds: Vec<(&str, usize)> = Vec::new();
+    for failure in failures {
+        let kind = match failure.error {
+            RequestError::XmlExtraction(_) => "extraction failure",
+            RequestError::XmlDeserialization(_) => "deserialization failure",
+            RequestError::Validation(_) => "validation failure",
+            #[cfg(feature = "client")]
+            RequestError::EmptyResponse => "empty response",
+            #[cfg(feature = "client")]
+            RequestError::MissingToolCall => "missing tool call",
+            _ => "request failure",
+        };
+        match kinds.iter_mut().find(|(seen, _)| *seen == kind) {
+            Some((_, count)) => *count += 1,
+            None => kinds.push((kind, 1)),
+        }
+    }
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    let mut summary = format!("{} attempt{}", attempts, plural(attempts));
+    let kinds = kinds
+        .iter()
+        .map(|(kind, count)| format!("{} {}{}", count, kind, plural(*count)))
+        .collect::<Vec<_>>();
+    if !kinds.is_empty() {
+        summary.push_str(&format!(": {}", kinds.join(", ")));
+    }
+    if let Some(last) = failures.last() {
+        summary.push_str(&format!("; last error: {}", last.error));
+    }
+    summary
+}
+
+impl From<DeError> for RequestError {
+    fn from(error: DeError) -> Self {
+        RequestError::XmlDeserialization(error.into())
+    }
+}
+
+/// Why no XML document for the type was found in a response.
+///
+/// Serializes as an object with the `reason` in snake case, e.g. `"root_not_found"`, and its
+/// fields.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ExtractionError {
+    /// The response holds no XML element at all. `expected` are the elements a document may
+    /// start with: the root, or the variants of an enum.
+    NoXmlFound { expected: Vec<String> },
+    /// The response holds XML, but not the root element `expected`.
+    RootNotFound { expected: String },
+    /// The response holds XML, but none of the variant elements of the enum, which are
+    /// `expected`, if known.
+    VariantNotFound { expected: Vec<String> },
+    /// The element `root` starts at the byte offset `found_at` of the response, without its
+    /// reasoning blocks, but is never closed, e.g. because the response was cut off.
+    UnclosedRoot { root: String, found_at: usize },
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::NoXmlFound { expected } => match &expected[..] {
+                [] => write!(f, "the response holds no XML"),
+                [root] => write!(
+                    f,
+                    "the response holds no XML, it must be a <{}> element",
+                    root
+                ),
+                names => write!(
+                    f,
+                    "the response holds no XML, it must be one of the elements: {}",
+                    names.join(", ")
+                ),
+            },
+            ExtractionError::RootNotFound { expected } => {
+                write!(f, "cannot find the root {} of the structure", expected)
+            }
+            ExtractionError::VariantNotFound { expected } if expected.is_empty() => {
+                write!(f, "cannot find the XML element of the enum")
+            }
+            ExtractionError::VariantNotFound { expected } => write!(
+                f,
+                "cannot find the XML element of the enum, it must be one of: {}",
+                expected.join(", ")
+            ),
+            ExtractionError::UnclosedRoot { root, found_at } => write!(
+                f,
+                "the element {} starting at byte {} is never closed",
+                root, found_at
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {}
+
+/// An XML document that didn't deserialize, with where in the document the error occurred.
+#[derive(Debug)]
+pub struct DeserializationError {
+    /// The error of the deserializer.
+    pub error: DeError,
+    /// The elements from the root down to the one the error occurred in, e.g.
+    /// `["NestedStruct", "person", "age"]`. A missing field is reported in the element it is
+    /// missing from. Empty when unknown.
+    pub path: Vec<String>,
+    /// The line and column in the document, counting from 1, of the start tag of the element
+    /// at the end of `path`, or of a syntax error, if known.
+    pub position: Option<(usize, usize)>,
+}
+
+impl fmt::Display for DeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        match (self.path.is_empty(), self.position) {
+            (true, None) => Ok(()),
+            (true, Some((line, column))) => write!(f, " (at line {}, column {})", line, column),
+            (false, None) => write!(f, " (at {})", self.path.join(".")),
+            (false, Some((line, column))) => write!(
+                f,
+                " (at {}, line {}, column {})",
+                self.path.join("."),
+                line,
+                column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DeserializationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<DeError> for DeserializationError {
+    fn from(error: DeError) -> Self {
+        DeserializationError {
+            error,
+            path: Vec::new(),
+            position: None,
+        }
+    }
+}
+
+/// The number of attempts `failures` were made in, some of which may have failed several times.
+fn attempt_count(failures: &[AttemptFailure]) -> usize {
+    let mut attempts: Vec<usize> = failures.iter().map(|f| f.attempt).collect();
+    attempts.dedup();
+    attempts.len()
+}
+
+/// `text` cut off after [`SERIALIZED_TEXT_LIMIT`] characters, noting how much was left out.
+fn capped(text: &str) -> std::borrow::Cow<'_, str> {
+    match text.char_indices().nth(SERIALIZED_TEXT_LIMIT) {
+        Some((end, _)) => format!(
+            "{}... ({} more characters)",
+            &text[..end],
+            text[end..].chars().count()
+        )
+        .into(),
+        None => text.into(),
+    }
+}
+
+impl RequestError {
+    /// The name of the variant in snake case, the `kind` the error is serialized with.
+    fn kind(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "client")]
+            RequestError::ChatRequest(_) => "chat_request",
+            #[cfg(feature = "client")]
+            RequestError::Backend(_) => "backend",
+            #[cfg(feature = "client")]
+            RequestError::EmptyResponse => "empty_response",
+            #[cfg(feature = "client")]
+            RequestError::MissingToolCall => "missing_tool_call",
+            #[cfg(feature = "client")]
+            RequestError::PromptTooLarge { .. } => "prompt_too_large",
+            RequestError::RetryLimitExceeded(_) => "retry_limit_exceeded",
+            RequestError::ValidationLimitExceeded(_) => "validation_limit_exceeded",
+            RequestError::XmlDeserialization(_) => "xml_deserialization",
+            RequestError::Validation(_) => "validation",
+            RequestError::XmlExtraction(_) => "xml_extraction",
+            RequestError::XmlSerialization(_) => "xml_serialization",
+        }
+    }
+}
+
+impl Serialize for RequestError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &capped(&self.to_string()))?;
+        match self {
+            #[cfg(feature = "client")]
+            RequestError::ChatRequest(_) | RequestError::Backend(_) => {
+                map.serialize_entry("status_code", &self.status_code())?;
+            }
+            #[cfg(feature = "client")]
+            RequestError::PromptTooLarge { estimated, limit } => {
+                map.serialize_entry("estimated", estimated)?;
+                map.serialize_entry("limit", limit)?;
+            }
+            RequestError::RetryLimitExceeded(failures) => {
+                map.serialize_entry("attempts", &attempt_count(failures))?;
+                map.serialize_entry("failures", failures)?;
+            }
+            RequestError::ValidationLimitExceeded(errors) => {
+                map.serialize_entry("errors", errors)?;
+            }
+            RequestError::XmlDeserialization(e) => {
+                map.serialize_entry("path", &e.path)?;
+                map.serialize_entry("line", &e.position.map(|(line, _)| line))?;
+                map.serialize_entry("column", &e.position.map(|(_, column)| column))?;
+            }
+            RequestError::XmlExtraction(e) => map.serialize_entry("extraction", e)?,
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+impl Serialize for AttemptFailure {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("attempt", &self.attempt)?;
+        map.serialize_entry("error", &self.error)?;
+        map.serialize_entry("text", &self.text.as_deref().map(capped))?;
+        map.serialize_entry("xml", &self.xml.as_deref().map(capped))?;
+        map.end()
+    }
+}
+
+/// Serializes as an object with the `message` of the error, the `path` and the `line` and
+/// `column`, if known.
+impl Serialize for DeserializationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("message", &capped(&self.error.to_string()))?;
+        map.serialize_entry("path", &self.path)?;
+        map.serialize_entry("line", &self.position.map(|(line, _)| line))?;
+        map.serialize_entry("column", &self.position.map(|(_, column)| column))?;
+        map.end()
+    }
 }
@@ -0,0 +1,143 @@
+//! The XML document of a value given in its JSON form, e.g. the arguments of a tool call, so
+//! that it is read by the same parsers as a response written in XML.
+
+use crate::cast::RESPONSE_ROOT;
+use crate::r#type::written_fields;
+use crate::{LlmPrompt, SchemaField, SchemaNode, SchemaVariant};
+use serde_json::Value;
+
+/// The document of `T` holding `value`, a JSON value of the form of [`LlmPrompt::json_schema`]
+/// or [`LlmPrompt::strict_json_schema`].
+///
+/// Nothing is checked here: a value that doesn't fit the schema is written as it is, and the
+/// deserializer reports what is wrong with it.
+pub(crate) fn json_document<T: LlmPrompt>(value: &Value) -> String {
+    let node = T::schema_node();
+    let mut xml = String::new();
+    match (T::IS_ENUM, &node) {
+        (true, SchemaNode::Enum { variants, .. }) => variant(&mut xml, variants, value),
+        _ => {
+            let root = match T::HAS_ROOT {
+                true => T::root_name(),
+                false => RESPONSE_ROOT,
+            };
+            element(&mut xml, root, |xml| content(xml, &node, value));
+        }
+    }
+    xml
+}
+
+/// Writes `<tag>`, the content written by `write`, and `</tag>`.
+fn element(xml: &mut String, tag: &str, write: impl FnOnce(&mut String)) {
+    xml.push('<');
+    xml.push_str(tag);
+    xml.push('>');
+    write(xml);
+    xml.push_str("</");
+    xml.push_str(tag);
+    xml.push('>');
+}
+
+/// Writes the content of an element of `node` holding `value`.
+fn content(xml: &mut String, node: &SchemaNode, value: &Value) {
+    match (node, value) {
+        (_, Value::Null) => {}
+        (SchemaNode::Optional(inner), _) => content(xml, &inner(), value),
+        (SchemaNode::Reference { node, .. }, _) => content(xml, &node(), value),
+        (SchemaNode::Struct { fields, .. } | SchemaNode::Fields { fields, .. }, _) => {
+            self::fields(xml, &written_fields(fields), value)
+        }
+        (SchemaNode::Enum { variants, .. }, _) => variant(xml, variants, value),
+        (SchemaNode::Sequence { item, .. }, Value::Array(values)) => {
+            let item = item();
+            for value in values {
+                element(xml, "item", |xml| item_content(xml, &item, value));
+            }
+        }
+        (SchemaNode::Map { value: of, .. }, Value::Object(entries)) => {
+            let of = of();
+            for (name, value) in entries {
+                element(xml, "entry", |xml| {
+                    element(xml, "key", |xml| escape(xml, name));
+                    element(xml, "value", |xml| item_content(xml, &of, value));
+                });
+            }
+        }
+        // the strict form of a map, an array of `{"key", "value"}` objects
+        (SchemaNode::Map { key, value: of, .. }, Value::Array(entries)) => {
+            let (key, of) = (key(), of());
+            for entry in entries {
+                element(xml, "entry", |xml| {
+                    element(xml, "key", |xml| item_content(xml, &key, &entry["key"]));
+                    element(xml, "value", |xml| item_content(xml, &of, &entry["value"]));
+                });
+            }
+        }
+        _ => text(xml, value),
+    }
+}
+
+/// Writes the content of a value element such as an `<item>`, which wraps a struct in its root
+/// element.
+fn item_content(xml: &mut String, node: &SchemaNode, value: &Value) {
+    match node {
+        SchemaNode::Struct { root, .. } if !value.is_null() => {
+            element(xml, root, |xml| content(xml, node, value))
+        }
+        _ => content(xml, node, value),
+    }
+}
+
+/// Writes the members of the object `value` that are `fields`, leaving out the others and those
+/// that are `null`.
+fn fields(xml: &mut String, fields: &[SchemaField], value: &Value) {
+    let Value::Object(members) = value else {
+        return text(xml, value);
+    };
+    for field in fields {
+        if let Some(value) = members.get(field.tag).filter(|value| !value.is_null()) {
+            let node = (field.node)();
+            element(xml, field.tag, |xml| content(xml, &node, value));
+        }
+    }
+}
+
+/// Writes the variant `value` names, `"Unit"` or `{"Variant": {...}}`, as its element.
+fn variant(xml: &mut String, variants: &[SchemaVariant], value: &Value) {
+    let (tag, fields) = match value {
+        Value::String(tag) => (tag.as_str(), &Value::Null),
+        Value::Object(members) if members.len() == 1 => {
+            let (tag, fields) = members.iter().next().unwrap();
+            (tag.as_str(), fields)
+        }
+        _ => return text(xml, value),
+    };
+    match variants.iter().find(|variant| variant.tag == tag) {
+        Some(variant) => {
+            let written = written_fields(&variant.fields);
+            element(xml, variant.tag, |xml| self::fields(xml, &written, fields));
+        }
+        // left for the deserializer to report as an unknown variant
+        None => element(xml, tag, |_| {}),
+    }
+}
+
+/// Writes `value` as text: a string as it is, anything else as its JSON.
+fn text(xml: &mut String, value: &Value) {
+    match value {
+        Value::String(text) => escape(xml, text),
+        Value::Null => {}
+        value => escape(xml, &value.to_string()),
+    }
+}
+
+fn escape(xml: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            c => xml.push(c),
+        }
+    }
+}
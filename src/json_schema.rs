@@ -0,0 +1,260 @@
+//! JSON Schema of the JSON form of a type, rendered from its schema tree, for providers whose
+//! structured output takes a JSON Schema instead of a prompt.
+
+use crate::r#type::written_fields;
+use crate::{FieldConstraints, LeafKind, SchemaField, SchemaNode, SchemaVariant};
+use serde_json::{Map, Value, json};
+
+impl SchemaNode {
+    /// The JSON Schema of the value, in the form `serde_json` reads it: a struct is an object
+    /// of its fields, an enum one of its variants as `"Unit"` or `{"Variant": {...}}`, a
+    /// sequence an array, a map an object and an `Option` a field that may be left out. The
+    /// names of a text enum are an `enum`, or a `oneOf` of their `const`s with the descriptions
+    /// of their `#[prompt(...)]` if any has one.
+    ///
+    /// Descriptions come from `#[prompt(...)]`, and the field constraints become `enum`,
+    /// `pattern`, `minimum`, `maximum`, `minItems` and `maxItems`. A type without a structure
+    /// of its own, e.g. a hand-written impl, accepts any value described by its prompt schema.
+    /// A recursive type refers to itself through `$defs`.
+    pub fn json_schema(&self) -> Value {
+        JsonSchema::new(false).document(self)
+    }
+
+    /// Like [`SchemaNode::json_schema`], restricted to what the strict structured output of
+    /// providers accepts: every field is required, with `null` for a missing `Option`, `anyOf`
+    /// takes the place of `oneOf`, and there are no keywords besides `type`, `properties`,
+    /// `required`, `additionalProperties`, `items`, `enum`, `anyOf`, `description`, `$ref` and
+    /// `$defs`.
+    ///
+    /// The constraints that are left out stay in the descriptions. A map becomes an array of
+    /// `{"key", "value"}` objects, as an object can't have arbitrary keys in strict mode, and a
+    /// type without a structure of its own a string. The names of a text enum are always an
+    /// `enum`, leaving out their descriptions, as strict mode takes no `oneOf` or `const`.
+    pub fn strict_json_schema(&self) -> Value {
+        JsonSchema::new(true).document(self)
+    }
+}
+
+/// Renders the JSON Schema of a tree of nodes.
+struct JsonSchema {
+    strict: bool,
+    // the structs and enums being rendered, outermost first, to refer back to on recursion
+    open: Vec<&'static str>,
+    recursive: Vec<&'static str>,
+    defs: Map<String, Value>,
+}
+
+impl JsonSchema {
+    fn new(strict: bool) -> Self {
+        JsonSchema {
+            strict,
+            open: Vec::new(),
+            recursive: Vec::new(),
+            defs: Map::new(),
+        }
+    }
+
+    fn document(mut self, node: &SchemaNode) -> Value {
+        let mut schema = self.node(node);
+        if !self.defs.is_empty()
+            && let Value::Object(object) = &mut schema
+        {
+            object.insert("$defs".into(), Value::Object(self.defs));
+        }
+        schema
+    }
+
+    fn node(&mut self, node: &SchemaNode) -> Value {
+        match node {
+            SchemaNode::Custom { schema } => match self.strict {
+                true => json!({ "type": "string", "description": schema }),
+                false => json!({ "description": schema }),
+            },
+            SchemaNode::Leaf { kind, .. } => self.leaf(*kind),
+            SchemaNode::Optional(inner) => nullable(self.node_of(*inner)),
+            SchemaNode::Struct { root, fields } => {
+                self.named(root, |this| this.object(&written_fields(fields)))
+            }
+            SchemaNode::Enum { name, variants } => self.named(name, |this| {
+                let variants = variants.iter().map(|v| this.variant(v)).collect();
+                let keyword = if this.strict { "anyOf" } else { "oneOf" };
+                let mut object = Map::new();
+                object.insert(keyword.into(), Value::Array(variants));
+                Value::Object(object)
+            }),
+            SchemaNode::Fields { fields, .. } => self.object(&written_fields(fields)),
+            SchemaNode::Sequence { item, unique, .. } => {
+                let mut schema = json!({ "type": "array", "items": self.node_of(*item) });
+                if *unique && !self.strict {
+                    schema["uniqueItems"] = Value::Bool(true);
+                }
+                schema
+            }
+            SchemaNode::Map { key, value, .. } => match self.strict {
+                true => {
+                    let entry = json!({
+                        "type": "object",
+                        "properties": { "key": self.node_of(*key), "value": self.node_of(*value) },
+                        "required": ["key", "value"],
+                        "additionalProperties": false,
+                    });
+                    json!({ "type": "array", "items": entry })
+                }
+                false => json!({ "type": "object", "additionalProperties": self.node_of(*value) }),
+            },
+            SchemaNode::Reference { node, .. } => self.node_of(*node),
+        }
+    }
+
+    fn node_of(&mut self, node: fn() -> SchemaNode) -> Value {
+        self.node(&node())
+    }
+
+    /// The schema of the struct or enum `name` built by `build`, or a reference to it when it
+    /// is reached again from inside.
+    fn named(&mut self, name: &'static str, build: impl FnOnce(&mut Self) -> Value) -> Value {
+        if self.open.contains(&name) {
+            if !self.recursive.contains(&name) {
+                self.recursive.push(name);
+            }
+            return self.reference(name);
+        }
+        self.open.push(name);
+        let schema = build(self);
+        self.open.pop();
+        // the outermost type is the document itself, the others move to `$defs`
+        match (self.open.is_empty(), self.recursive.contains(&name)) {
+            (false, true) => {
+                self.defs.insert(name.to_string(), schema);
+                self.reference(name)
+            }
+            _ => schema,
+        }
+    }
+
+    fn reference(&self, name: &str) -> Value {
+        match self.open.first() {
+            Some(outermost) if *outermost == name => json!({ "$ref": "#" }),
+            _ => json!({ "$ref": format!("#/$defs/{}", name) }),
+        }
+    }
+
+    fn object(&mut self, fields: &[SchemaField]) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for field in fields {
+            let node = (field.node)();
+            let mut schema = self.node(&node);
+            let optional = matches!(node, SchemaNode::Optional(_));
+            // the constraints are on the value inside an `Option`
+            match optional {
+                true => self.constrain(&mut schema["anyOf"][0], &field.constraints),
+                false => self.constrain(&mut schema, &field.constraints),
+            }
+            // strict mode has every field written, an `Option` as `null`
+            if optional && !self.strict {
+                schema = schema["anyOf"][0].take();
+            }
+            properties.insert(field.tag.into(), self.describe(schema, field.description));
+            if field.required || self.strict {
+                required.push(Value::from(field.tag));
+            }
+        }
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    }
+
+    fn variant(&mut self, variant: &SchemaVariant) -> Value {
+        let fields = written_fields(&variant.fields);
+        let schema = match fields.is_empty() {
+            true => json!({ "type": "string", "enum": [variant.tag] }),
+            false => json!({
+                "type": "object",
+                "properties": { variant.tag: self.object(&fields) },
+                "required": [variant.tag],
+                "additionalProperties": false,
+            }),
+        };
+        self.describe(schema, variant.description)
+    }
+
+    fn leaf(&self, kind: LeafKind) -> Value {
+        match kind {
+            LeafKind::String => json!({ "type": "string" }),
+            LeafKind::Integer => json!({ "type": "integer" }),
+            LeafKind::Float => json!({ "type": "number" }),
+            LeafKind::Bool => json!({ "type": "boolean" }),
+            LeafKind::Char if self.strict => json!({ "type": "string" }),
+            LeafKind::Char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+            LeafKind::OneOf(names)
+                if !self.strict && names.iter().any(|(_, description)| !description.is_empty()) =>
+            {
+                let names = names
+                    .iter()
+                    .map(|(name, description)| match description.is_empty() {
+                        true => json!({ "const": name }),
+                        false => json!({ "const": name, "description": description }),
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "type": "string", "oneOf": names })
+            }
+            LeafKind::OneOf(names) => {
+                let names = names.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+                json!({ "type": "string", "enum": names })
+            }
+        }
+    }
+
+    fn describe(&self, mut schema: Value, description: &str) -> Value {
+        // strict mode takes nothing next to a `$ref`
+        let is_ref = schema.get("$ref").is_some();
+        if let Value::Object(object) = &mut schema
+            && !description.is_empty()
+            && !(is_ref && self.strict)
+        {
+            object.insert("description".into(), Value::from(description));
+        }
+        schema
+    }
+
+    fn constrain(&self, schema: &mut Value, constraints: &FieldConstraints) {
+        let Value::Object(object) = schema else {
+            return;
+        };
+        if !constraints.one_of.is_empty() {
+            object.insert("enum".into(), Value::from(constraints.one_of.to_vec()));
+        }
+        if self.strict {
+            return;
+        }
+        let bounds = [
+            ("pattern", constraints.pattern.map(Value::from)),
+            ("minimum", constraints.min.map(number)),
+            ("maximum", constraints.max.map(number)),
+            ("minItems", constraints.min_items.map(Value::from)),
+            ("maxItems", constraints.max_items.map(Value::from)),
+        ];
+        for (keyword, value) in bounds {
+            if let Some(value) = value {
+                object.insert(keyword.into(), value);
+            }
+        }
+    }
+}
+
+/// `schema`, or `null`.
+fn nullable(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "type": "null" }] })
+}
+
+/// A bound as an integer when it is a whole number, e.g. `1` for `min = 1`.
+fn number(bound: f64) -> Value {
+    match bound.fract() == 0.0 && bound.abs() < i64::MAX as f64 {
+        true => Value::from(bound as i64),
+        false => Value::from(bound),
+    }
+}
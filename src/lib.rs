@@ -7,9 +7,26 @@
 //!
 //! For more details and usage examples, see the [README](https://github.com/vintcessun/llm_xml_caster).
 
+#[cfg(feature = "client")]
+mod backend;
+#[cfg(feature = "client")]
 mod bind;
+#[cfg(feature = "blocking")]
+mod blocking;
+mod cast;
 mod error;
+#[cfg(feature = "json")]
+mod json_document;
+#[cfg(feature = "json")]
+mod json_schema;
+mod repair;
+mod ser;
+#[cfg(feature = "client")]
+pub mod testing;
+mod token;
 pub mod r#type;
+mod validate;
+mod xsd;
 
 pub type Error = error::RequestError;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -22,16 +39,160 @@ pub use r#type::*;
 pub trait LlmPrompt {
     /// Returns the XML schema string containing field descriptions for LLM prompting.
     fn get_prompt_schema() -> &'static str;
+    /// Returns the schema of the fields without the surrounding root element.
+    ///
+    /// Used when the type is inlined into a parent with `#[prompt(flatten)]`.
+    fn get_fields_schema() -> &'static str {
+        Self::get_prompt_schema()
+    }
+    /// Returns the schema of the type written as a container element, such as an `<item>`.
+    ///
+    /// Differs from [`LlmPrompt::get_prompt_schema`] only where an element can't simply be left
+    /// out, e.g. an absent `Option` inside a `Vec` is still written as an empty `<item/>`.
+    fn get_item_schema() -> &'static str {
+        Self::get_prompt_schema()
+    }
+    /// Returns the prompt schema in the [`SchemaStyle::Compact`] style, which spends far fewer
+    /// tokens on indentation and boilerplate.
+    ///
+    /// Hand-written impls without a [`LlmPrompt::schema_node`] return their prompt schema.
+    fn get_prompt_schema_compact() -> String {
+        Self::schema_node().render_with_style(SchemaStyle::Compact)
+    }
     /// Returns the root XML element name expected by the deserializer.
     fn root_name() -> &'static str;
+    /// Returns the element names of the variants of an enum, which is written as one of them.
+    ///
+    /// Enums have no root element of their own, so these are what locates one in a response.
+    fn variant_names() -> &'static [&'static str] {
+        &[]
+    }
+    /// Returns a placeholder for the content of an element of this type, e.g. `42` for an
+    /// integer, or `None` to leave the element out.
+    ///
+    /// `depth` is how many more levels of nesting may follow, so that recursive types end: once
+    /// it runs out, an `Option` is left out and a sequence has no items. Hand-written impls
+    /// without an example leave their element out.
+    fn example_content(depth: usize) -> Option<String> {
+        let _ = depth;
+        None
+    }
+    /// Returns the placeholder content of a value element holding this type, such as an
+    /// `<item>`, where a struct is written inside its root element.
+    fn example_item(depth: usize) -> Option<String> {
+        Self::example_content(depth)
+    }
+    /// Returns the placeholder content of each variant of an enum, in order, e.g. to show the
+    /// model an example of every variant. Empty for other types and for text enums.
+    fn example_variants(depth: usize) -> Vec<String> {
+        let _ = depth;
+        Vec::new()
+    }
+    /// Returns a placeholder document of this type, as the LLM would write it, e.g. to check
+    /// what it is asked for. Strings read `example`, numbers `42` or `3.14`, sequences hold two
+    /// items, maps one entry, and enums are written as their first variant.
+    ///
+    /// The placeholders don't know about field constraints other than `one_of`, `min` and
+    /// `max`, so a `pattern` or `min_items` may not hold for them.
+    fn example_xml() -> String {
+        if Self::IS_ENUM {
+            Self::example_content(r#type::EXAMPLE_DEPTH).unwrap_or_default()
+        } else if Self::HAS_ROOT {
+            let content = Self::example_content(r#type::EXAMPLE_DEPTH);
+            r#type::example_element(Self::root_name(), content)
+        } else {
+            let content = Self::example_item(r#type::EXAMPLE_DEPTH);
+            r#type::example_element(cast::RESPONSE_ROOT, content)
+        }
+    }
+    /// Returns the structure of the element of this type, which the prompt schema is rendered
+    /// from and [`validate_xml`] checks a document against.
+    ///
+    /// Hand-written impls without one are described by their own prompt schema, and accept any
+    /// content.
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Custom {
+            schema: Self::get_prompt_schema(),
+        }
+    }
+    /// Returns an XML Schema (XSD 1.0) of the documents of this type, to check responses with
+    /// standard XML tooling.
+    ///
+    /// Structs are `complexType`s whose fields may come in any order, enums `group`s choosing
+    /// one variant, sequences and maps repeated `<item>` and `<entry>` elements, and an
+    /// `Option` field may be left out or empty. The descriptions become `xs:documentation`.
+    /// It describes the format the prompt asks for, so leniencies of the parsers, e.g. ` yes `
+    /// for a `bool` or a struct field without its root element, don't pass it.
+    fn xsd() -> String {
+        xsd::document::<Self>()
+    }
+    /// Returns an estimate of the number of tokens the prompt schema takes, see
+    /// [`estimate_tokens`].
+    fn schema_token_estimate() -> usize {
+        estimate_tokens(Self::get_prompt_schema())
+    }
+    /// Returns the JSON Schema of the JSON form of this type, e.g. for the structured output of
+    /// a provider, see [`SchemaNode::json_schema`].
+    #[cfg(feature = "json")]
+    fn json_schema() -> serde_json::Value {
+        Self::schema_node().json_schema()
+    }
+    /// Returns the JSON Schema of this type in the subset strict structured output accepts,
+    /// see [`SchemaNode::strict_json_schema`].
+    #[cfg(feature = "json")]
+    fn strict_json_schema() -> serde_json::Value {
+        Self::schema_node().strict_json_schema()
+    }
+    /// The prompt schema of a leaf type in [`SchemaText::ENGLISH`], a single line, if it is
+    /// fixed, e.g. for strings and numbers.
+    ///
+    /// `#[llm_prompt]` writes the English schema of a struct of such fields at compile time.
+    #[doc(hidden)]
+    const ENGLISH_LEAF_SCHEMA: Option<&'static str> = None;
     /// Indicates whether the type is an enum.
     const IS_ENUM: bool;
+    /// Indicates whether the type is written inside a root element of its own, as structs are.
+    ///
+    /// Containers and primitives, e.g. `Vec<T>` or `bool`, have none, so a response of one is
+    /// written inside a `<response>` element instead.
+    const HAS_ROOT: bool = true;
 }
 
-pub use bind::{generate_as, generate_as_with_retries};
+#[cfg(feature = "client")]
+pub use backend::{BackendResponse, HttpStatusError, LlmBackend};
+#[cfg(all(feature = "client", feature = "json"))]
+pub use bind::ToolMode;
+#[cfg(feature = "client")]
+pub use bind::{
+    Attempt, AttemptOutcome, Backoff, BadExample, Correction, CorrectionPolicy, CorrectionRole,
+    GenerateOptions, GenerationResult, IntoPrompt, generate_as, generate_as_best_of,
+    generate_as_best_of_with_options, generate_as_detailed, generate_as_with_example_value,
+    generate_as_with_options, generate_as_with_retries, generate_as_with_validator,
+    generate_from_text, generate_many_as, generate_many_as_stream, generate_many_as_with_options,
+};
+#[cfg(feature = "blocking")]
+pub use blocking::{
+    generate_as_blocking, generate_as_with_options_blocking, generate_as_with_retries_blocking,
+};
+#[cfg(feature = "json")]
+pub use cast::cast_from_json;
+pub use cast::{
+    CastOptions, Extraction, REASONING_TAGS, cast_from_reader, cast_from_str, cast_from_str_with,
+    extract_candidates, extract_from_reader, strip_reasoning,
+};
+/// Re-exported for the schemas `#[llm_prompt]` writes at compile time.
+#[doc(hidden)]
+pub use const_format;
+pub use error::{AttemptFailure, DeserializationError, ExtractionError};
 /// Procedural macro used to derive `LlmPrompt` implementation and integrate custom deserialization
 /// logic for LLM-generated XML.
 ///
 /// Place this attribute on any struct or enum that you want to be able to cast from XML output.
 /// Use `#[prompt("description")]` on fields to provide context to the LLM.
 pub use llm_xml_caster_helper::llm_prompt;
+/// Re-exported for the `#[prompt(pattern = "...")]` checks generated by the macro.
+#[cfg(feature = "regex")]
+pub use regex;
+pub use ser::to_llm_xml;
+pub use token::estimate_tokens;
+pub use validate::{IssueKind, ValidationIssue, ValidationReport, validate_xml};
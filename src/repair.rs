@@ -0,0 +1,156 @@
+/// Repairs the usual well-formedness slips in `xml`: an unescaped `&`, a `<` in text that
+/// can't start a tag, and a close tag whose case differs from its start tag (`<Name>..</name>`).
+///
+/// CDATA sections, comments and processing instructions are copied untouched. Returns `None`
+/// when nothing needed fixing or when the nesting is broken beyond a case mismatch, e.g.
+/// `<a><b></a></b>`, which is left for the LLM to correct.
+pub(crate) fn repair_xml(xml: &str) -> Option<String> {
+    let mut repaired = String::with_capacity(xml.len() + 16);
+    // the names of the open elements, as written in their start tags
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = xml;
+
+    while let Some(i) = rest.find(['<', '&']) {
+        repaired.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if rest.starts_with('&') {
+            repaired.push_str(if is_entity(rest) { "&" } else { "&amp;" });
+            rest = &rest[1..];
+            continue;
+        }
+
+        let verbatim = [
+            ("<![CDATA[", "]]>"),
+            ("<!--", "-->"),
+            ("<?", "?>"),
+            ("<!", ">"),
+        ]
+        .into_iter()
+        .find(|(start, _)| rest.starts_with(start));
+        if let Some((start, end)) = verbatim {
+            let len = start.len() + rest[start.len()..].find(end)? + end.len();
+            repaired.push_str(&rest[..len]);
+            rest = &rest[len..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>')?;
+            let name = open.pop()?;
+            if !name.eq_ignore_ascii_case(after[..end].trim()) {
+                return None;
+            }
+            repaired.push_str("</");
+            repaired.push_str(name);
+            repaired.push('>');
+            rest = &after[end + 1..];
+        } else if rest[1..].starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            let end = rest.find('>')?;
+            let tag = &rest[..=end];
+            if !tag.ends_with("/>") {
+                let name_len = tag[1..]
+                    .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                    .unwrap_or(0);
+                open.push(&tag[1..=name_len]);
+            }
+            repaired.push_str(tag);
+            rest = &rest[end + 1..];
+        } else {
+            // e.g. `a < b` or `x<5`, which should have been escaped
+            repaired.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    repaired.push_str(rest);
+
+    (open.is_empty() && repaired != xml).then_some(repaired)
+}
+
+/// Whether `text` starts with an entity such as `&amp;`, `&#8217;` or `&#x2019;`.
+fn is_entity(text: &str) -> bool {
+    let Some(end) = text.find(';').filter(|&end| end <= 10) else {
+        return false;
+    };
+    let name = &text[1..end];
+    match name.strip_prefix('#') {
+        Some(code) => match code.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !code.is_empty() && code.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
+/// Closes the elements left open in `xml`, a document cut off before its end.
+///
+/// An unfinished CDATA section is closed too, while a tag, comment or entity cut in half is
+/// dropped. Returns `None` when no element is left open or the nesting is broken.
+pub(crate) fn complete_truncated(xml: &str) -> Option<String> {
+    let mut completed = String::with_capacity(xml.len() + 32);
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = xml;
+
+    while let Some(i) = rest.find('<') {
+        completed.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            match cdata.find("]]>") {
+                Some(end) => {
+                    let len = "<![CDATA[".len() + end + "]]>".len();
+                    completed.push_str(&rest[..len]);
+                    rest = &rest[len..];
+                }
+                None => {
+                    completed.push_str(rest);
+                    completed.push_str("]]>");
+                    rest = "";
+                }
+            }
+            continue;
+        }
+
+        let Some(end) = [("<!--", "-->"), ("<?", "?>"), ("<!", ">"), ("<", ">")]
+            .into_iter()
+            .find(|(start, _)| rest.starts_with(start))
+            .and_then(|(start, end)| {
+                rest[start.len()..]
+                    .find(end)
+                    .map(|i| start.len() + i + end.len())
+            })
+        else {
+            // a tag or comment cut in half
+            rest = "";
+            break;
+        };
+        let tag = &rest[..end];
+        if let Some(name) = tag.strip_prefix("</") {
+            if open.pop()? != name.trim_end_matches('>').trim() {
+                return None;
+            }
+        } else if tag[1..].starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && !tag.ends_with("/>")
+        {
+            let name_len = tag[1..]
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .unwrap_or(0);
+            open.push(&tag[1..=name_len]);
+        }
+        completed.push_str(tag);
+        rest = &rest[end..];
+    }
+    // an entity cut in half, e.g. `&am`
+    let text_end = rest
+        .rfind('&')
+        .filter(|&amp| !rest[amp..].contains(';'))
+        .unwrap_or(rest.len());
+    completed.push_str(&rest[..text_end]);
+
+    if open.is_empty() {
+        return None;
+    }
+    for name in open.iter().rev() {
+        completed.push_str("</");
+        completed.push_str(name);
+        completed.push('>');
+    }
+    Some(completed)
+}
@@ -0,0 +1,440 @@
+//! Writes values in the XML dialect the parsers read, e.g. to build the `valid_example` of
+//! [`generate_as`](crate::generate_as) from a real value.
+
+use crate::cast::RESPONSE_ROOT;
+use crate::{Error, LlmPrompt, Result};
+use serde::ser::{self, Serialize};
+use std::fmt::{self, Display};
+
+/// Serializes `value` as the XML document the LLM is asked to write for `T`.
+///
+/// Strings are wrapped in CDATA, sequence items in `<item>`, map entries in
+/// `<entry><key>..</key><value>..</value></entry>`, tuples in `<first>`, `<second>`, ... and enum
+/// variants in an element of their own name. A `None` field is left out. Types serialize through
+/// their own `Serialize` implementation, so one whose parser expects a text format of its own
+/// (e.g. `Duration` as `90s`) has to serialize to that format. A type without a root element
+/// of its own, such as a `Vec<T>`, is written inside a `<response>` element.
+///
+/// # Errors
+///
+/// Returns `Error::XmlSerialization` for what the dialect can't express, such as raw bytes or a
+/// tuple of more than 4 elements.
+pub fn to_llm_xml<T: Serialize + LlmPrompt>(value: &T) -> Result<String> {
+    // a type without a root element is written inside `<response>`, as a value like an `<item>`
+    let serializer = match T::HAS_ROOT {
+        true => ContentSerializer::FIELD,
+        false => ContentSerializer::VALUE,
+    };
+    let content = value
+        .serialize(serializer)
+        .map_err(|e| Error::XmlSerialization(e.0))?;
+    // an enum is written as its variant element alone
+    Ok(match (T::IS_ENUM, T::HAS_ROOT) {
+        (true, _) => content.unwrap_or_default(),
+        (false, true) => element(T::root_name(), content),
+        (false, false) => element(RESPONSE_ROOT, content),
+    })
+}
+
+#[derive(Debug)]
+struct SerError(String);
+
+impl Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerError(msg.to_string())
+    }
+}
+
+/// `<name>content</name>`, or `<name/>` for an absent value.
+fn element(name: &str, content: Option<String>) -> String {
+    match content {
+        Some(content) => format!("<{name}>{content}</{name}>"),
+        None => format!("<{name}/>"),
+    }
+}
+
+/// Text wrapped in CDATA, splitting any `]]>` inside it across two sections.
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+const TUPLE_FIELDS: [&str; 4] = ["first", "second", "third", "fourth"];
+
+/// Serializes the content of an element, or `None` for a value that leaves it out.
+#[derive(Clone, Copy)]
+struct ContentSerializer {
+    /// Whether a struct is written inside an element of its own name, as the parsers expect of
+    /// one in an `<item>`, a tuple element or a map entry, but not of one in a field.
+    wrap_struct: bool,
+}
+
+impl ContentSerializer {
+    const FIELD: Self = ContentSerializer { wrap_struct: false };
+    const VALUE: Self = ContentSerializer { wrap_struct: true };
+}
+
+type Content = std::result::Result<Option<String>, SerError>;
+
+macro_rules! serialize_display {
+    ($($method:ident: $ty:ty),+) => {
+        $(
+            fn $method(self, v: $ty) -> Content {
+                Ok(Some(v.to_string()))
+            }
+        )+
+    };
+}
+
+impl ser::Serializer for ContentSerializer {
+    type Ok = Option<String>;
+    type Error = SerError;
+    type SerializeSeq = SeqWriter;
+    type SerializeTuple = TupleWriter;
+    type SerializeTupleStruct = TupleWriter;
+    type SerializeTupleVariant = TupleWriter;
+    type SerializeMap = MapWriter;
+    type SerializeStruct = StructWriter;
+    type SerializeStructVariant = StructWriter;
+
+    serialize_display!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64
+    );
+
+    fn serialize_char(self, v: char) -> Content {
+        Ok(Some(cdata(v.encode_utf8(&mut [0; 4]))))
+    }
+
+    fn serialize_str(self, v: &str) -> Content {
+        Ok(Some(cdata(v)))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Content {
+        Err(SerError(
+            "raw bytes can't be written as XML, use Base64Bytes or a Vec<u8>".to_string(),
+        ))
+    }
+
+    fn serialize_none(self) -> Content {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Content {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Content {
+        Ok(Some(String::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Content {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Content {
+        Ok(Some(element(variant, None)))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Content {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Content {
+        Ok(Some(element(
+            variant,
+            value.serialize(ContentSerializer::FIELD)?,
+        )))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> std::result::Result<SeqWriter, SerError> {
+        Ok(SeqWriter(String::new()))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> std::result::Result<TupleWriter, SerError> {
+        Ok(TupleWriter::new(None))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<TupleWriter, SerError> {
+        Ok(TupleWriter::new(None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<TupleWriter, SerError> {
+        Ok(TupleWriter::new(Some(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> std::result::Result<MapWriter, SerError> {
+        Ok(MapWriter {
+            out: String::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<StructWriter, SerError> {
+        Ok(StructWriter {
+            out: String::new(),
+            wrapper: self.wrap_struct.then_some(name),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<StructWriter, SerError> {
+        Ok(StructWriter {
+            out: String::new(),
+            wrapper: Some(variant),
+        })
+    }
+}
+
+/// Writes each element as an `<item>`, an absent one as `<item/>`.
+struct SeqWriter(String);
+
+impl ser::SerializeSeq for SeqWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.0
+            .push_str(&element("item", value.serialize(ContentSerializer::VALUE)?));
+        Ok(())
+    }
+
+    fn end(self) -> Content {
+        Ok(Some(self.0))
+    }
+}
+
+/// Writes the elements as `<first>`, `<second>`, ..., inside the variant element if any.
+struct TupleWriter {
+    out: String,
+    index: usize,
+    variant: Option<&'static str>,
+}
+
+impl TupleWriter {
+    fn new(variant: Option<&'static str>) -> Self {
+        TupleWriter {
+            out: String::new(),
+            index: 0,
+            variant,
+        }
+    }
+
+    fn write<T: ?Sized + Serialize>(&mut self, value: &T) -> std::result::Result<(), SerError> {
+        let name = TUPLE_FIELDS.get(self.index).ok_or_else(|| {
+            SerError(format!(
+                "tuples of more than {} elements can't be written as XML",
+                TUPLE_FIELDS.len()
+            ))
+        })?;
+        self.index += 1;
+        self.out
+            .push_str(&element(name, value.serialize(ContentSerializer::VALUE)?));
+        Ok(())
+    }
+
+    fn finish(self) -> Content {
+        Ok(Some(match self.variant {
+            Some(variant) => element(variant, Some(self.out)),
+            None => self.out,
+        }))
+    }
+}
+
+impl ser::SerializeTuple for TupleWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.write(value)
+    }
+
+    fn end(self) -> Content {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for TupleWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.write(value)
+    }
+
+    fn end(self) -> Content {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for TupleWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.write(value)
+    }
+
+    fn end(self) -> Content {
+        self.finish()
+    }
+}
+
+/// Writes each pair as an `<entry>` holding a `<key>` and a `<value>`.
+struct MapWriter {
+    out: String,
+    key: Option<Option<String>>,
+}
+
+impl ser::SerializeMap for MapWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.key = Some(key.serialize(ContentSerializer::VALUE)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        let key = self.key.take().unwrap_or_default();
+        let entry =
+            element("key", key) + &element("value", value.serialize(ContentSerializer::VALUE)?);
+        self.out.push_str(&element("entry", Some(entry)));
+        Ok(())
+    }
+
+    fn end(self) -> Content {
+        Ok(Some(self.out))
+    }
+}
+
+/// Writes each field as an element of its name, leaving out the absent ones, inside the variant
+/// or struct name element if any.
+struct StructWriter {
+    out: String,
+    wrapper: Option<&'static str>,
+}
+
+impl StructWriter {
+    fn write<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        if let Some(content) = value.serialize(ContentSerializer::FIELD)? {
+            self.out.push_str(&element(key, Some(content)));
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Content {
+        Ok(Some(match self.wrapper {
+            Some(wrapper) => element(wrapper, Some(self.out)),
+            None => self.out,
+        }))
+    }
+}
+
+impl ser::SerializeStruct for StructWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.write(key, value)
+    }
+
+    fn end(self) -> Content {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructWriter {
+    type Ok = Option<String>;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> std::result::Result<(), SerError> {
+        self.write(key, value)
+    }
+
+    fn end(self) -> Content {
+        self.finish()
+    }
+}
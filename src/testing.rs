@@ -0,0 +1,127 @@
+//! Backends for testing code that generates values, without calling a model.
+
+use crate::{BackendResponse, Error, LlmBackend, Result};
+use genai::chat::{ChatOptions, ChatRequest};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A request received by a [`MockLlm`].
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// The name of the model the request was sent to.
+    pub model_name: String,
+    /// The request, with the messages sent.
+    pub request: ChatRequest,
+    /// The chat options sent with it.
+    pub options: ChatOptions,
+}
+
+/// A scripted reply, sent after its latency.
+#[derive(Debug)]
+struct MockReply {
+    latency: Duration,
+    result: Result<BackendResponse>,
+}
+
+/// A backend replaying a script of canned replies, one per request, and recording the requests
+/// it received.
+///
+/// A reply is either a response or an error standing for a request that failed to reach the
+/// model, and may be delayed, e.g. to test transport retries and backoff. A request after the
+/// script ran out fails with `Error::Backend`.
+#[derive(Debug, Default)]
+pub struct MockLlm {
+    replies: Mutex<VecDeque<MockReply>>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+/// [`MockLlm`] under the name it was introduced with.
+pub type MockBackend = MockLlm;
+
+impl MockLlm {
+    /// A backend answering the requests with `texts`, in order.
+    pub fn new<S: Into<String>>(texts: impl IntoIterator<Item = S>) -> Self {
+        texts
+            .into_iter()
+            .fold(Self::default(), |mock, text| mock.with_text(text))
+    }
+
+    /// Queues a response of `text` after the replies already queued.
+    pub fn with_text(self, text: impl Into<String>) -> Self {
+        self.with_response(BackendResponse::from_text(text))
+    }
+
+    /// Queues a response calling the tool of the request with `arguments` after the replies
+    /// already queued, e.g. to test [tool mode](crate::GenerateOptions::with_tool_mode).
+    #[cfg(feature = "json")]
+    pub fn with_tool_call(self, arguments: serde_json::Value) -> Self {
+        self.with_response(BackendResponse::from_tool_arguments(arguments))
+    }
+
+    /// Queues `response` after the replies already queued.
+    pub fn with_response(self, response: BackendResponse) -> Self {
+        self.with_reply(Ok(response))
+    }
+
+    /// Queues `error` after the replies already queued, as a request that failed to reach the
+    /// model, e.g. `Error::Backend(Box::new(HttpStatusError::new(429).with_retry_after("2")))`
+    /// for a rate limit.
+    pub fn with_error(self, error: Error) -> Self {
+        self.with_reply(Err(error))
+    }
+
+    /// Delays the reply queued last by `latency`.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        if let Some(reply) = self.replies.lock().unwrap().back_mut() {
+            reply.latency = latency;
+        }
+        self
+    }
+
+    fn with_reply(self, result: Result<BackendResponse>) -> Self {
+        self.replies.lock().unwrap().push_back(MockReply {
+            latency: Duration::ZERO,
+            result,
+        });
+        self
+    }
+
+    /// The requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The number of requests received so far.
+    pub fn calls(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    /// The number of replies not sent yet.
+    pub fn remaining(&self) -> usize {
+        self.replies.lock().unwrap().len()
+    }
+}
+
+impl LlmBackend for MockLlm {
+    async fn chat(
+        &self,
+        model_name: &str,
+        request: &ChatRequest,
+        options: &ChatOptions,
+    ) -> Result<BackendResponse> {
+        self.requests.lock().unwrap().push(MockRequest {
+            model_name: model_name.to_string(),
+            request: request.clone(),
+            options: options.clone(),
+        });
+        let reply = self.replies.lock().unwrap().pop_front();
+        let Some(reply) = reply else {
+            return Err(Error::Backend("MockLlm has no replies left".into()));
+        };
+        if !reply.latency.is_zero() {
+            tokio::time::sleep(reply.latency).await;
+        }
+        reply.result
+    }
+}
@@ -0,0 +1,59 @@
+//! Rough token counts of prompts, to check a request fits the context window of a model before
+//! sending it.
+
+/// An estimate of the number of tokens `text` takes, e.g. to pick a model or a `max_tokens`.
+///
+/// With the `tiktoken` feature, this is the exact count in the `o200k_base` encoding of recent
+/// OpenAI models, which other providers' tokenizers land near as well. Otherwise it is a
+/// heuristic following how such encodings split text: a token for a word of up to seven letters,
+/// for up to three digits or punctuation characters, for a line break with its indentation, and
+/// for most CJK characters. It is usually within 20% of the exact count, erring on the high side
+/// for XML.
+pub fn estimate_tokens(text: &str) -> usize {
+    #[cfg(feature = "tiktoken")]
+    {
+        tiktoken_rs::o200k_base_singleton()
+            .encode_ordinary(text)
+            .len()
+    }
+    #[cfg(not(feature = "tiktoken"))]
+    heuristic_tokens(text)
+}
+
+#[cfg(not(feature = "tiktoken"))]
+fn heuristic_tokens(text: &str) -> usize {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Class {
+        Letter,
+        Digit,
+        Space,
+        Punctuation,
+        Other,
+    }
+    let class = |c: char| match c {
+        c if c.is_ascii_alphabetic() => Class::Letter,
+        c if c.is_ascii_digit() => Class::Digit,
+        c if c.is_whitespace() => Class::Space,
+        c if c.is_ascii() => Class::Punctuation,
+        _ => Class::Other,
+    };
+
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(first) = chars.next() {
+        let run_class = class(first);
+        let mut len: usize = 1;
+        while chars.next_if(|&c| class(c) == run_class).is_some() {
+            len += 1;
+        }
+        tokens += match run_class {
+            Class::Letter => len.div_ceil(7),
+            Class::Digit | Class::Punctuation => len.div_ceil(3),
+            // a single space is part of the word after it
+            Class::Space if len == 1 && first == ' ' => 0,
+            Class::Space => 1,
+            Class::Other => (len * 3).div_ceil(4),
+        };
+    }
+    tokens
+}
@@ -0,0 +1,108 @@
+use super::xml::XmlText;
+use crate::LlmPrompt;
+use serde::{Deserialize, Deserializer};
+
+/// Binary data the LLM writes as standard base64, instead of one `<item>` per byte like `Vec<u8>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Base64Bytes(pub Vec<u8>);
+
+impl From<Base64Bytes> for Vec<u8> {
+    fn from(bytes: Base64Bytes) -> Self {
+        bytes.0
+    }
+}
+
+/// Decodes standard base64, skipping whitespace and line breaks; padding may be left out.
+fn decode_base64(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    let mut padded = false;
+
+    for (position, c) in s.chars().enumerate() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '=' {
+            padded = true;
+            continue;
+        }
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => {
+                return Err(format!(
+                    "invalid base64 character '{}' at position {}",
+                    c,
+                    position + 1
+                ));
+            }
+        };
+        if padded {
+            return Err(format!(
+                "unexpected base64 character '{}' after the = padding at position {}",
+                c,
+                position + 1
+            ));
+        }
+
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    if bits == 6 {
+        return Err(
+            "the base64 content is truncated, its last group has a single character".to_string(),
+        );
+    }
+    Ok(out)
+}
+
+pub fn custom_base64_bytes_parser<'de, D>(deserializer: D) -> Result<Base64Bytes, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+
+    decode_base64(text.clean()).map(Base64Bytes).map_err(|e| {
+        serde::de::Error::custom(format!(
+            "can not decode the content as base64: {}. Write standard base64 (A-Z, a-z, 0-9, + and /) inside <![CDATA[...]]>",
+            e
+        ))
+    })
+}
+
+impl<'de> Deserialize<'de> for Base64Bytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        custom_base64_bytes_parser(deserializer)
+    }
+}
+
+impl LlmPrompt for Base64Bytes {
+    fn get_prompt_schema() -> &'static str {
+        "binary data encoded as standard base64 (A-Z, a-z, 0-9, + and /, padded with =). please use the format <![CDATA[{base64 content}]]> to return it, e.g. <![CDATA[SGVsbG8=]]>. Return <![CDATA[]]> for no data"
+    }
+
+    fn root_name() -> &'static str {
+        "Base64Bytes"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("<![CDATA[SGVsbG8=]]>".to_string())
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
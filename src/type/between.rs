@@ -0,0 +1,104 @@
+use super::example::example_element;
+use super::text::fill;
+use super::xml::XmlValue;
+use crate::{Cache, LlmPrompt, SchemaField, SchemaNode, SchemaText};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+use std::marker::PhantomData;
+
+/// An open-ended range where either endpoint may be absent, e.g. "at least 100" or "before 2020".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Between<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct XmlBetween<T> {
+    min: Option<XmlValue<T>>,
+    max: Option<XmlValue<T>>,
+}
+
+pub struct BetweenParser<T>(PhantomData<T>)
+where
+    T: DeserializeOwned + PartialOrd;
+
+impl<T> BetweenParser<T>
+where
+    T: DeserializeOwned + PartialOrd,
+{
+    pub fn custom_between_parser<'de, D>(deserializer: D) -> Result<Between<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let wrapper = match XmlBetween::<T>::deserialize(deserializer) {
+            Ok(wrapper) => wrapper,
+            Err(e) => {
+                return Err(serde::de::Error::custom(format!(
+                    "The XML structure is invalid. A range must consist of an optional <min> and an optional <max> element. Details: {}",
+                    e
+                )));
+            }
+        };
+
+        let range = Between {
+            min: wrapper.min.map(|w| w.0),
+            max: wrapper.max.map(|w| w.0),
+        };
+
+        if let (Some(min), Some(max)) = (&range.min, &range.max)
+            && min > max
+        {
+            return Err(serde::de::Error::custom(
+                "The range is inverted: the value in <min> is greater than the value in <max>. The lower bound must be less than or equal to the upper bound, please swap the two values.",
+            ));
+        }
+
+        Ok(range)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Between<T>
+where
+    T: DeserializeOwned + PartialOrd,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BetweenParser::<T>::custom_between_parser(deserializer)
+    }
+}
+
+impl<T: LlmPrompt + 'static> LlmPrompt for Between<T> {
+    fn get_prompt_schema() -> &'static str {
+        let cache = Cache::<Between<T>>::get();
+        cache
+            .prompt_schema
+            .get_or_init(|| fill(SchemaText::current().range, &[T::get_prompt_schema()]))
+    }
+
+    fn root_name() -> &'static str {
+        let cache = Cache::<Between<T>>::get();
+        cache
+            .root_name
+            .get_or_init(|| format!("Between<{}>", T::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        let bound = |name| example_element(name, T::example_item(depth.saturating_sub(1)));
+        Some(bound("min") + &bound("max"))
+    }
+
+    fn schema_node() -> SchemaNode {
+        let bound = |tag| SchemaField {
+            required: false,
+            ..SchemaField::element(tag, Option::<T>::schema_node)
+        };
+        SchemaNode::Fields {
+            schema: Self::get_prompt_schema(),
+            fields: vec![bound("min"), bound("max")],
+        }
+    }
+
+    const IS_ENUM: bool = false;
+}
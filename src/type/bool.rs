@@ -1,34 +1,95 @@
-use crate::LlmPrompt;
-use serde::{Deserialize, Deserializer};
+use super::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode, SchemaText};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, MapAccess, Visitor, value::MapAccessDeserializer},
+};
+use std::fmt;
+
+struct BoolVisitor;
+
+impl<'de> Visitor<'de> for BoolVisitor {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a boolean value or a string representing a boolean")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<bool, E> {
+        Ok(v)
+    }
+
+    // any non-zero number is true, like in C
+    fn visit_i64<E>(self, v: i64) -> Result<bool, E> {
+        Ok(v != 0)
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<bool, E> {
+        Ok(v != 0)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<bool, E>
+    where
+        E: de::Error,
+    {
+        let clean_s = v.trim().to_lowercase();
+
+        match clean_s.as_str() {
+            // the true values bucket
+            "true" | "1" | "yes" | "y" | "t" | "on" | "真" | "checked" | "selected" | "enabled"
+            | "correct" => Ok(true),
+            // the false values bucket
+            "false" | "0" | "no" | "n" | "f" | "off" | "假" | "null" | "none" | "" | "disabled"
+            | "incorrect" => Ok(false),
+            // if the LLM outputs other nonsense, default to error
+            _ => Err(de::Error::custom(format!(
+                "can not parse '{}' as a boolean value",
+                clean_s
+            ))),
+        }
+    }
+
+    fn visit_unit<E>(self) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    // fields reached through `#[serde(flatten)]` arrive as a map holding the text
+    fn visit_map<A>(self, map: A) -> Result<bool, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let XmlText(text) = XmlText::deserialize(MapAccessDeserializer::new(map))?;
+        self.visit_str(&text)
+    }
+}
 
 pub fn custom_bool_parser<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
-    let clean_s = s.trim().to_lowercase();
-
-    match clean_s.as_str() {
-        // the true values bucket
-        "true" | "1" | "yes" | "y" | "t" | "on" | "真" | "checked" | "selected" => Ok(true),
-        // the false values bucket
-        "false" | "0" | "no" | "n" | "f" | "off" | "假" | "null" | "none" | "" => Ok(false),
-        // if the LLM outputs other nonsense, default to error
-        _ => Err(serde::de::Error::custom(format!(
-            "can not parse '{}' as a boolean value",
-            clean_s
-        ))),
-    }
+    deserializer.deserialize_any(BoolVisitor)
 }
 
 impl LlmPrompt for bool {
     fn get_prompt_schema() -> &'static str {
-        "it is a boolean value, either `true` or `false`"
+        SchemaText::current().bool
     }
 
     fn root_name() -> &'static str {
         "bool"
     }
 
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("true".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::leaf::<bool>(LeafKind::Bool)
+    }
+
+    const ENGLISH_LEAF_SCHEMA: Option<&'static str> = Some(SchemaText::ENGLISH.bool);
+
     const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
 }
@@ -0,0 +1,60 @@
+use super::r#enum::EnumParser;
+use super::schema::{recursive_enum_schema, recursive_struct_schema};
+use crate::{Cache, LlmPrompt, SchemaNode};
+use serde::{Deserializer, de::DeserializeOwned};
+use std::marker::PhantomData;
+
+pub struct BoxParser<T>(PhantomData<T>);
+
+impl<T> BoxParser<T>
+where
+    T: DeserializeOwned + LlmPrompt,
+{
+    pub fn custom_box_parser<'de, D>(deserializer: D) -> Result<Box<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        EnumParser::<T>::custom_enum_parser(deserializer).map(Box::new)
+    }
+}
+
+/// Cache key for the short schema used when `Box<T>` is reached while `T` itself is being built.
+struct RecursiveReference<T>(PhantomData<T>);
+
+impl<T: LlmPrompt + 'static> LlmPrompt for Box<T> {
+    fn get_prompt_schema() -> &'static str {
+        if Cache::<T>::is_building() {
+            // Expanding `T` here would recurse forever, refer back to the schema being built instead
+            let cache = Cache::<RecursiveReference<T>>::get();
+            return cache.prompt_schema.get_or_init(|| match T::IS_ENUM {
+                true => recursive_enum_schema(),
+                false => recursive_struct_schema(T::root_name()),
+            });
+        }
+        T::get_prompt_schema()
+    }
+
+    fn get_fields_schema() -> &'static str {
+        T::get_fields_schema()
+    }
+
+    fn root_name() -> &'static str {
+        T::root_name()
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        T::example_content(depth)
+    }
+
+    fn example_item(depth: usize) -> Option<String> {
+        T::example_item(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        T::schema_node()
+    }
+
+    const IS_ENUM: bool = T::IS_ENUM;
+
+    const HAS_ROOT: bool = T::HAS_ROOT;
+}
@@ -0,0 +1,44 @@
+use super::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode, SchemaText};
+use serde::{Deserialize, Deserializer};
+
+pub fn custom_char_parser<'de, D>(deserializer: D) -> Result<char, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    let mut chars = clean_s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(serde::de::Error::custom(format!(
+            "can not parse '{}' as a char value, it must be exactly one character",
+            clean_s
+        ))),
+    }
+}
+
+impl LlmPrompt for char {
+    fn get_prompt_schema() -> &'static str {
+        SchemaText::current().char
+    }
+
+    fn root_name() -> &'static str {
+        "char"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("A".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::leaf::<char>(LeafKind::Char)
+    }
+
+    const ENGLISH_LEAF_SCHEMA: Option<&'static str> = Some(SchemaText::ENGLISH.char);
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
@@ -0,0 +1,100 @@
+//! Runtime checks behind the `#[prompt(...)]` field constraints.
+//!
+//! The macro wraps a field's parser with these checks; the returned messages become
+//! deserialization errors, so they are phrased for the LLM reading the correction message.
+
+/// Matches `value` case-insensitively against `allowed`, returning the canonical spelling.
+pub fn check_one_of(value: String, allowed: &[&str]) -> Result<String, String> {
+    let trimmed = value.trim();
+    let lowered = trimmed.to_lowercase();
+    allowed
+        .iter()
+        .find(|candidate| candidate.to_lowercase() == lowered)
+        .map(|candidate| candidate.to_string())
+        .ok_or_else(|| {
+            format!(
+                "can not accept '{}', the value must be exactly one of: {}",
+                trimmed,
+                allowed.join(", ")
+            )
+        })
+}
+
+/// Checks that `value` matches `pattern`, compiling it into `regex` on first use.
+///
+/// `desc` is the readable name of the pattern given with `#[prompt(desc = "...")]`.
+#[cfg(feature = "regex")]
+pub fn check_pattern(
+    value: String,
+    regex: &std::sync::OnceLock<regex::Regex>,
+    pattern: &str,
+    desc: Option<&str>,
+) -> Result<String, String> {
+    let regex = regex.get_or_init(|| {
+        regex::Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("invalid #[prompt(pattern = {:?})]: {}", pattern, e))
+    });
+    if regex.is_match(&value) {
+        return Ok(value);
+    }
+    Err(match desc {
+        Some(desc) => format!(
+            "'{}' is not {}, the value must match the regular expression {}",
+            value, desc, pattern
+        ),
+        None => format!(
+            "'{}' does not match the regular expression {}",
+            value, pattern
+        ),
+    })
+}
+
+/// Checks that `value` lies within the inclusive bounds, a missing bound is unbounded.
+pub fn check_range<T>(value: T, min: Option<T>, max: Option<T>) -> Result<T, String>
+where
+    T: PartialOrd + std::fmt::Display,
+{
+    if let Some(min) = min
+        && value < min
+    {
+        return Err(format!(
+            "the value {} is below the minimum of {}, it must be greater than or equal to {}",
+            value, min, min
+        ));
+    }
+    if let Some(max) = max
+        && value > max
+    {
+        return Err(format!(
+            "the value {} is above the maximum of {}, it must be less than or equal to {}",
+            value, max, max
+        ));
+    }
+    Ok(value)
+}
+
+/// Checks that a list holds between `min` and `max` items inclusive, a missing bound is unbounded.
+pub fn check_items<T>(
+    value: Vec<T>,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Result<Vec<T>, String> {
+    let len = value.len();
+    if let Some(min) = min
+        && len < min
+    {
+        return Err(format!(
+            "the list has {} items, but it must contain at least {} items",
+            len, min
+        ));
+    }
+    if let Some(max) = max
+        && len > max
+    {
+        return Err(format!(
+            "the list has {} items, but it must contain at most {} items",
+            len, max
+        ));
+    }
+    Ok(value)
+}
@@ -0,0 +1,78 @@
+use super::r#macro::parsed_leaf;
+use super::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// Parses `90`, `1.5`, `90s`, `5m`, `2h30m` or `1h 15m`; a bare number is a count of seconds.
+fn parse_duration(s: &str) -> Option<Duration> {
+    if let Ok(secs) = s.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+
+    let mut total = 0.0;
+    let mut rest = s.trim_start();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number = rest[..number_len].parse::<f64>().ok()?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let factor = match rest[..unit_len].to_ascii_lowercase().as_str() {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return None,
+        };
+        total += number * factor;
+        rest = rest[unit_len..].trim_start();
+    }
+
+    Duration::try_from_secs_f64(total).ok()
+}
+
+pub fn custom_duration_parser<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    parse_duration(clean_s).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a duration, it must be a number of seconds like 90 or 1.5, or numbers with units like 90s, 5m or 2h30m (units: ms, s, m, h, d)",
+            clean_s
+        ))
+    })
+}
+
+impl LlmPrompt for Duration {
+    fn get_prompt_schema() -> &'static str {
+        "a duration, either a number of seconds like 90 or 1.5, or numbers with units like 90s, 5m or 2h30m (units: ms for milliseconds, s for seconds, m for minutes, h for hours, d for days)"
+    }
+
+    fn root_name() -> &'static str {
+        "Duration"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("90s".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_duration_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
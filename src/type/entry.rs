@@ -0,0 +1,171 @@
+//! The `<entry><key>..</key><value>..</value></entry>` grammar shared by every map type.
+
+use super::text::{SchemaText, fill};
+use super::xml::XmlValue;
+use super::{ContainerMistake, report_container_mistake};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, DeserializeOwned, IgnoredAny, MapAccess, Visitor},
+};
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+
+#[derive(Deserialize)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+const ENTRY: &str = "entry";
+
+/// The `<entry>` children of a map element.
+///
+/// Unless `strict` is set, any other child, such as a `<note>` the LLM added, is skipped.
+struct XmlEntries<K, V> {
+    strict: bool,
+    marker: PhantomData<(K, V)>,
+}
+
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for XmlEntries<K, V> {
+    type Value = Vec<Entry<K, V>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of <entry> elements")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Vec::new())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == ENTRY {
+                entries.push(map.next_value()?);
+            } else if self.strict {
+                return Err(de::Error::unknown_field(&key, &[ENTRY]));
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(entries)
+    }
+}
+
+fn deserialize_map_entries<'de, D, K, V>(
+    deserializer: D,
+    strict: bool,
+) -> Result<Vec<(K, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    let visitor = XmlEntries {
+        strict,
+        marker: PhantomData,
+    };
+    match deserializer.deserialize_map(visitor) {
+        Ok(entries) => Ok(entries.into_iter().map(|e| (e.key, e.value)).collect()),
+        Err(e) => {
+            report_container_mistake(ContainerMistake::MissingEntry);
+            Err(de::Error::custom(format!(
+                "The XML structure is invalid. The sequence must consist of <entry> elements, each containing a <key> and a <value>. Details: {}",
+                e
+            )))
+        }
+    }
+}
+
+/// Reads the `<entry>` pairs of any map, handing the whole `<key>` and `<value>` elements to
+/// `K` and `V`.
+///
+/// The macro passes wrappers that run the key and value types' own parsers here and collects
+/// the pairs into the map type of the field.
+pub struct MapEntriesParser<K, V>(PhantomData<(K, V)>);
+
+impl<K, V> MapEntriesParser<K, V>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    pub fn custom_map_entries_parser<'de, D>(deserializer: D) -> Result<Vec<(K, V)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_map_entries(deserializer, false)
+    }
+
+    /// Like [`MapEntriesParser::custom_map_entries_parser`], but rejects any child other than
+    /// `<entry>` instead of skipping it.
+    pub fn custom_strict_map_entries_parser<'de, D>(
+        deserializer: D,
+    ) -> Result<Vec<(K, V)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_map_entries(deserializer, true)
+    }
+}
+
+/// Reads the `<entry>` pairs of a map, with each key and value read from the element content.
+pub(crate) fn deserialize_entries<'de, D, K, V>(deserializer: D) -> Result<Vec<(K, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    MapEntriesParser::<XmlValue<K>, XmlValue<V>>::custom_map_entries_parser(deserializer)
+        .map(|entries| entries.into_iter().map(|(k, v)| (k.0, v.0)).collect())
+}
+
+/// The positions of the first entry whose key repeats an earlier one, e.g. `Some((0, 2))` when
+/// the third entry has the key of the first.
+fn find_duplicate_key<K: PartialEq, V>(entries: &[(K, V)]) -> Option<(usize, usize)> {
+    entries.iter().enumerate().find_map(|(index, (key, _))| {
+        entries[..index]
+            .iter()
+            .position(|(earlier, _)| earlier == key)
+            .map(|first| (first, index))
+    })
+}
+
+/// Checks that no key appears in more than one `<entry>`, naming the repeated key.
+pub fn check_unique_keys<K: PartialEq + Display, V>(entries: &[(K, V)]) -> Result<(), String> {
+    match find_duplicate_key(entries) {
+        Some((first, index)) => Err(format!(
+            "the key '{}' appears in both <entry> {} and <entry> {}, but every key must be unique",
+            entries[index].0,
+            first + 1,
+            index + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Checks that no key appears in more than one `<entry>`, for keys that can't be displayed.
+pub fn check_unique_key_entries<K: PartialEq, V>(entries: &[(K, V)]) -> Result<(), String> {
+    match find_duplicate_key(entries) {
+        Some((first, index)) => Err(format!(
+            "<entry> {} has the same key as <entry> {}, but every key must be unique",
+            index + 1,
+            first + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+/// How the value of a map entry is described, given its item schema.
+///
+/// An enum value is the element of one of its variants, so the schema says where that element
+/// goes instead of leaving the LLM to guess whether `<value>` itself names the variant.
+pub(crate) fn value_schema(is_enum: bool, val_schema: &str) -> String {
+    if is_enum {
+        fill(SchemaText::current().enum_value, &[val_schema])
+    } else {
+        val_schema.to_string()
+    }
+}
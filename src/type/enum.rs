@@ -1,16 +1,11 @@
 use serde::{Deserialize, Deserializer, de::DeserializeOwned};
 use std::marker::PhantomData;
 
+use super::xml::{XmlEnumName, XmlValue};
 use crate::LlmPrompt;
 
 pub struct EnumParser<T: DeserializeOwned>(PhantomData<T>);
 
-#[derive(Deserialize)]
-struct EnumWrapper<T> {
-    #[serde(rename = "$value")]
-    content: T,
-}
-
 impl<T: DeserializeOwned + LlmPrompt> EnumParser<T> {
     pub fn custom_enum_parser<'de, D>(deserializer: D) -> Result<T, D::Error>
     where
@@ -18,7 +13,23 @@ impl<T: DeserializeOwned + LlmPrompt> EnumParser<T> {
     {
         match T::IS_ENUM {
             false => T::deserialize(deserializer),
-            true => EnumWrapper::<T>::deserialize(deserializer).map(|w| w.content),
+            true => XmlValue::<T>::deserialize(deserializer).map(|w| w.0),
         }
     }
 }
+
+/// The raw name of a unit variant, used by `#[llm_prompt(text_enum)]` enums as their
+/// `#[serde(try_from = "...")]` source.
+///
+/// Accepts both `<color>Red</color>` and the element form `<color><Red/></color>`; matching the
+/// name against the variants is left to the generated `TryFrom` implementation.
+pub struct TextEnumValue(pub String);
+
+impl<'de> Deserialize<'de> for TextEnumValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        XmlEnumName::deserialize(deserializer).map(|name| TextEnumValue(name.0))
+    }
+}
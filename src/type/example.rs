@@ -0,0 +1,34 @@
+//! Building blocks of the placeholder documents returned by [`LlmPrompt::example_xml`].
+
+use crate::LlmPrompt;
+
+/// How deep [`LlmPrompt::example_xml`] nests before cutting recursive types off.
+pub(crate) const EXAMPLE_DEPTH: usize = 4;
+
+/// `<name>content</name>`, or `<name/>` for an absent value.
+pub fn example_element(name: &str, content: Option<String>) -> String {
+    match content {
+        Some(content) => format!("<{name}>{content}</{name}>"),
+        None => format!("<{name}/>"),
+    }
+}
+
+/// Two `<item>` examples of `T`, or none once `depth` runs out.
+pub(crate) fn example_items<T: LlmPrompt>(depth: usize) -> Option<String> {
+    let count = if depth == 0 { 0 } else { 2 };
+    Some(
+        (0..count)
+            .map(|_| example_element("item", T::example_item(depth - 1)))
+            .collect(),
+    )
+}
+
+/// One `<entry>` example of `K` and `V`, or none once `depth` runs out.
+pub(crate) fn example_entries<K: LlmPrompt, V: LlmPrompt>(depth: usize) -> Option<String> {
+    if depth == 0 {
+        return Some(String::new());
+    }
+    let entry = example_element("key", K::example_item(depth - 1))
+        + &example_element("value", V::example_item(depth - 1));
+    Some(example_element("entry", Some(entry)))
+}
@@ -1,4 +1,5 @@
 use super::r#macro::impl_llm_numeric_parser;
+use super::number::normalize_float;
 
 macro_rules! impl_llm_float_parser {
     (
@@ -6,7 +7,13 @@ macro_rules! impl_llm_float_parser {
     ) => {
         impl_llm_numeric_parser!(
             $ty,
-            "float value, a number that can have a fractional part, e.g., 3.14, -0.001, or 2.0"
+            crate::LeafKind::Float,
+            float,
+            "3.14",
+            normalize_float,
+            (|v: f64, _| Ok::<$ty, String>(v as $ty)),
+            // every whole number is a valid float
+            (|_: i128| String::from("it is out of range"))
         );
     };
 }
@@ -1,4 +1,26 @@
 use super::r#macro::impl_llm_numeric_parser;
+use super::number::strip_digit_grouping;
+
+/// Converts a float written for an integer, e.g. `7.0` or `4.2e1`.
+///
+/// Only allowed with `#[prompt(coerce_float)]`, and then only for whole numbers: `42.5` is an
+/// error instead of being truncated to 42.
+fn integer_from_float<T: TryFrom<i128>>(v: f64, coerce_float: bool) -> Result<T, String> {
+    if !coerce_float {
+        return Err("a whole number without a decimal point is required".to_string());
+    }
+    if !v.is_finite() || v.fract() != 0.0 {
+        return Err(format!(
+            "{} has a fractional part, but a whole number is required",
+            v
+        ));
+    }
+    // `as` saturates, so anything beyond i128 is caught by the conversion below
+    if v < i128::MIN as f64 || v >= i128::MAX as f64 {
+        return Err(format!("{} is out of range", v));
+    }
+    T::try_from(v as i128).map_err(|_| format!("{} is out of range", v))
+}
 
 macro_rules! impl_llm_integer_parser {
     (
@@ -6,7 +28,36 @@ macro_rules! impl_llm_integer_parser {
     ) => {
         impl_llm_numeric_parser!(
             $ty,
-            "integer value, a whole number without a fractional part, e.g., 42, -7, or 0"
+            crate::LeafKind::Integer,
+            integer,
+            "42",
+            strip_digit_grouping,
+            integer_from_float,
+            (|_: i128| format!(
+                "the value must be between {} and {}",
+                <$ty>::MIN,
+                <$ty>::MAX
+            ))
+        );
+    };
+    (
+        $ty:ty,unsigned
+    ) => {
+        impl_llm_numeric_parser!(
+            $ty,
+            crate::LeafKind::Integer,
+            unsigned_integer,
+            "42",
+            strip_digit_grouping,
+            integer_from_float,
+            (|whole: i128| if whole < 0 {
+                format!(
+                    "the value must be \u{2265} 0, a {} can't be negative",
+                    stringify!($ty)
+                )
+            } else {
+                format!("the value must be at most {}", <$ty>::MAX)
+            })
         );
     };
 }
@@ -16,9 +67,11 @@ impl_llm_integer_parser!(i16);
 impl_llm_integer_parser!(i32);
 impl_llm_integer_parser!(i64);
 impl_llm_integer_parser!(i128);
+impl_llm_integer_parser!(isize);
 
-impl_llm_integer_parser!(u8);
-impl_llm_integer_parser!(u16);
-impl_llm_integer_parser!(u32);
-impl_llm_integer_parser!(u64);
-impl_llm_integer_parser!(u128);
+impl_llm_integer_parser!(u8, unsigned);
+impl_llm_integer_parser!(u16, unsigned);
+impl_llm_integer_parser!(u32, unsigned);
+impl_llm_integer_parser!(u64, unsigned);
+impl_llm_integer_parser!(u128, unsigned);
+impl_llm_integer_parser!(usize, unsigned);
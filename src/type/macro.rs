@@ -1,17 +1,32 @@
 macro_rules! impl_llm_numeric_parser {
     (
         $ty:ty,
-        $prompt:expr
+        $kind:expr,
+        $prompt:ident,
+        $example:expr,
+        $normalize:expr,
+        $from_float:expr,
+        $out_of_range:expr
     ) => {
         paste::paste! {
             pub fn [<custom_ $ty _parser>]<'de, D>(deserializer: D) -> Result<$ty, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                [<custom_ $ty _format_parser>](deserializer, crate::NumberFormat::default())
+            }
+
+            /// Like the plain parser, with the leniency of the field's `#[prompt(...)]` flags.
+            pub fn [<custom_ $ty _format_parser>]<'de, D>(deserializer: D, format: crate::NumberFormat) -> Result<$ty, D::Error>
             where
                 D: serde::Deserializer<'de>,
             {
                 use serde::de::{self, Visitor};
                 use std::fmt;
 
-                struct MyVisitor;
+                struct MyVisitor {
+                    format: crate::NumberFormat,
+                }
 
                 impl<'de> Visitor<'de> for MyVisitor {
                     type Value = $ty;
@@ -32,35 +47,121 @@ macro_rules! impl_llm_numeric_parser {
                     fn visit_u64<E>(self, v: u64) -> Result<$ty, E> { Ok(v as $ty) }
                     fn visit_u128<E>(self, v: u128) -> Result<$ty, E> { Ok(v as $ty) }
 
-                    fn visit_f32<E>(self, v: f32) -> Result<$ty, E> { Ok(v as $ty) }
-                    fn visit_f64<E>(self, v: f64) -> Result<$ty, E> { Ok(v as $ty) }
+                    fn visit_f32<E>(self, v: f32) -> Result<$ty, E>
+                    where
+                        E: de::Error,
+                    {
+                        self.visit_f64(v as f64)
+                    }
+
+                    fn visit_f64<E>(self, v: f64) -> Result<$ty, E>
+                    where
+                        E: de::Error,
+                    {
+                        $from_float(v, self.format.coerce_float).map_err(|reason| {
+                            de::Error::custom(format!("can not read {} as a {} value, {}", v, stringify!($ty), reason))
+                        })
+                    }
 
                     fn visit_str<E>(self, v: &str) -> Result<$ty, E>
                     where
                         E: de::Error,
                     {
+                        // the LLM sometimes quotes numbers like a JSON string, e.g. "12"
                         let val_str = v.trim();
-                        lexical_core::parse::<$ty>(val_str.as_bytes())
-                            .map_err(|_| de::Error::custom(format!("can not parse '{}' as a {} value", v, stringify!($ty))))
+                        let val_str = val_str
+                            .strip_prefix('"')
+                            .and_then(|s| s.strip_suffix('"'))
+                            .map_or(val_str, str::trim);
+                        let val_str = if self.format.strip_suffix {
+                            crate::r#type::number::strip_unit(val_str)
+                        } else {
+                            val_str
+                        };
+                        let (val_str, scale) = match val_str.strip_suffix('%') {
+                            Some(percent) if self.format.percent => (percent.trim_end(), 100.0),
+                            _ => (val_str, 1.0),
+                        };
+                        let val_str = $normalize(val_str);
+                        if scale == 1.0 {
+                            if let Ok(value) = lexical_core::parse::<$ty>(val_str.as_bytes()) {
+                                return Ok(value);
+                            }
+                            // a whole number that doesn't fit, e.g. -5 for a u8
+                            if let Ok(whole) = lexical_core::parse::<i128>(val_str.as_bytes()) {
+                                return Err(de::Error::custom(format!("can not parse '{}' as a {} value, {}", v, stringify!($ty), $out_of_range(whole))));
+                            }
+                        }
+                        match lexical_core::parse::<f64>(val_str.as_bytes()) {
+                            Ok(float) => $from_float(float / scale, self.format.coerce_float).map_err(|reason| {
+                                de::Error::custom(format!("can not parse '{}' as a {} value, {}", v, stringify!($ty), reason))
+                            }),
+                            Err(_) => Err(de::Error::custom(format!("can not parse '{}' as a {} value", v, stringify!($ty)))),
+                        }
+                    }
+
+                    // fields reached through `#[serde(flatten)]` arrive as a map holding the text
+                    fn visit_map<A>(self, map: A) -> Result<$ty, A::Error>
+                    where
+                        A: de::MapAccess<'de>,
+                    {
+                        use serde::Deserialize;
+
+                        let crate::r#type::xml::XmlText(text) =
+                            crate::r#type::xml::XmlText::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                        self.visit_str(&text)
                     }
                 }
 
-                deserializer.[<deserialize_ $ty>](MyVisitor)
+                deserializer.deserialize_any(MyVisitor { format })
             }
 
             impl crate::LlmPrompt for $ty {
                 fn get_prompt_schema() -> &'static str {
-                    $prompt
+                    crate::SchemaText::current().$prompt
                 }
 
                 fn root_name() -> &'static str {
                     stringify!($ty)
                 }
 
+                fn example_content(_depth: usize) -> Option<String> {
+                    Some($example.to_string())
+                }
+
+                fn schema_node() -> crate::SchemaNode {
+                    crate::SchemaNode::leaf::<$ty>($kind)
+                }
+
+                const ENGLISH_LEAF_SCHEMA: Option<&'static str> =
+                    Some(crate::SchemaText::ENGLISH.$prompt);
+
                 const IS_ENUM: bool = false;
+
+                const HAS_ROOT: bool = false;
             }
         }
     };
 }
 
 pub(crate) use impl_llm_numeric_parser;
+
+/// The schema node of a leaf read by one of the lenient parsers, for a type whose own
+/// `Deserialize` doesn't take the text its prompt asks for, e.g. `90s` for a `Duration`.
+macro_rules! parsed_leaf {
+    (
+        $kind:expr,
+        $parser:path
+    ) => {
+        crate::SchemaNode::Leaf {
+            kind: $kind,
+            schema: Self::get_prompt_schema(),
+            check: Some(
+                (|xml: &str| crate::check_field(xml, |de| $parser(de).map(drop)))
+                    as crate::ElementCheck,
+            ),
+        }
+    };
+}
+
+pub(crate) use parsed_leaf;
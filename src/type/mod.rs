@@ -1,61 +1,236 @@
+mod base64;
+pub use base64::*;
 mod bool;
 pub use bool::*;
+mod char;
+pub use char::*;
+mod duration;
+pub use duration::*;
+mod example;
+pub use example::*;
 mod r#enum;
 pub use r#enum::*;
 mod float;
 pub use float::*;
 mod integer;
 pub use integer::*;
+mod net;
+pub use net::*;
+mod nonzero;
+pub use nonzero::*;
+mod number;
+pub use number::*;
 mod option;
 pub use option::*;
+mod path;
+pub use path::*;
 mod string;
 pub use string::*;
 mod vector;
 pub use vector::*;
 mod btreemap;
 pub use btreemap::*;
+mod entry;
+pub use entry::*;
 mod hashmap;
 pub use hashmap::*;
+mod set;
+pub use set::*;
+mod between;
+pub use between::*;
+mod boxed;
+pub use boxed::*;
+mod constraint;
+mod schema;
+mod shared;
+mod text;
+mod tuple;
+pub use constraint::*;
+pub use schema::*;
+pub use text::*;
+pub use tuple::*;
 mod third;
-#[cfg(any(feature = "third", feature = "ordered_float"))]
+pub(crate) mod xml;
+#[cfg(any(
+    feature = "third",
+    feature = "ordered_float",
+    feature = "indexmap",
+    feature = "chrono",
+    feature = "time",
+    feature = "uuid",
+    feature = "url",
+    feature = "decimal",
+    feature = "json"
+))]
 pub use third::*;
 
 mod r#macro;
 
-use dashmap::DashMap;
 use std::{
     any::TypeId,
+    cell::{Cell, RefCell},
+    hash::{Hash, Hasher},
     sync::{LazyLock, OnceLock},
 };
 
-// due to the ICF could be wrongly implemented by the compiler, we have to use a global cache to store the prompt schema and root name for each type, and use the type id as the key to access the cache.
-// although this may cause a performance issue, but it is the only way to ensure the correctness of the prompt schema and root name for each type.
-// the case is that generate a big prompt is really time consuming, so we have to cache the prompt schema and root name for each type to avoid generating the prompt schema and root name for each type every time we need to use it.
-// maybe future we can use a more efficient way to store the prompt schema and root name for each type, but for now, this is the only way to ensure the correctness of the prompt schema and root name for each type.
-// Now is RUST 1.92
+// Rust has no generic statics, and a static inside a generic function is one static shared by
+// every instantiation, so the cache of each type is found by its `TypeId` instead. Schemas are
+// handed out as `&'static str`, so the caches are slots of chunks owned by a static rather than
+// leaked allocations: a lookup is an atomic load and a comparison of keys, and a full chunk is
+// followed by one twice its size.
 
+/// The schemas of a type in one language, rendered on first use.
 pub struct CacheInner {
     pub prompt_schema: OnceLock<String>,
+    pub fields_schema: OnceLock<String>,
     pub root_name: OnceLock<String>,
 }
 
-static CACHE_HOLDER: LazyLock<DashMap<TypeId, &'static CacheInner>> = LazyLock::new(DashMap::new);
+/// The number of slots of the first chunk.
+const FIRST_CHUNK: usize = 256;
+/// The number of slots tried in a chunk before moving on to the next one.
+const MAX_PROBES: usize = 16;
+
+/// The cache of one type in one language, claimed by the first thread that asks for a key
+/// hashing to it or to a claimed slot before it.
+struct Slot {
+    key: OnceLock<(TypeId, &'static str)>,
+    inner: CacheInner,
+}
+
+/// Slots of the cache, and the chunk taking the keys whose slots in this one are claimed.
+///
+/// Slots are never given back, so a key finding a free slot within its probes in a chunk is in
+/// none of the later ones.
+struct Chunk {
+    slots: Box<[Slot]>,
+    next: OnceLock<Box<Chunk>>,
+}
+
+impl Chunk {
+    fn new(len: usize) -> Self {
+        let slot = || Slot {
+            key: OnceLock::new(),
+            inner: CacheInner {
+                prompt_schema: OnceLock::new(),
+                fields_schema: OnceLock::new(),
+                root_name: OnceLock::new(),
+            },
+        };
+        Chunk {
+            slots: (0..len).map(|_| slot()).collect(),
+            next: OnceLock::new(),
+        }
+    }
+}
+
+static CACHE: LazyLock<Chunk> = LazyLock::new(|| Chunk::new(FIRST_CHUNK));
+
+thread_local! {
+    // the types whose schema is being built on this thread, innermost last
+    static SCHEMA_IN_PROGRESS: RefCell<Vec<TypeId>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Hashes the keys of the cache by mixing their words, as a `TypeId` is a hash already.
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u64(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Takes the innermost type off the schemas in progress when the build is done, even if it
+/// panicked.
+struct InProgress;
+
+impl Drop for InProgress {
+    fn drop(&mut self) {
+        SCHEMA_IN_PROGRESS.with(|stack| stack.borrow_mut().pop());
+    }
+}
 
 pub struct Cache<T>(std::marker::PhantomData<T>);
 
 impl<T: 'static> Cache<T> {
+    /// Returns the cache of `T` for the language of [`SchemaText::current`].
+    ///
+    /// Once any thread has looked it up, this takes no lock and allocates nothing.
     pub fn get() -> &'static CacheInner {
+        let key = (TypeId::of::<T>(), SchemaText::current().language);
+        let mut hasher = KeyHasher::default();
+        key.hash(&mut hasher);
+        let start = hasher.finish() as usize;
+        let mut chunk: &'static Chunk = &CACHE;
+        loop {
+            // probes the slots from the one the key hashes to, claiming the first free one
+            let len = chunk.slots.len();
+            for offset in 0..MAX_PROBES {
+                let slot = &chunk.slots[(start + offset) % len];
+                let (tid, language) = *slot.key.get_or_init(|| key);
+                if tid == key.0 && (std::ptr::eq(language, key.1) || language == key.1) {
+                    return &slot.inner;
+                }
+            }
+            chunk = chunk.next.get_or_init(|| Box::new(Chunk::new(len * 2)));
+        }
+    }
+
+    /// Builds the schema of `T`, recording that it is in progress while `build` runs.
+    ///
+    /// A recursive type reaches itself again through `Box<T>`, which checks [`Cache::is_building`]
+    /// and refers back to the outer schema instead of re-entering its initialization.
+    pub fn build_schema(build: impl FnOnce() -> String) -> String {
+        SCHEMA_IN_PROGRESS.with(|stack| stack.borrow_mut().push(TypeId::of::<T>()));
+        let _in_progress = InProgress;
+        build()
+    }
+
+    /// Returns whether the schema of `T` is currently being built on this thread.
+    pub fn is_building() -> bool {
         let tid = TypeId::of::<T>();
+        SCHEMA_IN_PROGRESS.with(|stack| stack.borrow().contains(&tid))
+    }
+}
+
+/// A container written without the elements of the grammar that wrap its parts, as reported by
+/// its parser, for the correction to warn about it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ContainerMistake {
+    /// A sequence whose values aren't each in an `<item>`.
+    MissingItem,
+    /// A map whose pairs aren't each in an `<entry>`.
+    MissingEntry,
+}
+
+thread_local! {
+    // the mistake of the innermost container that failed in the document being read
+    static CONTAINER_MISTAKE: Cell<Option<ContainerMistake>> = const { Cell::new(None) };
+}
 
-        if let Some(inner) = CACHE_HOLDER.get(&tid) {
-            return *inner;
+/// Records that a container failed with `mistake`, unless one inside it did already.
+pub(crate) fn report_container_mistake(mistake: ContainerMistake) {
+    CONTAINER_MISTAKE.with(|reported| {
+        if reported.get().is_none() {
+            reported.set(Some(mistake));
         }
+    });
+}
 
-        *CACHE_HOLDER.entry(tid).or_insert_with(|| {
-            Box::leak(Box::new(CacheInner {
-                prompt_schema: OnceLock::new(),
-                root_name: OnceLock::new(),
-            }))
-        })
-    }
+/// The mistake recorded since the last call, clearing it.
+pub(crate) fn take_container_mistake() -> Option<ContainerMistake> {
+    CONTAINER_MISTAKE.take()
 }
@@ -0,0 +1,80 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+macro_rules! impl_llm_net_parser {
+    (
+        $ty:ident,
+        $parser:ident,
+        $description:expr,
+        $example:expr
+    ) => {
+        pub fn $parser<'de, D>(deserializer: D) -> Result<$ty, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            use serde::Deserialize;
+
+            let text = super::xml::XmlText::deserialize(deserializer)?;
+            let clean_s = text.clean();
+
+            clean_s.parse::<$ty>().map_err(|_| {
+                serde::de::Error::custom(format!(
+                    concat!(
+                        "can not parse '{}' as ",
+                        $description,
+                        ", it must look like ",
+                        $example
+                    ),
+                    clean_s
+                ))
+            })
+        }
+
+        impl crate::LlmPrompt for $ty {
+            fn get_prompt_schema() -> &'static str {
+                concat!($description, ", e.g. ", $example)
+            }
+
+            fn root_name() -> &'static str {
+                stringify!($ty)
+            }
+
+            fn example_content(_depth: usize) -> Option<String> {
+                // the first of the examples, e.g. `192.168.1.10` of `192.168.1.10 or 2001:db8::1`
+                $example.split(" or ").next().map(str::to_string)
+            }
+
+            fn schema_node() -> crate::SchemaNode {
+                super::r#macro::parsed_leaf!(crate::LeafKind::String, $parser)
+            }
+
+            const IS_ENUM: bool = false;
+
+            const HAS_ROOT: bool = false;
+        }
+    };
+}
+
+impl_llm_net_parser!(
+    IpAddr,
+    custom_ip_addr_parser,
+    "an IPv4 or IPv6 address",
+    "192.168.1.10 or 2001:db8::1"
+);
+impl_llm_net_parser!(
+    Ipv4Addr,
+    custom_ipv4_addr_parser,
+    "an IPv4 address in dotted decimal form",
+    "192.168.1.10"
+);
+impl_llm_net_parser!(
+    Ipv6Addr,
+    custom_ipv6_addr_parser,
+    "an IPv6 address in colon separated hex form",
+    "2001:db8::1"
+);
+impl_llm_net_parser!(
+    SocketAddr,
+    custom_socket_addr_parser,
+    "an IP address with a port, IPv6 addresses go inside brackets",
+    "192.168.1.10:8080 or [2001:db8::1]:443"
+);
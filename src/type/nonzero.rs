@@ -0,0 +1,82 @@
+use std::num::{
+    NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize, NonZeroU8,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize,
+};
+
+macro_rules! impl_llm_non_zero_parser {
+    (
+        $ty:ident,
+        $inner:ident,
+        $prompt:ident
+    ) => {
+        paste::paste! {
+            pub fn [<custom_ $ty:snake _parser>]<'de, D>(deserializer: D) -> Result<$ty, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value = crate::[<custom_ $inner _parser>](deserializer)?;
+                $ty::new(value).ok_or_else(|| {
+                    serde::de::Error::custom(concat!(
+                        "can not accept 0 as a ",
+                        stringify!($ty),
+                        " value, it must not be zero"
+                    ))
+                })
+            }
+
+            impl crate::LlmPrompt for $ty {
+                fn get_prompt_schema() -> &'static str {
+                    crate::SchemaText::current().$prompt
+                }
+
+                fn root_name() -> &'static str {
+                    stringify!($ty)
+                }
+
+                fn example_content(_depth: usize) -> Option<String> {
+                    Some("42".to_string())
+                }
+
+                fn schema_node() -> crate::SchemaNode {
+                    crate::SchemaNode::leaf::<$ty>(crate::LeafKind::Integer)
+                }
+
+                const IS_ENUM: bool = false;
+
+                const HAS_ROOT: bool = false;
+            }
+        }
+    };
+}
+
+macro_rules! impl_llm_non_zero_unsigned_parser {
+    (
+        $ty:ident,
+        $inner:ident
+    ) => {
+        impl_llm_non_zero_parser!($ty, $inner, positive_integer);
+    };
+}
+
+macro_rules! impl_llm_non_zero_signed_parser {
+    (
+        $ty:ident,
+        $inner:ident
+    ) => {
+        impl_llm_non_zero_parser!($ty, $inner, non_zero_integer);
+    };
+}
+
+impl_llm_non_zero_signed_parser!(NonZeroI8, i8);
+impl_llm_non_zero_signed_parser!(NonZeroI16, i16);
+impl_llm_non_zero_signed_parser!(NonZeroI32, i32);
+impl_llm_non_zero_signed_parser!(NonZeroI64, i64);
+impl_llm_non_zero_signed_parser!(NonZeroI128, i128);
+impl_llm_non_zero_signed_parser!(NonZeroIsize, isize);
+
+impl_llm_non_zero_unsigned_parser!(NonZeroU8, u8);
+impl_llm_non_zero_unsigned_parser!(NonZeroU16, u16);
+impl_llm_non_zero_unsigned_parser!(NonZeroU32, u32);
+impl_llm_non_zero_unsigned_parser!(NonZeroU64, u64);
+impl_llm_non_zero_unsigned_parser!(NonZeroU128, u128);
+impl_llm_non_zero_unsigned_parser!(NonZeroUsize, usize);
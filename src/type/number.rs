@@ -0,0 +1,86 @@
+use std::borrow::Cow;
+
+/// How leniently a numeric field reads its text, set by the field's `#[prompt(...)]` flags.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumberFormat {
+    /// Accept a whole number written as a float, e.g. `7.0` for an integer.
+    pub coerce_float: bool,
+    /// Read a trailing `%` as a percentage, e.g. `85%` as `0.85`.
+    pub percent: bool,
+    /// Ignore a unit written after the number and a space, e.g. `3.2 points`.
+    pub strip_suffix: bool,
+}
+
+/// Cuts a unit off the end of a number, at the first space that isn't followed by a digit.
+///
+/// Spaces between digits are kept, so `1 234 points` leaves the grouped `1 234`.
+pub(crate) fn strip_unit(s: &str) -> &str {
+    s.char_indices()
+        .filter(|(_, c)| c.is_whitespace())
+        .find(|(i, _)| {
+            !s[*i..]
+                .trim_start()
+                .starts_with(|c: char| c.is_ascii_digit())
+        })
+        .map_or(s, |(i, _)| s[..i].trim_end())
+}
+
+/// Removes thousands separators from an integer, e.g. `1,234,567`, `1 234 567` or `1_000_000`.
+///
+/// Commas and spaces have to split the digits into groups of three, so a malformed `12,34` is
+/// left alone and fails to parse. Underscores may group digits freely, as in Rust literals.
+pub(crate) fn strip_digit_grouping(s: &str) -> Cow<'_, str> {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let sign = &s[..s.len() - digits.len()];
+
+    let separators = [',', ' ', '_'];
+    let used: Vec<char> = separators
+        .into_iter()
+        .filter(|sep| digits.contains(*sep))
+        .collect();
+    let [sep] = used[..] else {
+        // nothing to strip, or separators mixed in a way that is not a grouping
+        return Cow::Borrowed(s);
+    };
+
+    let groups: Vec<&str> = digits.split(sep).collect();
+    let is_digits = |group: &str| !group.is_empty() && group.bytes().all(|b| b.is_ascii_digit());
+    let well_formed = if sep == '_' {
+        groups.iter().all(|group| is_digits(group))
+    } else {
+        (1..=3).contains(&groups[0].len())
+            && groups.iter().all(|group| is_digits(group))
+            && groups[1..].iter().all(|group| group.len() == 3)
+    };
+
+    if well_formed {
+        Cow::Owned(format!("{}{}", sign, groups.concat()))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Rewrites the ways LLMs write floats into the form the parser reads.
+///
+/// `nan`, `inf`, `infinity` and `∞` are accepted in any case. Thousands separators are removed
+/// as for integers, so `1,234` is 1234, while a single comma that doesn't group the digits is a
+/// decimal comma, so `3,14` is 3.14.
+pub(crate) fn normalize_float(s: &str) -> Cow<'_, str> {
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let sign = &s[..s.len() - unsigned.len()];
+    match unsigned.to_lowercase().as_str() {
+        "nan" => return Cow::Borrowed("NaN"),
+        "inf" | "infinity" | "∞" => return Cow::Owned(format!("{}inf", sign)),
+        _ => {}
+    }
+
+    let (whole, fraction) = s.split_once('.').map_or((s, None), |(w, f)| (w, Some(f)));
+    match (strip_digit_grouping(whole), fraction) {
+        (Cow::Owned(whole), Some(fraction)) => Cow::Owned(format!("{}.{}", whole, fraction)),
+        (Cow::Owned(whole), None) => Cow::Owned(whole),
+        (Cow::Borrowed(_), None) if s.matches(',').count() == 1 => {
+            Cow::Owned(s.replacen(',', ".", 1))
+        }
+        (Cow::Borrowed(_), _) => Cow::Borrowed(s),
+    }
+}
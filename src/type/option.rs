@@ -1,20 +1,21 @@
 use super::Cache;
-use crate::LlmPrompt;
+use super::xml::{XmlOptionalElement, XmlOptionalValue};
+use crate::{LlmPrompt, SchemaNode};
 use serde::{Deserialize, Deserializer, de::DeserializeOwned};
 use std::marker::PhantomData;
 
-pub struct OptionParser<T: DeserializeOwned>(PhantomData<T>);
+/// The cache key of the item schema of `Option<T>`, which differs from its field schema.
+struct OptionItem<T>(PhantomData<T>);
 
-#[derive(Deserialize)]
-#[serde(transparent)]
-struct XmlOption<T>(Option<T>);
+pub struct OptionParser<T: DeserializeOwned>(PhantomData<T>);
 
 impl<T: DeserializeOwned> OptionParser<T> {
+    /// Reads an optional value, where an empty element is `None` like a missing one.
     pub fn custom_option_parser<'de, D>(deserializer: D) -> Result<Option<T>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        match XmlOption::<T>::deserialize(deserializer) {
+        match XmlOptionalElement::<T>::deserialize(deserializer) {
             Ok(wrapper) => Ok(wrapper.0),
             Err(e) => Err(serde::de::Error::custom(format!(
                 "The XML structure is invalid. Reason: {}",
@@ -24,22 +25,87 @@ impl<T: DeserializeOwned> OptionParser<T> {
     }
 }
 
+/// Parses an optional element, e.g. an `Option<T>` field or an `<item>` of a `Vec<Option<T>>`.
+///
+/// An empty element (`<item/>` or `<item></item>`) is `None`. Anything else is `Some`, and
+/// content that doesn't parse as `T` is an error instead of being dropped.
+pub struct OptionItemParser<T>(PhantomData<T>);
+
+impl<T: DeserializeOwned> OptionItemParser<T> {
+    /// Hands a non-empty element to `T` whole, for types with a parser of their own.
+    pub fn custom_option_item_parser<'de, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        XmlOptionalElement::<T>::deserialize(deserializer).map(|w| w.0)
+    }
+
+    /// Reads `T` from inside a non-empty element, for structs and enums written as
+    /// `<item><Person>..</Person></item>`.
+    pub fn custom_option_value_parser<'de, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        XmlOptionalValue::<T>::deserialize(deserializer).map(|w| w.0)
+    }
+}
+
+impl<T: DeserializeOwned + LlmPrompt> OptionItemParser<T> {
+    /// Reads an optional struct or enum field, holding the struct's fields or the variant's
+    /// element directly.
+    pub fn custom_option_field_parser<'de, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match T::IS_ENUM {
+            false => XmlOptionalElement::<T>::deserialize(deserializer).map(|w| w.0),
+            true => XmlOptionalValue::<T>::deserialize(deserializer).map(|w| w.0),
+        }
+    }
+}
+
 impl<T: LlmPrompt + 'static> LlmPrompt for Option<T> {
     fn get_prompt_schema() -> &'static str {
-        let sub_schema = T::get_prompt_schema();
         let cache = Cache::<Option<T>>::get();
-        cache.prompt_schema.get_or_init(|| {
-            format!("Optional. if not provided, do not include any tags. If provided, the format is: {}", sub_schema)
-        })
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render())
+    }
+
+    fn get_item_schema() -> &'static str {
+        let cache = Cache::<OptionItem<T>>::get();
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render_item())
     }
 
     fn root_name() -> &'static str {
-        let sub_root_name = T::root_name();
         let cache = Cache::<Option<T>>::get();
         cache
             .root_name
-            .get_or_init(|| format!("Option<{}>", sub_root_name))
+            .get_or_init(|| format!("Option<{}>", T::root_name()))
+    }
+
+    /// The present form, until `depth` runs out and the element is left out.
+    fn example_content(depth: usize) -> Option<String> {
+        if depth == 0 {
+            return None;
+        }
+        T::example_content(depth)
+    }
+
+    fn example_item(depth: usize) -> Option<String> {
+        if depth == 0 {
+            return None;
+        }
+        T::example_item(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Optional(T::schema_node)
     }
 
     const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
 }
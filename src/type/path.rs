@@ -0,0 +1,37 @@
+use super::r#macro::parsed_leaf;
+use super::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use serde::{Deserialize, Deserializer};
+use std::path::PathBuf;
+
+pub fn custom_path_buf_parser<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+
+    // only the surrounding whitespace goes, separators are kept exactly as written
+    Ok(PathBuf::from(text.clean()))
+}
+
+impl LlmPrompt for PathBuf {
+    fn get_prompt_schema() -> &'static str {
+        "return a filesystem path, written exactly as it appears (keep / or \\ separators and spaces unchanged). please use the format <![CDATA[{path}]]> to return the path, e.g. <![CDATA[C:\\Program Files\\app\\config.toml]]> or <![CDATA[/var/log/app.log]]>"
+    }
+
+    fn root_name() -> &'static str {
+        "PathBuf"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("<![CDATA[/var/log/app.log]]>".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_path_buf_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
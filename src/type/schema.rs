@@ -0,0 +1,652 @@
+//! The structure of the XML written for each type, which the prompt schema is rendered from and
+//! [`validate_xml`](crate::validate_xml) walks a document against.
+//!
+//! The nodes of the children are held as functions building them, so a recursive type is only
+//! expanded as deep as the document being walked, or until rendering reaches it again.
+
+use super::entry::value_schema;
+use super::text::{SchemaText, fill, text_enum_schema};
+use super::xml::XmlResponse;
+use crate::LlmPrompt;
+use quick_xml::{
+    DeError,
+    de::{Deserializer, SliceReader},
+};
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+
+/// Checks an element of a field or value, given as its whole XML, e.g. `<age>42</age>`.
+pub type ElementCheck = fn(&str) -> Result<(), String>;
+
+/// What kind of text a [`SchemaNode::Leaf`] holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeafKind {
+    String,
+    /// A whole number, signed or not.
+    Integer,
+    Float,
+    Bool,
+    Char,
+    /// One of the given names, e.g. a `text_enum`, each with its description, empty if it has
+    /// none.
+    OneOf(&'static [(&'static str, &'static str)]),
+}
+
+/// How a schema is written in the prompt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaStyle {
+    /// Indented, with each rule spelled out, as [`LlmPrompt::get_prompt_schema`] returns it.
+    #[default]
+    Full,
+    /// Without indentation, with short notes in place of the sentences on optional values,
+    /// items and entries, and a struct or enum that appears again referring to its first
+    /// description. Takes far fewer tokens, and leaves a little more to the model.
+    Compact,
+    /// Like [`SchemaStyle::Full`], with the descriptions listed in a legend after the schema,
+    /// which the model is told not to reproduce, instead of comments next to the elements, for
+    /// models that copy the comments into their output.
+    Legend,
+}
+
+/// The structure of the element of a type, as returned by [`LlmPrompt::schema_node`].
+#[derive(Clone, Debug)]
+pub enum SchemaNode {
+    /// A type described only by its own `schema`, e.g. a tuple or a hand-written impl. Its
+    /// content isn't checked.
+    Custom { schema: &'static str },
+    /// Text without child elements, such as a string or a number, described by `schema` and
+    /// read by `check` if any.
+    Leaf {
+        kind: LeafKind,
+        schema: &'static str,
+        check: Option<ElementCheck>,
+    },
+    /// A value that may also be written as an empty element, e.g. an `Option<T>`.
+    Optional(fn() -> SchemaNode),
+    /// The field elements of a struct, which a value position wraps in the `root` element.
+    Struct {
+        root: &'static str,
+        fields: Vec<SchemaField>,
+    },
+    /// A single element naming one of the variants of the enum `name`.
+    Enum {
+        name: &'static str,
+        variants: Vec<SchemaVariant>,
+    },
+    /// `<item>` elements holding the values, distinct ones for a set, in the order they are
+    /// written if `ordered`, e.g. for a `Vec` or an `IndexSet`.
+    Sequence {
+        item: fn() -> SchemaNode,
+        unique: bool,
+        ordered: bool,
+    },
+    /// `<entry>` elements holding a `<key>` and a `<value>`, in the order they are written if
+    /// `ordered`, e.g. for an `IndexMap`.
+    Map {
+        key: fn() -> SchemaNode,
+        value: fn() -> SchemaNode,
+        ordered: bool,
+    },
+    /// Field elements written without an element of their own around them, e.g. the `<min>`
+    /// and `<max>` of a `Between` or the `<first>` and `<second>` of a tuple, which the prompt
+    /// describes by `schema`.
+    Fields {
+        schema: &'static str,
+        fields: Vec<SchemaField>,
+    },
+    /// A type the prompt describes by a `schema` referring to one given elsewhere, e.g. the
+    /// `weak` shadow of a recursive type, with the structure of `node`.
+    Reference {
+        schema: &'static str,
+        node: fn() -> SchemaNode,
+    },
+}
+
+impl SchemaNode {
+    /// The leaf of `T`, described by its prompt schema and read by its lenient parser, e.g.
+    /// `42` or ` yes ` for a `bool`.
+    pub fn leaf<T: LlmPrompt + DeserializeOwned>(kind: LeafKind) -> Self {
+        SchemaNode::Leaf {
+            kind,
+            schema: T::get_prompt_schema(),
+            check: Some(check_element::<T>),
+        }
+    }
+
+    /// The schema of the node as written in the prompt, i.e. what
+    /// [`LlmPrompt::get_prompt_schema`] returns for its type.
+    pub fn render(&self) -> String {
+        Renderer::new(SchemaStyle::Full).node(self)
+    }
+
+    /// The schema of the node as written in the prompt in `style`, i.e. what
+    /// [`LlmPrompt::get_prompt_schema_compact`] returns for [`SchemaStyle::Compact`].
+    pub fn render_with_style(&self, style: SchemaStyle) -> String {
+        self.render_document(style, None, false)
+    }
+
+    /// The schema of the node written as a container element such as an `<item>`, i.e. what
+    /// [`LlmPrompt::get_item_schema`] returns for its type.
+    pub fn render_item(&self) -> String {
+        Renderer::new(SchemaStyle::Full).item(self)
+    }
+
+    /// The schema of the node written as a container element in `style`.
+    pub fn render_item_with_style(&self, style: SchemaStyle) -> String {
+        self.render_document(style, None, true)
+    }
+
+    /// The schema of the node in `style`, expanding at most `max_depth` levels of nested
+    /// structs and enums, the outermost included, for a type too deep to be written out whole.
+    ///
+    /// The ones below are written as a short note without their fields, which leaves the model
+    /// to guess them. A recursive type needs no limit: it refers back to itself once reached
+    /// again, at any depth.
+    pub fn render_with_max_depth(&self, style: SchemaStyle, max_depth: usize) -> String {
+        self.render_document(style, Some(max_depth), false)
+    }
+
+    /// The schema of the node, or of the node written as a container element if `item`.
+    pub(crate) fn render_document(
+        &self,
+        style: SchemaStyle,
+        max_depth: Option<usize>,
+        item: bool,
+    ) -> String {
+        let mut renderer = Renderer::new(style);
+        renderer.max_depth = max_depth;
+        let schema = match item {
+            true => renderer.item(self),
+            false => renderer.node(self),
+        };
+        renderer.finish(schema)
+    }
+
+    /// The schema of the fields of a struct without its root element, i.e. what
+    /// [`LlmPrompt::get_fields_schema`] returns for its type. Other nodes are rendered whole.
+    pub fn render_fields(&self) -> String {
+        match self {
+            SchemaNode::Struct { root, fields } => {
+                Renderer::new(SchemaStyle::Full).fields(root, fields)
+            }
+            node => node.render(),
+        }
+    }
+}
+
+/// A field of a struct or of an enum variant.
+#[derive(Clone, Debug)]
+pub struct SchemaField {
+    /// The element name of the field, empty for a flattened one.
+    pub tag: &'static str,
+    /// The description of its `#[prompt(...)]`, with the notes of its constraints.
+    pub description: &'static str,
+    /// Whether the element has to be written, i.e. the field is neither an `Option` nor
+    /// `#[serde(default)]`.
+    pub required: bool,
+    /// Whether the field is `#[prompt(flatten)]`, with the fields of its struct written in
+    /// place of it.
+    pub flatten: bool,
+    /// The structure of the content of the element.
+    pub node: fn() -> SchemaNode,
+    /// Reads the element with the field's own parser, which also applies the constraints of
+    /// its `#[prompt(...)]`.
+    pub check: Option<ElementCheck>,
+    /// The constraints of its `#[prompt(...)]`, on the value inside an `Option`.
+    pub constraints: FieldConstraints,
+}
+
+impl SchemaField {
+    /// A required field `tag` holding `node`, without a description or constraints, e.g. the
+    /// `<first>` of a tuple.
+    pub fn element(tag: &'static str, node: fn() -> SchemaNode) -> Self {
+        SchemaField {
+            tag,
+            description: "",
+            required: true,
+            flatten: false,
+            node,
+            check: None,
+            constraints: FieldConstraints::default(),
+        }
+    }
+}
+
+/// The constraints a field's `#[prompt(...)]` puts on its value, none by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldConstraints {
+    /// The values of `one_of(...)`, empty if any value is allowed.
+    pub one_of: &'static [&'static str],
+    /// The regular expression of `pattern = "..."`.
+    pub pattern: Option<&'static str>,
+    /// The inclusive bounds of `min = ...` and `max = ...`.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The bounds of `min_items = ...` and `max_items = ...`.
+    pub min_items: Option<usize>,
+    pub max_items: Option<usize>,
+}
+
+/// A variant of an enum, written as an element of its own name.
+#[derive(Clone, Debug)]
+pub struct SchemaVariant {
+    /// The element name of the variant.
+    pub tag: &'static str,
+    /// The description of its `#[prompt(...)]`.
+    pub description: &'static str,
+    /// The fields of the variant, none for a unit variant.
+    pub fields: Vec<SchemaField>,
+}
+
+/// `fields` as they are written, with the fields of each flattened struct in place of it.
+pub(crate) fn written_fields(fields: &[SchemaField]) -> Vec<SchemaField> {
+    let mut written = Vec::with_capacity(fields.len());
+    for field in fields {
+        match (field.flatten, (field.node)()) {
+            (true, SchemaNode::Struct { fields, .. }) => written.extend(written_fields(&fields)),
+            (true, _) => {}
+            (false, _) => written.push(field.clone()),
+        }
+    }
+    written
+}
+
+/// The schema of a struct reached again while its own schema is rendered.
+pub(crate) fn recursive_struct_schema(root: &str) -> String {
+    let text = fill(SchemaText::current().recursive_struct, &[root, root]);
+    format!("<{root}>{text}</{root}>")
+}
+
+/// The schema of an enum reached again while its own schema is rendered.
+pub(crate) fn recursive_enum_schema() -> String {
+    SchemaText::current().recursive_enum.to_string()
+}
+
+/// Renders the prompt schema of a tree of nodes, with the sentences of the current
+/// [`SchemaText`].
+struct Renderer {
+    style: SchemaStyle,
+    text: &'static SchemaText,
+    // the structs and enums being rendered, outermost first, to refer back to on recursion
+    open: Vec<&'static str>,
+    // the structs and enums described so far in the compact style, to refer back to
+    described: Vec<&'static str>,
+    // the lines of the legend in the legend style, each description once
+    legend: Vec<String>,
+    // how many structs and enums enclose the node being rendered, and how many may
+    depth: usize,
+    max_depth: Option<usize>,
+}
+
+impl Renderer {
+    fn new(style: SchemaStyle) -> Self {
+        Renderer {
+            style,
+            text: SchemaText::current(),
+            open: Vec::new(),
+            described: Vec::new(),
+            legend: Vec::new(),
+            depth: 0,
+            max_depth: None,
+        }
+    }
+
+    /// `schema` followed by the legend, if any.
+    fn finish(self, schema: String) -> String {
+        match self.legend.is_empty() {
+            true => schema,
+            false => format!(
+                "{}\n{}\n{}",
+                schema,
+                self.text.legend,
+                self.legend.join("\n")
+            ),
+        }
+    }
+
+    /// Lists `description` in the legend as that of `tag` in `owner`, or of the value `tag`
+    /// without an owner, in the legend style.
+    ///
+    /// Called before the children are rendered, so that the legend reads from the outside in.
+    fn note(&mut self, owner: &str, tag: &str, description: &str) {
+        let line = match owner.is_empty() {
+            true => format!("- {}: {}", tag, description),
+            false => format!("- {}/{}: {}", owner, tag, description),
+        };
+        if self.style == SchemaStyle::Legend
+            && !description.is_empty()
+            && !self.legend.contains(&line)
+        {
+            self.legend.push(line);
+        }
+    }
+
+    /// `element` with a comment holding `description` after it, but in the legend style.
+    fn describe(&self, element: String, description: &str) -> String {
+        match self.style {
+            SchemaStyle::Legend => element,
+            _ => format!("{} <!-- {} -->", element, comment_text(description)),
+        }
+    }
+
+    fn node(&mut self, node: &SchemaNode) -> String {
+        let name = match node {
+            SchemaNode::Struct { root: name, .. } | SchemaNode::Enum { name, .. } => Some(*name),
+            _ => None,
+        };
+        let Some(name) = name else {
+            return self.styled_node(node);
+        };
+        // a type already being rendered is referred to as it is, however deep
+        let reached = self.open.contains(&name) || self.described.contains(&name);
+        if !reached && self.max_depth.is_some_and(|max| self.depth >= max) {
+            return match node {
+                SchemaNode::Struct { .. } => {
+                    let text = fill(self.text.truncated_struct, &[name]);
+                    format!("<{name}>{text}</{name}>")
+                }
+                _ => self.text.truncated_enum.to_string(),
+            };
+        }
+        self.depth += 1;
+        let schema = self.styled_node(node);
+        self.depth -= 1;
+        schema
+    }
+
+    fn styled_node(&mut self, node: &SchemaNode) -> String {
+        if self.style == SchemaStyle::Compact {
+            return self.compact_node(node);
+        }
+        match node {
+            // the legend says what the names mean, in place of the comments after them
+            SchemaNode::Leaf {
+                kind: LeafKind::OneOf(names),
+                ..
+            } if self.style == SchemaStyle::Legend => {
+                for (name, description) in *names {
+                    self.note("", name, description);
+                }
+                let names = names.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+                text_enum_schema(&names.join(", "), &[])
+            }
+            SchemaNode::Custom { schema }
+            | SchemaNode::Leaf { schema, .. }
+            | SchemaNode::Fields { schema, .. }
+            | SchemaNode::Reference { schema, .. } => schema.to_string(),
+            SchemaNode::Optional(inner) => {
+                let inner = self.node(&inner());
+                fill(self.text.optional, &[&inner])
+            }
+            SchemaNode::Struct { root, .. } if self.open.contains(root) => {
+                recursive_struct_schema(root)
+            }
+            SchemaNode::Struct { root, fields } => {
+                format!("<{root}>\n  {}\n</{root}>", self.fields(root, fields))
+            }
+            SchemaNode::Enum { name, .. } if self.open.contains(name) => recursive_enum_schema(),
+            SchemaNode::Enum { name, variants } => {
+                self.open.push(name);
+                let mut parts = vec![self.text.enum_variants.to_string()];
+                for variant in variants {
+                    self.note(name, variant.tag, variant.description);
+                    let inner = variant
+                        .fields
+                        .iter()
+                        .map(|field| self.field(variant.tag, field))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let element = match inner.is_empty() {
+                        true => format!("<{}/>", variant.tag),
+                        false => format!(
+                            "<{tag}>\n{inner}\n</{tag}>",
+                            tag = variant.tag,
+                            inner = indent(&inner)
+                        ),
+                    };
+                    parts.push(self.describe(element, variant.description));
+                }
+                self.open.pop();
+                parts.join("\n")
+            }
+            SchemaNode::Sequence {
+                item,
+                unique: false,
+                ..
+            } => {
+                let item = self.item(&item());
+                fill(self.text.sequence, &[&item])
+            }
+            SchemaNode::Sequence {
+                item,
+                unique: true,
+                ordered,
+            } => {
+                let item = self.node(&item());
+                let template = match ordered {
+                    true => self.text.ordered_set,
+                    false => self.text.set,
+                };
+                fill(template, &[&item])
+            }
+            SchemaNode::Map {
+                key,
+                value,
+                ordered,
+            } => {
+                let value = value();
+                let key = self.node(&key());
+                let value = value_schema(is_enum(&value), &self.item(&value));
+                let template = match ordered {
+                    true => self.text.ordered_map,
+                    false => self.text.map,
+                };
+                fill(template, &[&key, &value])
+            }
+        }
+    }
+
+    fn item(&mut self, node: &SchemaNode) -> String {
+        match node {
+            SchemaNode::Optional(inner) => {
+                let inner = self.node(&inner());
+                let template = match self.style {
+                    SchemaStyle::Full | SchemaStyle::Legend => self.text.optional_item,
+                    SchemaStyle::Compact => self.text.compact_optional_item,
+                };
+                fill(template, &[&inner])
+            }
+            node => self.node(node),
+        }
+    }
+
+    fn fields(&mut self, root: &'static str, fields: &[SchemaField]) -> String {
+        if self.style == SchemaStyle::Compact {
+            self.described.push(root);
+            return self.compact_fields(fields);
+        }
+        self.open.push(root);
+        let parts = fields
+            .iter()
+            .map(|field| self.field(root, field))
+            .collect::<Vec<_>>();
+        self.open.pop();
+        parts.join("\n  ")
+    }
+
+    /// A field of the struct or variant `owner`.
+    fn field(&mut self, owner: &str, field: &SchemaField) -> String {
+        if field.flatten {
+            let fields = match (field.node)() {
+                SchemaNode::Struct { root, fields } => {
+                    self.note(owner, root, field.description);
+                    self.fields(root, &fields)
+                }
+                node => self.node(&node),
+            };
+            return match field.description.is_empty() || self.style == SchemaStyle::Legend {
+                true => fields,
+                false => format!("<!-- {} -->\n  {}", comment_text(field.description), fields),
+            };
+        }
+        self.note(owner, field.tag, field.description);
+        let element = format!(
+            "<{tag}>\n{schema}\n</{tag}>",
+            tag = field.tag,
+            schema = indent(&self.node(&(field.node)()))
+        );
+        self.describe(element, field.description)
+    }
+}
+
+impl Renderer {
+    fn compact_node(&mut self, node: &SchemaNode) -> String {
+        match node {
+            SchemaNode::Leaf { kind, .. } => match kind {
+                LeafKind::String => self.text.compact_string.to_string(),
+                LeafKind::Integer => self.text.compact_integer.to_string(),
+                LeafKind::Float => self.text.compact_float.to_string(),
+                LeafKind::Bool => self.text.compact_bool.to_string(),
+                LeafKind::Char => self.text.compact_char.to_string(),
+                LeafKind::OneOf(names) => {
+                    let names = names.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+                    fill(self.text.compact_one_of, &[&names.join(" | ")])
+                }
+            },
+            SchemaNode::Custom { schema } | SchemaNode::Reference { schema, .. } => {
+                schema.to_string()
+            }
+            SchemaNode::Optional(inner) => {
+                let inner = self.node(&inner());
+                fill(self.text.compact_optional, &[&inner])
+            }
+            // the first description of a struct or enum also stands for the later ones, and for
+            // itself when it recurses
+            SchemaNode::Struct { root, .. } if self.described.contains(root) => {
+                let text = fill(self.text.compact_same_struct, &[root]);
+                format!("<{root}>{text}</{root}>")
+            }
+            SchemaNode::Struct { root, fields } => {
+                format!("<{root}>\n{}\n</{root}>", self.fields(root, fields))
+            }
+            SchemaNode::Enum { name, .. } if self.described.contains(name) => {
+                fill(self.text.compact_same_enum, &[name])
+            }
+            SchemaNode::Enum { name, variants } => {
+                self.described.push(name);
+                let mut parts = vec![fill(self.text.compact_enum_variants, &[name])];
+                for variant in variants {
+                    let fields = self.compact_fields(&variant.fields);
+                    let element = match fields.is_empty() {
+                        true => format!("<{}/>", variant.tag),
+                        false => format!("<{tag}>\n{fields}\n</{tag}>", tag = variant.tag),
+                    };
+                    parts.push(comment(element, variant.description));
+                }
+                parts.join("\n")
+            }
+            SchemaNode::Fields { fields, .. } => self.compact_fields(fields),
+            SchemaNode::Sequence {
+                item,
+                unique: false,
+                ..
+            } => {
+                let item = self.item(&item());
+                fill(self.text.compact_sequence, &[&item])
+            }
+            SchemaNode::Sequence {
+                item, unique: true, ..
+            } => {
+                let item = self.node(&item());
+                fill(self.text.compact_set, &[&item])
+            }
+            SchemaNode::Map { key, value, .. } => {
+                let key = self.node(&key());
+                let value = self.item(&value());
+                fill(self.text.compact_map, &[&key, &value])
+            }
+        }
+    }
+
+    fn compact_fields(&mut self, fields: &[SchemaField]) -> String {
+        fields
+            .iter()
+            .map(|field| {
+                let node = (field.node)();
+                if !field.flatten {
+                    let element = format!("<{tag}>{}</{tag}>", self.node(&node), tag = field.tag);
+                    return comment(element, field.description);
+                }
+                let fields = match node {
+                    SchemaNode::Struct { fields, .. } => self.compact_fields(&fields),
+                    node => self.node(&node),
+                };
+                // the description of flattened fields goes before them, as in the full style
+                match field.description.is_empty() {
+                    true => fields,
+                    false => format!("<!-- {} -->\n{}", comment_text(field.description), fields),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `element` followed by a comment holding `description`, if any.
+fn comment(element: String, description: &str) -> String {
+    match description.is_empty() {
+        true => element,
+        false => format!("{} <!-- {} -->", element, comment_text(description)),
+    }
+}
+
+/// `description` as the text of an XML comment, which can't hold `--` and whose `<` and `>`
+/// would read as markup: `a --> b <c>` becomes `a - -&gt; b &lt;c&gt;`.
+pub(crate) fn comment_text(description: &str) -> Cow<'_, str> {
+    if !description.contains(['<', '>']) && !description.contains("--") {
+        return Cow::Borrowed(description);
+    }
+    let mut text = description.replace('<', "&lt;").replace('>', "&gt;");
+    // `---` takes two rounds
+    while text.contains("--") {
+        text = text.replace("--", "- -");
+    }
+    Cow::Owned(text)
+}
+
+/// Whether `node` is written as the element of a variant, like a type whose `IS_ENUM` is set,
+/// which is the case for a text enum too.
+fn is_enum(node: &SchemaNode) -> bool {
+    match node {
+        SchemaNode::Enum { .. }
+        | SchemaNode::Leaf {
+            kind: LeafKind::OneOf(_),
+            ..
+        } => true,
+        SchemaNode::Reference { node, .. } => is_enum(&node()),
+        _ => false,
+    }
+}
+
+/// Indents every line of `text` by two spaces.
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads the element `xml` as a `T`, with the container and primitive grammar of a `<response>`.
+pub fn check_element<T: DeserializeOwned>(xml: &str) -> Result<(), String> {
+    T::deserialize(XmlResponse::element(&mut Deserializer::from_str(xml)))
+        .map(drop)
+        .map_err(|e| e.to_string())
+}
+
+/// Reads the element `xml` with `parse`, the parser the macro generated for a field.
+pub fn check_field<'de>(
+    xml: &'de str,
+    parse: impl FnOnce(&mut Deserializer<'de, SliceReader<'de>>) -> Result<(), DeError>,
+) -> Result<(), String> {
+    parse(&mut Deserializer::from_str(xml)).map_err(|e| e.to_string())
+}
@@ -0,0 +1,105 @@
+use super::example::example_items;
+use super::vector::VecParser;
+use crate::{Cache, LlmPrompt, SchemaNode};
+use serde::{Deserializer, de::DeserializeOwned};
+use std::collections::{BTreeSet, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+// Sets share the <item> grammar of `Vec`, duplicated items are collapsed silently
+
+pub struct HashSetParser<T>(PhantomData<T>)
+where
+    T: DeserializeOwned + Eq + Hash;
+
+impl<T> HashSetParser<T>
+where
+    T: DeserializeOwned + Eq + Hash,
+{
+    pub fn custom_hashset_parser<'de, D>(deserializer: D) -> Result<HashSet<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        VecParser::<T>::custom_vector_parser(deserializer).map(|items| items.into_iter().collect())
+    }
+}
+
+pub struct BTreeSetParser<T>(PhantomData<T>)
+where
+    T: DeserializeOwned + Ord;
+
+impl<T> BTreeSetParser<T>
+where
+    T: DeserializeOwned + Ord,
+{
+    pub fn custom_btreeset_parser<'de, D>(deserializer: D) -> Result<BTreeSet<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        VecParser::<T>::custom_vector_parser(deserializer).map(|items| items.into_iter().collect())
+    }
+}
+
+impl<T: LlmPrompt + Eq + Hash + 'static> LlmPrompt for HashSet<T> {
+    fn get_prompt_schema() -> &'static str {
+        let cache = Cache::<HashSet<T>>::get();
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render())
+    }
+
+    fn root_name() -> &'static str {
+        let cache = Cache::<HashSet<T>>::get();
+        cache
+            .root_name
+            .get_or_init(|| format!("HashSet<{}>", T::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        example_items::<T>(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Sequence {
+            item: T::schema_node,
+            unique: true,
+            ordered: false,
+        }
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl<T: LlmPrompt + Ord + 'static> LlmPrompt for BTreeSet<T> {
+    fn get_prompt_schema() -> &'static str {
+        let cache = Cache::<BTreeSet<T>>::get();
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render())
+    }
+
+    fn root_name() -> &'static str {
+        let cache = Cache::<BTreeSet<T>>::get();
+        cache
+            .root_name
+            .get_or_init(|| format!("BTreeSet<{}>", T::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        example_items::<T>(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Sequence {
+            item: T::schema_node,
+            unique: true,
+            ordered: false,
+        }
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
@@ -0,0 +1,64 @@
+use crate::{LlmPrompt, SchemaNode};
+use std::{rc::Rc, sync::Arc};
+
+// Shared pointers are transparent in the XML, the schema is exactly the one of `T`
+
+impl<T: LlmPrompt> LlmPrompt for Rc<T> {
+    fn get_prompt_schema() -> &'static str {
+        T::get_prompt_schema()
+    }
+
+    fn get_fields_schema() -> &'static str {
+        T::get_fields_schema()
+    }
+
+    fn root_name() -> &'static str {
+        T::root_name()
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        T::example_content(depth)
+    }
+
+    fn example_item(depth: usize) -> Option<String> {
+        T::example_item(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        T::schema_node()
+    }
+
+    const IS_ENUM: bool = T::IS_ENUM;
+
+    const HAS_ROOT: bool = T::HAS_ROOT;
+}
+
+impl<T: LlmPrompt> LlmPrompt for Arc<T> {
+    fn get_prompt_schema() -> &'static str {
+        T::get_prompt_schema()
+    }
+
+    fn get_fields_schema() -> &'static str {
+        T::get_fields_schema()
+    }
+
+    fn root_name() -> &'static str {
+        T::root_name()
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        T::example_content(depth)
+    }
+
+    fn example_item(depth: usize) -> Option<String> {
+        T::example_item(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        T::schema_node()
+    }
+
+    const IS_ENUM: bool = T::IS_ENUM;
+
+    const HAS_ROOT: bool = T::HAS_ROOT;
+}
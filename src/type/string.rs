@@ -1,23 +1,223 @@
-use crate::LlmPrompt;
+use super::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode, SchemaText};
 use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
 
 pub fn custom_string_parser<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
+    let XmlText(s) = XmlText::deserialize(deserializer)?;
 
     Ok(s.trim().to_string())
 }
 
+/// How a string field reads its text, set by the field's `#[prompt(...)]` flags.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StringFormat {
+    /// Keep the text exactly as written instead of trimming it, e.g. for indented code.
+    pub preserve_whitespace: bool,
+    /// Unwrap the value from one pair of quotes, backticks or markdown emphasis.
+    pub clean: bool,
+    /// Decode HTML entities the LLM escaped into the text, e.g. `&lt;tag&gt;` or `&#8217;`.
+    pub decode_entities: bool,
+}
+
+/// Like [`custom_string_parser`], with the leniency of the field's `#[prompt(...)]` flags.
+pub fn custom_string_format_parser<'de, D>(
+    deserializer: D,
+    format: StringFormat,
+) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let XmlText(s) = XmlText::deserialize(deserializer)?;
+
+    let mut text = if format.preserve_whitespace {
+        // only a CDATA wrapper that reached the text escaped is removed, not the whitespace
+        s.trim()
+            .strip_prefix("<![CDATA[")
+            .and_then(|rest| rest.strip_suffix("]]>"))
+            .map_or(s.clone(), str::to_string)
+    } else {
+        s.trim().to_string()
+    };
+    if format.decode_entities {
+        text = decode_entities(&text);
+        if !format.preserve_whitespace {
+            text = text.trim().to_string();
+        }
+    }
+    if format.clean {
+        text = unwrap_markers(&text).to_string();
+    }
+    Ok(text)
+}
+
+/// Unwraps a value from one pair of quotes, backticks or markdown emphasis.
+///
+/// `"John Doe"`, `` `parse_args` `` and `**Important**` become the bare text. The pair is kept
+/// when it also occurs inside the value, so `"a" and "b"` is left alone.
+fn unwrap_markers(s: &str) -> &str {
+    const PAIRS: [(&str, &str); 6] = [
+        ("**", "**"),
+        ("\"", "\""),
+        ("\u{201c}", "\u{201d}"),
+        ("'", "'"),
+        ("`", "`"),
+        ("*", "*"),
+    ];
+
+    for (open, close) in PAIRS {
+        if let Some(inner) = s
+            .strip_prefix(open)
+            .and_then(|rest| rest.strip_suffix(close))
+            && !inner.is_empty()
+            && !inner.contains(open)
+            && !inner.contains(close)
+        {
+            return inner.trim();
+        }
+    }
+    s
+}
+
+/// Decodes named and numeric HTML entities, repeating while the text still changes so that a
+/// double-escaped `&amp;lt;` ends up as `<`.
+///
+/// An `&` that doesn't start a known entity, as in `Tom & Jerry` or `&unknown;`, is kept.
+fn decode_entities(s: &str) -> String {
+    // a few passes cover any escaping seen in practice, and keep `&amp;amp;...` chains bounded
+    const MAX_PASSES: usize = 4;
+
+    let mut text = s.to_string();
+    for _ in 0..MAX_PASSES {
+        let decoded = decode_entities_once(&text);
+        if decoded == text {
+            break;
+        }
+        text = decoded;
+    }
+    text
+}
+
+fn decode_entities_once(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((entity_char(&rest[1..=end])?, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character of an entity name such as `amp`, `#8217` or `#x2019`.
+fn entity_char(name: &str) -> Option<char> {
+    if let Some(code) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        return u32::from_str_radix(code, 16).ok().and_then(char::from_u32);
+    }
+    if let Some(code) = name.strip_prefix('#') {
+        return code.parse().ok().and_then(char::from_u32);
+    }
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '\u{2013}',
+        "mdash" => '\u{2014}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "ldquo" => '\u{201c}',
+        "rdquo" => '\u{201d}',
+        "hellip" => '\u{2026}',
+        "copy" => '\u{a9}',
+        "reg" => '\u{ae}',
+        "trade" => '\u{2122}',
+        _ => return None,
+    };
+    Some(c)
+}
+
+pub fn custom_cow_str_parser<'de, D>(deserializer: D) -> Result<Cow<'static, str>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    custom_string_parser(deserializer).map(Cow::Owned)
+}
+
+/// Like [`custom_cow_str_parser`], with the leniency of the field's `#[prompt(...)]` flags.
+pub fn custom_cow_str_format_parser<'de, D>(
+    deserializer: D,
+    format: StringFormat,
+) -> Result<Cow<'static, str>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    custom_string_format_parser(deserializer, format).map(Cow::Owned)
+}
+
 impl LlmPrompt for String {
     fn get_prompt_schema() -> &'static str {
-        "return a string value. please use the format <![CDATA[{actual string content without any escaping}]]> to return the string content. Note that the CDATA tags must be exactly in this format, otherwise the parsing will fail. If you need to return an empty string, please return <![CDATA[]]>"
+        SchemaText::current().string
     }
 
     fn root_name() -> &'static str {
         "string"
     }
 
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("<![CDATA[example]]>".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::leaf::<String>(LeafKind::String)
+    }
+
+    const ENGLISH_LEAF_SCHEMA: Option<&'static str> = Some(SchemaText::ENGLISH.string);
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl LlmPrompt for Cow<'static, str> {
+    fn get_prompt_schema() -> &'static str {
+        String::get_prompt_schema()
+    }
+
+    fn root_name() -> &'static str {
+        String::root_name()
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        String::example_content(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        String::schema_node()
+    }
+
+    const ENGLISH_LEAF_SCHEMA: Option<&'static str> = String::ENGLISH_LEAF_SCHEMA;
+
     const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
 }
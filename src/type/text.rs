@@ -0,0 +1,290 @@
+//! The fixed sentences of the prompt schema, such as the note on optional elements or the
+//! CDATA instructions of a string, in English or another language.
+//!
+//! A `{}` in a sentence stands for the part filled in, e.g. the format of each item.
+
+use super::schema::comment_text;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// The sentences the prompt schema is written with, see [`SchemaText::ENGLISH`] and
+/// [`SchemaText::SIMPLIFIED_CHINESE`].
+///
+/// Other languages start from one of them and replace the sentences they need, under a
+/// `language` of their own:
+///
+/// ```rust
+/// use llm_xml_caster::SchemaText;
+///
+/// static TERSE: SchemaText = SchemaText {
+///     language: "en-terse",
+///     optional: "Optional, leave the tags out if absent: {}",
+///     ..*SchemaText::ENGLISH
+/// };
+/// ```
+///
+/// Only the structure of the schema, the primitives, ranges, tuples and the `indexmap`
+/// collections are written with these. The prompt schemas of other types, e.g. `Duration` or a
+/// hand-written impl, stay as they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SchemaText {
+    /// The name of the set, e.g. `en`. Schemas are cached per language, so two different sets
+    /// need two different names.
+    pub language: &'static str,
+    /// The system message of a request, given the root name and the schema.
+    pub request: &'static str,
+    /// The line before the examples of a request.
+    pub examples: &'static str,
+    /// An example of a request, given its number and its XML.
+    pub example: &'static str,
+    /// The line before the mistakes to avoid in a request.
+    pub bad_examples: &'static str,
+    /// A mistake to avoid, given the incorrect XML, why it is incorrect and the correct XML.
+    pub bad_example: &'static str,
+    /// The system message of a request in tool mode, given the name of the tool.
+    pub tool_request: &'static str,
+    /// The description of the tool of a request in tool mode.
+    pub tool_description: &'static str,
+    pub string: &'static str,
+    pub integer: &'static str,
+    pub unsigned_integer: &'static str,
+    pub float: &'static str,
+    pub bool: &'static str,
+    pub char: &'static str,
+    /// A non-zero integer, e.g. a `NonZeroI32`.
+    pub non_zero_integer: &'static str,
+    /// A positive integer, e.g. a `NonZeroU32`.
+    pub positive_integer: &'static str,
+    /// An `Option` field, given the format of its value.
+    pub optional: &'static str,
+    /// An `Option` written as an `<item>` or a `<value>`, given the format of its value.
+    pub optional_item: &'static str,
+    /// A sequence, given the format of an item.
+    pub sequence: &'static str,
+    /// A set, given the format of an item.
+    pub set: &'static str,
+    /// A map, given the formats of a key and of a value.
+    pub map: &'static str,
+    /// A set that keeps the order of its items, e.g. an `IndexSet`, given the format of an
+    /// item.
+    pub ordered_set: &'static str,
+    /// A map that keeps the order of its entries, e.g. an `IndexMap`, given the formats of a
+    /// key and of a value.
+    pub ordered_map: &'static str,
+    /// A `Between` range, given the format of a bound.
+    pub range: &'static str,
+    /// A tuple, given the number of its values and the elements they are written in.
+    pub tuple: &'static str,
+    /// The value of a map that is an enum, given the variants.
+    pub enum_value: &'static str,
+    /// The line before the variants of an enum.
+    pub enum_variants: &'static str,
+    /// A text enum, given its names.
+    pub text_enum: &'static str,
+    /// The line before the descriptions of the names of a text enum.
+    pub text_enum_meanings: &'static str,
+    /// The line before the descriptions in the [`SchemaStyle::Legend`](crate::SchemaStyle)
+    /// style.
+    pub legend: &'static str,
+    /// A struct reached again inside itself, given its root name twice.
+    pub recursive_struct: &'static str,
+    /// An enum reached again inside itself.
+    pub recursive_enum: &'static str,
+    /// A struct past the maximum depth of the schema, given its root name.
+    pub truncated_struct: &'static str,
+    /// An enum past the maximum depth of the schema.
+    pub truncated_enum: &'static str,
+    /// The counterparts of the above in the [`SchemaStyle::Compact`](crate::SchemaStyle) style.
+    pub compact_string: &'static str,
+    pub compact_integer: &'static str,
+    pub compact_float: &'static str,
+    pub compact_bool: &'static str,
+    pub compact_char: &'static str,
+    /// A text enum, given its names.
+    pub compact_one_of: &'static str,
+    pub compact_optional: &'static str,
+    pub compact_optional_item: &'static str,
+    pub compact_sequence: &'static str,
+    pub compact_set: &'static str,
+    pub compact_map: &'static str,
+    /// A struct described earlier in the schema, given its root name.
+    pub compact_same_struct: &'static str,
+    /// The line before the variants of an enum, given its name.
+    pub compact_enum_variants: &'static str,
+    /// An enum described earlier in the schema, given its name.
+    pub compact_same_enum: &'static str,
+}
+
+impl SchemaText {
+    /// The English sentences, used by default.
+    pub const ENGLISH: &'static SchemaText = &SchemaText {
+        language: "en",
+        request: "You must respond with a valid XML document(root name is {}) that adheres to the following schema: {}",
+        examples: "Here are some examples of valid responses. They only show the structure: do not copy their values, write your own from the request.",
+        example: "Example {}:\n{}",
+        bad_examples: "Avoid these common mistakes:",
+        bad_example: "Incorrect: {} — because {}; Correct: {}",
+        tool_request: "You must respond by calling the tool {} with the value as its arguments.",
+        tool_description: "Takes the value of the response.",
+        string: "return a string value. please use the format <![CDATA[{actual string content without any escaping}]]> to return the string content. Note that the CDATA tags must be exactly in this format, otherwise the parsing will fail. If you need to return an empty string, please return <![CDATA[]]>",
+        integer: "integer value, a whole number without a fractional part, e.g., 42, -7, or 0",
+        unsigned_integer: "integer value, a non-negative whole number without a fractional part, e.g., 42, 7, or 0",
+        float: "float value, a number that can have a fractional part, e.g., 3.14, -0.001, or 2.0",
+        bool: "it is a boolean value, either `true` or `false`",
+        char: "a single character, e.g. A",
+        non_zero_integer: "non-zero integer value, a whole number that must not be 0, e.g., 42, -7, or 1",
+        positive_integer: "positive integer value, a whole number that must not be 0, e.g., 1, 42, or 7",
+        optional: "Optional. if not provided, do not include any tags; an empty element is read as not provided too. If provided, the format is: {}",
+        optional_item: "Optional. if there is no value, leave the element empty (e.g. <item/> or <value/>) but keep it in place. If provided, the format is: {}",
+        sequence: "A series(0 or more elements) of items where each item has the following format:<item>{}</item>\nNOTICE: Even a single item must be enclosed within <item></item> tags.",
+        set: "A set(0 or more distinct elements) of items where each item has the following format:<item>{}</item>\nNOTICE: Even a single item must be enclosed within <item></item> tags. Duplicate items are collapsed into one, so list each value only once.",
+        map: "a sequence of key-value pairs, where each key is {} and each value is {}. The XML format should be: <entry><key>{key}</key><value>{value}</value></entry>, and this structure can be repeated multiple times.",
+        ordered_set: "An ordered set(0 or more distinct elements) of items where each item has the following format:<item>{}</item>\nNOTICE: Even a single item must be enclosed within <item></item> tags. Duplicate items are collapsed into the first one, so list each value only once.",
+        ordered_map: "an ordered sequence of key-value pairs, where each key is {} and each value is {}. The XML format should be: <entry><key>{key}</key><value>{value}</value></entry>, and this structure can be repeated multiple times. The order of the entries is kept.",
+        range: "a range with an optional lower and upper bound, where each bound is {}. The XML format should be: <min>{lower bound}</min><max>{upper bound}</max>. Either bound may be omitted (leave out its tag entirely) when the range is open on that side, and both may be omitted when there is no restriction at all. When both are provided, <min> must be less than or equal to <max>.",
+        tuple: "a tuple of {} values in a fixed order. The XML format should be: {}. Every element is required.",
+        enum_value: "the element of one of the variants below, written directly inside <value> (e.g. <value><VariantName>...</VariantName></value>, or <value><VariantName/></value> for a variant without fields). {}",
+        enum_variants: "The following are possible XML structures for the current enum type:",
+        text_enum: "one of: {}. Write the value as plain text.",
+        text_enum_meanings: "The values mean:",
+        legend: "Field meanings, for reference only (never write these descriptions or any comments into the response):",
+        recursive_struct: "a nested {} with the same format as the enclosing <{}> described above, recursively",
+        recursive_enum: "a nested value with the same format as the enclosing enum described above, recursively",
+        truncated_struct: "a nested {} element, whose fields are not shown as the schema is nested too deeply",
+        truncated_enum: "the element of one of the variants of a nested enum, which are not shown as the schema is nested too deeply",
+        compact_string: "text as <![CDATA[...]]>",
+        compact_integer: "integer",
+        compact_float: "number",
+        compact_bool: "true or false",
+        compact_char: "one character",
+        compact_one_of: "one of: {}",
+        compact_optional: "opt. {}",
+        compact_optional_item: "opt., empty element if none: {}",
+        compact_sequence: "list: <item>{}</item> per item, even a single one",
+        compact_set: "set: <item>{}</item> per distinct item",
+        compact_map: "map: <entry><key>{}</key><value>{}</value></entry> per entry",
+        compact_same_struct: "same format as <{}> above",
+        compact_enum_variants: "one of the {} variants:",
+        compact_same_enum: "one of the {} variants above",
+    };
+
+    /// The Simplified Chinese sentences.
+    pub const SIMPLIFIED_CHINESE: &'static SchemaText = &SchemaText {
+        language: "zh-CN",
+        request: "你必须返回一个有效的 XML 文档（根元素名为 {}），并遵循以下结构：{}",
+        examples: "以下是一些有效响应的示例。它们只展示结构：不要照抄其中的值，请根据请求填写你自己的值。",
+        example: "示例 {}：\n{}",
+        bad_examples: "请避免以下常见错误：",
+        bad_example: "错误：{} —— 原因：{}；正确：{}",
+        tool_request: "你必须调用工具 {} 作答，以该值作为它的参数。",
+        tool_description: "接收响应的值。",
+        string: "返回一个字符串值。请使用 <![CDATA[{实际的字符串内容，不做任何转义}]]> 的格式返回字符串内容。注意 CDATA 标签必须严格按照此格式书写，否则解析会失败。如果需要返回空字符串，请返回 <![CDATA[]]>",
+        integer: "整数值，即不带小数部分的整数，例如 42、-7 或 0",
+        unsigned_integer: "整数值，即不带小数部分的非负整数，例如 42、7 或 0",
+        float: "浮点数值，即可以带小数部分的数字，例如 3.14、-0.001 或 2.0",
+        bool: "布尔值，`true` 或 `false`",
+        char: "单个字符，例如 A",
+        non_zero_integer: "非零整数值，即不能为 0 的整数，例如 42、-7 或 1",
+        positive_integer: "正整数值，即不能为 0 的整数，例如 1、42 或 7",
+        optional: "可选。如果不提供，则不要包含任何标签；空元素同样视为未提供。如果提供，格式为：{}",
+        optional_item: "可选。如果没有值，请保留该元素但使其为空（例如 <item/> 或 <value/>）。如果提供，格式为：{}",
+        sequence: "由若干项（0 个或多个元素）组成的序列，每一项的格式如下：<item>{}</item>\n注意：即使只有一项，也必须用 <item></item> 标签包裹。",
+        set: "由若干项（0 个或多个互不相同的元素）组成的集合，每一项的格式如下：<item>{}</item>\n注意：即使只有一项，也必须用 <item></item> 标签包裹。重复的项会被合并为一项，因此每个值只需列出一次。",
+        map: "由键值对组成的序列，其中每个键是 {}，每个值是 {}。XML 格式应为：<entry><key>{键}</key><value>{值}</value></entry>，该结构可以重复多次。",
+        ordered_set: "由若干项（0 个或多个互不相同的元素）组成的有序集合，每一项的格式如下：<item>{}</item>\n注意：即使只有一项，也必须用 <item></item> 标签包裹。重复的项会被合并为第一次出现的那一项，因此每个值只需列出一次。",
+        ordered_map: "由键值对组成的有序序列，其中每个键是 {}，每个值是 {}。XML 格式应为：<entry><key>{键}</key><value>{值}</value></entry>，该结构可以重复多次。条目的顺序会被保留。",
+        range: "一个带有可选下界和上界的范围，每个边界是 {}。XML 格式应为：<min>{下界}</min><max>{上界}</max>。范围在某一侧没有限制时，可以省略该侧的边界（完全不写它的标签）；没有任何限制时，两个边界都可以省略。两者都提供时，<min> 必须小于或等于 <max>。",
+        tuple: "由 {} 个值按固定顺序组成的元组。XML 格式应为：{}。每个元素都必须提供。",
+        enum_value: "下列某个变体的元素，直接写在 <value> 内（例如 <value><VariantName>...</VariantName></value>，没有字段的变体则写作 <value><VariantName/></value>）。{}",
+        enum_variants: "以下是当前枚举类型可能的 XML 结构：",
+        text_enum: "以下之一：{}。请以纯文本形式写出该值。",
+        text_enum_meanings: "各值的含义：",
+        legend: "字段含义，仅供参考（切勿把这些说明或任何注释写入回答）：",
+        recursive_struct: "一个嵌套的 {}，其格式与上文描述的外层 <{}> 相同，可递归嵌套",
+        recursive_enum: "一个嵌套的值，其格式与上文描述的外层枚举相同，可递归嵌套",
+        truncated_struct: "一个嵌套的 {} 元素，由于结构嵌套过深，此处不再列出其字段",
+        truncated_enum: "一个嵌套枚举的某个变体的元素，由于结构嵌套过深，此处不再列出其变体",
+        compact_string: "文本，写作 <![CDATA[...]]>",
+        compact_integer: "整数",
+        compact_float: "数字",
+        compact_bool: "true 或 false",
+        compact_char: "单个字符",
+        compact_one_of: "以下之一：{}",
+        compact_optional: "可选，{}",
+        compact_optional_item: "可选，无值时留空元素：{}",
+        compact_sequence: "列表：每项写作 <item>{}</item>，即使只有一项",
+        compact_set: "集合：每个不同的项写作 <item>{}</item>",
+        compact_map: "映射：每个条目写作 <entry><key>{}</key><value>{}</value></entry>",
+        compact_same_struct: "格式同上文的 <{}>",
+        compact_enum_variants: "{} 的变体之一：",
+        compact_same_enum: "上文 {} 的变体之一",
+    };
+
+    /// The sentences schemas are rendered with on this thread: those of the innermost
+    /// [`SchemaText::scope`], or else the default.
+    pub fn current() -> &'static SchemaText {
+        SCOPED
+            .with(Cell::get)
+            // only ever set from a `&'static SchemaText`
+            .unwrap_or_else(|| unsafe { &*DEFAULT.load(Ordering::Acquire) })
+    }
+
+    /// Sets the sentences schemas are rendered with by default, [`SchemaText::ENGLISH`] unless
+    /// set otherwise.
+    pub fn set_default(text: &'static SchemaText) {
+        DEFAULT.store(std::ptr::from_ref(text).cast_mut(), Ordering::Release);
+    }
+
+    /// Runs `f` with schemas rendered with these sentences on this thread, e.g. to get
+    /// `T::get_prompt_schema()` in another language than the default.
+    pub fn scope<R>(&'static self, f: impl FnOnce() -> R) -> R {
+        // puts back the outer sentences, even if `f` panics
+        struct Restore(Option<&'static SchemaText>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED.with(|scoped| scoped.set(self.0));
+            }
+        }
+        let _restore = Restore(SCOPED.with(|scoped| scoped.replace(Some(self))));
+        f()
+    }
+}
+
+// a pointer rather than a lock, as it is read on every schema lookup
+static DEFAULT: AtomicPtr<SchemaText> =
+    AtomicPtr::new(std::ptr::from_ref(SchemaText::ENGLISH).cast_mut());
+
+thread_local! {
+    static SCOPED: Cell<Option<&'static SchemaText>> = const { Cell::new(None) };
+}
+
+/// `template` with each `{}` replaced by the next of `parts`.
+pub(crate) fn fill(template: &str, parts: &[&str]) -> String {
+    let mut pieces = template.split("{}");
+    let mut filled = pieces.next().unwrap_or_default().to_string();
+    for (index, piece) in pieces.enumerate() {
+        filled.push_str(parts.get(index).copied().unwrap_or_default());
+        filled.push_str(piece);
+    }
+    filled
+}
+
+/// The prompt schema of a text enum, given its comma-separated names and the descriptions of
+/// those that have one.
+pub fn text_enum_schema(allowed: &str, described: &[(&str, &str)]) -> String {
+    let text = SchemaText::current();
+    let schema = fill(text.text_enum, &[allowed]);
+    if described.is_empty() {
+        return schema;
+    }
+    let lines = described
+        .iter()
+        .map(|(tag, description)| format!("{} <!-- {} -->", tag, comment_text(description)))
+        .collect::<Vec<_>>();
+    format!(
+        "{} {}\n{}",
+        schema,
+        text.text_enum_meanings,
+        lines.join("\n")
+    )
+}
@@ -0,0 +1,174 @@
+use crate::r#type::r#macro::parsed_leaf;
+use crate::r#type::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+
+// accepted besides RFC 3339, the LLM often writes a space instead of the `T`
+const NAIVE_DATE_TIME_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+fn parse_naive_date_time(s: &str) -> Option<NaiveDateTime> {
+    NAIVE_DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+}
+
+fn parse_fixed_offset(s: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f%:z"))
+        .ok()
+}
+
+pub fn custom_datetime_utc_parser<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    parse_fixed_offset(clean_s)
+        .map(|dt| dt.with_timezone(&Utc))
+        // a missing timezone is read as UTC
+        .or_else(|| parse_naive_date_time(clean_s).map(|dt| dt.and_utc()))
+        .ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "can not parse '{}' as a date and time, it must be in RFC 3339 format like 2024-05-01T13:45:00Z",
+                clean_s
+            ))
+        })
+}
+
+pub fn custom_datetime_fixed_offset_parser<'de, D>(
+    deserializer: D,
+) -> Result<DateTime<FixedOffset>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    parse_fixed_offset(clean_s).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a date and time with a UTC offset, it must be in RFC 3339 format like 2024-05-01T13:45:00+02:00",
+            clean_s
+        ))
+    })
+}
+
+pub fn custom_naive_date_parser<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    NaiveDate::parse_from_str(clean_s, "%Y-%m-%d").map_err(|_| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a date, it must be in YYYY-MM-DD format like 2024-05-01",
+            clean_s
+        ))
+    })
+}
+
+pub fn custom_naive_date_time_parser<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    parse_naive_date_time(clean_s).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a date and time, it must be in YYYY-MM-DDTHH:MM:SS format like 2024-05-01T13:45:00",
+            clean_s
+        ))
+    })
+}
+
+impl LlmPrompt for DateTime<Utc> {
+    fn get_prompt_schema() -> &'static str {
+        "a date and time in RFC 3339 format, e.g. 2024-05-01T13:45:00Z. A value without a timezone is read as UTC"
+    }
+
+    fn root_name() -> &'static str {
+        "DateTime<Utc>"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("2024-05-01T13:45:00Z".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_datetime_utc_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl LlmPrompt for DateTime<FixedOffset> {
+    fn get_prompt_schema() -> &'static str {
+        "a date and time with its UTC offset in RFC 3339 format, e.g. 2024-05-01T13:45:00+02:00"
+    }
+
+    fn root_name() -> &'static str {
+        "DateTime<FixedOffset>"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("2024-05-01T13:45:00+02:00".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_datetime_fixed_offset_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl LlmPrompt for NaiveDate {
+    fn get_prompt_schema() -> &'static str {
+        "a calendar date in YYYY-MM-DD format, e.g. 2024-05-01"
+    }
+
+    fn root_name() -> &'static str {
+        "NaiveDate"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("2024-05-01".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_naive_date_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl LlmPrompt for NaiveDateTime {
+    fn get_prompt_schema() -> &'static str {
+        "a date and time without a timezone in YYYY-MM-DDTHH:MM:SS format, e.g. 2024-05-01T13:45:00"
+    }
+
+    fn root_name() -> &'static str {
+        "NaiveDateTime"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("2024-05-01T13:45:00".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_naive_date_time_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
@@ -0,0 +1,50 @@
+use crate::r#type::r#macro::parsed_leaf;
+use crate::r#type::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+pub fn custom_decimal_parser<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    // tolerate what the schema asks to leave out: currency symbols and codes, thousands separators
+    let number: String = clean_s
+        .chars()
+        .filter(|c| !matches!(c, '$' | '€' | '£' | '¥' | ',') && !c.is_whitespace())
+        .collect();
+    let number = number.trim_matches(|c: char| c.is_ascii_alphabetic());
+
+    Decimal::from_str(number).map_err(|_| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a decimal number, it must be a plain number like 1234.56 without currency symbols or thousands separators",
+            clean_s
+        ))
+    })
+}
+
+impl LlmPrompt for Decimal {
+    fn get_prompt_schema() -> &'static str {
+        "an exact decimal number written as plain digits with a . as the decimal point, without currency symbols or thousands separators, e.g. 1234.56 or -0.5"
+    }
+
+    fn root_name() -> &'static str {
+        "Decimal"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("1234.56".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::Float, custom_decimal_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
@@ -0,0 +1,110 @@
+use crate::r#type::entry::deserialize_entries;
+use crate::r#type::example::{example_entries, example_items};
+use crate::{Cache, LlmPrompt, SchemaNode, VecParser};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserializer, de::DeserializeOwned};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+pub struct IndexMapParser<K, V>(PhantomData<(K, V)>)
+where
+    K: DeserializeOwned + Eq + Hash,
+    V: DeserializeOwned;
+
+impl<K, V> IndexMapParser<K, V>
+where
+    K: DeserializeOwned + Eq + Hash,
+    V: DeserializeOwned,
+{
+    pub fn custom_indexmap_parser<'de, D>(deserializer: D) -> Result<IndexMap<K, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // entries are collected in document order
+        deserialize_entries(deserializer).map(|entries| entries.into_iter().collect())
+    }
+}
+
+impl<K, V> LlmPrompt for IndexMap<K, V>
+where
+    K: LlmPrompt + Eq + Hash + 'static,
+    V: LlmPrompt + 'static,
+{
+    fn get_prompt_schema() -> &'static str {
+        let cache = Cache::<IndexMap<K, V>>::get();
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render())
+    }
+
+    fn root_name() -> &'static str {
+        let cache = Cache::<IndexMap<K, V>>::get();
+        cache
+            .root_name
+            .get_or_init(|| format!("IndexMap<{}, {}>", K::root_name(), V::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        example_entries::<K, V>(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Map {
+            key: K::schema_node,
+            value: V::schema_node,
+            ordered: true,
+        }
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+pub struct IndexSetParser<T>(PhantomData<T>)
+where
+    T: DeserializeOwned + Eq + Hash;
+
+impl<T> IndexSetParser<T>
+where
+    T: DeserializeOwned + Eq + Hash,
+{
+    pub fn custom_indexset_parser<'de, D>(deserializer: D) -> Result<IndexSet<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        VecParser::<T>::custom_vector_parser(deserializer).map(|items| items.into_iter().collect())
+    }
+}
+
+impl<T: LlmPrompt + Eq + Hash + 'static> LlmPrompt for IndexSet<T> {
+    fn get_prompt_schema() -> &'static str {
+        let cache = Cache::<IndexSet<T>>::get();
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render())
+    }
+
+    fn root_name() -> &'static str {
+        let cache = Cache::<IndexSet<T>>::get();
+        cache
+            .root_name
+            .get_or_init(|| format!("IndexSet<{}>", T::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        example_items::<T>(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Sequence {
+            item: T::schema_node,
+            unique: true,
+            ordered: true,
+        }
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
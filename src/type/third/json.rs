@@ -0,0 +1,37 @@
+use crate::LlmPrompt;
+use crate::r#type::xml::XmlText;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+pub fn custom_json_value_parser<'de, D>(deserializer: D) -> Result<Value, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    serde_json::from_str(clean_s).map_err(|e| {
+        serde::de::Error::custom(format!(
+            "can not parse the content as JSON: {}. Write a single valid JSON value inside <![CDATA[...]]>",
+            e
+        ))
+    })
+}
+
+impl LlmPrompt for Value {
+    fn get_prompt_schema() -> &'static str {
+        "free-form JSON, usually an object. please use the format <![CDATA[{valid JSON}]]> to return it, e.g. <![CDATA[{\"key\": \"value\", \"count\": 2}]]>. The content must be valid JSON, with double-quoted keys and strings"
+    }
+
+    fn root_name() -> &'static str {
+        "Value"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some(r#"<![CDATA[{"key": "value", "count": 2}]]>"#.to_string())
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
@@ -2,3 +2,31 @@
 mod ordered_float;
 #[cfg(feature = "ordered_float")]
 pub use ordered_float::*;
+#[cfg(feature = "indexmap")]
+mod indexmap;
+#[cfg(feature = "indexmap")]
+pub use indexmap::*;
+#[cfg(feature = "chrono")]
+mod chrono;
+#[cfg(feature = "chrono")]
+pub use chrono::*;
+#[cfg(feature = "time")]
+mod time;
+#[cfg(feature = "time")]
+pub use time::*;
+#[cfg(feature = "uuid")]
+mod uuid;
+#[cfg(feature = "uuid")]
+pub use uuid::*;
+#[cfg(feature = "url")]
+mod url;
+#[cfg(feature = "url")]
+pub use url::*;
+#[cfg(feature = "decimal")]
+mod decimal;
+#[cfg(feature = "decimal")]
+pub use decimal::*;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::*;
@@ -1,20 +1,17 @@
-use crate::LlmPrompt;
+use crate::r#type::xml::XmlTransparent;
+use crate::{LlmPrompt, SchemaNode};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Deserializer, de::DeserializeOwned};
 use std::marker::PhantomData;
 
 pub struct OrderedFloatParser<T: DeserializeOwned>(PhantomData<T>);
 
-#[derive(Deserialize)]
-#[serde(transparent)]
-struct XmlOrderedFloat<T>(T);
-
 impl<T: DeserializeOwned> OrderedFloatParser<T> {
     pub fn custom_ordered_float_parser<'de, D>(deserializer: D) -> Result<OrderedFloat<T>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        match XmlOrderedFloat::<T>::deserialize(deserializer) {
+        match XmlTransparent::<T>::deserialize(deserializer) {
             Ok(wrapper) => Ok(OrderedFloat(wrapper.0)),
             Err(e) => Err(serde::de::Error::custom(format!(
                 "The XML structure is invalid. Reason: {}",
@@ -33,5 +30,19 @@ impl<T: LlmPrompt + 'static> LlmPrompt for OrderedFloat<T> {
         T::root_name()
     }
 
+    fn example_content(depth: usize) -> Option<String> {
+        T::example_content(depth)
+    }
+
+    fn example_item(depth: usize) -> Option<String> {
+        T::example_item(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        T::schema_node()
+    }
+
     const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = T::HAS_ROOT;
 }
@@ -0,0 +1,141 @@
+use crate::r#type::r#macro::parsed_leaf;
+use crate::r#type::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use serde::{Deserialize, Deserializer};
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+fn parse_offset_date_time(s: &str) -> Option<OffsetDateTime> {
+    // the LLM often writes a space instead of the `T`
+    let s = match s.get(10..11) {
+        Some(" ") => format!("{}T{}", &s[..10], &s[11..]),
+        _ => s.to_string(),
+    };
+    OffsetDateTime::parse(&s, &Rfc3339).ok().or_else(|| {
+        // a missing offset is read as UTC
+        PrimitiveDateTime::parse(
+            &s,
+            format_description!(
+                "[year]-[month]-[day]T[hour]:[minute]:[second][optional [.[subsecond]]]"
+            ),
+        )
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+    })
+}
+
+pub fn custom_offset_date_time_parser<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    parse_offset_date_time(clean_s).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a date and time, it must be in RFC 3339 format like 2024-05-01T13:45:00Z",
+            clean_s
+        ))
+    })
+}
+
+pub fn custom_date_parser<'de, D>(deserializer: D) -> Result<Date, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    Date::parse(clean_s, format_description!("[year]-[month]-[day]")).map_err(|_| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a date, it must be in ISO 8601 YYYY-MM-DD format like 2024-05-01",
+            clean_s
+        ))
+    })
+}
+
+pub fn custom_time_parser<'de, D>(deserializer: D) -> Result<Time, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    Time::parse(
+        clean_s,
+        format_description!("[hour]:[minute][optional [:[second][optional [.[subsecond]]]]]"),
+    )
+    .map_err(|_| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a time of day, it must be in ISO 8601 HH:MM:SS format like 13:45:00",
+            clean_s
+        ))
+    })
+}
+
+impl LlmPrompt for OffsetDateTime {
+    fn get_prompt_schema() -> &'static str {
+        "a date and time in RFC 3339 format, e.g. 2024-05-01T13:45:00Z or 2024-05-01T13:45:00+02:00. A value without an offset is read as UTC"
+    }
+
+    fn root_name() -> &'static str {
+        "OffsetDateTime"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("2024-05-01T13:45:00Z".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_offset_date_time_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl LlmPrompt for Date {
+    fn get_prompt_schema() -> &'static str {
+        "a calendar date in ISO 8601 YYYY-MM-DD format, e.g. 2024-05-01"
+    }
+
+    fn root_name() -> &'static str {
+        "Date"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("2024-05-01".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_date_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl LlmPrompt for Time {
+    fn get_prompt_schema() -> &'static str {
+        "a time of day in ISO 8601 HH:MM:SS format (24-hour clock), e.g. 13:45:00"
+    }
+
+    fn root_name() -> &'static str {
+        "Time"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("13:45:00".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_time_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
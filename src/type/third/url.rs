@@ -0,0 +1,42 @@
+use crate::r#type::r#macro::parsed_leaf;
+use crate::r#type::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use serde::{Deserialize, Deserializer};
+use url::Url;
+
+pub fn custom_url_parser<'de, D>(deserializer: D) -> Result<Url, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    Url::parse(clean_s).map_err(|e| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a URL ({}), it must be an absolute URL including its scheme like https://example.com/page",
+            clean_s, e
+        ))
+    })
+}
+
+impl LlmPrompt for Url {
+    fn get_prompt_schema() -> &'static str {
+        "an absolute URL including its scheme, e.g. https://example.com/page?id=1"
+    }
+
+    fn root_name() -> &'static str {
+        "Url"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("https://example.com/page?id=1".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_url_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
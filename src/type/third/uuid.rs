@@ -0,0 +1,52 @@
+use crate::r#type::r#macro::parsed_leaf;
+use crate::r#type::xml::XmlText;
+use crate::{LeafKind, LlmPrompt, SchemaNode};
+use serde::{Deserialize, Deserializer};
+use uuid::Uuid;
+
+pub fn custom_uuid_parser<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = XmlText::deserialize(deserializer)?;
+    let clean_s = text.clean();
+
+    let mut raw = clean_s;
+    if let Some(inner) = raw.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+        raw = inner.trim();
+    }
+    if let Some(prefix) = raw.get(..9)
+        && prefix.eq_ignore_ascii_case("urn:uuid:")
+    {
+        raw = &raw[9..];
+    }
+
+    Uuid::try_parse(raw).map_err(|_| {
+        serde::de::Error::custom(format!(
+            "can not parse '{}' as a UUID, it must be 32 hex digits in the canonical 8-4-4-4-12 form like 550e8400-e29b-41d4-a716-446655440000",
+            clean_s
+        ))
+    })
+}
+
+impl LlmPrompt for Uuid {
+    fn get_prompt_schema() -> &'static str {
+        "a UUID in canonical 8-4-4-4-12 hex form, e.g. 550e8400-e29b-41d4-a716-446655440000"
+    }
+
+    fn root_name() -> &'static str {
+        "Uuid"
+    }
+
+    fn example_content(_depth: usize) -> Option<String> {
+        Some("550e8400-e29b-41d4-a716-446655440000".to_string())
+    }
+
+    fn schema_node() -> SchemaNode {
+        parsed_leaf!(LeafKind::String, custom_uuid_parser)
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
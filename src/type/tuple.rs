@@ -0,0 +1,97 @@
+use super::example::example_element;
+use super::text::fill;
+use super::xml::XmlValue;
+use crate::{Cache, LlmPrompt, SchemaField, SchemaNode, SchemaText};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+use std::marker::PhantomData;
+
+/// Parses tuples of 2 to 4 elements written as `<first>..</first><second>..</second>...`.
+pub struct TupleParser<T>(PhantomData<T>);
+
+/// A tuple read with the `<first>`, `<second>`, ... grammar instead of serde's sequence one.
+struct XmlTuple<T>(T);
+
+macro_rules! impl_llm_tuple {
+    (
+        $xml:ident,
+        $len:literal,
+        $($field:ident: $ty:ident),+
+    ) => {
+        #[derive(Deserialize)]
+        struct $xml<$($ty),+> {
+            $($field: XmlValue<$ty>,)+
+        }
+
+        impl<'de, $($ty: DeserializeOwned),+> Deserialize<'de> for XmlTuple<($($ty,)+)> {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let wrapper = $xml::<$($ty),+>::deserialize(deserializer)?;
+                Ok(XmlTuple(($(wrapper.$field.0,)+)))
+            }
+        }
+
+        impl<$($ty: DeserializeOwned),+> TupleParser<($($ty,)+)> {
+            pub fn custom_tuple_parser<'de, D>(deserializer: D) -> Result<($($ty,)+), D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                match XmlTuple::<($($ty,)+)>::deserialize(deserializer) {
+                    Ok(wrapper) => Ok(wrapper.0),
+                    Err(e) => Err(serde::de::Error::custom(format!(
+                        "The XML structure is invalid. A tuple of {} values must consist of the elements {}. Details: {}",
+                        $len,
+                        [$(concat!("<", stringify!($field), ">")),+].join(", "),
+                        e
+                    ))),
+                }
+            }
+        }
+
+        impl<$($ty: LlmPrompt + 'static),+> LlmPrompt for ($($ty,)+) {
+            fn get_prompt_schema() -> &'static str {
+                let cache = Cache::<($($ty,)+)>::get();
+                cache.prompt_schema.get_or_init(|| {
+                    let elements = [$(format!(
+                        concat!("<", stringify!($field), ">{}</", stringify!($field), ">"),
+                        $ty::get_prompt_schema()
+                    )),+];
+                    fill(
+                        SchemaText::current().tuple,
+                        &[stringify!($len), &elements.concat()],
+                    )
+                })
+            }
+
+            fn root_name() -> &'static str {
+                let cache = Cache::<($($ty,)+)>::get();
+                cache
+                    .root_name
+                    .get_or_init(|| format!("({})", [$($ty::root_name()),+].join(", ")))
+            }
+
+            fn example_content(depth: usize) -> Option<String> {
+                Some([$(example_element(
+                    stringify!($field),
+                    $ty::example_item(depth.saturating_sub(1)),
+                )),+].concat())
+            }
+
+            fn schema_node() -> SchemaNode {
+                SchemaNode::Fields {
+                    schema: Self::get_prompt_schema(),
+                    fields: vec![$(SchemaField::element(stringify!($field), $ty::schema_node)),+],
+                }
+            }
+
+            const IS_ENUM: bool = false;
+
+            const HAS_ROOT: bool = false;
+        }
+    };
+}
+
+impl_llm_tuple!(XmlTuple2, 2, first: T1, second: T2);
+impl_llm_tuple!(XmlTuple3, 3, first: T1, second: T2, third: T3);
+impl_llm_tuple!(XmlTuple4, 4, first: T1, second: T2, third: T3, fourth: T4);
@@ -1,20 +1,100 @@
-use crate::{Cache, LlmPrompt};
-use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+use super::example::example_items;
+use super::xml::XmlValue;
+use super::{ContainerMistake, report_container_mistake};
+use crate::{Cache, LlmPrompt, SchemaNode};
+use serde::{
+    Deserialize, Deserializer,
+    de::{
+        self, DeserializeOwned, IgnoredAny, MapAccess, Visitor,
+        value::{StrDeserializer, StringDeserializer},
+    },
+};
+use std::collections::VecDeque;
+use std::fmt;
 use std::marker::PhantomData;
 
 pub struct VecParser<T>(PhantomData<T>);
 
-#[derive(Deserialize)]
-struct ItemWrapper<T> {
-    #[serde(rename = "$value")]
-    content: T,
+const ITEM: &str = "item";
+
+/// The items of a sequence element, each read as `I`.
+///
+/// The canonical form wraps every item in `<item>`. Unless `strict` is set, the LLM's usual
+/// deviations are accepted too: when the first child is not an `<item>`, every child is taken
+/// as an item (`<tags><tag>a</tag><tag>b</tag></tags>`), bare text is a single item
+/// (`<tags>a</tags>`), and once `<item>` is used any stray sibling such as a `<note>` is skipped.
+struct XmlItems<I> {
+    strict: bool,
+    marker: PhantomData<I>,
+}
+
+impl<'de, I: Deserialize<'de>> Visitor<'de> for XmlItems<I> {
+    type Value = Vec<I>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of <item> elements")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Vec<I>, E>
+    where
+        E: de::Error,
+    {
+        match (self.strict, v.trim().is_empty()) {
+            (_, true) => Ok(Vec::new()),
+            (true, false) => Err(de::Error::unknown_field("$text", &[ITEM])),
+            (false, false) => I::deserialize(StrDeserializer::new(v)).map(|item| vec![item]),
+        }
+    }
+
+    fn visit_unit<E>(self) -> Result<Vec<I>, E> {
+        Ok(Vec::new())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Vec<I>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut items = Vec::new();
+        // whether the children are wrapped in <item>, decided by the first one
+        let mut wrapped = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == ITEM {
+                wrapped.get_or_insert(true);
+                items.push(map.next_value()?);
+            } else if self.strict {
+                return Err(de::Error::unknown_field(&key, &[ITEM]));
+            } else if key.starts_with('@') || wrapped == Some(true) {
+                // an attribute or a stray sibling of the items
+                map.next_value::<IgnoredAny>()?;
+            } else if key == "$text" {
+                wrapped = Some(false);
+                let text = map.next_value::<String>()?;
+                items.push(I::deserialize(StringDeserializer::new(text))?);
+            } else {
+                wrapped = Some(false);
+                items.push(map.next_value()?);
+            }
+        }
+        Ok(items)
+    }
 }
 
-#[derive(Deserialize)]
-#[serde(deny_unknown_fields)]
-struct XmlSeq<T> {
-    #[serde(rename = "item", default = "Vec::new")]
-    items: Vec<ItemWrapper<T>>,
+fn deserialize_items<'de, D, I>(deserializer: D, strict: bool) -> Result<Vec<I>, D::Error>
+where
+    D: Deserializer<'de>,
+    I: Deserialize<'de>,
+{
+    let visitor = XmlItems {
+        strict,
+        marker: PhantomData,
+    };
+    deserializer.deserialize_map(visitor).map_err(|e| {
+        report_container_mistake(ContainerMistake::MissingItem);
+        de::Error::custom(format!(
+            "The XML structure is invalid. It must be a sequence of <item> elements, each containing the value. Details: {}",
+            e
+        ))
+    })
 }
 
 impl<T> VecParser<T>
@@ -25,32 +105,125 @@ where
     where
         D: Deserializer<'de>,
     {
-        match XmlSeq::<T>::deserialize(deserializer) {
-            Ok(wrapper) => Ok(wrapper.items.into_iter().map(|w| w.content).collect()),
-            Err(e) => Err(serde::de::Error::custom(format!(
-                "The XML structure is invalid. It must be a sequence of <item> elements, each containing the value. Details: {}",
-                e
-            ))),
-        }
+        deserialize_items::<D, XmlValue<T>>(deserializer, false)
+            .map(|items| items.into_iter().map(|w| w.0).collect())
+    }
+
+    /// Like [`VecParser::custom_vector_parser`], but only accepts the `<item>` form.
+    pub fn custom_strict_vector_parser<'de, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_items::<D, XmlValue<T>>(deserializer, true)
+            .map(|items| items.into_iter().map(|w| w.0).collect())
+    }
+
+    /// Like [`VecParser::custom_vector_parser`], but hands every whole `<item>` element to `T`.
+    ///
+    /// The macro passes a wrapper that runs the element type's own parser here, which is how
+    /// `Vec<Vec<T>>` and other nested containers keep their grammar at every level.
+    pub fn custom_item_vector_parser<'de, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_items(deserializer, false)
+    }
+
+    /// Like [`VecParser::custom_item_vector_parser`], but only accepts the `<item>` form.
+    pub fn custom_strict_item_vector_parser<'de, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_items(deserializer, true)
+    }
+}
+
+/// Reads a value written inside an element, e.g. a struct as `<item><Person>..</Person></item>`.
+pub struct ItemParser<T>(PhantomData<T>);
+
+impl<T> ItemParser<T>
+where
+    T: DeserializeOwned,
+{
+    pub fn custom_item_parser<'de, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        XmlValue::<T>::deserialize(deserializer).map(|w| w.0)
+    }
+}
+
+pub struct VecDequeParser<T>(PhantomData<T>);
+
+impl<T> VecDequeParser<T>
+where
+    T: DeserializeOwned,
+{
+    pub fn custom_vecdeque_parser<'de, D>(deserializer: D) -> Result<VecDeque<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        VecParser::<T>::custom_vector_parser(deserializer).map(VecDeque::from)
     }
 }
 
 impl<T: LlmPrompt + 'static> LlmPrompt for Vec<T> {
     fn get_prompt_schema() -> &'static str {
-        let sub_schema = T::get_prompt_schema();
         let cache = Cache::<Vec<T>>::get();
-        cache.prompt_schema.get_or_init(|| {
-            format!("A series(0 or more elements) of items where each item has the following format:<item>{}</item>\nNOTICE: Even a single item must be enclosed within <item></item> tags.", sub_schema)
-        })
+        cache
+            .prompt_schema
+            .get_or_init(|| Self::schema_node().render())
     }
 
     fn root_name() -> &'static str {
-        let sub_root_name = T::root_name();
         let cache = Cache::<Vec<T>>::get();
         cache
             .root_name
-            .get_or_init(|| format!("Vec<{}>", sub_root_name))
+            .get_or_init(|| format!("Vec<{}>", T::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        example_items::<T>(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Sequence {
+            item: T::schema_node,
+            unique: false,
+            ordered: true,
+        }
     }
 
     const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
+}
+
+impl<T: LlmPrompt + 'static> LlmPrompt for VecDeque<T> {
+    fn get_prompt_schema() -> &'static str {
+        Vec::<T>::get_prompt_schema()
+    }
+
+    fn root_name() -> &'static str {
+        let cache = Cache::<VecDeque<T>>::get();
+        cache
+            .root_name
+            .get_or_init(|| format!("VecDeque<{}>", T::root_name()))
+    }
+
+    fn example_content(depth: usize) -> Option<String> {
+        example_items::<T>(depth)
+    }
+
+    fn schema_node() -> SchemaNode {
+        SchemaNode::Sequence {
+            item: T::schema_node,
+            unique: false,
+            ordered: true,
+        }
+    }
+
+    const IS_ENUM: bool = false;
+
+    const HAS_ROOT: bool = false;
 }
@@ -0,0 +1,826 @@
+//! Crate-owned wrappers around the quick-xml deserialization semantics the parsers rely on.
+//!
+//! Every `$value` lookup and every transparent pass-through goes through the types in this
+//! module, so a change in how a quick-xml release treats these constructs only has to be
+//! absorbed here instead of leaking into `Vec`, `Option`, map or enum parsing.
+//!
+//! Supported quick-xml releases: `>=0.38, <0.40` (see the requirement in `Cargo.toml`). The
+//! assertions below pin the API the crate is written against at compile time, so a release
+//! without `Event::GeneralRef` (added in 0.38) or with another shape of the deserializer, its
+//! errors or reader positions fails the build here. Their behavior is not checked at compile
+//! time: `cargo xtask quick-xml-matrix` runs the test suite against each pinned release, which
+//! is what catches a change in these semantics.
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{
+        self, DeserializeSeed, IgnoredAny, MapAccess, VariantAccess, Visitor,
+        value::{MapAccessDeserializer, StrDeserializer, StringDeserializer},
+    },
+};
+use std::{fmt, marker::PhantomData};
+
+// The quick-xml API relied on by `cast`, `validate` and `bind`.
+const _: fn(&'static str) -> Result<(), quick_xml::DeError> = quick_xml::de::from_str::<()>;
+const _: fn(&'static [u8]) -> Result<(), quick_xml::DeError> =
+    quick_xml::de::from_reader::<&'static [u8], ()>;
+const _: fn(quick_xml::Error) -> quick_xml::DeError = quick_xml::DeError::InvalidXml;
+const _: fn(quick_xml::events::BytesRef<'static>) -> quick_xml::events::Event<'static> =
+    quick_xml::events::Event::GeneralRef;
+const _: fn(&quick_xml::Reader<&'static [u8]>) -> u64 = quick_xml::Reader::buffer_position;
+const _: fn(&quick_xml::Reader<&'static [u8]>) -> u64 = quick_xml::Reader::error_position;
+
+/// The special field name quick-xml uses for "whatever is inside this element".
+const VALUE: &str = "$value";
+/// The special field name quick-xml uses for the text content of an element.
+const TEXT: &str = "$text";
+
+/// The content of an element, e.g. the `T` in `<item>T</item>`.
+///
+/// quick-xml reports an element without any content (`<item/>` or `<item></item>`) as a
+/// missing `$value` field. Here it is read as empty text instead, so `T` decides whether an
+/// empty element is acceptable (`String` yields `""`, numbers report a parse error).
+pub(crate) struct XmlValue<T>(pub T);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for XmlValue<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match XmlOptionalValue::<T>::deserialize(deserializer)? {
+            XmlOptionalValue(Some(v)) => Ok(XmlValue(v)),
+            XmlOptionalValue(None) => {
+                T::deserialize(StrDeserializer::<D::Error>::new("")).map(XmlValue)
+            }
+        }
+    }
+}
+
+/// The content of an element like [`XmlValue`], but `None` when the element is empty.
+pub(crate) struct XmlOptionalValue<T>(pub Option<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for XmlOptionalValue<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ValueVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for ValueVisitor<T> {
+            type Value = XmlOptionalValue<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an element containing a value")
+            }
+
+            // bare text, e.g. a single item written without its `<item>` wrapper
+            fn visit_str<E>(self, v: &str) -> Result<XmlOptionalValue<T>, E>
+            where
+                E: de::Error,
+            {
+                T::deserialize(StrDeserializer::new(v)).map(|v| XmlOptionalValue(Some(v)))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<XmlOptionalValue<T>, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut value = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    if key != VALUE {
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    }
+                    if value.is_some() {
+                        return Err(de::Error::duplicate_field(VALUE));
+                    }
+                    value = Some(map.next_value()?);
+                }
+                Ok(XmlOptionalValue(value))
+            }
+        }
+
+        deserializer.deserialize_struct("XmlValue", &[VALUE], ValueVisitor(PhantomData))
+    }
+}
+
+/// A whole element handed to `T`, or `None` when the element is empty (`<item/>`,
+/// `<item></item>`, only whitespace or an empty CDATA section).
+///
+/// quick-xml reports an empty element as present, so `T` would be asked to parse nothing. The
+/// first entry of the element is read here to tell the two apart and then replayed to `T`.
+pub(crate) struct XmlOptionalElement<T>(pub Option<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for XmlOptionalElement<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ElementVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for ElementVisitor<T> {
+            type Value = XmlOptionalElement<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an element that is either empty or contains a value")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<XmlOptionalElement<T>, E>
+            where
+                E: de::Error,
+            {
+                if XmlText(v.to_string()).clean().is_empty() {
+                    return Ok(XmlOptionalElement(None));
+                }
+                T::deserialize(StrDeserializer::new(v)).map(|v| XmlOptionalElement(Some(v)))
+            }
+
+            fn visit_unit<E>(self) -> Result<XmlOptionalElement<T>, E> {
+                Ok(XmlOptionalElement(None))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<XmlOptionalElement<T>, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let Some(key) = map.next_key::<String>()? else {
+                    return Ok(XmlOptionalElement(None));
+                };
+                // text has to be read to know whether it is empty
+                let text = if key == TEXT {
+                    let text = map.next_value::<XmlText>()?;
+                    if text.clean().is_empty() {
+                        while map.next_key::<IgnoredAny>()?.is_some() {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                        return Ok(XmlOptionalElement(None));
+                    }
+                    Some(text.0)
+                } else {
+                    None
+                };
+                let map = PeekedMap {
+                    key: Some(key),
+                    text,
+                    map,
+                };
+                T::deserialize(MapAccessDeserializer::new(map)).map(|v| XmlOptionalElement(Some(v)))
+            }
+        }
+
+        deserializer.deserialize_any(ElementVisitor(PhantomData))
+    }
+}
+
+/// A map whose first key (and possibly its text value) was already read, handing it out again
+/// before the remaining entries.
+struct PeekedMap<A> {
+    key: Option<String>,
+    text: Option<String>,
+    map: A,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for PeekedMap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.key.take() {
+            Some(key) => seed.deserialize(StringDeserializer::new(key)).map(Some),
+            None => self.map.next_key_seed(seed),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, A::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.text.take() {
+            Some(text) => seed.deserialize(StringDeserializer::new(text)),
+            None => self.map.next_value_seed(seed),
+        }
+    }
+}
+
+/// A value read directly from the current element, without any wrapper element of its own.
+#[cfg(feature = "ordered_float")]
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub(crate) struct XmlTransparent<T>(pub T);
+
+/// The text of an element, e.g. the `text` in `<name>text</name>`.
+///
+/// quick-xml hands over plain text for ordinary fields, but fields reached through
+/// `#[serde(flatten)]` are buffered first and arrive as a map holding a `$text` (or `$value`)
+/// entry. Both shapes are accepted here, and an element without text is read as `""`.
+pub(crate) struct XmlText(pub String);
+
+impl XmlText {
+    /// The trimmed text, with a CDATA wrapper removed if the LLM escaped it into plain text.
+    pub(crate) fn clean(&self) -> &str {
+        let text = self.0.trim();
+        text.strip_prefix("<![CDATA[")
+            .and_then(|rest| rest.strip_suffix("]]>"))
+            .map_or(text, str::trim)
+    }
+}
+
+impl<'de> Deserialize<'de> for XmlText {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TextVisitor;
+
+        impl<'de> Visitor<'de> for TextVisitor {
+            type Value = XmlText;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the text content of an element")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<XmlText, E> {
+                Ok(XmlText(v.to_string()))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<XmlText, E> {
+                Ok(XmlText(v))
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<XmlText, E> {
+                Ok(XmlText(v.to_string()))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<XmlText, E> {
+                Ok(XmlText(v.to_string()))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<XmlText, E> {
+                Ok(XmlText(v.to_string()))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<XmlText, E> {
+                Ok(XmlText(v.to_string()))
+            }
+
+            fn visit_unit<E>(self) -> Result<XmlText, E> {
+                Ok(XmlText(String::new()))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<XmlText, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut text = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    if (key == TEXT || key == VALUE) && text.is_none() {
+                        text = Some(map.next_value::<XmlText>()?);
+                    } else {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+                Ok(text.unwrap_or(XmlText(String::new())))
+            }
+        }
+
+        deserializer.deserialize_any(TextVisitor)
+    }
+}
+
+/// The name an enum value was written with, either as an element (`<Red/>`) or as the text of
+/// the surrounding element (`<color>Red</color>`).
+///
+/// quick-xml exposes both through `deserialize_enum`: an element reports its tag as the variant,
+/// while text content is reported as a `$text` variant carrying the text.
+pub(crate) struct XmlEnumName(pub String);
+
+impl<'de> Deserialize<'de> for XmlEnumName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NameVisitor;
+
+        impl<'de> Visitor<'de> for NameVisitor {
+            type Value = XmlEnumName;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an enum value written as text or as an empty element")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<XmlEnumName, E> {
+                Ok(XmlEnumName(v.to_string()))
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<XmlEnumName, A::Error>
+            where
+                A: de::EnumAccess<'de>,
+            {
+                let (name, variant) = data.variant::<String>()?;
+                if name == TEXT {
+                    let XmlText(text) = variant.newtype_variant()?;
+                    Ok(XmlEnumName(text))
+                } else {
+                    variant.unit_variant()?;
+                    Ok(XmlEnumName(name))
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("XmlEnumName", &[], NameVisitor)
+    }
+}
+
+/// The element names of the values of a tuple, in order.
+const TUPLE: &[&str] = &["first", "second", "third", "fourth"];
+
+/// A value read from a whole element with the crate's grammar instead of serde's, for types
+/// without a root element of their own, e.g. a `Vec<T>` written at the top level of a response
+/// as `<response><item>..</item></response>`.
+///
+/// serde's impls of these types ask for a sequence, a map, an option or a primitive, so the
+/// request is answered here: sequences and tuples read their `<item>` or `<first>`, `<second>`,
+/// ... children, maps their `<entry>` children, an empty element is `None`, and primitives go
+/// through the lenient parsers of the fields. Each child is read the same way, and a struct or
+/// enum is read from inside its element like an [`XmlValue`].
+pub(crate) struct XmlResponse<D> {
+    de: D,
+    /// Whether `de` is the surrounding element rather than the value inside it.
+    element: bool,
+}
+
+impl<D> XmlResponse<D> {
+    /// The value of the element `de`, e.g. the document root.
+    pub(crate) fn element(de: D) -> Self {
+        XmlResponse { de, element: true }
+    }
+
+    fn content(de: D) -> Self {
+        XmlResponse { de, element: false }
+    }
+}
+
+macro_rules! forward_to_inner {
+    ($($method:ident),* $(,)?) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+            self.de.$method(visitor)
+        }
+    )*};
+}
+
+macro_rules! deserialize_text {
+    ($($method:ident => $visit:ident($parser:path)),* $(,)?) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+            let XmlText(text) = XmlText::deserialize(self.de)?;
+            visitor.$visit($parser(StringDeserializer::<D::Error>::new(text))?)
+        }
+    )*};
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for XmlResponse<D> {
+    type Error = D::Error;
+
+    forward_to_inner!(
+        deserialize_any,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_unit,
+        deserialize_identifier,
+        deserialize_ignored_any,
+    );
+
+    deserialize_text!(
+        deserialize_bool => visit_bool(crate::custom_bool_parser),
+        deserialize_i8 => visit_i8(crate::custom_i8_parser),
+        deserialize_i16 => visit_i16(crate::custom_i16_parser),
+        deserialize_i32 => visit_i32(crate::custom_i32_parser),
+        deserialize_i64 => visit_i64(crate::custom_i64_parser),
+        deserialize_i128 => visit_i128(crate::custom_i128_parser),
+        deserialize_u8 => visit_u8(crate::custom_u8_parser),
+        deserialize_u16 => visit_u16(crate::custom_u16_parser),
+        deserialize_u32 => visit_u32(crate::custom_u32_parser),
+        deserialize_u64 => visit_u64(crate::custom_u64_parser),
+        deserialize_u128 => visit_u128(crate::custom_u128_parser),
+        deserialize_f32 => visit_f32(crate::custom_f32_parser),
+        deserialize_f64 => visit_f64(crate::custom_f64_parser),
+        deserialize_char => visit_char(crate::custom_char_parser),
+        deserialize_str => visit_string(crate::custom_string_parser),
+        deserialize_string => visit_string(crate::custom_string_parser),
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        match self.element {
+            true => self
+                .de
+                .deserialize_struct("XmlValue", &[VALUE], OptionVisitor(visitor)),
+            false => self.de.deserialize_option(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        self.de.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        match self.element {
+            true => self.de.deserialize_map(ChildrenVisitor {
+                visitor,
+                names: &["item"],
+            }),
+            false => self.de.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        match self.element {
+            true => self.de.deserialize_map(ChildrenVisitor {
+                visitor,
+                names: TUPLE,
+            }),
+            false => self.de.deserialize_tuple(len, visitor),
+        }
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        match self.element {
+            true => self.deserialize_tuple(len, visitor),
+            false => self.de.deserialize_tuple_struct(name, len, visitor),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        match self.element {
+            true => self.de.deserialize_map(EntriesVisitor(visitor)),
+            false => self.de.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        let seed = ForwardSeed::Struct(name, fields, visitor);
+        match self.element {
+            true => self
+                .de
+                .deserialize_struct("XmlValue", &[VALUE], ContentVisitor(seed)),
+            false => seed.deserialize(self.de),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        let seed = ForwardSeed::Enum(name, variants, visitor);
+        match self.element {
+            true => self
+                .de
+                .deserialize_struct("XmlValue", &[VALUE], ContentVisitor(seed)),
+            false => seed.deserialize(self.de),
+        }
+    }
+}
+
+/// Reads a child element with [`XmlResponse`].
+struct ElementSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for ElementSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<S::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.deserialize(XmlResponse::element(deserializer))
+    }
+}
+
+/// The struct or enum request of a visitor, replayed on the value inside an element.
+enum ForwardSeed<V> {
+    Struct(&'static str, &'static [&'static str], V),
+    Enum(&'static str, &'static [&'static str], V),
+}
+
+impl<'de, V: Visitor<'de>> DeserializeSeed<'de> for ForwardSeed<V> {
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self {
+            ForwardSeed::Struct(name, fields, visitor) => {
+                deserializer.deserialize_struct(name, fields, visitor)
+            }
+            ForwardSeed::Enum(name, variants, visitor) => {
+                deserializer.deserialize_enum(name, variants, visitor)
+            }
+        }
+    }
+}
+
+/// Hands the content of an element to a seed, or empty text when there is none, like
+/// [`XmlValue`].
+struct ContentVisitor<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for ContentVisitor<S> {
+    type Value = S::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an element containing a value")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<S::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.deserialize(StrDeserializer::new(v))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<S::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == VALUE {
+                let value = map.next_value_seed(self.0)?;
+                while map.next_key::<IgnoredAny>()?.is_some() {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                return Ok(value);
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        self.0.deserialize(StrDeserializer::new(""))
+    }
+}
+
+/// An optional element, `None` when it is empty.
+struct OptionVisitor<V>(V);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for OptionVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an element that is either empty or contains a value")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<V::Value, E>
+    where
+        E: de::Error,
+    {
+        if XmlText(v.to_string()).clean().is_empty() {
+            return self.0.visit_none();
+        }
+        self.0
+            .visit_some(XmlResponse::content(StrDeserializer::new(v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<V::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_none()
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<V::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == VALUE {
+                let value = map.next_value_seed(SomeSeed(self.0))?;
+                while map.next_key::<IgnoredAny>()?.is_some() {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                return Ok(value);
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        self.0.visit_none()
+    }
+}
+
+/// The present value of an optional element.
+struct SomeSeed<V>(V);
+
+impl<'de, V: Visitor<'de>> DeserializeSeed<'de> for SomeSeed<V> {
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.visit_some(XmlResponse::content(deserializer))
+    }
+}
+
+/// The children named one of `names` of an element, handed to a sequence visitor.
+struct ChildrenVisitor<V> {
+    visitor: V,
+    names: &'static [&'static str],
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for ChildrenVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a sequence of <{}> elements",
+            self.names.join(">, <")
+        )
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<V::Value, E>
+    where
+        E: de::Error,
+    {
+        match v.trim().is_empty() {
+            true => self.visit_unit(),
+            false => Err(de::Error::unknown_field(TEXT, self.names)),
+        }
+    }
+
+    fn visit_unit<E>(self) -> Result<V::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor
+            .visit_seq(de::value::SeqDeserializer::new(std::iter::empty::<()>()))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<V::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.visitor.visit_seq(Children {
+            map,
+            names: self.names,
+        })
+    }
+}
+
+struct Children<A> {
+    map: A,
+    names: &'static [&'static str],
+}
+
+impl<'de, A: MapAccess<'de>> de::SeqAccess<'de> for Children<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>, A::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        while let Some(key) = self.map.next_key::<String>()? {
+            if self.names.contains(&key.as_str()) {
+                return self.map.next_value_seed(ElementSeed(seed)).map(Some);
+            }
+            // an attribute or a stray sibling of the values
+            self.map.next_value::<IgnoredAny>()?;
+        }
+        Ok(None)
+    }
+}
+
+/// The `<entry>` children of an element, handed to a map visitor.
+struct EntriesVisitor<V>(V);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for EntriesVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of <entry> elements")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<V::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.0.visit_map(Entries(map))
+    }
+}
+
+struct Entries<A>(A);
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Entries<A> {
+    type Error = A::Error;
+
+    // an <entry> holds both the key and the value, so a visitor has to read them together
+    fn next_key_seed<K>(&mut self, _seed: K) -> Result<Option<K::Value>, A::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        Err(de::Error::custom(
+            "the key and value of an <entry> must be read together",
+        ))
+    }
+
+    fn next_value_seed<V>(&mut self, _seed: V) -> Result<V::Value, A::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Err(de::Error::custom(
+            "the key and value of an <entry> must be read together",
+        ))
+    }
+
+    fn next_entry_seed<K, V>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<Option<(K::Value, V::Value)>, A::Error>
+    where
+        K: DeserializeSeed<'de>,
+        V: DeserializeSeed<'de>,
+    {
+        while let Some(name) = self.0.next_key::<String>()? {
+            if name == "entry" {
+                return self.0.next_value_seed(EntrySeed { key, value }).map(Some);
+            }
+            self.0.next_value::<IgnoredAny>()?;
+        }
+        Ok(None)
+    }
+}
+
+/// The `<key>` and `<value>` of an `<entry>`.
+struct EntrySeed<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<'de, K: DeserializeSeed<'de>, V: DeserializeSeed<'de>> DeserializeSeed<'de>
+    for EntrySeed<K, V>
+{
+    type Value = (K::Value, V::Value);
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("entry", &["key", "value"], self)
+    }
+}
+
+impl<'de, K: DeserializeSeed<'de>, V: DeserializeSeed<'de>> Visitor<'de> for EntrySeed<K, V> {
+    type Value = (K::Value, V::Value);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an <entry> with a <key> and a <value>")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let (mut key_seed, mut value_seed) = (Some(self.key), Some(self.value));
+        let (mut key, mut value) = (None, None);
+        while let Some(name) = map.next_key::<String>()? {
+            if name == "key"
+                && let Some(seed) = key_seed.take()
+            {
+                key = Some(map.next_value_seed(ElementSeed(seed))?);
+            } else if name == "value"
+                && let Some(seed) = value_seed.take()
+            {
+                value = Some(map.next_value_seed(ElementSeed(seed))?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok((
+            key.ok_or_else(|| de::Error::missing_field("key"))?,
+            value.ok_or_else(|| de::Error::missing_field("value"))?,
+        ))
+    }
+}
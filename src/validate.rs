@@ -0,0 +1,407 @@
+//! Checking a document against the schema of a type, reporting everything wrong with it
+//! instead of the first error the deserializer runs into.
+
+use crate::cast::document_root;
+use crate::r#type::written_fields;
+use crate::{LlmPrompt, SchemaField, SchemaNode, SchemaVariant};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::fmt;
+
+/// What is wrong with an element, see [`ValidationIssue`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The document isn't well-formed XML, so nothing after the error was checked.
+    Malformed,
+    /// An element the type has no field, variant, `<item>` or `<entry>` for.
+    UnknownElement,
+    /// A required field, or the variant of an enum, that isn't written.
+    MissingElement,
+    /// A field written more than once.
+    DuplicateElement,
+    /// Content that doesn't read as the type of its element, or breaks a constraint of the
+    /// field's `#[prompt(...)]`.
+    InvalidValue,
+}
+
+/// A problem found by [`validate_xml`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// What kind of problem it is.
+    pub kind: IssueKind,
+    /// The path of the element, e.g. `/Person/address/city` or `/response/item[2]`, counting
+    /// items from 1. Empty for a malformed document.
+    pub path: String,
+    /// The byte offset of the element in the document, or of its parent for a missing one.
+    pub offset: usize,
+    /// What is wrong, phrased like the errors of the deserializer.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "byte {}: {}", self.offset, self.message),
+            false => write!(f, "{} (byte {}): {}", self.path, self.offset, self.message),
+        }
+    }
+}
+
+/// Everything [`validate_xml`] found wrong with a document, in document order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The problems found, none for a valid document.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Whether no problem was found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return f.write_str("the document is valid");
+        }
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the document `xml` of `T` against its schema, without deserializing a `T`.
+///
+/// Unlike casting, which stops at the first error, every element is checked: unknown or
+/// duplicated elements, missing required fields and variants, and values that don't read as
+/// their type or break a constraint of their field, each with its path and byte offset in
+/// `xml`. `xml` is a single document, e.g. one of [`extract_candidates`](crate::extract_candidates).
+///
+/// The checks follow the [`schema_node`](LlmPrompt::schema_node) of each type, so the content
+/// of a type without one, such as a hand-written impl, isn't checked. Leniencies of the
+/// parsers that aren't in the schema, e.g. a sequence without `<item>` wrappers, are reported.
+pub fn validate_xml<T: LlmPrompt>(xml: &str) -> ValidationReport {
+    let mut walker = Walker {
+        xml,
+        issues: Vec::new(),
+    };
+    match parse_document(xml) {
+        Ok(root) => walker.document::<T>(&root),
+        Err(issue) => walker.issues.push(issue),
+    }
+    ValidationReport {
+        issues: walker.issues,
+    }
+}
+
+/// An element of the document, with the span it covers.
+struct Element {
+    name: String,
+    start: usize,
+    end: usize,
+    children: Vec<Element>,
+    /// Whether there is any text or CDATA content besides whitespace.
+    has_text: bool,
+}
+
+impl Element {
+    fn new(name: &[u8], start: usize) -> Self {
+        Element {
+            name: String::from_utf8_lossy(name).into_owned(),
+            start,
+            end: start,
+            children: Vec::new(),
+            has_text: false,
+        }
+    }
+
+    /// Whether the element has no content, which an `Option` reads as `None`.
+    fn is_empty(&self) -> bool {
+        self.children.is_empty() && !self.has_text
+    }
+}
+
+/// The first element of `xml` with all of its descendants.
+fn parse_document(xml: &str) -> Result<Element, ValidationIssue> {
+    let malformed = |offset: u64, message: String| ValidationIssue {
+        kind: IssueKind::Malformed,
+        path: String::new(),
+        offset: offset as usize,
+        message,
+    };
+    let mut reader = Reader::from_str(xml);
+    let mut open: Vec<Element> = Vec::new();
+    loop {
+        let start = reader.buffer_position() as usize;
+        let element = match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                open.push(Element::new(e.name().as_ref(), start));
+                continue;
+            }
+            Ok(Event::Empty(e)) => Element {
+                end: reader.buffer_position() as usize,
+                ..Element::new(e.name().as_ref(), start)
+            },
+            Ok(Event::End(_)) => match open.pop() {
+                Some(element) => Element {
+                    end: reader.buffer_position() as usize,
+                    ..element
+                },
+                None => continue,
+            },
+            Ok(Event::Text(text)) => {
+                if let Some(parent) = open.last_mut() {
+                    parent.has_text |= text.iter().any(|b| !b.is_ascii_whitespace());
+                }
+                continue;
+            }
+            Ok(Event::CData(text)) => {
+                if let Some(parent) = open.last_mut() {
+                    parent.has_text |= text.iter().any(|b| !b.is_ascii_whitespace());
+                }
+                continue;
+            }
+            Ok(Event::GeneralRef(_)) => {
+                if let Some(parent) = open.last_mut() {
+                    parent.has_text = true;
+                }
+                continue;
+            }
+            Ok(Event::Eof) => {
+                let message = match open.last() {
+                    Some(element) => format!("the document ends before </{}>", element.name),
+                    None => "the document holds no element".to_string(),
+                };
+                return Err(malformed(reader.buffer_position(), message));
+            }
+            Ok(_) => continue,
+            Err(e) => return Err(malformed(reader.error_position(), e.to_string())),
+        };
+        match open.last_mut() {
+            Some(parent) => parent.children.push(element),
+            None => return Ok(element),
+        }
+    }
+}
+
+/// Walks the elements of a document along the schema nodes, collecting the issues.
+struct Walker<'a> {
+    xml: &'a str,
+    issues: Vec<ValidationIssue>,
+}
+
+impl Walker<'_> {
+    fn issue(&mut self, kind: IssueKind, path: String, offset: usize, message: String) {
+        self.issues.push(ValidationIssue {
+            kind,
+            path,
+            offset,
+            message,
+        });
+    }
+
+    /// Checks the root element of a document of `T`: a variant for an enum, its own root for
+    /// a struct, and `<response>` otherwise.
+    fn document<T: LlmPrompt>(&mut self, root: &Element) {
+        let path = format!("/{}", root.name);
+        let node = T::schema_node();
+        if let (true, SchemaNode::Enum { variants, .. }) = (T::IS_ENUM, &node) {
+            self.variant(root, variants, path);
+        } else if T::IS_ENUM || root.name == document_root::<T>() {
+            self.node(root, &node, &path);
+        } else {
+            let message = format!(
+                "the root element must be <{}>, not <{}>",
+                document_root::<T>(),
+                root.name
+            );
+            self.issue(IssueKind::UnknownElement, path, root.start, message);
+        }
+    }
+
+    fn node(&mut self, element: &Element, node: &SchemaNode, path: &str) {
+        match node {
+            SchemaNode::Custom { .. } => {}
+            SchemaNode::Reference { node, .. } => self.node(element, &node(), path),
+            SchemaNode::Optional(inner) => {
+                if !element.is_empty() {
+                    self.node(element, &inner(), path);
+                }
+            }
+            // the parser reads the whole element, so it decides about child elements too
+            SchemaNode::Leaf {
+                check: Some(check), ..
+            } => {
+                if let Err(message) = check(&self.xml[element.start..element.end]) {
+                    self.issue(IssueKind::InvalidValue, path.into(), element.start, message);
+                }
+            }
+            SchemaNode::Leaf { check: None, .. } => {
+                for child in &element.children {
+                    let message = format!("unknown element <{}> in a text value", child.name);
+                    self.unknown(child, path, message);
+                }
+            }
+            SchemaNode::Struct { root, fields } => {
+                let fields = written_fields(fields);
+                match &element.children[..] {
+                    // a value position wraps the fields in the root element
+                    [inner] if inner.name == *root && fields.iter().all(|f| f.tag != *root) => {
+                        self.fields(inner, &fields, &format!("{}/{}", path, root));
+                    }
+                    _ => self.fields(element, &fields, path),
+                }
+            }
+            SchemaNode::Enum { variants, .. } => {
+                let mut found = false;
+                for child in &element.children {
+                    let child_path = format!("{}/{}", path, child.name);
+                    if found && variants.iter().any(|v| v.tag == child.name) {
+                        let message =
+                            format!("a second variant <{}>, only one is allowed", child.name);
+                        self.issue(
+                            IssueKind::DuplicateElement,
+                            child_path,
+                            child.start,
+                            message,
+                        );
+                    } else {
+                        found |= self.variant(child, variants, child_path);
+                    }
+                }
+                if !found && element.children.is_empty() {
+                    let message = format!("missing the variant, one of: {}", tags(variants));
+                    self.issue(
+                        IssueKind::MissingElement,
+                        path.into(),
+                        element.start,
+                        message,
+                    );
+                }
+            }
+            SchemaNode::Sequence { item, .. } => {
+                let item = item();
+                let mut count = 0;
+                for child in &element.children {
+                    if child.name == "item" {
+                        count += 1;
+                        self.node(child, &item, &format!("{}/item[{}]", path, count));
+                    } else {
+                        let message = format!(
+                            "unknown element <{}>, every value must be in an <item>",
+                            child.name
+                        );
+                        self.unknown(child, path, message);
+                    }
+                }
+            }
+            SchemaNode::Fields { fields, .. } => {
+                self.fields(element, &written_fields(fields), path);
+            }
+            SchemaNode::Map { key, value, .. } => {
+                let fields = [
+                    SchemaField::element("key", *key),
+                    SchemaField::element("value", *value),
+                ];
+                let mut count = 0;
+                for child in &element.children {
+                    if child.name == "entry" {
+                        count += 1;
+                        self.fields(child, &fields, &format!("{}/entry[{}]", path, count));
+                    } else {
+                        let message = format!(
+                            "unknown element <{}>, every pair must be in an <entry>",
+                            child.name
+                        );
+                        self.unknown(child, path, message);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks `element` as one of `variants`, returning whether it is one.
+    fn variant(&mut self, element: &Element, variants: &[SchemaVariant], path: String) -> bool {
+        match variants.iter().find(|v| v.tag == element.name) {
+            Some(variant) => {
+                self.fields(element, &written_fields(&variant.fields), &path);
+                true
+            }
+            None => {
+                let message = format!(
+                    "unknown variant <{}>, expected one of: {}",
+                    element.name,
+                    tags(variants)
+                );
+                self.issue(IssueKind::UnknownElement, path, element.start, message);
+                false
+            }
+        }
+    }
+
+    fn fields(&mut self, element: &Element, fields: &[SchemaField], path: &str) {
+        let mut seen = vec![false; fields.len()];
+        for child in &element.children {
+            let child_path = format!("{}/{}", path, child.name);
+            let Some(i) = fields.iter().position(|f| f.tag == child.name) else {
+                let expected = fields.iter().map(|f| f.tag).collect::<Vec<_>>().join(", ");
+                let message = match expected.is_empty() {
+                    true => format!("unknown element <{}>, no child is expected", child.name),
+                    false => format!(
+                        "unknown element <{}>, expected one of: {}",
+                        child.name, expected
+                    ),
+                };
+                self.issue(IssueKind::UnknownElement, child_path, child.start, message);
+                continue;
+            };
+            if seen[i] {
+                let message = format!("duplicate field <{}>", child.name);
+                self.issue(
+                    IssueKind::DuplicateElement,
+                    child_path,
+                    child.start,
+                    message,
+                );
+                continue;
+            }
+            seen[i] = true;
+            let before = self.issues.len();
+            self.node(child, &(fields[i].node)(), &child_path);
+            // the field's parser repeats what was already reported inside it
+            if self.issues.len() == before
+                && let Some(check) = fields[i].check
+                && let Err(message) = check(&self.xml[child.start..child.end])
+            {
+                self.issue(IssueKind::InvalidValue, child_path, child.start, message);
+            }
+        }
+        for (field, seen) in fields.iter().zip(seen) {
+            if field.required && !seen {
+                let message = format!("missing field <{}>", field.tag);
+                let path = format!("{}/{}", path, field.tag);
+                self.issue(IssueKind::MissingElement, path, element.start, message);
+            }
+        }
+    }
+
+    fn unknown(&mut self, child: &Element, path: &str, message: String) {
+        let path = format!("{}/{}", path, child.name);
+        self.issue(IssueKind::UnknownElement, path, child.start, message);
+    }
+}
+
+/// The element names of `variants`, for a message.
+fn tags(variants: &[SchemaVariant]) -> String {
+    variants
+        .iter()
+        .map(|v| v.tag)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,289 @@
+//! XML Schema (XSD 1.0) of the documents of a type, rendered from its schema tree, to check
+//! responses with standard XML tooling.
+
+use crate::cast::RESPONSE_ROOT;
+use crate::r#type::written_fields;
+use crate::{LeafKind, LlmPrompt, SchemaField, SchemaNode, SchemaVariant};
+use quick_xml::escape::escape;
+
+/// The XSD of a document of `T`, see [`LlmPrompt::xsd`].
+pub(crate) fn document<T: LlmPrompt + ?Sized>() -> String {
+    let mut xsd = Xsd::default();
+    let roots = match (T::IS_ENUM, T::schema_node()) {
+        // the document is the element of one of the variants
+        (_, SchemaNode::Enum { variants, .. }) => variants.iter().map(|v| xsd.variant(v)).collect(),
+        (false, SchemaNode::Struct { root, fields }) if T::HAS_ROOT => {
+            xsd.define_struct(root, &fields);
+            vec![format!("<xs:element name=\"{}\" type=\"{}\"/>", root, root)]
+        }
+        (_, node) => vec![xsd.element(RESPONSE_ROOT, &node, "", "")],
+    };
+    let body = roots
+        .iter()
+        .chain(&xsd.definitions)
+        .map(|part| indent(part))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\n{}\n</xs:schema>\n",
+        body
+    )
+}
+
+/// The content of an element: a named type, or a type declared in place.
+enum Content {
+    Type(&'static str),
+    Inline(String),
+}
+
+/// Renders the declarations of a tree of nodes, collecting the named definitions.
+#[derive(Default)]
+struct Xsd {
+    // a `complexType` for each struct and a `group` for each enum, declared once
+    definitions: Vec<String>,
+    defined: Vec<&'static str>,
+}
+
+impl Xsd {
+    /// The declaration of the element `name` holding `node`, with `occurs` attributes and a
+    /// `doc` annotation if any.
+    fn element(&mut self, name: &str, node: &SchemaNode, occurs: &str, doc: &str) -> String {
+        let content = self.content(node, false);
+        self.declare(name, occurs, doc, content)
+    }
+
+    fn declare(&self, name: &str, occurs: &str, doc: &str, content: Content) -> String {
+        let (ty, inline) = match content {
+            Content::Type(ty) => (format!(" type=\"{}\"", ty), None),
+            Content::Inline(inline) => (String::new(), Some(inline)),
+        };
+        let open = format!("<xs:element name=\"{}\"{}{}", name, occurs, ty);
+        let children = annotation(doc)
+            .into_iter()
+            .chain(inline)
+            .collect::<Vec<_>>();
+        match children.is_empty() {
+            true => format!("{}/>", open),
+            false => format!(
+                "{}>\n{}\n</xs:element>",
+                open,
+                children
+                    .iter()
+                    .map(|child| indent(child))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        }
+    }
+
+    /// The content of an element holding `node`, which may also be left empty when
+    /// `emptiable`, as an absent `Option` is.
+    fn content(&mut self, node: &SchemaNode, emptiable: bool) -> Content {
+        match node {
+            SchemaNode::Custom { .. } => Content::Type("xs:anyType"),
+            SchemaNode::Leaf { kind, .. } => leaf(*kind, emptiable),
+            SchemaNode::Optional(inner) => self.content(&inner(), true),
+            SchemaNode::Struct { root, fields } => {
+                self.define_struct(root, fields);
+                let occurs = if emptiable { " minOccurs=\"0\"" } else { "" };
+                Content::Inline(format!(
+                    "<xs:complexType>\n  <xs:sequence>\n    <xs:element name=\"{root}\" type=\"{root}\"{occurs}/>\n  </xs:sequence>\n</xs:complexType>"
+                ))
+            }
+            SchemaNode::Enum { name, variants } => {
+                self.define_enum(name, variants);
+                let occurs = if emptiable { " minOccurs=\"0\"" } else { "" };
+                Content::Inline(format!(
+                    "<xs:complexType>\n  <xs:group ref=\"{name}\"{occurs}/>\n</xs:complexType>"
+                ))
+            }
+            SchemaNode::Sequence { item, .. } => {
+                let item = self.element(
+                    "item",
+                    &item(),
+                    " minOccurs=\"0\" maxOccurs=\"unbounded\"",
+                    "",
+                );
+                Content::Inline(complex_sequence(&item))
+            }
+            SchemaNode::Fields { fields, .. } => match self.fields(&written_fields(fields)) {
+                // an empty element is an absent `Option` of them
+                Some(fields) => {
+                    let fields = match emptiable {
+                        true => fields.replacen("<xs:all>", "<xs:all minOccurs=\"0\">", 1),
+                        false => fields,
+                    };
+                    Content::Inline(format!(
+                        "<xs:complexType>\n{}\n</xs:complexType>",
+                        indent(&fields)
+                    ))
+                }
+                None => Content::Inline("<xs:complexType/>".to_string()),
+            },
+            SchemaNode::Map { key, value, .. } => {
+                let fields = format!(
+                    "<xs:complexType>\n  <xs:all>\n{}\n{}\n  </xs:all>\n</xs:complexType>",
+                    indent(&indent(&self.element("key", &key(), "", ""))),
+                    indent(&indent(&self.element("value", &value(), "", "")))
+                );
+                let entry = self.declare(
+                    "entry",
+                    " minOccurs=\"0\" maxOccurs=\"unbounded\"",
+                    "",
+                    Content::Inline(fields),
+                );
+                Content::Inline(complex_sequence(&entry))
+            }
+            SchemaNode::Reference { node, .. } => self.content(&node(), emptiable),
+        }
+    }
+
+    /// Declares the `complexType` of the fields of the struct `root`, unless it already is.
+    fn define_struct(&mut self, root: &'static str, fields: &[SchemaField]) {
+        if self.defined.contains(&root) {
+            return;
+        }
+        self.defined.push(root);
+        let fields = self.fields(&written_fields(fields));
+        let definition = match fields {
+            Some(fields) => format!(
+                "<xs:complexType name=\"{}\">\n{}\n</xs:complexType>",
+                root,
+                indent(&fields)
+            ),
+            None => format!("<xs:complexType name=\"{}\"/>", root),
+        };
+        self.definitions.push(definition);
+    }
+
+    /// Declares the `group` choosing one of the variants of the enum `name`, unless it already
+    /// is.
+    fn define_enum(&mut self, name: &'static str, variants: &[SchemaVariant]) {
+        if self.defined.contains(&name) {
+            return;
+        }
+        self.defined.push(name);
+        let variants = variants
+            .iter()
+            .map(|v| indent(&indent(&self.variant(v))))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.definitions.push(format!(
+            "<xs:group name=\"{}\">\n  <xs:choice>\n{}\n  </xs:choice>\n</xs:group>",
+            name, variants
+        ));
+    }
+
+    fn variant(&mut self, variant: &SchemaVariant) -> String {
+        let content = match self.fields(&written_fields(&variant.fields)) {
+            Some(fields) => format!("<xs:complexType>\n{}\n</xs:complexType>", indent(&fields)),
+            None => "<xs:complexType/>".to_string(),
+        };
+        self.declare(
+            variant.tag,
+            "",
+            variant.description,
+            Content::Inline(content),
+        )
+    }
+
+    /// The fields in any order, as serde reads them, or `None` without fields.
+    fn fields(&mut self, fields: &[SchemaField]) -> Option<String> {
+        if fields.is_empty() {
+            return None;
+        }
+        let elements = fields
+            .iter()
+            .map(|field| {
+                let occurs = if field.required {
+                    ""
+                } else {
+                    " minOccurs=\"0\""
+                };
+                let element = self.element(field.tag, &(field.node)(), occurs, field.description);
+                indent(&element)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(format!("<xs:all>\n{}\n</xs:all>", elements))
+    }
+}
+
+/// The simple type of a leaf, which also takes an empty text when `emptiable`.
+fn leaf(kind: LeafKind, emptiable: bool) -> Content {
+    let (base, facets) = match kind {
+        LeafKind::String => ("xs:string", Vec::new()),
+        LeafKind::Integer => ("xs:integer", Vec::new()),
+        LeafKind::Float => ("xs:double", Vec::new()),
+        LeafKind::Bool => ("xs:boolean", Vec::new()),
+        LeafKind::Char => ("xs:string", vec!["<xs:length value=\"1\"/>".to_string()]),
+        LeafKind::OneOf(names) => {
+            let values = names
+                .iter()
+                .map(|(name, _)| format!("<xs:enumeration value=\"{}\"/>", escape(*name)))
+                .collect();
+            ("xs:string", values)
+        }
+    };
+    // an empty string is a string already
+    let emptiable = emptiable && kind != LeafKind::String;
+    if facets.is_empty() && !emptiable {
+        return Content::Type(base);
+    }
+    let restricted = restriction(base, &facets);
+    if !emptiable {
+        return Content::Inline(restricted);
+    }
+    let empty = restriction("xs:string", &["<xs:length value=\"0\"/>".to_string()]);
+    let (members, types) = match facets.is_empty() {
+        true => (format!(" memberTypes=\"{}\"", base), vec![empty]),
+        false => (String::new(), vec![restricted, empty]),
+    };
+    let types = types
+        .iter()
+        .map(|t| indent(&indent(t)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Content::Inline(format!(
+        "<xs:simpleType>\n  <xs:union{}>\n{}\n  </xs:union>\n</xs:simpleType>",
+        members, types
+    ))
+}
+
+/// A `simpleType` restricting `base` by `facets`.
+fn restriction(base: &str, facets: &[String]) -> String {
+    let facets = facets
+        .iter()
+        .map(|facet| indent(&indent(facet)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "<xs:simpleType>\n  <xs:restriction base=\"{}\">\n{}\n  </xs:restriction>\n</xs:simpleType>",
+        base, facets
+    )
+}
+
+fn annotation(doc: &str) -> Option<String> {
+    (!doc.is_empty()).then(|| {
+        format!(
+            "<xs:annotation>\n  <xs:documentation>{}</xs:documentation>\n</xs:annotation>",
+            escape(doc)
+        )
+    })
+}
+
+/// A `complexType` of the repeated element `element`.
+fn complex_sequence(element: &str) -> String {
+    format!(
+        "<xs:complexType>\n  <xs:sequence>\n{}\n  </xs:sequence>\n</xs:complexType>",
+        indent(&indent(element))
+    )
+}
+
+/// Indents every line of `text` by two spaces.
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
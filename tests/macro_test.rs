@@ -1,11 +1,12 @@
-use llm_xml_caster::{LlmPrompt, llm_prompt};
+use llm_xml_caster::{Base64Bytes, Between, LlmPrompt, llm_prompt};
 use ordered_float::OrderedFloat;
 use quick_xml::de::from_str;
-use serde::Deserialize;
-use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::BufReader;
 
 #[llm_prompt]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct SimpleStruct {
     #[prompt("The name of the person")]
     name: String,
@@ -51,7 +52,7 @@ fn test_simple_struct_deserialization() {
 }
 
 #[llm_prompt]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct NestedStruct {
     #[prompt("The person details")]
     person: SimpleStruct,
@@ -104,7 +105,7 @@ fn test_nested_struct_deserialization() {
 }
 
 #[llm_prompt]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 enum TestEnum {
     #[prompt("A simple variant")]
     Simple,
@@ -173,7 +174,7 @@ fn test_enum_deserialization() {
 }
 
 #[llm_prompt]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct CollectionsStruct {
     #[prompt("A list of strings")]
     tags: Vec<String>,
@@ -253,7 +254,7 @@ fn test_ordered_float_deserialization() {
 }
 
 #[llm_prompt]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct ComplexStruct {
     #[prompt("A nested struct")]
     nested: NestedStruct,
@@ -303,7 +304,7 @@ fn test_complex_struct_deserialization() {
 }
 
 #[llm_prompt]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct ThirdStruct {
     #[prompt("An optional list of strings")]
     optional_list: Option<Vec<String>>,
@@ -341,7 +342,7 @@ fn test_third_struct_deserialization() {
 }
 
 #[llm_prompt(weak = true)]
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum PythonValue {
     #[prompt("python's None value")]
     None,
@@ -657,3 +658,6303 @@ fib(n)"#
         }
     );
 }
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct SearchFilter {
+    #[prompt("The accepted price range")]
+    price: Between<i32>,
+}
+
+#[test]
+fn test_between_schema() {
+    let schema = SearchFilter::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("<price>"));
+    assert!(schema.contains("<min>{lower bound}</min><max>{upper bound}</max>"));
+    assert!(schema.contains("Either bound may be omitted"));
+    assert_eq!(<Between<i32>>::root_name(), "Between<i32>");
+}
+
+#[test]
+fn test_between_deserialization() {
+    let xml_both = r#"
+    <SearchFilter>
+        <price><min>100</min><max>200</max></price>
+    </SearchFilter>
+    "#;
+    let decoded_both: SearchFilter = from_str(xml_both).unwrap();
+    assert_eq!(
+        decoded_both,
+        SearchFilter {
+            price: Between {
+                min: Some(100),
+                max: Some(200),
+            },
+        }
+    );
+
+    let xml_min_only = r#"
+    <SearchFilter>
+        <price><min>100</min></price>
+    </SearchFilter>
+    "#;
+    let decoded_min_only: SearchFilter = from_str(xml_min_only).unwrap();
+    assert_eq!(
+        decoded_min_only,
+        SearchFilter {
+            price: Between {
+                min: Some(100),
+                max: None,
+            },
+        }
+    );
+
+    let xml_max_only = r#"
+    <SearchFilter>
+        <price><max>2020</max></price>
+    </SearchFilter>
+    "#;
+    let decoded_max_only: SearchFilter = from_str(xml_max_only).unwrap();
+    assert_eq!(
+        decoded_max_only,
+        SearchFilter {
+            price: Between {
+                min: None,
+                max: Some(2020),
+            },
+        }
+    );
+
+    let xml_none = r#"
+    <SearchFilter>
+        <price></price>
+    </SearchFilter>
+    "#;
+    let decoded_none: SearchFilter = from_str(xml_none).unwrap();
+    assert_eq!(
+        decoded_none,
+        SearchFilter {
+            price: Between {
+                min: None,
+                max: None,
+            },
+        }
+    );
+}
+
+#[test]
+fn test_between_inverted_bounds() {
+    let xml = r#"
+    <SearchFilter>
+        <price><min>200</min><max>100</max></price>
+    </SearchFilter>
+    "#;
+    let err = from_str::<SearchFilter>(xml).unwrap_err();
+    assert!(err.to_string().contains("The range is inverted"));
+}
+
+#[test]
+fn test_empty_item_deserialization() {
+    let xml = r#"
+    <CollectionsStruct>
+        <tags>
+            <item><![CDATA[tag1]]></item>
+            <item></item>
+            <item/>
+        </tags>
+    </CollectionsStruct>
+    "#;
+    let decoded: CollectionsStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        CollectionsStruct {
+            tags: vec!["tag1".to_string(), "".to_string(), "".to_string()],
+            description: None,
+        }
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Address {
+    #[prompt("The street of the address")]
+    street: String,
+    #[prompt("Whether the address is a business address")]
+    is_business: bool,
+    #[prompt("The apartment number, if any")]
+    apartment: Option<String>,
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Customer {
+    #[prompt("The name of the customer")]
+    name: String,
+    #[prompt("The shipping address of the customer", flatten)]
+    address: Address,
+}
+
+#[test]
+fn test_flatten_schema() {
+    let schema = Customer::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("<Customer>"));
+    assert!(schema.contains("<!-- The shipping address of the customer -->"));
+    assert!(schema.contains("<street>"));
+    assert!(schema.contains("<is_business>"));
+    assert!(schema.contains("<apartment>"));
+    assert!(!schema.contains("<address>"));
+    assert!(!schema.contains("<Address>"));
+}
+
+#[test]
+fn test_flatten_deserialization() {
+    let xml = r#"
+    <Customer>
+        <name><![CDATA[Alice]]></name>
+        <street><![CDATA[ 221B Baker Street ]]></street>
+        <is_business>no</is_business>
+        <apartment><![CDATA[B]]></apartment>
+    </Customer>
+    "#;
+    let decoded: Customer = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        Customer {
+            name: "Alice".to_string(),
+            address: Address {
+                street: "221B Baker Street".to_string(),
+                is_business: false,
+                apartment: Some("B".to_string()),
+            },
+        }
+    );
+
+    let xml_without_apartment = r#"
+    <Customer>
+        <name><![CDATA[Bob]]></name>
+        <street><![CDATA[Main Street]]></street>
+        <is_business>true</is_business>
+    </Customer>
+    "#;
+    let decoded_without_apartment: Customer = from_str(xml_without_apartment).unwrap();
+    assert_eq!(
+        decoded_without_apartment,
+        Customer {
+            name: "Bob".to_string(),
+            address: Address {
+                street: "Main Street".to_string(),
+                is_business: true,
+                apartment: None,
+            },
+        }
+    );
+}
+
+#[llm_prompt(root = "invoice")]
+#[derive(Deserialize, Debug, PartialEq)]
+struct ExtractedInvoiceV2 {
+    #[prompt("The invoice number")]
+    number: String,
+    #[prompt("The total amount")]
+    total: f64,
+}
+
+#[test]
+fn test_custom_root_schema() {
+    let schema = ExtractedInvoiceV2::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.starts_with("<invoice>"));
+    assert!(schema.ends_with("</invoice>"));
+    assert!(!schema.contains("ExtractedInvoiceV2"));
+    assert_eq!(ExtractedInvoiceV2::root_name(), "invoice");
+}
+
+#[test]
+fn test_custom_root_deserialization() {
+    let xml = r#"
+    <invoice>
+        <number><![CDATA[INV-001]]></number>
+        <total>99.5</total>
+    </invoice>
+    "#;
+    let decoded: ExtractedInvoiceV2 = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        ExtractedInvoiceV2 {
+            number: "INV-001".to_string(),
+            total: 99.5,
+        }
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct CamelCaseStruct {
+    #[prompt("Whether the person is a student")]
+    is_student: bool,
+    #[prompt("The full name of the person")]
+    #[serde(rename = "fullName")]
+    name: String,
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+struct PascalCaseStruct {
+    #[prompt("The user id")]
+    user_id: i32,
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+struct KebabCaseStruct {
+    #[prompt("The zip code")]
+    zip_code: String,
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum RenamedEnum {
+    #[prompt("A variant without data")]
+    NoData,
+    #[prompt("A variant with data")]
+    #[serde(rename_all = "camelCase")]
+    WithData {
+        #[prompt("The value of the variant")]
+        inner_value: i32,
+    },
+}
+
+#[test]
+fn test_rename_all_schema() {
+    let camel = CamelCaseStruct::get_prompt_schema();
+    assert!(camel.contains("<isStudent>"));
+    assert!(camel.contains("<fullName>"));
+    assert!(!camel.contains("<is_student>"));
+
+    let pascal = PascalCaseStruct::get_prompt_schema();
+    assert!(pascal.contains("<UserId>"));
+
+    let kebab = KebabCaseStruct::get_prompt_schema();
+    assert!(kebab.contains("<zip-code>"));
+
+    let renamed_enum = RenamedEnum::get_prompt_schema();
+    println!("Schema :\n{}", renamed_enum);
+    assert!(renamed_enum.contains("<no_data/>"));
+    assert!(renamed_enum.contains("<with_data>"));
+    assert!(renamed_enum.contains("<innerValue>"));
+}
+
+#[test]
+fn test_rename_all_deserialization() {
+    let camel: CamelCaseStruct = from_str(
+        r#"<CamelCaseStruct><isStudent>yes</isStudent><fullName><![CDATA[Jane]]></fullName></CamelCaseStruct>"#,
+    )
+    .unwrap();
+    assert_eq!(
+        camel,
+        CamelCaseStruct {
+            is_student: true,
+            name: "Jane".to_string(),
+        }
+    );
+
+    let pascal: PascalCaseStruct =
+        from_str(r#"<PascalCaseStruct><UserId>7</UserId></PascalCaseStruct>"#).unwrap();
+    assert_eq!(pascal, PascalCaseStruct { user_id: 7 });
+
+    let kebab: KebabCaseStruct =
+        from_str(r#"<KebabCaseStruct><zip-code>10001</zip-code></KebabCaseStruct>"#).unwrap();
+    assert_eq!(
+        kebab,
+        KebabCaseStruct {
+            zip_code: "10001".to_string(),
+        }
+    );
+
+    let no_data: RenamedEnum = from_str(r#"<no_data/>"#).unwrap();
+    assert_eq!(no_data, RenamedEnum::NoData);
+    let with_data: RenamedEnum =
+        from_str(r#"<with_data><innerValue>3</innerValue></with_data>"#).unwrap();
+    assert_eq!(with_data, RenamedEnum::WithData { inner_value: 3 });
+}
+
+#[llm_prompt(text_enum)]
+#[derive(Deserialize, Debug, PartialEq)]
+enum Color {
+    #[prompt("The color of fire")]
+    Red,
+    Green,
+    Blue,
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Palette {
+    #[prompt("The main color")]
+    main: Color,
+    #[prompt("The other colors")]
+    others: Vec<Color>,
+}
+
+#[test]
+fn test_text_enum_schema() {
+    let schema = Palette::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("one of: Red, Green, Blue"));
+    assert!(schema.contains("Red <!-- The color of fire -->"));
+}
+
+#[test]
+fn test_text_enum_deserialization() {
+    let xml = r#"
+    <Palette>
+        <main>  red </main>
+        <others>
+            <item>Green</item>
+            <item><Blue/></item>
+            <item><![CDATA[BLUE]]></item>
+        </others>
+    </Palette>
+    "#;
+    let decoded: Palette = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        Palette {
+            main: Color::Red,
+            others: vec![Color::Green, Color::Blue, Color::Blue],
+        }
+    );
+
+    let xml_element = r#"
+    <Palette>
+        <main><Green/></main>
+        <others></others>
+    </Palette>
+    "#;
+    let decoded_element: Palette = from_str(xml_element).unwrap();
+    assert_eq!(
+        decoded_element,
+        Palette {
+            main: Color::Green,
+            others: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_text_enum_unknown_value() {
+    let xml = r#"
+    <Palette>
+        <main>Purple</main>
+        <others></others>
+    </Palette>
+    "#;
+    let err = from_str::<Palette>(xml).unwrap_err();
+    assert!(
+        err.to_string().contains(
+            "can not parse 'Purple' as a Color value, it must be one of: Red, Green, Blue"
+        )
+    );
+}
+
+mod glob_import {
+    // the crate's one-parameter `Result` takes the place of the prelude's here
+    use llm_xml_caster::*;
+    use serde::Deserialize;
+
+    #[llm_prompt(text_enum)]
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub enum Mood {
+        Happy,
+        Sad,
+    }
+
+    #[llm_prompt]
+    #[derive(Deserialize, Debug, PartialEq)]
+    pub struct Diary {
+        #[prompt("The mood of each day")]
+        pub moods: Vec<Mood>,
+        #[prompt("The number of entries")]
+        pub entries: Option<u32>,
+        #[prompt("The weather", one_of("sunny", "rainy"))]
+        pub weather: String,
+    }
+}
+
+#[test]
+fn test_macro_output_with_glob_import() {
+    use glob_import::{Diary, Mood};
+
+    let xml = "<Diary><moods><item>happy</item><item><Sad/></item></moods><entries>2</entries><weather>Sunny</weather></Diary>";
+    let decoded: Diary = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        Diary {
+            moods: vec![Mood::Happy, Mood::Sad],
+            entries: Some(2),
+            weather: "sunny".to_string(),
+        }
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Review {
+    #[prompt(
+        "The sentiment of the review",
+        one_of("positive", "neutral", "negative")
+    )]
+    sentiment: String,
+    #[prompt(one_of("Low", "High"))]
+    priority: Option<String>,
+}
+
+#[test]
+fn test_one_of_schema() {
+    let schema = Review::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains(
+        "The sentiment of the review (must be exactly one of: positive, neutral, negative)"
+    ));
+    assert!(schema.contains("must be exactly one of: Low, High"));
+}
+
+#[test]
+fn test_one_of_deserialization() {
+    let xml = r#"
+    <Review>
+        <sentiment><![CDATA[ Positive ]]></sentiment>
+        <priority>high</priority>
+    </Review>
+    "#;
+    let decoded: Review = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        Review {
+            sentiment: "positive".to_string(),
+            priority: Some("High".to_string()),
+        }
+    );
+
+    let xml_without_priority = r#"
+    <Review>
+        <sentiment>neutral</sentiment>
+    </Review>
+    "#;
+    let decoded_without_priority: Review = from_str(xml_without_priority).unwrap();
+    assert_eq!(
+        decoded_without_priority,
+        Review {
+            sentiment: "neutral".to_string(),
+            priority: None,
+        }
+    );
+
+    let xml_wrong = r#"
+    <Review>
+        <sentiment>angry</sentiment>
+    </Review>
+    "#;
+    let err = from_str::<Review>(xml_wrong).unwrap_err();
+    assert!(err.to_string().contains(
+        "can not accept 'angry', the value must be exactly one of: positive, neutral, negative"
+    ));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct RangeStruct {
+    #[prompt("The confidence of the answer", min = 0.0, max = 1.0)]
+    confidence: f64,
+    #[prompt("The number of retries", min = 1)]
+    retries: u32,
+    #[prompt("The temperature in celsius", max = -10)]
+    temperature: Option<i32>,
+}
+
+fn parse_range_struct(confidence: &str, retries: &str) -> Result<RangeStruct, quick_xml::DeError> {
+    from_str(&format!(
+        "<RangeStruct><confidence>{}</confidence><retries>{}</retries></RangeStruct>",
+        confidence, retries
+    ))
+}
+
+#[test]
+fn test_range_schema() {
+    let schema = RangeStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("(a float between 0.0 and 1.0 inclusive)"));
+    assert!(schema.contains("(an integer greater than or equal to 1)"));
+    assert!(schema.contains("(an integer less than or equal to -10)"));
+}
+
+#[test]
+fn test_range_deserialization() {
+    assert_eq!(
+        parse_range_struct("0.5", "3").unwrap(),
+        RangeStruct {
+            confidence: 0.5,
+            retries: 3,
+            temperature: None,
+        }
+    );
+    // bounds are inclusive
+    assert_eq!(parse_range_struct("1.0", "1").unwrap().confidence, 1.0);
+    assert_eq!(parse_range_struct("0.0", "1").unwrap().retries, 1);
+
+    let above = parse_range_struct("1.2", "3").unwrap_err();
+    assert!(
+        above.to_string().contains(
+            "the value 1.2 is above the maximum of 1, it must be less than or equal to 1"
+        )
+    );
+    let below = parse_range_struct("0.5", "0").unwrap_err();
+    assert!(
+        below.to_string().contains(
+            "the value 0 is below the minimum of 1, it must be greater than or equal to 1"
+        )
+    );
+
+    let too_warm = from_str::<RangeStruct>(
+        "<RangeStruct><confidence>0.1</confidence><retries>1</retries><temperature>-5</temperature></RangeStruct>",
+    )
+    .unwrap_err();
+    assert!(too_warm.to_string().contains("above the maximum of -10"));
+    let temperature: RangeStruct = from_str(
+        "<RangeStruct><confidence>0.1</confidence><retries>1</retries><temperature>-20</temperature></RangeStruct>",
+    )
+    .unwrap();
+    assert_eq!(temperature.temperature, Some(-20));
+}
+
+type Score = f64;
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct ConvertedRangeStruct {
+    #[prompt("The score of the answer", min = 0, max = 1)]
+    score: Score,
+    #[prompt("The ordered weight", min = 0, max = 2.5)]
+    weight: OrderedFloat<f64>,
+    #[prompt("The size in bytes", max = 5_000_000_000)]
+    size: u64,
+}
+
+#[test]
+fn test_range_bound_conversion() {
+    let parse = |score: &str, weight: &str, size: &str| {
+        from_str::<ConvertedRangeStruct>(&format!(
+            "<ConvertedRangeStruct><score>{}</score><weight>{}</weight><size>{}</size></ConvertedRangeStruct>",
+            score, weight, size
+        ))
+    };
+    let schema = ConvertedRangeStruct::get_prompt_schema();
+    assert!(schema.contains("(a number between 0 and 1 inclusive)"));
+    assert!(schema.contains("(a float between 0 and 2.5 inclusive)"));
+    assert!(schema.contains("(an integer less than or equal to 5_000_000_000)"));
+
+    assert_eq!(
+        parse("0.5", "2.5", "4000000000").unwrap(),
+        ConvertedRangeStruct {
+            score: 0.5,
+            weight: OrderedFloat(2.5),
+            size: 4_000_000_000,
+        }
+    );
+    assert!(
+        parse("1.5", "1", "1").unwrap_err().to_string().contains(
+            "the value 1.5 is above the maximum of 1, it must be less than or equal to 1"
+        )
+    );
+    assert!(
+        parse("0.5", "-1", "1").unwrap_err().to_string().contains(
+            "the value -1 is below the minimum of 0, it must be greater than or equal to 0"
+        )
+    );
+    assert!(
+        parse("0.5", "1", "6000000000")
+            .unwrap_err()
+            .to_string()
+            .contains("the value 6000000000 is above the maximum of 5000000000")
+    );
+}
+
+#[cfg(feature = "regex")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct PatternStruct {
+    #[prompt(
+        "The publication date",
+        pattern = r"^\d{4}-\d{2}-\d{2}$",
+        desc = "an ISO date"
+    )]
+    date: String,
+    #[prompt("The product code", pattern = r"^[A-Z]{3}-\d+$")]
+    code: Option<String>,
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_pattern_schema() {
+    let schema = PatternStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains(r"(an ISO date, matching the regular expression ^\d{4}-\d{2}-\d{2}$)"));
+    assert!(schema.contains(r"(matching the regular expression ^[A-Z]{3}-\d+$)"));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_pattern_deserialization() {
+    let parsed: PatternStruct = from_str(
+        "<PatternStruct><date><![CDATA[ 2024-05-01 ]]></date><code>ABC-42</code></PatternStruct>",
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        PatternStruct {
+            date: "2024-05-01".to_string(),
+            code: Some("ABC-42".to_string()),
+        }
+    );
+
+    let bad_date =
+        from_str::<PatternStruct>("<PatternStruct><date>May 1st, 2024</date></PatternStruct>")
+            .unwrap_err();
+    assert!(bad_date.to_string().contains(
+        r"'May 1st, 2024' is not an ISO date, the value must match the regular expression ^\d{4}-\d{2}-\d{2}$"
+    ));
+
+    let bad_code = from_str::<PatternStruct>(
+        "<PatternStruct><date>2024-05-01</date><code>abc-42</code></PatternStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        bad_code
+            .to_string()
+            .contains(r"'abc-42' does not match the regular expression ^[A-Z]{3}-\d+$")
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct TaggedArticle {
+    #[prompt("Tags describing the article", min_items = 1, max_items = 3)]
+    tags: Vec<String>,
+    #[prompt("Related article ids", max_items = 2)]
+    related: Option<Vec<u32>>,
+}
+
+fn parse_tagged_article(tags: &[&str]) -> Result<TaggedArticle, quick_xml::DeError> {
+    let items: String = tags
+        .iter()
+        .map(|tag| format!("<item>{}</item>", tag))
+        .collect();
+    from_str(&format!(
+        "<TaggedArticle><tags>{}</tags></TaggedArticle>",
+        items
+    ))
+}
+
+#[test]
+fn test_items_schema() {
+    let schema = TaggedArticle::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("(between 1 and 3 items)"));
+    assert!(schema.contains("(at most 2 items)"));
+}
+
+#[test]
+fn test_items_deserialization() {
+    assert_eq!(parse_tagged_article(&["rust"]).unwrap().tags, vec!["rust"]);
+    assert_eq!(
+        parse_tagged_article(&["rust", "xml", "llm"]).unwrap().tags,
+        vec!["rust", "xml", "llm"]
+    );
+
+    let empty = parse_tagged_article(&[]).unwrap_err();
+    assert!(
+        empty
+            .to_string()
+            .contains("the list has 0 items, but it must contain at least 1 items")
+    );
+    let too_many = parse_tagged_article(&["a", "b", "c", "d"]).unwrap_err();
+    assert!(
+        too_many
+            .to_string()
+            .contains("the list has 4 items, but it must contain at most 3 items")
+    );
+
+    let related = from_str::<TaggedArticle>(
+        "<TaggedArticle><tags><item>a</item></tags><related><item>1</item><item>2</item><item>3</item></related></TaggedArticle>",
+    )
+    .unwrap_err();
+    assert!(related.to_string().contains("at most 2 items"));
+}
+
+// `Vec<Box<T>>` is exactly the shape a recursive type is written with
+#[allow(clippy::vec_box)]
+#[llm_prompt]
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Comment {
+    #[prompt("The text of the comment")]
+    text: String,
+    #[prompt("The replies to this comment")]
+    replies: Vec<Box<Comment>>,
+    #[prompt("The comment this one quotes")]
+    quoted: Option<Box<Comment>>,
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+enum Expr {
+    #[prompt("A literal number")]
+    Num {
+        #[prompt("The value")]
+        value: i64,
+    },
+    #[prompt("The sum of two expressions")]
+    Add {
+        #[prompt("The left operand")]
+        left: Box<Expr>,
+        #[prompt("The right operand")]
+        right: Box<Expr>,
+    },
+}
+
+#[test]
+fn test_box_schema() {
+    let schema = Comment::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains(
+        "<Comment>a nested Comment with the same format as the enclosing <Comment> described above, recursively</Comment>"
+    ));
+    assert_eq!(<Box<Comment>>::get_prompt_schema(), schema);
+    assert_eq!(<Box<Comment>>::root_name(), "Comment");
+
+    let schema = Expr::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(
+        schema
+            .contains("a nested value with the same format as the enclosing enum described above")
+    );
+}
+
+#[test]
+fn test_box_deserialization() {
+    let comment: Comment = from_str(
+        "<Comment><text>first</text><replies><item><Comment><text>second</text><replies></replies></Comment></item></replies><quoted><text>zero</text><replies/></quoted></Comment>",
+    )
+    .unwrap();
+    assert_eq!(
+        comment,
+        Comment {
+            text: "first".to_string(),
+            replies: vec![Box::new(Comment {
+                text: "second".to_string(),
+                replies: vec![],
+                quoted: None,
+            })],
+            quoted: Some(Box::new(Comment {
+                text: "zero".to_string(),
+                replies: vec![],
+                quoted: None,
+            })),
+        }
+    );
+
+    let expr: Expr = from_str(
+        "<Add><left><Num><value>1</value></Num></left><right><Add><left><Num><value>2</value></Num></left><right><Num><value> 3 </value></Num></right></Add></right></Add>",
+    )
+    .unwrap();
+    assert_eq!(
+        expr,
+        Expr::Add {
+            left: Box::new(Expr::Num { value: 1 }),
+            right: Box::new(Expr::Add {
+                left: Box::new(Expr::Num { value: 2 }),
+                right: Box::new(Expr::Num { value: 3 }),
+            }),
+        }
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct SharedStruct {
+    #[prompt("The shared person")]
+    person: std::sync::Arc<SimpleStruct>,
+    #[prompt("The shared label")]
+    label: std::rc::Rc<String>,
+    #[prompt("An optional shared count")]
+    count: Option<std::sync::Arc<u32>>,
+}
+
+#[test]
+fn test_shared_schema() {
+    let schema = SharedStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("<SimpleStruct>"));
+    assert!(schema.contains(String::get_prompt_schema()));
+    assert_eq!(
+        <std::sync::Arc<SimpleStruct>>::get_prompt_schema(),
+        SimpleStruct::get_prompt_schema()
+    );
+    assert_eq!(
+        <std::sync::Arc<SimpleStruct>>::root_name(),
+        SimpleStruct::root_name()
+    );
+}
+
+#[test]
+fn test_shared_deserialization() {
+    let xml = r#"
+    <SharedStruct>
+        <person>
+            <name><![CDATA[Jane Doe]]></name>
+            <age>25</age>
+            <is_student>false</is_student>
+        </person>
+        <label><![CDATA[  padded label  ]]></label>
+        <count> 7 </count>
+    </SharedStruct>
+    "#;
+    let decoded: SharedStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        SharedStruct {
+            person: std::sync::Arc::new(SimpleStruct {
+                name: "Jane Doe".to_string(),
+                age: 25,
+                is_student: false,
+            }),
+            label: std::rc::Rc::new("padded label".to_string()),
+            count: Some(std::sync::Arc::new(7)),
+        }
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct CowStruct {
+    #[prompt("The title")]
+    title: std::borrow::Cow<'static, str>,
+    #[prompt("An optional subtitle")]
+    subtitle: Option<std::borrow::Cow<'static, str>>,
+    #[prompt("The authors")]
+    authors: Vec<std::borrow::Cow<'static, str>>,
+}
+
+#[test]
+fn test_cow_schema() {
+    let schema = CowStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert_eq!(
+        <std::borrow::Cow<'static, str>>::get_prompt_schema(),
+        String::get_prompt_schema()
+    );
+    assert!(schema.contains(String::get_prompt_schema()));
+}
+
+#[test]
+fn test_cow_deserialization() {
+    let xml = r#"
+    <CowStruct>
+        <title><![CDATA[  The Title  ]]></title>
+        <subtitle><![CDATA[ A subtitle ]]></subtitle>
+        <authors>
+            <item><![CDATA[Alice]]></item>
+            <item><![CDATA[Bob]]></item>
+        </authors>
+    </CowStruct>
+    "#;
+    let decoded: CowStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        CowStruct {
+            title: "The Title".into(),
+            subtitle: Some("A subtitle".into()),
+            authors: vec!["Alice".into(), "Bob".into()],
+        }
+    );
+    assert!(matches!(decoded.title, std::borrow::Cow::Owned(_)));
+
+    let xml = "<CowStruct><title>Untitled</title><authors></authors></CowStruct>";
+    let decoded: CowStruct = from_str(xml).unwrap();
+    assert_eq!(decoded.subtitle, None);
+    assert!(decoded.authors.is_empty());
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct GradeStruct {
+    #[prompt("The grade letter")]
+    grade: char,
+    #[prompt("The chosen option, if any")]
+    choice: Option<char>,
+}
+
+#[test]
+fn test_char_schema() {
+    let schema = GradeStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("a single character, e.g. A"));
+}
+
+#[test]
+fn test_char_deserialization() {
+    let decoded: GradeStruct =
+        from_str("<GradeStruct><grade> B </grade><choice>C</choice></GradeStruct>").unwrap();
+    assert_eq!(
+        decoded,
+        GradeStruct {
+            grade: 'B',
+            choice: Some('C'),
+        }
+    );
+
+    let decoded: GradeStruct =
+        from_str("<GradeStruct><grade><![CDATA[A]]></grade></GradeStruct>").unwrap();
+    assert_eq!(decoded.grade, 'A');
+    assert_eq!(decoded.choice, None);
+
+    let decoded: GradeStruct =
+        from_str("<GradeStruct><grade>&lt;![CDATA[D]]&gt;</grade></GradeStruct>").unwrap();
+    assert_eq!(decoded.grade, 'D');
+
+    let err = from_str::<GradeStruct>("<GradeStruct><grade>AB</grade></GradeStruct>").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse 'AB' as a char value, it must be exactly one character")
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct SizeStruct {
+    #[prompt("The number of items")]
+    count: usize,
+    #[prompt("The offset from the start")]
+    offset: isize,
+}
+
+#[test]
+fn test_size_deserialization() {
+    let decoded: SizeStruct =
+        from_str("<SizeStruct><count>12</count><offset>-3</offset></SizeStruct>").unwrap();
+    assert_eq!(
+        decoded,
+        SizeStruct {
+            count: 12,
+            offset: -3,
+        }
+    );
+
+    let decoded: SizeStruct =
+        from_str(r#"<SizeStruct><count>"12"</count><offset> "-3" </offset></SizeStruct>"#).unwrap();
+    assert_eq!(decoded.count, 12);
+    assert_eq!(decoded.offset, -3);
+
+    let err =
+        from_str::<SizeStruct>("<SizeStruct><count>-1</count><offset>0</offset></SizeStruct>")
+            .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse '-1' as a usize value")
+    );
+
+    assert_eq!(usize::root_name(), "usize");
+    assert!(SizeStruct::get_prompt_schema().contains(u64::get_prompt_schema()));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct NonZeroStruct {
+    #[prompt("The page number")]
+    page: std::num::NonZeroU32,
+    #[prompt("The number of copies")]
+    copies: std::num::NonZeroU8,
+    #[prompt("The signed step")]
+    step: Option<std::num::NonZero<i64>>,
+}
+
+#[test]
+fn test_non_zero_deserialization() {
+    let decoded: NonZeroStruct =
+        from_str("<NonZeroStruct><page>3</page><copies>2</copies><step>-1</step></NonZeroStruct>")
+            .unwrap();
+    assert_eq!(
+        decoded,
+        NonZeroStruct {
+            page: std::num::NonZeroU32::new(3).unwrap(),
+            copies: std::num::NonZeroU8::new(2).unwrap(),
+            step: std::num::NonZero::new(-1),
+        }
+    );
+
+    let zero = from_str::<NonZeroStruct>(
+        "<NonZeroStruct><page>0</page><copies>1</copies></NonZeroStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        zero.to_string()
+            .contains("can not accept 0 as a NonZeroU32 value, it must not be zero")
+    );
+
+    let overflow = from_str::<NonZeroStruct>(
+        "<NonZeroStruct><page>1</page><copies>300</copies></NonZeroStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        overflow
+            .to_string()
+            .contains("can not parse '300' as a u8 value")
+    );
+
+    let schema = NonZeroStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("positive integer value, a whole number that must not be 0"));
+    assert!(schema.contains("non-zero integer value"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct TupleStruct {
+    #[prompt("The best keyword and its score")]
+    best: (String, i32),
+    #[prompt("Every keyword with its score")]
+    scores: Vec<(String, i32)>,
+    #[prompt("A point in space")]
+    point: Option<(f64, f64, f64)>,
+}
+
+#[test]
+fn test_tuple_schema() {
+    let schema = TupleStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(
+        schema.contains("a tuple of 2 values in a fixed order. The XML format should be: <first>")
+    );
+    assert!(schema.contains("</first><second>"));
+    assert!(schema.contains("a tuple of 3 values"));
+    assert_eq!(<(String, i32)>::root_name(), "(string, i32)");
+}
+
+#[test]
+fn test_tuple_deserialization() {
+    let xml = r#"
+    <TupleStruct>
+        <best><first><![CDATA[rust]]></first><second>9</second></best>
+        <scores>
+            <item><first>xml</first><second>7</second></item>
+            <item><first>llm</first><second>-2</second></item>
+        </scores>
+        <point><first>1.5</first><second>0</second><third>-2</third></point>
+    </TupleStruct>
+    "#;
+    let decoded: TupleStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        TupleStruct {
+            best: ("rust".to_string(), 9),
+            scores: vec![("xml".to_string(), 7), ("llm".to_string(), -2)],
+            point: Some((1.5, 0.0, -2.0)),
+        }
+    );
+
+    let err = from_str::<TupleStruct>(
+        "<TupleStruct><best><first>rust</first></best><scores></scores></TupleStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("A tuple of 2 values must consist of the elements <first>, <second>")
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct SetStruct {
+    #[prompt("The keywords of the article")]
+    tags: HashSet<String>,
+    #[prompt("The referenced page numbers")]
+    pages: BTreeSet<u32>,
+}
+
+#[test]
+fn test_set_schema() {
+    let schema = SetStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("Duplicate items are collapsed into one"));
+    assert_eq!(<BTreeSet<u32>>::root_name(), "BTreeSet<u32>");
+    assert_eq!(<HashSet<String>>::root_name(), "HashSet<string>");
+}
+
+#[test]
+fn test_set_deserialization() {
+    let xml = r#"
+    <SetStruct>
+        <tags>
+            <item><![CDATA[rust]]></item>
+            <item><![CDATA[xml]]></item>
+            <item><![CDATA[rust]]></item>
+        </tags>
+        <pages>
+            <item>12</item>
+            <item>3</item>
+            <item>12</item>
+        </pages>
+    </SetStruct>
+    "#;
+    let decoded: SetStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded.tags,
+        HashSet::from(["rust".to_string(), "xml".to_string()])
+    );
+    assert_eq!(decoded.pages.into_iter().collect::<Vec<_>>(), vec![3, 12]);
+
+    let decoded: SetStruct = from_str("<SetStruct><tags></tags><pages/></SetStruct>").unwrap();
+    assert!(decoded.tags.is_empty());
+    assert!(decoded.pages.is_empty());
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct StepQueue {
+    #[prompt("The steps to perform, in order")]
+    steps: VecDeque<String>,
+    #[prompt("Follow-up steps, if any")]
+    follow_up: Option<VecDeque<String>>,
+}
+
+#[test]
+fn test_vecdeque_schema() {
+    assert_eq!(
+        <VecDeque<String>>::get_prompt_schema(),
+        <Vec<String>>::get_prompt_schema()
+    );
+    assert_eq!(<VecDeque<String>>::root_name(), "VecDeque<string>");
+}
+
+#[test]
+fn test_vecdeque_deserialization() {
+    let xml = r#"
+    <StepQueue>
+        <steps>
+            <item>open</item>
+            <item>read</item>
+            <item>close</item>
+        </steps>
+        <follow_up><item>log</item></follow_up>
+    </StepQueue>
+    "#;
+    let decoded: StepQueue = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        StepQueue {
+            steps: VecDeque::from(["open".to_string(), "read".to_string(), "close".to_string()]),
+            follow_up: Some(VecDeque::from(["log".to_string()])),
+        }
+    );
+
+    let decoded: StepQueue = from_str("<StepQueue><steps></steps></StepQueue>").unwrap();
+    assert!(decoded.steps.is_empty());
+    assert_eq!(decoded.follow_up, None);
+}
+
+#[cfg(feature = "indexmap")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct OrderedAttributes {
+    #[prompt("The attributes in the order they appear")]
+    attributes: indexmap::IndexMap<String, String>,
+    #[prompt("The distinct colors in the order they appear")]
+    colors: indexmap::IndexSet<String>,
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn test_indexmap_deserialization() {
+    let xml = r#"
+    <OrderedAttributes>
+        <attributes>
+            <entry><key>zeta</key><value>1</value></entry>
+            <entry><key>alpha</key><value>2</value></entry>
+            <entry><key>mid</key><value>3</value></entry>
+        </attributes>
+        <colors>
+            <item>red</item>
+            <item>blue</item>
+            <item>red</item>
+            <item>amber</item>
+        </colors>
+    </OrderedAttributes>
+    "#;
+    let decoded: OrderedAttributes = from_str(xml).unwrap();
+    assert_eq!(
+        decoded.attributes.keys().collect::<Vec<_>>(),
+        vec!["zeta", "alpha", "mid"]
+    );
+    assert_eq!(decoded.attributes["alpha"], "2");
+    assert_eq!(
+        decoded.colors.iter().collect::<Vec<_>>(),
+        vec!["red", "blue", "amber"]
+    );
+
+    let schema = OrderedAttributes::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("The order of the entries is kept."));
+    assert_eq!(
+        <indexmap::IndexMap<String, u32>>::root_name(),
+        "IndexMap<string, u32>"
+    );
+}
+
+#[cfg(feature = "chrono")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct ChronoStruct {
+    #[prompt("When the event was published")]
+    published: chrono::DateTime<chrono::Utc>,
+    #[prompt("When the event starts, in local time")]
+    starts: Option<chrono::DateTime<chrono::FixedOffset>>,
+    #[prompt("The day of the event")]
+    day: chrono::NaiveDate,
+    #[prompt("When the doors open")]
+    doors: chrono::NaiveDateTime,
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_chrono_deserialization() {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    let xml = r#"
+    <ChronoStruct>
+        <published>2024-05-01T13:45:00Z</published>
+        <starts>2024-05-02T20:00:00+02:00</starts>
+        <day> 2024-05-02 </day>
+        <doors><![CDATA[2024-05-02 19:30:00]]></doors>
+    </ChronoStruct>
+    "#;
+    let decoded: ChronoStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded.published,
+        Utc.with_ymd_and_hms(2024, 5, 1, 13, 45, 0).unwrap()
+    );
+    assert_eq!(
+        decoded.starts.unwrap().with_timezone(&Utc),
+        Utc.with_ymd_and_hms(2024, 5, 2, 18, 0, 0).unwrap()
+    );
+    assert_eq!(decoded.day, NaiveDate::from_ymd_opt(2024, 5, 2).unwrap());
+    assert_eq!(
+        decoded.doors,
+        NaiveDate::from_ymd_opt(2024, 5, 2)
+            .unwrap()
+            .and_hms_opt(19, 30, 0)
+            .unwrap()
+    );
+
+    // no timezone is read as UTC
+    let decoded: ChronoStruct = from_str(
+        "<ChronoStruct><published>2024-05-01T13:45:00</published><day>2024-05-02</day><doors>2024-05-02T19:30:00</doors></ChronoStruct>",
+    )
+    .unwrap();
+    assert_eq!(
+        decoded.published,
+        Utc.with_ymd_and_hms(2024, 5, 1, 13, 45, 0).unwrap()
+    );
+    assert_eq!(decoded.starts, None);
+
+    let err = from_str::<ChronoStruct>(
+        "<ChronoStruct><published>2024-05-01T13:45:00Z</published><day>next tuesday</day><doors>2024-05-02T19:30:00</doors></ChronoStruct>",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse 'next tuesday' as a date, it must be in YYYY-MM-DD format like 2024-05-01"
+    ));
+
+    let schema = ChronoStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains("RFC 3339"));
+    assert!(schema.contains("YYYY-MM-DD"));
+}
+
+#[cfg(feature = "time")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct TimeStruct {
+    #[prompt("When the meeting was scheduled")]
+    scheduled: time::OffsetDateTime,
+    #[prompt("When the meeting was moved, if it was")]
+    moved: Option<time::OffsetDateTime>,
+    #[prompt("The day of the meeting")]
+    day: time::Date,
+    #[prompt("The start time of the meeting")]
+    start: time::Time,
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_time_deserialization() {
+    use time::macros::{date, datetime, time};
+
+    let xml = r#"
+    <TimeStruct>
+        <scheduled>2024-05-01T13:45:00+02:00</scheduled>
+        <moved> 2024-05-03 09:00:00Z </moved>
+        <day><![CDATA[2024-05-02]]></day>
+        <start>09:30</start>
+    </TimeStruct>
+    "#;
+    let decoded: TimeStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        TimeStruct {
+            scheduled: datetime!(2024-05-01 13:45:00 +02:00),
+            moved: Some(datetime!(2024-05-03 09:00:00 UTC)),
+            day: date!(2024 - 05 - 02),
+            start: time!(09:30),
+        }
+    );
+
+    let decoded: TimeStruct = from_str(
+        "<TimeStruct><scheduled>2024-05-01T13:45:00</scheduled><day>2024-05-02</day><start>09:30:15</start></TimeStruct>",
+    )
+    .unwrap();
+    assert_eq!(decoded.scheduled, datetime!(2024-05-01 13:45:00 UTC));
+    assert_eq!(decoded.moved, None);
+    assert_eq!(decoded.start, time!(09:30:15));
+
+    let err = from_str::<TimeStruct>(
+        "<TimeStruct><scheduled>yesterday</scheduled><day>2024-05-02</day><start>09:30</start></TimeStruct>",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse 'yesterday' as a date and time, it must be in RFC 3339 format like 2024-05-01T13:45:00Z"
+    ));
+
+    let err = from_str::<TimeStruct>(
+        "<TimeStruct><scheduled>2024-05-01T13:45:00Z</scheduled><day>2024-05-02</day><start>half past nine</start></TimeStruct>",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("as a time of day"));
+}
+
+#[cfg(feature = "uuid")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct UuidStruct {
+    #[prompt("The id of the record")]
+    id: uuid::Uuid,
+    #[prompt("The id of the parent record, if any")]
+    parent: Option<uuid::Uuid>,
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn test_uuid_deserialization() {
+    let expected = uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+    for written in [
+        "550e8400-e29b-41d4-a716-446655440000",
+        " {550e8400-e29b-41d4-a716-446655440000} ",
+        "urn:uuid:550e8400-e29b-41d4-a716-446655440000",
+        "550E8400-E29B-41D4-A716-446655440000",
+    ] {
+        let decoded: UuidStruct =
+            from_str(&format!("<UuidStruct><id>{}</id></UuidStruct>", written)).unwrap();
+        assert_eq!(
+            decoded,
+            UuidStruct {
+                id: expected,
+                parent: None,
+            }
+        );
+    }
+
+    let err = from_str::<UuidStruct>("<UuidStruct><id>record-42</id></UuidStruct>").unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse 'record-42' as a UUID, it must be 32 hex digits in the canonical 8-4-4-4-12 form"
+    ));
+
+    assert!(UuidStruct::get_prompt_schema().contains("a UUID in canonical 8-4-4-4-12 hex form"));
+}
+
+#[cfg(feature = "url")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct LinkStruct {
+    #[prompt("The link to the document")]
+    link: url::Url,
+    #[prompt("The link to the source, if any")]
+    source: Option<url::Url>,
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn test_url_deserialization() {
+    let decoded: LinkStruct = from_str(
+        "<LinkStruct><link><![CDATA[ https://example.com/docs?page=2&lang=en ]]></link></LinkStruct>",
+    )
+    .unwrap();
+    assert_eq!(
+        decoded,
+        LinkStruct {
+            link: url::Url::parse("https://example.com/docs?page=2&lang=en").unwrap(),
+            source: None,
+        }
+    );
+
+    let err = from_str::<LinkStruct>("<LinkStruct><link>example.com/docs</link></LinkStruct>")
+        .unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse 'example.com/docs' as a URL (relative URL without a base), it must be an absolute URL"
+    ));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct NetStruct {
+    #[prompt("The client address")]
+    client: std::net::IpAddr,
+    #[prompt("The gateway address")]
+    gateway: std::net::Ipv4Addr,
+    #[prompt("The IPv6 address of the host")]
+    host: std::net::Ipv6Addr,
+    #[prompt("The address the server listens on")]
+    listen: std::net::SocketAddr,
+}
+
+#[test]
+fn test_net_deserialization() {
+    let xml = r#"
+    <NetStruct>
+        <client> 2001:db8::42 </client>
+        <gateway>10.0.0.1</gateway>
+        <host><![CDATA[::1]]></host>
+        <listen>[2001:db8::1]:443</listen>
+    </NetStruct>
+    "#;
+    let decoded: NetStruct = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        NetStruct {
+            client: "2001:db8::42".parse().unwrap(),
+            gateway: std::net::Ipv4Addr::new(10, 0, 0, 1),
+            host: std::net::Ipv6Addr::LOCALHOST,
+            listen: "[2001:db8::1]:443".parse().unwrap(),
+        }
+    );
+
+    let err = from_str::<NetStruct>(
+        "<NetStruct><client>10.0.0.2</client><gateway>10.0.0.256</gateway><host>::1</host><listen>0.0.0.0:80</listen></NetStruct>",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse '10.0.0.256' as an IPv4 address in dotted decimal form, it must look like 192.168.1.10"
+    ));
+
+    let schema = NetStruct::get_prompt_schema();
+    println!("Schema :\n{}", schema);
+    assert!(schema.contains(
+        "an IP address with a port, IPv6 addresses go inside brackets, e.g. 192.168.1.10:8080"
+    ));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct StackFrame {
+    #[prompt("The file the error points to")]
+    file: std::path::PathBuf,
+    #[prompt("The windows install directory")]
+    install_dir: std::path::PathBuf,
+    #[prompt("Every file mentioned in the trace")]
+    mentioned: Vec<std::path::PathBuf>,
+}
+
+#[test]
+fn test_path_buf_deserialization() {
+    let xml = r#"
+    <StackFrame>
+        <file><![CDATA[ /home/user/my project/src/main.rs ]]></file>
+        <install_dir><![CDATA[C:\Program Files\My App\bin]]></install_dir>
+        <mentioned>
+            <item><![CDATA[/usr/lib/libc.so]]></item>
+            <item><![CDATA[D:\work\lib.rs]]></item>
+        </mentioned>
+    </StackFrame>
+    "#;
+    let decoded: StackFrame = from_str(xml).unwrap();
+    assert_eq!(
+        decoded,
+        StackFrame {
+            file: "/home/user/my project/src/main.rs".into(),
+            install_dir: r"C:\Program Files\My App\bin".into(),
+            mentioned: vec!["/usr/lib/libc.so".into(), r"D:\work\lib.rs".into()],
+        }
+    );
+    assert_eq!(
+        decoded.install_dir.to_str(),
+        Some(r"C:\Program Files\My App\bin")
+    );
+
+    assert!(StackFrame::get_prompt_schema().contains("a filesystem path"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct EstimateStruct {
+    #[prompt("How long the task takes")]
+    estimate: std::time::Duration,
+}
+
+#[test]
+fn test_duration_deserialization() {
+    use std::time::Duration;
+
+    for (written, expected) in [
+        ("90", Duration::from_secs(90)),
+        ("1.5", Duration::from_millis(1500)),
+        ("90s", Duration::from_secs(90)),
+        ("250ms", Duration::from_millis(250)),
+        ("5m", Duration::from_secs(300)),
+        ("2h30m", Duration::from_secs(9000)),
+        (" 1h 15M ", Duration::from_secs(4500)),
+        ("1d", Duration::from_secs(86400)),
+    ] {
+        let decoded: EstimateStruct = from_str(&format!(
+            "<EstimateStruct><estimate>{}</estimate></EstimateStruct>",
+            written
+        ))
+        .unwrap();
+        assert_eq!(decoded.estimate, expected, "parsing '{}'", written);
+    }
+
+    for written in ["about 2 hours", "-5", "5 weeks"] {
+        let err = from_str::<EstimateStruct>(&format!(
+            "<EstimateStruct><estimate>{}</estimate></EstimateStruct>",
+            written
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains(&format!(
+            "can not parse '{}' as a duration, it must be a number of seconds like 90 or 1.5, or numbers with units like 90s, 5m or 2h30m",
+            written
+        )));
+    }
+}
+
+#[cfg(feature = "decimal")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct PriceStruct {
+    #[prompt("The total amount of the invoice")]
+    total: rust_decimal::Decimal,
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn test_decimal_deserialization() {
+    let expected = rust_decimal::Decimal::new(123456, 2);
+    for written in ["1234.56", "$1,234.56", " 1,234.56 USD ", "€1234.56"] {
+        let decoded: PriceStruct = from_str(&format!(
+            "<PriceStruct><total>{}</total></PriceStruct>",
+            written
+        ))
+        .unwrap();
+        assert_eq!(decoded.total, expected, "parsing '{}'", written);
+    }
+
+    let err = from_str::<PriceStruct>("<PriceStruct><total>twelve dollars</total></PriceStruct>")
+        .unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse 'twelve dollars' as a decimal number, it must be a plain number like 1234.56"
+    ));
+}
+
+#[cfg(feature = "json")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct MetadataStruct {
+    #[prompt("Any additional metadata as JSON")]
+    metadata: serde_json::Value,
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_value_deserialization() {
+    use serde_json::json;
+
+    let parse = |content: &str| {
+        from_str::<MetadataStruct>(&format!(
+            "<MetadataStruct><metadata><![CDATA[{}]]></metadata></MetadataStruct>",
+            content
+        ))
+    };
+
+    assert_eq!(
+        parse(r#"{"author": "Alice <a@example.com>", "pages": 12}"#)
+            .unwrap()
+            .metadata,
+        json!({"author": "Alice <a@example.com>", "pages": 12})
+    );
+    assert_eq!(parse("[1, 2, 3]").unwrap().metadata, json!([1, 2, 3]));
+    assert_eq!(
+        parse(r#"{"a": {"b": [true, null, {"c": 1.5}]}}"#)
+            .unwrap()
+            .metadata,
+        json!({"a": {"b": [true, null, {"c": 1.5}]}})
+    );
+
+    let err = parse("{author: 'Alice'}").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse the content as JSON: key must be a string at line 1 column 2")
+    );
+
+    assert!(MetadataStruct::get_prompt_schema().contains("free-form JSON"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_bare_value_name_left_alone() {
+    // a type of its own named `Value`, not `serde_json::Value`
+    #[llm_prompt]
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Value {
+        #[prompt("The score")]
+        score: u32,
+    }
+
+    #[llm_prompt]
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Rating {
+        #[prompt("The rated value")]
+        value: Value,
+    }
+
+    let rating: Rating = from_str("<Rating><value><score>3</score></value></Rating>").unwrap();
+    assert_eq!(rating.value, Value { score: 3 });
+    assert!(!Rating::get_prompt_schema().contains("free-form JSON"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct AttachmentStruct {
+    #[prompt("The raw bytes of the attachment")]
+    data: Base64Bytes,
+    #[prompt("The thumbnail, if any")]
+    thumbnail: Option<Base64Bytes>,
+}
+
+fn parse_attachment(content: &str) -> Result<AttachmentStruct, quick_xml::DeError> {
+    from_str(&format!(
+        "<AttachmentStruct><data><![CDATA[{}]]></data></AttachmentStruct>",
+        content
+    ))
+}
+
+#[test]
+fn test_base64_deserialization() {
+    assert_eq!(
+        parse_attachment("SGVsbG8sIHdvcmxkIQ==").unwrap(),
+        AttachmentStruct {
+            data: Base64Bytes(b"Hello, world!".to_vec()),
+            thumbnail: None,
+        }
+    );
+    // line breaks and missing padding are tolerated
+    assert_eq!(
+        parse_attachment("SGVsbG8s\nIHdvcmxk\r\nIQ").unwrap().data,
+        Base64Bytes(b"Hello, world!".to_vec())
+    );
+    assert_eq!(parse_attachment("").unwrap().data, Base64Bytes(Vec::new()));
+
+    let err = parse_attachment("SGVs*bG8=").unwrap_err();
+    assert!(err.to_string().contains(
+        "can not decode the content as base64: invalid base64 character '*' at position 5"
+    ));
+
+    let decoded: AttachmentStruct =
+        from_str("<AttachmentStruct><data></data><thumbnail>AAEC</thumbnail></AttachmentStruct>")
+            .unwrap();
+    assert_eq!(decoded.thumbnail, Some(Base64Bytes(vec![0, 1, 2])));
+    assert!(AttachmentStruct::get_prompt_schema().contains("standard base64"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct NestedContainersStruct {
+    #[prompt("The rows of the table")]
+    rows: Vec<Vec<String>>,
+    #[prompt("The score of each player, if known")]
+    scores: Option<HashMap<String, i32>>,
+    #[prompt("The measurements of each sensor")]
+    readings: HashMap<String, Vec<f64>>,
+    #[prompt("The answers of each group")]
+    groups: Vec<BTreeMap<String, Vec<i32>>>,
+}
+
+#[test]
+fn test_nested_containers_schema() {
+    let schema = NestedContainersStruct::get_prompt_schema();
+    println!("Schema:\n{}", schema);
+    assert!(schema.contains("<rows>"));
+    assert!(schema.contains("<item>A series(0 or more elements) of items"));
+    assert!(schema.contains("<scores>"));
+    assert!(schema.contains("<readings>"));
+    assert!(schema.contains("each value is A series(0 or more elements) of items"));
+    assert!(schema.contains("<groups>"));
+}
+
+#[test]
+fn test_nested_containers_deserialization() {
+    let xml = r#"
+    <NestedContainersStruct>
+        <rows>
+            <item><item> a </item><item><![CDATA[b]]></item></item>
+            <item></item>
+            <item><item>c</item></item>
+        </rows>
+        <scores>
+            <entry><key>alice</key><value> 3 </value></entry>
+        </scores>
+        <readings>
+            <entry><key>north</key><value><item>1.5</item><item>"2"</item></value></entry>
+        </readings>
+        <groups>
+            <item>
+                <entry><key>first</key><value><item>1</item><item> 2 </item></value></entry>
+                <entry><key>second</key><value></value></entry>
+            </item>
+        </groups>
+    </NestedContainersStruct>
+    "#;
+    let decoded: NestedContainersStruct = from_str(xml).unwrap();
+    println!("Decoded: {:?}", decoded);
+    assert_eq!(
+        decoded,
+        NestedContainersStruct {
+            rows: vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec![],
+                vec!["c".to_string()],
+            ],
+            scores: Some(HashMap::from([("alice".to_string(), 3)])),
+            readings: HashMap::from([("north".to_string(), vec![1.5, 2.0])]),
+            groups: vec![BTreeMap::from([
+                ("first".to_string(), vec![1, 2]),
+                ("second".to_string(), vec![]),
+            ])],
+        }
+    );
+
+    let err = from_str::<NestedContainersStruct>(
+        "<NestedContainersStruct><rows/><readings><entry><key>x</key><value><item>abc</item></value></entry></readings><groups/></NestedContainersStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse 'abc' as a f64 value")
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct SurveyStruct {
+    #[prompt("The answer to each question, empty where unknown")]
+    answers: Vec<Option<String>>,
+    #[prompt("The rating of each question, empty where not rated")]
+    ratings: Vec<Option<i32>>,
+    #[prompt("The respondent of each question, empty where anonymous")]
+    respondents: Vec<Option<SimpleStruct>>,
+}
+
+#[test]
+fn test_vec_option_schema() {
+    let schema = SurveyStruct::get_prompt_schema();
+    println!("Schema:\n{}", schema);
+    assert!(schema.contains("<item>Optional. if there is no value, leave the element empty"));
+}
+
+#[test]
+fn test_vec_option_deserialization() {
+    let xml = r#"
+    <SurveyStruct>
+        <answers>
+            <item> yes </item>
+            <item/>
+            <item></item>
+            <item><![CDATA[no]]></item>
+        </answers>
+        <ratings>
+            <item>"4"</item>
+            <item/>
+            <item>5</item>
+        </ratings>
+        <respondents>
+            <item/>
+            <item><SimpleStruct><name>Ann</name><age>30</age><is_student>no</is_student></SimpleStruct></item>
+        </respondents>
+    </SurveyStruct>
+    "#;
+    let decoded: SurveyStruct = from_str(xml).unwrap();
+    println!("Decoded: {:?}", decoded);
+    assert_eq!(
+        decoded,
+        SurveyStruct {
+            answers: vec![Some("yes".to_string()), None, None, Some("no".to_string())],
+            ratings: vec![Some(4), None, Some(5)],
+            respondents: vec![
+                None,
+                Some(SimpleStruct {
+                    name: "Ann".to_string(),
+                    age: 30,
+                    is_student: false,
+                }),
+            ],
+        }
+    );
+
+    let xml_all_empty = r#"
+    <SurveyStruct>
+        <answers><item/><item/></answers>
+        <ratings><item></item></ratings>
+        <respondents/>
+    </SurveyStruct>
+    "#;
+    let decoded: SurveyStruct = from_str(xml_all_empty).unwrap();
+    assert_eq!(
+        decoded,
+        SurveyStruct {
+            answers: vec![None, None],
+            ratings: vec![None],
+            respondents: vec![],
+        }
+    );
+
+    let err = from_str::<SurveyStruct>(
+        "<SurveyStruct><answers/><ratings><item>many</item></ratings><respondents/></SurveyStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse 'many' as a i32 value")
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct InventoryStruct {
+    #[prompt("The stock of each product")]
+    stock: BTreeMap<String, i32>,
+    #[prompt("The count of each python value")]
+    counts: BTreeMap<PythonValueWeak, i32>,
+}
+
+#[test]
+fn test_btreemap_entries_deserialization() {
+    let xml = r#"
+    <InventoryStruct>
+        <stock>
+            <entry><key><![CDATA[ apple ]]></key><value> 3 </value></entry>
+            <entry><key>pear</key><value>"5"</value></entry>
+        </stock>
+        <counts>
+            <entry><key><None/></key><value>1</value></entry>
+            <entry><key><Int><val>7</val></Int></key><value>2</value></entry>
+        </counts>
+    </InventoryStruct>
+    "#;
+    let decoded: InventoryStruct = from_str(xml).unwrap();
+    println!("Decoded: {:?}", decoded);
+    assert_eq!(
+        decoded,
+        InventoryStruct {
+            stock: BTreeMap::from([("apple".to_string(), 3), ("pear".to_string(), 5)]),
+            counts: BTreeMap::from([
+                (PythonValueWeak(PythonValue::None), 1),
+                (PythonValueWeak(PythonValue::Int { val: 7 }), 2),
+            ]),
+        }
+    );
+
+    let decoded_empty: InventoryStruct =
+        from_str("<InventoryStruct><stock></stock><counts/></InventoryStruct>").unwrap();
+    assert!(decoded_empty.stock.is_empty());
+    assert!(decoded_empty.counts.is_empty());
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct DuplicateKeysStruct {
+    #[prompt("The price of each product")]
+    prices: HashMap<String, i32>,
+    #[prompt("The stock of each product", on_duplicate = "error")]
+    #[serde(default)]
+    stock: HashMap<String, i32>,
+    #[prompt("The count of each python value", on_duplicate = "error")]
+    #[serde(default)]
+    counts: BTreeMap<PythonValueWeak, i32>,
+}
+
+fn parse_duplicate_keys(content: &str) -> Result<DuplicateKeysStruct, quick_xml::DeError> {
+    from_str(&format!(
+        "<DuplicateKeysStruct><prices><entry><key>a</key><value>1</value></entry><entry><key>a</key><value>2</value></entry></prices>{}</DuplicateKeysStruct>",
+        content
+    ))
+}
+
+#[test]
+fn test_map_duplicate_keys() {
+    // the last entry wins by default
+    let decoded = parse_duplicate_keys("").unwrap();
+    assert_eq!(decoded.prices, HashMap::from([("a".to_string(), 2)]));
+    assert!(decoded.stock.is_empty());
+
+    let decoded = parse_duplicate_keys(
+        "<stock><entry><key>a</key><value>1</value></entry><entry><key>b</key><value>2</value></entry></stock>",
+    )
+    .unwrap();
+    assert_eq!(decoded.stock.len(), 2);
+
+    let err = parse_duplicate_keys(
+        "<stock><entry><key>a</key><value>1</value></entry><entry><key>b</key><value>2</value></entry><entry><key> a </key><value>3</value></entry></stock>",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains(
+        "the key 'a' appears in both <entry> 1 and <entry> 3, but every key must be unique"
+    ));
+
+    let err = parse_duplicate_keys(
+        "<counts><entry><key><None/></key><value>1</value></entry><entry><key><None/></key><value>2</value></entry></counts>",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("<entry> 2 has the same key as <entry> 1, but every key must be unique")
+    );
+
+    let schema = DuplicateKeysStruct::get_prompt_schema();
+    assert!(schema.contains("The stock of each product (every key must be unique)"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct EnumMapStruct {
+    #[prompt("The state of each task")]
+    tasks: HashMap<String, TestEnum>,
+    #[prompt("The state of each step")]
+    steps: BTreeMap<i32, TestEnum>,
+}
+
+#[test]
+fn test_enum_map_values_schema() {
+    let schema = EnumMapStruct::get_prompt_schema();
+    println!("Schema:\n{}", schema);
+    assert!(schema.contains(
+        "each value is the element of one of the variants below, written directly inside <value>"
+    ));
+    assert!(schema.contains("<Simple/>"));
+    assert!(schema.contains("<WithIntData>"));
+}
+
+#[test]
+fn test_enum_map_values_deserialization() {
+    let xml = r#"
+    <EnumMapStruct>
+        <tasks>
+            <entry><key>build</key><value><WithIntData><value>1</value></WithIntData></value></entry>
+            <entry><key>deploy</key><value><Simple/></value></entry>
+        </tasks>
+        <steps>
+            <entry><key>2</key><value><WithStringData><value> done </value></WithStringData></value></entry>
+            <entry><key>1</key><value><Simple/></value></entry>
+        </steps>
+    </EnumMapStruct>
+    "#;
+    let decoded: EnumMapStruct = from_str(xml).unwrap();
+    println!("Decoded: {:?}", decoded);
+    assert_eq!(
+        decoded,
+        EnumMapStruct {
+            tasks: HashMap::from([
+                ("build".to_string(), TestEnum::WithIntData { value: 1 }),
+                ("deploy".to_string(), TestEnum::Simple),
+            ]),
+            steps: BTreeMap::from([
+                (1, TestEnum::Simple),
+                (
+                    2,
+                    TestEnum::WithStringData {
+                        value: "done".to_string(),
+                    },
+                ),
+            ]),
+        }
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct OptionalFieldsStruct {
+    #[prompt("The age of the person, if known")]
+    age: Option<i32>,
+    #[prompt("The person's guardian, if any")]
+    guardian: Option<SimpleStruct>,
+    #[prompt("The state of the person, if known")]
+    state: Option<TestEnum>,
+}
+
+fn parse_optional_fields(content: &str) -> Result<OptionalFieldsStruct, quick_xml::DeError> {
+    from_str(&format!(
+        "<OptionalFieldsStruct>{}</OptionalFieldsStruct>",
+        content
+    ))
+}
+
+#[test]
+fn test_option_field_errors() {
+    let empty = OptionalFieldsStruct {
+        age: None,
+        guardian: None,
+        state: None,
+    };
+    // a missing or empty element is None
+    assert_eq!(parse_optional_fields("").unwrap(), empty);
+    assert_eq!(
+        parse_optional_fields("<age></age><guardian/><state>  </state>").unwrap(),
+        empty
+    );
+
+    let decoded = parse_optional_fields(
+        "<age> 42 </age><guardian><name>Bo</name><age>50</age><is_student>no</is_student></guardian><state><WithIntData><value>3</value></WithIntData></state>",
+    )
+    .unwrap();
+    assert_eq!(decoded.age, Some(42));
+    assert_eq!(decoded.guardian.map(|g| g.name), Some("Bo".to_string()));
+    assert_eq!(decoded.state, Some(TestEnum::WithIntData { value: 3 }));
+
+    // content that doesn't parse is an error instead of a silent None
+    let err = parse_optional_fields("<age>abc</age>").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse 'abc' as a i32 value")
+    );
+    let err = parse_optional_fields("<guardian><name>Bo</name></guardian>").unwrap_err();
+    assert!(err.to_string().contains("missing field `age`"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct EmptyOptionStruct {
+    #[prompt("The description, if any")]
+    description: Option<String>,
+    #[prompt("The count, if any")]
+    count: Option<i32>,
+    #[prompt("The flag, if any")]
+    flag: Option<bool>,
+    #[prompt("The note, kept even when empty", keep_empty)]
+    note: Option<String>,
+}
+
+#[test]
+fn test_empty_option_elements() {
+    for empty in ["", "<x/>", "<x></x>", "<x>   </x>", "<x><![CDATA[]]></x>"] {
+        let field = |name: &str| {
+            if empty.is_empty() {
+                String::new()
+            } else {
+                empty.replace('x', name)
+            }
+        };
+        let xml = format!(
+            "<EmptyOptionStruct>{}{}{}</EmptyOptionStruct>",
+            field("description"),
+            field("count"),
+            field("flag")
+        );
+        let decoded: EmptyOptionStruct = from_str(&xml).unwrap();
+        assert_eq!(
+            decoded,
+            EmptyOptionStruct {
+                description: None,
+                count: None,
+                flag: None,
+                note: None,
+            },
+            "{}",
+            xml
+        );
+    }
+
+    let decoded: EmptyOptionStruct = from_str(
+        "<EmptyOptionStruct><description><![CDATA[ hi ]]></description><count>0</count><flag>false</flag><note/></EmptyOptionStruct>",
+    )
+    .unwrap();
+    assert_eq!(
+        decoded,
+        EmptyOptionStruct {
+            description: Some("hi".to_string()),
+            count: Some(0),
+            flag: Some(false),
+            note: Some(String::new()),
+        }
+    );
+
+    let schema = EmptyOptionStruct::get_prompt_schema();
+    assert!(schema.contains("an empty element is read as not provided too"));
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct LenientSeqStruct {
+    #[prompt("The tags of the post")]
+    tags: Vec<String>,
+    #[prompt("The scores of the post", strict_seq)]
+    #[serde(default)]
+    scores: Vec<i32>,
+}
+
+fn parse_lenient_seq(content: &str) -> Result<LenientSeqStruct, quick_xml::DeError> {
+    from_str(&format!("<LenientSeqStruct>{}</LenientSeqStruct>", content))
+}
+
+#[test]
+fn test_lenient_seq_deserialization() {
+    let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+    let decoded = parse_lenient_seq(
+        "<tags><item>a</item><item>b</item></tags><scores><item>1</item></scores>",
+    )
+    .unwrap();
+    assert_eq!(decoded.tags, tags(&["a", "b"]));
+    assert_eq!(decoded.scores, vec![1]);
+
+    // the <item> wrapper is optional unless the field is strict
+    let decoded =
+        parse_lenient_seq("<tags><tag>a</tag><tag> b </tag><item>c</item></tags>").unwrap();
+    assert_eq!(decoded.tags, tags(&["a", "b", "c"]));
+    let decoded = parse_lenient_seq("<tags><![CDATA[single]]></tags>").unwrap();
+    assert_eq!(decoded.tags, tags(&["single"]));
+
+    let err =
+        parse_lenient_seq("<tags/><scores><item>1</item><score>2</score></scores>").unwrap_err();
+    assert!(err.to_string().contains(
+        "It must be a sequence of <item> elements, each containing the value. Details: unknown field `score`, expected `item`"
+    ));
+    let err = parse_lenient_seq("<tags/><scores>3</scores>").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unknown field `$text`, expected `item`")
+    );
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct StrayChildrenStruct {
+    #[prompt("The tags of the post")]
+    tags: Vec<String>,
+    #[prompt("The price of each product")]
+    prices: HashMap<String, i32>,
+    #[prompt("The stock of each product", strict_seq)]
+    #[serde(default)]
+    stock: HashMap<String, i32>,
+}
+
+#[test]
+fn test_stray_children_deserialization() {
+    let xml = r#"
+    <StrayChildrenStruct>
+        <tags>
+            <item>a</item>
+            <note>these are the tags</note>
+            <item>b</item>
+        </tags>
+        <prices>
+            <entry><key>apple</key><value>3</value></entry>
+            <note>prices are in cents</note>
+            <entry><key>pear</key><value>5</value></entry>
+        </prices>
+    </StrayChildrenStruct>
+    "#;
+    let decoded: StrayChildrenStruct = from_str(xml).unwrap();
+    assert_eq!(decoded.tags, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(
+        decoded.prices,
+        HashMap::from([("apple".to_string(), 3), ("pear".to_string(), 5)])
+    );
+    assert!(decoded.stock.is_empty());
+
+    let err = from_str::<StrayChildrenStruct>(
+        "<StrayChildrenStruct><tags/><prices/><stock><entry><key>a</key><value>1</value></entry><note>x</note></stock></StrayChildrenStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unknown field `note`, expected `entry`")
+    );
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize, PartialEq)]
+struct GroupedNumbersStruct {
+    #[prompt("The population of the city")]
+    population: u64,
+    #[prompt("The change since last year")]
+    change: i32,
+}
+
+#[test]
+fn test_grouped_integer_deserialization() {
+    let parse = |population: &str, change: &str| {
+        from_str::<GroupedNumbersStruct>(&format!(
+            "<GroupedNumbersStruct><population>{}</population><change>{}</change></GroupedNumbersStruct>",
+            population, change
+        ))
+    };
+
+    for (population, change) in [
+        ("1,234,567", "-1,234"),
+        ("1_234_567", "-12_34"),
+        ("1 234 567", "-1 234"),
+    ] {
+        let decoded = parse(population, change).unwrap();
+        assert_eq!(decoded.population, 1_234_567);
+        assert_eq!(decoded.change, -1234);
+    }
+
+    let err = parse("12,34", "0").unwrap_err();
+    assert!(err.to_string().contains("can not parse '12,34'"));
+    let err = parse("abc", "0").unwrap_err();
+    assert!(err.to_string().contains("can not parse 'abc'"));
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize, PartialEq)]
+struct CoerceFloatStruct {
+    #[prompt("The number of guests", coerce_float)]
+    guests: u32,
+    #[prompt("The temperature offset", coerce_float)]
+    offset: i64,
+    #[prompt("The number of rooms")]
+    rooms: u32,
+}
+
+#[test]
+fn test_coerce_float_integers() {
+    let parse = |guests: &str, offset: &str, rooms: &str| {
+        from_str::<CoerceFloatStruct>(&format!(
+            "<CoerceFloatStruct><guests>{}</guests><offset>{}</offset><rooms>{}</rooms></CoerceFloatStruct>",
+            guests, offset, rooms
+        ))
+    };
+
+    let decoded = parse("7.0", "-3.00", "2").unwrap();
+    assert_eq!((decoded.guests, decoded.offset, decoded.rooms), (7, -3, 2));
+    let decoded = parse("4.2e1", "-1.5E2", "2").unwrap();
+    assert_eq!((decoded.guests, decoded.offset), (42, -150));
+
+    let err = parse("7.5", "0", "2").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("7.5 has a fractional part, but a whole number is required")
+    );
+    let err = parse("-1.0", "0", "2").unwrap_err();
+    assert!(err.to_string().contains("-1 is out of range"));
+
+    // without `coerce_float` the decimal point is rejected
+    let err = parse("7", "0", "2.0").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("a whole number without a decimal point is required")
+    );
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize, PartialEq)]
+struct PercentUnitStruct {
+    #[prompt("How confident the answer is", percent)]
+    confidence: f64,
+    #[prompt("The score of the answer", strip_suffix = "points")]
+    score: f32,
+    #[prompt("The final score of the answer")]
+    final_score: f32,
+}
+
+#[test]
+fn test_percent_and_unit_schema() {
+    let schema = PercentUnitStruct::get_prompt_schema();
+    assert!(schema.contains(
+        "How confident the answer is (may be written as a percentage, e.g. 85% for 0.85)"
+    ));
+    assert!(schema.contains(
+        "The score of the answer (a number in points, the unit may follow it, e.g. 3.2 points)"
+    ));
+}
+
+#[test]
+fn test_percent_and_unit_deserialization() {
+    let parse = |confidence: &str, score: &str, final_score: &str| {
+        from_str::<PercentUnitStruct>(&format!(
+            "<PercentUnitStruct><confidence>{}</confidence><score>{}</score><final_score>{}</final_score></PercentUnitStruct>",
+            confidence, score, final_score
+        ))
+    };
+
+    let decoded = parse("85%", "3.2 points", "4").unwrap();
+    assert_eq!(decoded.confidence, 0.85);
+    assert_eq!(decoded.score, 3.2);
+    let decoded = parse("0.85", "3.2", "4").unwrap();
+    assert_eq!(decoded.confidence, 0.85);
+    assert_eq!(decoded.score, 3.2);
+    let decoded = parse("-12.5 %", "-1 points", "4").unwrap();
+    assert_eq!(decoded.confidence, -0.125);
+    assert_eq!(decoded.score, -1.0);
+
+    // without `strip_suffix` the unit is an error
+    let err = parse("85%", "3.2", "3.2 points").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse '3.2 points' as a f32 value")
+    );
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize)]
+struct TextualFloatStruct {
+    #[prompt("The measured value")]
+    value: f64,
+    #[prompt("The ordered value")]
+    ordered: OrderedFloat<f64>,
+}
+
+#[test]
+fn test_textual_float_deserialization() {
+    let parse = |value: &str| {
+        from_str::<TextualFloatStruct>(&format!(
+            "<TextualFloatStruct><value>{0}</value><ordered>{0}</ordered></TextualFloatStruct>",
+            value
+        ))
+        .unwrap()
+    };
+
+    for (text, expected) in [
+        ("inf", f64::INFINITY),
+        ("Infinity", f64::INFINITY),
+        ("-INF", f64::NEG_INFINITY),
+        ("-∞", f64::NEG_INFINITY),
+        ("2,75", 2.75),
+        ("-0,5", -0.5),
+        ("1,234", 1234.0),
+        ("1,234,567.25", 1234567.25),
+    ] {
+        let decoded = parse(text);
+        assert_eq!(decoded.value, expected, "{}", text);
+        assert_eq!(decoded.ordered, OrderedFloat(expected), "{}", text);
+    }
+
+    for text in ["NaN", "nan"] {
+        let decoded = parse(text);
+        assert!(decoded.value.is_nan());
+        assert!(decoded.ordered.is_nan());
+    }
+
+    let err = from_str::<TextualFloatStruct>(
+        "<TextualFloatStruct><value>1,2,3</value><ordered>0</ordered></TextualFloatStruct>",
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse '1,2,3' as a f64 value")
+    );
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize)]
+struct SnippetStruct {
+    #[prompt("The code of the snippet", preserve_whitespace)]
+    code: String,
+    #[prompt("The closing line of the poem", preserve_whitespace)]
+    closing: String,
+    #[prompt("The title of the snippet")]
+    title: String,
+}
+
+#[test]
+fn test_preserve_whitespace_schema() {
+    assert!(
+        SnippetStruct::get_prompt_schema().contains(
+            "The code of the snippet (whitespace inside the CDATA is preserved verbatim)"
+        )
+    );
+}
+
+#[test]
+fn test_preserve_whitespace_deserialization() {
+    let code = "\n    fn main() {\n        println!(\"hi\");\n    }\n";
+    let xml = format!(
+        "<SnippetStruct><code><![CDATA[{}]]></code><closing><![CDATA[and miles to go\n]]></closing><title><![CDATA[  hello  ]]></title></SnippetStruct>",
+        code
+    );
+    let decoded: SnippetStruct = from_str(&xml).unwrap();
+    assert_eq!(decoded.code, code);
+    assert_eq!(decoded.closing, "and miles to go\n");
+    assert_eq!(decoded.title, "hello");
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize)]
+struct CleanStringStruct {
+    #[prompt("The name of the author", clean)]
+    author: String,
+    #[prompt("The quote of the author", clean)]
+    quote: String,
+    #[prompt("The function to call", clean)]
+    function: String,
+    #[prompt("The headline", clean)]
+    headline: String,
+    #[prompt("The raw headline")]
+    raw: String,
+}
+
+#[test]
+fn test_clean_string_deserialization() {
+    let xml = r#"
+    <CleanStringStruct>
+        <author><![CDATA[ "John Doe" ]]></author>
+        <quote><![CDATA["Yes" and "no"]]></quote>
+        <function><![CDATA[`parse_args`]]></function>
+        <headline><![CDATA[**Important update**]]></headline>
+        <raw><![CDATA[**Important update**]]></raw>
+    </CleanStringStruct>
+    "#;
+    let decoded: CleanStringStruct = from_str(xml).unwrap();
+    assert_eq!(decoded.author, "John Doe");
+    assert_eq!(decoded.quote, r#""Yes" and "no""#);
+    assert_eq!(decoded.function, "parse_args");
+    assert_eq!(decoded.headline, "Important update");
+    assert_eq!(decoded.raw, "**Important update**");
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize)]
+struct EntityStruct {
+    #[prompt("The named entities", decode_entities)]
+    named: String,
+    #[prompt("The numeric entities", decode_entities)]
+    numeric: String,
+    #[prompt("The double-escaped text", decode_entities)]
+    double: String,
+    #[prompt("The text with a bare ampersand", decode_entities)]
+    bare: String,
+    #[prompt("The raw text")]
+    raw: String,
+}
+
+#[test]
+fn test_decode_entities_deserialization() {
+    let xml = r#"
+    <EntityStruct>
+        <named><![CDATA[&lt;tag&gt; &quot;a&quot;&nbsp;b]]></named>
+        <numeric><![CDATA[it&#8217;s &#x201C;fine&#x201D;]]></numeric>
+        <double><![CDATA[R&amp;amp;D &amp;lt;b&amp;gt;]]></double>
+        <bare><![CDATA[Tom & Jerry, AT&T; &unknown; & &#xZZ;]]></bare>
+        <raw><![CDATA[&lt;tag&gt;]]></raw>
+    </EntityStruct>
+    "#;
+    let decoded: EntityStruct = from_str(xml).unwrap();
+    assert_eq!(decoded.named, "<tag> \"a\"\u{a0}b");
+    assert_eq!(decoded.numeric, "it\u{2019}s \u{201c}fine\u{201d}");
+    assert_eq!(decoded.double, "R&D <b>");
+    assert_eq!(decoded.bare, "Tom & Jerry, AT&T; &unknown; & &#xZZ;");
+    assert_eq!(decoded.raw, "&lt;tag&gt;");
+}
+
+#[test]
+fn test_bool_parser_visitor_forms() {
+    use serde::de::value::{BoolDeserializer, Error, I64Deserializer, U64Deserializer};
+
+    #[llm_prompt]
+    #[derive(Debug, Deserialize)]
+    struct Toggle {
+        #[prompt("Whether the feature is on")]
+        state: bool,
+    }
+
+    assert!(llm_xml_caster::custom_bool_parser(BoolDeserializer::<Error>::new(true)).unwrap());
+    assert!(!llm_xml_caster::custom_bool_parser(BoolDeserializer::<Error>::new(false)).unwrap());
+    assert!(llm_xml_caster::custom_bool_parser(U64Deserializer::<Error>::new(2)).unwrap());
+    assert!(!llm_xml_caster::custom_bool_parser(U64Deserializer::<Error>::new(0)).unwrap());
+    assert!(llm_xml_caster::custom_bool_parser(I64Deserializer::<Error>::new(-1)).unwrap());
+    assert!(!llm_xml_caster::custom_bool_parser(I64Deserializer::<Error>::new(0)).unwrap());
+
+    let parse =
+        |text: &str| from_str::<Toggle>(&format!("<Toggle><state>{}</state></Toggle>", text));
+    for text in ["Enabled", "correct", "yes"] {
+        assert!(parse(text).unwrap().state, "{}", text);
+    }
+    for text in ["disabled", "Incorrect", "no"] {
+        assert!(!parse(text).unwrap().state, "{}", text);
+    }
+    let err = parse("maybe").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("can not parse 'maybe' as a boolean value")
+    );
+}
+
+#[llm_prompt]
+#[derive(Debug, Deserialize)]
+struct UnsignedStruct {
+    #[prompt("The age of the person")]
+    age: u8,
+    #[prompt("The balance of the account")]
+    balance: i8,
+}
+
+#[test]
+fn test_unsigned_integer_schema() {
+    assert_eq!(
+        u8::get_prompt_schema(),
+        "integer value, a non-negative whole number without a fractional part, e.g., 42, 7, or 0"
+    );
+    assert_eq!(
+        i8::get_prompt_schema(),
+        "integer value, a whole number without a fractional part, e.g., 42, -7, or 0"
+    );
+}
+
+#[test]
+fn test_unsigned_integer_errors() {
+    let parse = |age: &str, balance: &str| {
+        from_str::<UnsignedStruct>(&format!(
+            "<UnsignedStruct><age>{}</age><balance>{}</balance></UnsignedStruct>",
+            age, balance
+        ))
+    };
+
+    let decoded = parse("5", "-5").unwrap();
+    assert_eq!((decoded.age, decoded.balance), (5, -5));
+
+    let err = parse("-5", "0").unwrap_err();
+    assert!(err.to_string().contains(
+        "can not parse '-5' as a u8 value, the value must be ≥ 0, a u8 can't be negative"
+    ));
+    let err = parse("300", "0").unwrap_err();
+    assert!(err.to_string().contains("the value must be at most 255"));
+    let err = parse("5", "-200").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("the value must be between -128 and 127")
+    );
+}
+
+#[test]
+fn test_cast_from_str() {
+    let text = r#"Sure! Here is the person you asked for:
+    <SimpleStruct>
+        <name><![CDATA[Alice]]></name>
+        <age>30</age>
+        <is_student>false</is_student>
+    </SimpleStruct>
+    Let me know if you need anything else."#;
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(text).unwrap();
+    assert_eq!(decoded.name, "Alice");
+    assert_eq!(decoded.age, 30);
+
+    let err = llm_xml_caster::cast_from_str::<SimpleStruct>("I can't help with that.").unwrap_err();
+    assert!(matches!(err, llm_xml_caster::Error::XmlExtraction(_)));
+    assert!(
+        err.to_string()
+            .contains("the response holds no XML, it must be a <SimpleStruct> element")
+    );
+
+    let text = "The answer is:\n```xml\n<WithIntData><value>7</value></WithIntData>\n```";
+    let decoded: TestEnum = llm_xml_caster::cast_from_str(text).unwrap();
+    assert_eq!(decoded, TestEnum::WithIntData { value: 7 });
+    let err = llm_xml_caster::cast_from_str::<TestEnum>("no idea").unwrap_err();
+    assert!(matches!(err, llm_xml_caster::Error::XmlExtraction(_)));
+}
+
+#[test]
+fn test_enum_extraction_from_response() {
+    assert_eq!(
+        TestEnum::variant_names(),
+        ["Simple", "WithStringData", "WithFloatData", "WithIntData"]
+    );
+
+    let text = "Since 7 < 10, the best fit is the integer variant:\n<WithIntData><value>7</value></WithIntData>\nHope this helps!";
+    let decoded: TestEnum = llm_xml_caster::cast_from_str(text).unwrap();
+    assert_eq!(decoded, TestEnum::WithIntData { value: 7 });
+
+    let text = "Nothing else is needed, so I pick <Simple/> as the answer.";
+    let decoded: TestEnum = llm_xml_caster::cast_from_str(text).unwrap();
+    assert_eq!(decoded, TestEnum::Simple);
+
+    let err = llm_xml_caster::cast_from_str::<TestEnum>("I think <Other/> fits.").unwrap_err();
+    assert!(err.to_string().contains(
+        "cannot find the XML element of the enum, it must be one of: Simple, WithStringData, WithFloatData, WithIntData"
+    ));
+}
+
+#[test]
+fn test_extraction_skips_prolog_and_mentions() {
+    let person = "<SimpleStruct><name><![CDATA[Bob]]></name><age>41</age><is_student>no</is_student></SimpleStruct>";
+
+    let text = format!(
+        "```xml\n<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n```",
+        person
+    );
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.name, "Bob");
+
+    let text = format!(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE SimpleStruct>\n<!-- the person -->\n{}",
+        person
+    );
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.age, 41);
+
+    // the literal tag in the prose must not be taken as the start of the XML
+    let text = format!(
+        "I will wrap the answer in a `<SimpleStruct>` element, closed by `</SimpleStruct>`:\n<?xml version=\"1.0\"?>\n{}\nDone.",
+        person
+    );
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.name, "Bob");
+    assert!(!decoded.is_student);
+}
+
+#[test]
+fn test_extract_candidates() {
+    use llm_xml_caster::{CastOptions, Extraction, cast_from_str_with, extract_candidates};
+
+    let using = |extraction| CastOptions {
+        extraction,
+        ..CastOptions::default()
+    };
+
+    let wrong = "<SimpleStruct><name><![CDATA[Bob]]></name><age>forty</age><is_student>no</is_student></SimpleStruct>";
+    let right = "<SimpleStruct><name><![CDATA[Bob]]></name><age>40</age><is_student>no</is_student></SimpleStruct>";
+    let text = format!("Wrong:\n{}\nCorrected:\n{}", wrong, right);
+    assert_eq!(extract_candidates::<SimpleStruct>(&text), [wrong, right]);
+
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.age, 40);
+    let decoded: SimpleStruct = cast_from_str_with(&text, using(Extraction::Last)).unwrap();
+    assert_eq!(decoded.age, 40);
+    let err = cast_from_str_with::<SimpleStruct>(&text, using(Extraction::First)).unwrap_err();
+    assert!(matches!(err, llm_xml_caster::Error::XmlDeserialization(_)));
+
+    // a same-named tag inside CDATA doesn't close the element
+    let nested = "<SimpleStruct><name><![CDATA[</SimpleStruct> <SimpleStruct>]]></name><age>3</age><is_student>yes</is_student></SimpleStruct>";
+    assert_eq!(extract_candidates::<SimpleStruct>(nested), [nested]);
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(nested).unwrap();
+    assert_eq!(decoded.name, "</SimpleStruct> <SimpleStruct>");
+
+    // a document cut off at the end leaves the earlier complete one
+    let text = format!("{}\nAnd again:\n<SimpleStruct><name><![CDATA[Bo", right);
+    assert_eq!(extract_candidates::<SimpleStruct>(&text), [right]);
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.age, 40);
+}
+
+#[test]
+fn test_reasoning_blocks_are_stripped() {
+    let draft = "<SimpleStruct><name><![CDATA[Draft]]></name><age>1</age><is_student>no</is_student></SimpleStruct>";
+    let answer = "<SimpleStruct><name><![CDATA[Final]]></name><age>2</age><is_student>yes</is_student></SimpleStruct>";
+
+    let text = format!(
+        "<think>Let me draft it first:\n{}\nThat looks fine.</think>\n{}",
+        draft, answer
+    );
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.name, "Final");
+
+    // the opening tag left out, as reasoning models often do
+    let text = format!("Drafting:\n{}\n</think>\n{}", draft, answer);
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.name, "Final");
+
+    // a block that is never closed keeps the answer after it
+    let text = format!("<think>The answer should be:\n{}", answer);
+    let decoded: SimpleStruct = llm_xml_caster::cast_from_str(&text).unwrap();
+    assert_eq!(decoded.name, "Final");
+
+    assert_eq!(
+        llm_xml_caster::strip_reasoning("a<plan>b</plan>c", &["plan"]),
+        "ac"
+    );
+}
+
+#[test]
+fn test_repair_malformed_xml() {
+    use llm_xml_caster::{CastOptions, cast_from_str_with};
+
+    let repair = CastOptions {
+        repair: true,
+        ..CastOptions::default()
+    };
+    let cast = |xml: &str| cast_from_str_with::<SimpleStruct>(xml, repair);
+
+    // an unescaped `&`, while the entity and the CDATA are left alone
+    let decoded = cast("<SimpleStruct><name>Tom & Jerry &amp; <![CDATA[A&B]]></name><age>3</age><is_student>no</is_student></SimpleStruct>").unwrap();
+    assert_eq!(decoded.name, "Tom & Jerry & A&B");
+    // a `<` that should have been escaped
+    let decoded = cast(
+        "<SimpleStruct><name>a < b</name><age>3</age><is_student>no</is_student></SimpleStruct>",
+    )
+    .unwrap();
+    assert_eq!(decoded.name, "a < b");
+    // close tags in the wrong case
+    let decoded = cast(
+        "<SimpleStruct><name>Ann</Name><age>3</AGE><is_student>no</is_student></SimpleStruct>",
+    )
+    .unwrap();
+    assert_eq!((decoded.name.as_str(), decoded.age), ("Ann", 3));
+
+    // off by default
+    let broken = "<SimpleStruct><name>Tom & Jerry</name><age>3</age><is_student>no</is_student></SimpleStruct>";
+    assert!(llm_xml_caster::cast_from_str::<SimpleStruct>(broken).is_err());
+
+    // broken nesting isn't repaired, and the original error is reported
+    let nested = "<SimpleStruct><name>Ann & Bob<age>3</name></age><is_student>no</is_student></SimpleStruct>";
+    let original = quick_xml::de::from_str::<SimpleStruct>(nested).unwrap_err();
+    let Err(llm_xml_caster::Error::XmlDeserialization(err)) = cast(nested) else {
+        panic!("expected a deserialization error");
+    };
+    assert_eq!(err.error.to_string(), original.to_string());
+}
+
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct TruncatedNote {
+    #[prompt("The title of the note")]
+    title: String,
+    #[prompt("Tags of the note")]
+    tags: Option<Vec<String>>,
+    #[prompt("The body of the note")]
+    body: Option<String>,
+}
+
+#[test]
+fn test_complete_truncated_xml() {
+    use llm_xml_caster::{CastOptions, cast_from_str_with};
+
+    let complete = CastOptions {
+        complete_truncated: true,
+        ..CastOptions::default()
+    };
+    let cast = |xml: &str| cast_from_str_with::<TruncatedNote>(xml, complete);
+
+    // cut off inside a string field
+    let decoded = cast(
+        "Here it is:\n<TruncatedNote><title><![CDATA[Groceries]]></title><body><![CDATA[Buy milk and egg",
+    )
+    .unwrap();
+    assert_eq!(decoded.title, "Groceries");
+    assert_eq!(decoded.tags, None);
+    assert_eq!(decoded.body.as_deref(), Some("Buy milk and egg"));
+
+    // cut off inside a list, in the middle of a tag
+    let decoded =
+        cast("<TruncatedNote><title>Groceries</title><tags><item>food</item><item>week</item><it")
+            .unwrap();
+    assert_eq!(
+        decoded.tags,
+        Some(vec!["food".to_string(), "week".to_string()])
+    );
+    assert_eq!(decoded.body, None);
+
+    // a partial entity is dropped
+    let decoded = cast("<TruncatedNote><title>Salt &amp; pep &am").unwrap();
+    assert_eq!(decoded.title, "Salt & pep");
+
+    // cut off right after the root, where the required title is missing
+    assert!(matches!(
+        cast("<TruncatedNote>"),
+        Err(llm_xml_caster::Error::XmlDeserialization(_))
+    ));
+
+    // off by default
+    let truncated = "<TruncatedNote><title>Groceries</title><body>Buy";
+    assert!(llm_xml_caster::cast_from_str::<TruncatedNote>(truncated).is_err());
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_generate_options_builder() {
+    use llm_xml_caster::{CastOptions, Extraction, GenerateOptions};
+
+    // nothing is sent by default
+    let options = GenerateOptions::default();
+    assert_eq!(options.chat_options().temperature, None);
+    assert_eq!(options.chat_options().max_tokens, None);
+    assert!(!options.cast_options().repair);
+
+    let options = GenerateOptions::new()
+        .with_chat_options(genai::chat::ChatOptions::default().with_top_p(0.5))
+        .with_temperature(0.2)
+        .with_max_tokens(2048)
+        .with_stop_sequences(vec!["</SimpleStruct>".to_string()])
+        .with_cast_options(CastOptions {
+            extraction: Extraction::Last,
+            repair: true,
+            ..CastOptions::default()
+        });
+    let chat = options.chat_options();
+    assert_eq!(chat.temperature, Some(0.2));
+    assert_eq!(chat.max_tokens, Some(2048));
+    assert_eq!(chat.top_p, Some(0.5));
+    assert_eq!(chat.stop_sequences, vec!["</SimpleStruct>".to_string()]);
+    assert_eq!(options.cast_options().extraction, Extraction::Last);
+    assert!(options.cast_options().repair);
+
+    // the cast options alone still convert
+    let options: GenerateOptions = CastOptions {
+        complete_truncated: true,
+        ..CastOptions::default()
+    }
+    .into();
+    assert!(options.cast_options().complete_truncated);
+    assert_eq!(options.chat_options().temperature, None);
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_retry_backoff_delays() {
+    use llm_xml_caster::{Backoff, GenerateOptions};
+    use std::time::Duration;
+
+    assert_eq!(Backoff::None.delay(3), Duration::ZERO);
+    let fixed = Backoff::Fixed(Duration::from_millis(250));
+    assert_eq!(fixed.delay(0), Duration::from_millis(250));
+    assert_eq!(fixed.delay(5), Duration::from_millis(250));
+
+    // doubles from `initial` up to `max`, less up to half of it as jitter
+    let exponential = Backoff::Exponential {
+        initial: Duration::from_secs(1),
+        max: Duration::from_secs(10),
+    };
+    for (retry, full) in [(0, 1), (1, 2), (2, 4), (3, 8), (4, 10), (40, 10)] {
+        let full = Duration::from_secs(full);
+        let delay = exponential.delay(retry);
+        assert!(
+            delay <= full && delay >= full / 2,
+            "retry {}: {:?}",
+            retry,
+            delay
+        );
+    }
+
+    // transport failures aren't retried unless asked
+    assert_eq!(GenerateOptions::default().transport_retries(), 0);
+    let options = GenerateOptions::new()
+        .with_transport_retries(2)
+        .with_backoff(fixed);
+    assert_eq!(options.transport_retries(), 2);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_generation_without_runtime() {
+    // no model is reachable under this name, so only the error is checked
+    let client = genai::Client::default();
+    let result = llm_xml_caster::generate_as_with_retries_blocking::<SimpleStruct>(
+        &client,
+        "no-such-model",
+        vec![genai::chat::ChatMessage::user("Give me person info")],
+        "<SimpleStruct>...</SimpleStruct>",
+        1,
+    );
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "blocking")]
+#[tokio::test]
+#[should_panic(expected = "can't be called from within an async runtime")]
+async fn test_blocking_generation_inside_runtime_panics() {
+    let client = genai::Client::default();
+    let _ = llm_xml_caster::generate_as_blocking::<SimpleStruct>(
+        &client,
+        "no-such-model",
+        vec![genai::chat::ChatMessage::user("Give me person info")],
+        "<SimpleStruct>...</SimpleStruct>",
+    );
+}
+
+#[test]
+fn test_to_llm_xml_round_trip() {
+    use llm_xml_caster::to_llm_xml;
+
+    fn round_trip<T>(value: &T) -> String
+    where
+        T: Serialize + serde::de::DeserializeOwned + LlmPrompt + PartialEq + std::fmt::Debug,
+    {
+        let xml = to_llm_xml(value).unwrap();
+        assert_eq!(&from_str::<T>(&xml).unwrap(), value, "{}", xml);
+        xml
+    }
+
+    let person = SimpleStruct {
+        name: "Alice ]]> & <Bob>".to_string(),
+        age: 28,
+        is_student: false,
+    };
+    assert_eq!(
+        round_trip(&person),
+        "<SimpleStruct><name><![CDATA[Alice ]]]]><![CDATA[> & <Bob>]]></name><age>28</age><is_student>false</is_student></SimpleStruct>"
+    );
+
+    let complex = ComplexStruct {
+        nested: NestedStruct {
+            person,
+            score: 92.5,
+            state: true,
+        },
+        enum_list: vec![
+            TestEnum::Simple,
+            TestEnum::WithStringData {
+                value: "text".to_string(),
+            },
+            TestEnum::WithFloatData { value: 1.5 },
+            TestEnum::WithIntData { value: -456 },
+        ],
+        optional_float: None,
+    };
+    let xml = round_trip(&complex);
+    assert!(xml.contains("<enum_list><item><Simple/></item><item><WithStringData>"));
+    assert!(!xml.contains("optional_float"));
+
+    round_trip(&CollectionsStruct {
+        tags: vec![],
+        description: Some("Hello World".to_string()),
+    });
+    round_trip(&ThirdStruct {
+        optional_list: Some(vec!["item1".to_string(), "item2".to_string()]),
+    });
+
+    // a struct in an <item> is written inside its root element, unlike one in a field
+    let reply = Comment {
+        text: "second".to_string(),
+        replies: vec![],
+        quoted: None,
+    };
+    let xml = round_trip(&Comment {
+        text: "first".to_string(),
+        replies: vec![Box::new(reply)],
+        quoted: Some(Box::new(Comment {
+            text: "zeroth".to_string(),
+            replies: vec![],
+            quoted: None,
+        })),
+    });
+    assert!(xml.contains("<replies><item><Comment><text>"));
+    assert!(xml.contains("<quoted><text>"));
+
+    // enums are written as their variant element, and maps as <entry> elements
+    assert_eq!(round_trip(&TestEnum::Simple), "<Simple/>");
+    let mut dict = BTreeMap::new();
+    dict.insert(
+        PythonValueWeak(PythonValue::String {
+            val: "key".to_string(),
+        }),
+        PythonValueWeak(PythonValue::List {
+            val: vec![
+                PythonValueWeak(PythonValue::Int { val: 1 }),
+                PythonValueWeak(PythonValue::Float {
+                    val: OrderedFloat(2.5),
+                }),
+                PythonValueWeak(PythonValue::None),
+            ],
+        }),
+    );
+    let xml = round_trip(&PythonValue::Dict { val: dict });
+    assert!(xml.starts_with("<Dict><val><entry><key><String>"));
+    round_trip(&PythonValue::Bool { val: true });
+}
+
+#[test]
+fn test_example_xml() {
+    assert_eq!(
+        SimpleStruct::example_xml(),
+        "<SimpleStruct><name><![CDATA[example]]></name><age>42</age><is_student>true</is_student></SimpleStruct>"
+    );
+    from_str::<SimpleStruct>(&SimpleStruct::example_xml()).unwrap();
+
+    // nested structs, a list of enums written as their first variant, and a present Option
+    let complex: ComplexStruct = from_str(&ComplexStruct::example_xml()).unwrap();
+    assert_eq!(complex.enum_list, vec![TestEnum::Simple, TestEnum::Simple]);
+    assert!(complex.optional_float.is_some());
+    assert_eq!(TestEnum::example_xml(), "<Simple/>");
+    let collections: CollectionsStruct = from_str(&CollectionsStruct::example_xml()).unwrap();
+    assert_eq!(collections.tags.len(), 2);
+
+    // constrained fields use a value that passes the check
+    let review: Review = from_str(&Review::example_xml()).unwrap();
+    assert_eq!(review.sentiment, "positive");
+    let range: RangeStruct = from_str(&RangeStruct::example_xml()).unwrap();
+    assert_eq!((range.retries, range.temperature), (1, Some(-10)));
+
+    // recursive types are cut off once the depth runs out
+    let comment: Comment = from_str(&Comment::example_xml()).unwrap();
+    assert_eq!(comment.replies.len(), 2);
+    let expr: Expr = from_str(&Expr::example_xml()).unwrap();
+    assert!(matches!(expr, Expr::Num { value: 42 }));
+    from_str::<PythonValue>(&PythonValue::example_xml()).unwrap();
+}
+
+#[cfg(feature = "client")]
+const ALICE_XML: &str =
+    "<SimpleStruct><name>Alice</name><age>28</age><is_student>false</is_student></SimpleStruct>";
+
+#[cfg(feature = "client")]
+fn alice() -> SimpleStruct {
+    SimpleStruct {
+        name: "Alice".to_string(),
+        age: 28,
+        is_student: false,
+    }
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_retry_loop_with_mock_backend() {
+    use llm_xml_caster::{Error, generate_as_with_retries, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+
+    // a response without the root element is corrected by the next attempt
+    let backend = MockLlm::new(["I don't know", ALICE_XML]);
+    let person: SimpleStruct = generate_as_with_retries(&backend, "mock", prompt(), "", 3)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    assert_eq!(backend.calls(), 2);
+
+    let backend = MockLlm::new([
+        "I don't know",
+        "<SimpleStruct><age>old</age></SimpleStruct>",
+        ALICE_XML,
+    ]);
+    match generate_as_with_retries::<SimpleStruct>(&backend, "mock", prompt(), "", 2).await {
+        Err(Error::RetryLimitExceeded(errs)) => {
+            assert!(matches!(errs[0].error, Error::XmlExtraction(_)));
+            assert!(matches!(errs[1].error, Error::XmlDeserialization(_)));
+            assert_eq!(errs.len(), 2);
+        }
+        other => panic!("expected the retry limit to be exceeded, got {:?}", other),
+    }
+    assert_eq!(backend.remaining(), 1);
+
+    // an exhausted mock fails like a request that didn't reach the model
+    let backend = MockLlm::new(Vec::<String>::new());
+    let result = generate_as_with_retries::<SimpleStruct>(&backend, "mock", prompt(), "", 3).await;
+    assert!(matches!(result, Err(Error::Backend(_))));
+    assert_eq!(backend.calls(), 1);
+
+    // `MockBackend` is the same backend
+    let backend = llm_xml_caster::testing::MockBackend::new([ALICE_XML]);
+    let person: SimpleStruct = generate_as_with_retries(&backend, "mock", prompt(), "", 1)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_generation_options_with_mock_backend() {
+    use llm_xml_caster::{
+        AttemptOutcome, BackendResponse, Backoff, Error, GenerateOptions, generate_as_best_of,
+        generate_as_best_of_with_options, generate_as_detailed, testing::MockLlm,
+    };
+    use std::ops::ControlFlow;
+    use std::sync::{Arc, Mutex};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+
+    let backend = MockLlm::new(["I don't know"]).with_response(BackendResponse {
+        model_name: Some("mock-1".to_string()),
+        ..BackendResponse::from_text(format!("Sure: {}", ALICE_XML))
+    });
+    let result = generate_as_detailed::<SimpleStruct>(
+        &backend,
+        "mock",
+        prompt(),
+        "",
+        3,
+        GenerateOptions::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.value, alice());
+    assert_eq!(result.text, format!("Sure: {}", ALICE_XML));
+    assert_eq!(result.xml, ALICE_XML);
+    assert_eq!(result.attempts, 2);
+    assert!(matches!(result.errors[..], [Error::XmlExtraction(_)]));
+    assert_eq!(result.model_name.as_deref(), Some("mock-1"));
+
+    // the callback sees every attempt, and breaking stops without retrying
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let options = GenerateOptions::new().with_on_attempt({
+        let seen = seen.clone();
+        move |attempt| {
+            let failed = !matches!(attempt.outcome, AttemptOutcome::Ok);
+            seen.lock().unwrap().push((attempt.number, failed));
+            match attempt.outcome {
+                AttemptOutcome::Deserialization(_) => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        }
+    });
+    let backend = MockLlm::new([
+        "I don't know",
+        "<SimpleStruct><age>old</age></SimpleStruct>",
+        ALICE_XML,
+    ]);
+    let result =
+        generate_as_detailed::<SimpleStruct>(&backend, "mock", prompt(), "", 3, options).await;
+    assert!(matches!(result, Err(Error::RetryLimitExceeded(errs)) if errs.len() == 2));
+    assert_eq!(*seen.lock().unwrap(), [(1, true), (2, true)]);
+    assert_eq!(backend.remaining(), 1);
+
+    // transport failures are retried apart from the attempts
+    let backend = MockLlm::new(Vec::<String>::new());
+    let options = GenerateOptions::new().with_transport_retries(2);
+    let result =
+        generate_as_detailed::<SimpleStruct>(&backend, "mock", prompt(), "", 3, options).await;
+    assert!(matches!(result, Err(Error::RetryLimitExceeded(errs)) if errs.len() == 3));
+    assert_eq!(backend.calls(), 3);
+
+    // best-of returns the candidate that casts
+    let backend = MockLlm::new(["I don't know", ALICE_XML]);
+    let person: SimpleStruct = generate_as_best_of(&backend, "mock", prompt(), "", 2)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    assert_eq!(backend.calls(), 2);
+
+    // a candidate gets the transport retries, and the fallback sends the same request
+    let backend = MockLlm::default()
+        .with_error(Error::Backend("503 Service Unavailable".into()))
+        .with_text(ALICE_XML);
+    let options = GenerateOptions::new()
+        .with_transport_retries(1)
+        .with_backoff(Backoff::None);
+    let person: SimpleStruct =
+        generate_as_best_of_with_options(&backend, "mock", prompt(), "", 1, 3, options)
+            .await
+            .unwrap();
+    assert_eq!(person, alice());
+    let backend = MockLlm::new(["I don't know", ALICE_XML]);
+    let person: SimpleStruct = generate_as_best_of_with_options(
+        &backend,
+        "mock",
+        prompt(),
+        "",
+        1,
+        3,
+        GenerateOptions::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(person, alice());
+    let requests = backend.requests();
+    assert_eq!(requests[1].request.system, requests[0].request.system);
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_mock_llm_script_and_requests() {
+    use llm_xml_caster::{
+        Backoff, Error, GenerateOptions, HttpStatusError, generate_as_detailed,
+        generate_as_with_options, testing::MockLlm,
+    };
+    use std::time::{Duration, Instant};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+
+    // the retry sees the failed response's error, with the options of the first request
+    let llm = MockLlm::new(["<SimpleStruct><age>old</age></SimpleStruct>", ALICE_XML]);
+    let options = GenerateOptions::new().with_temperature(0.3);
+    let person: SimpleStruct = generate_as_with_options(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    let requests = llm.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].model_name, "mock");
+    assert_eq!(requests[1].options.temperature, Some(0.3));
+    let first = &requests[0].request.messages;
+    let retry = &requests[1].request.messages;
+    assert!(retry.len() > first.len());
+    let correction = format!("{:?}", &retry[first.len()..]);
+    assert!(correction.contains("<age>old</age>"), "{}", correction);
+
+    // an injected transport error is retried after the backoff, and latencies are waited out
+    let llm = MockLlm::default()
+        .with_error(Error::Backend("503 Service Unavailable".into()))
+        .with_text(ALICE_XML)
+        .with_latency(Duration::from_millis(20));
+    let options = GenerateOptions::new()
+        .with_transport_retries(1)
+        .with_backoff(Backoff::Fixed(Duration::from_millis(10)));
+    let start = Instant::now();
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    assert_eq!(result.value, alice());
+    assert_eq!(result.attempts, 1);
+    assert!(matches!(result.errors[..], [Error::Backend(_)]));
+    assert_eq!(llm.calls(), 2);
+
+    // a request the provider rejects is returned at once, without waiting
+    let llm = MockLlm::default()
+        .with_error(Error::Backend(Box::new(HttpStatusError::new(400))))
+        .with_text(ALICE_XML);
+    let options = GenerateOptions::new()
+        .with_transport_retries(3)
+        .with_backoff(Backoff::Fixed(Duration::from_secs(60)));
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "mock", prompt(), "", 3, options).await;
+    assert!(matches!(result, Err(Error::Backend(_))));
+    assert_eq!(llm.calls(), 1);
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_correction_policies() {
+    use llm_xml_caster::{
+        CorrectionPolicy, GenerateOptions, generate_as_with_options, testing::MockLlm,
+    };
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let bad_age = "<SimpleStruct><name>Alice</name><age>old</age><is_student>false</is_student></SimpleStruct>";
+    let missing_fields = "<SimpleStruct><name>Alice</name></SimpleStruct>";
+    // the messages each retry added, debug-printed
+    let added_messages = |llm: &MockLlm| {
+        let requests = llm.requests();
+        requests
+            .windows(2)
+            .map(|pair| {
+                let (before, after) = (&pair[0].request.messages, &pair[1].request.messages);
+                format!("{:?}", &after[before.len()..])
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // by default the schema is resent on the first correction and for structural errors only
+    let llm = MockLlm::new(["I don't know", bad_age, missing_fields, ALICE_XML]);
+    let person: SimpleStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "", 4, GenerateOptions::new())
+            .await
+            .unwrap();
+    assert_eq!(person, alice());
+    let counts: Vec<usize> = llm
+        .requests()
+        .iter()
+        .map(|r| r.request.messages.len())
+        .collect();
+    let base = counts[0];
+    assert_eq!(counts, [base, base + 2, base + 3, base + 5]);
+    let corrections = added_messages(&llm);
+    assert!(
+        corrections[0].contains("The format body is") && corrections[0].contains("valid example")
+    );
+    assert!(corrections[1].contains("<age>old</age>"));
+    assert!(
+        !corrections[1].contains("The format body is") && !corrections[1].contains("valid example")
+    );
+    assert!(
+        corrections[2].contains("missing field") && corrections[2].contains("The format body is")
+    );
+
+    let llm = MockLlm::new(["I don't know", bad_age, ALICE_XML]);
+    let options = GenerateOptions::new()
+        .with_correction(CorrectionPolicy::AlwaysResend)
+        .with_quote_limit(20);
+    let _: SimpleStruct = generate_as_with_options(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    let counts: Vec<usize> = llm
+        .requests()
+        .iter()
+        .map(|r| r.request.messages.len())
+        .collect();
+    assert_eq!(counts, [base, base + 2, base + 4]);
+    let corrections = added_messages(&llm);
+    assert!(
+        corrections[1].contains("The format body is") && corrections[1].contains("valid example")
+    );
+    // the offending XML is cut off after 20 characters
+    assert!(corrections[1].contains("<SimpleStruct><name>... (71 more characters)"));
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_correction_message_and_role() {
+    use genai::chat::ChatRole;
+    use llm_xml_caster::{
+        CorrectionRole, GenerateOptions, generate_as_with_options, testing::MockLlm,
+    };
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Gib mir Personendaten")];
+    let bad_age = "<SimpleStruct><name>Alice</name><age>old</age><is_student>false</is_student></SimpleStruct>";
+    let llm = MockLlm::new(["Keine Ahnung", bad_age, ALICE_XML]);
+    let options = GenerateOptions::new()
+        .with_correction_role(CorrectionRole::User)
+        .with_correction_message(|correction| {
+            format!(
+                "Fehler: {}\nXML: {}\nSchema gesendet: {}\nBeispiel: {}",
+                correction.error,
+                correction.xml.unwrap_or("-"),
+                correction.schema.is_some(),
+                correction.example.unwrap_or("-"),
+            )
+        });
+    let person: SimpleStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "<SimpleStruct/>", 3, options)
+            .await
+            .unwrap();
+    assert_eq!(person, alice());
+
+    let requests = llm.requests();
+    let base = requests[0].request.messages.len();
+    // one message per correction, in the user role
+    let extraction = &requests[1].request.messages[base..];
+    assert_eq!(extraction.len(), 1);
+    assert!(matches!(extraction[0].role, ChatRole::User));
+    let rendered = format!("{:?}", extraction[0].content);
+    assert!(rendered.contains("Fehler: "), "{}", rendered);
+    assert!(rendered.contains("XML: -"));
+    assert!(rendered.contains("Schema gesendet: true"));
+    assert!(rendered.contains("Beispiel: <SimpleStruct/>"));
+
+    let parse = &requests[2].request.messages[base + 1..];
+    assert_eq!(parse.len(), 1);
+    assert!(matches!(parse[0].role, ChatRole::User));
+    let rendered = format!("{:?}", parse[0].content);
+    assert!(rendered.contains("XML: <SimpleStruct><name>Alice</name><age>old</age>"));
+    assert!(rendered.contains("Schema gesendet: false"));
+    assert!(rendered.contains("Beispiel: -"));
+
+    // by default both messages are the assistant's
+    let llm = MockLlm::new(["Keine Ahnung", ALICE_XML]);
+    let _: SimpleStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "", 3, GenerateOptions::new())
+            .await
+            .unwrap();
+    let added = &llm.requests()[1].request.messages[base..];
+    assert_eq!(added.len(), 2);
+    assert!(
+        added
+            .iter()
+            .all(|message| matches!(message.role, ChatRole::Assistant))
+    );
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_generate_as_with_validator() {
+    use llm_xml_caster::{Error, GenerateOptions, generate_as_with_validator, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me an adult's info")];
+    let minor =
+        "<SimpleStruct><name>Tom</name><age>12</age><is_student>true</is_student></SimpleStruct>";
+    let adult = |person: &SimpleStruct| match person.age >= 18 {
+        true => Ok(()),
+        false => Err(format!("{} is {}, not an adult", person.name, person.age)),
+    };
+
+    // the rejection is sent back as the correction, and the next response passes
+    let llm = MockLlm::new([minor, ALICE_XML]);
+    let person: SimpleStruct =
+        generate_as_with_validator(&llm, "mock", prompt(), "", 3, GenerateOptions::new(), adult)
+            .await
+            .unwrap();
+    assert_eq!(person, alice());
+    let requests = llm.requests();
+    let base = requests[0].request.messages.len();
+    let correction = format!("{:?}", &requests[1].request.messages[base..]);
+    assert!(
+        correction.contains("Tom is 12, not an adult"),
+        "{}",
+        correction
+    );
+
+    // responses that parse but never pass are told apart from ones that never parse
+    let llm = MockLlm::new(["I don't know", minor, minor]);
+    let result =
+        generate_as_with_validator(&llm, "mock", prompt(), "", 3, GenerateOptions::new(), adult)
+            .await;
+    match result {
+        Err(Error::ValidationLimitExceeded(errs)) => {
+            assert!(matches!(
+                errs[..],
+                [
+                    Error::XmlExtraction(_),
+                    Error::Validation(_),
+                    Error::Validation(_)
+                ]
+            ));
+        }
+        other => panic!(
+            "expected the validation limit to be exceeded, got {:?}",
+            other
+        ),
+    }
+    let llm = MockLlm::new(["I don't know", "I still don't know"]);
+    let result =
+        generate_as_with_validator(&llm, "mock", prompt(), "", 2, GenerateOptions::new(), adult)
+            .await;
+    assert!(matches!(result, Err(Error::RetryLimitExceeded(_))));
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_escalating_attempts() {
+    use llm_xml_caster::{GenerateOptions, generate_as_detailed, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let llm = MockLlm::new(["I don't know", "I don't know", "I don't know", ALICE_XML]);
+    let options = GenerateOptions::new()
+        .with_attempt_temperatures(vec![0.1, 0.4, 0.7])
+        .with_fallback_models(vec!["flash".to_string(), "pro".to_string()]);
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "lite", prompt(), "", 4, options)
+        .await
+        .unwrap();
+
+    let requests = llm.requests();
+    let models: Vec<&str> = requests.iter().map(|r| r.model_name.as_str()).collect();
+    assert_eq!(models, ["lite", "flash", "pro", "pro"]);
+    let temperatures: Vec<Option<f64>> = requests.iter().map(|r| r.options.temperature).collect();
+    assert_eq!(temperatures, [Some(0.1), Some(0.4), Some(0.7), Some(0.7)]);
+    assert_eq!(result.requested_model, "pro");
+    assert_eq!(result.temperature, Some(0.7));
+
+    // without escalation every attempt is the same
+    let llm = MockLlm::new(["I don't know", ALICE_XML]);
+    let options = GenerateOptions::new().with_temperature(0.2);
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "lite", prompt(), "", 2, options)
+        .await
+        .unwrap();
+    assert!(llm.requests().iter().all(|r| r.model_name == "lite"));
+    assert_eq!(
+        (result.requested_model.as_str(), result.temperature),
+        ("lite", Some(0.2))
+    );
+}
+
+/// Answers `Person <n>` prompts with a person aged `n`, slower for lower `n`, and never for
+/// `Person 2`, tracking how many requests are in flight at once.
+#[cfg(feature = "client")]
+#[derive(Default)]
+struct PeopleBackend {
+    in_flight: std::sync::atomic::AtomicUsize,
+    max_in_flight: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(feature = "client")]
+impl llm_xml_caster::LlmBackend for PeopleBackend {
+    async fn chat(
+        &self,
+        _model_name: &str,
+        request: &genai::chat::ChatRequest,
+        _options: &genai::chat::ChatOptions,
+    ) -> llm_xml_caster::Result<llm_xml_caster::BackendResponse> {
+        use std::sync::atomic::Ordering;
+
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+        let messages = format!("{:?}", request.messages);
+        let start = messages.find("Person ").unwrap() + "Person ".len();
+        let n: u64 = messages[start..start + 1].parse().unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5 * (6 - n))).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(llm_xml_caster::BackendResponse::from_text(match n {
+            2 => "I don't know".to_string(),
+            n => format!(
+                "<SimpleStruct><name>Person {n}</name><age>{n}</age><is_student>false</is_student></SimpleStruct>"
+            ),
+        }))
+    }
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_generate_many_as() {
+    use futures::StreamExt;
+    use llm_xml_caster::{Error, GenerateOptions, generate_many_as, generate_many_as_stream};
+    use std::sync::atomic::Ordering;
+
+    let prompts = || {
+        (0..6)
+            .map(|n| vec![genai::chat::ChatMessage::user(format!("Person {n}"))])
+            .collect::<Vec<_>>()
+    };
+
+    let backend = PeopleBackend::default();
+    let results = generate_many_as::<SimpleStruct>(&backend, "mock", prompts(), "", 3, 2).await;
+    assert_eq!(results.len(), 6);
+    for (n, result) in results.iter().enumerate() {
+        match n {
+            2 => assert!(matches!(result, Err(Error::RetryLimitExceeded(errs)) if errs.len() == 2)),
+            n => assert_eq!(result.as_ref().unwrap().age, n as i32),
+        }
+    }
+    assert_eq!(backend.max_in_flight.load(Ordering::SeqCst), 3);
+
+    // the stream yields the results as they finish, with the index of their prompt
+    let backend = PeopleBackend::default();
+    let finished: Vec<usize> = generate_many_as_stream::<SimpleStruct>(
+        &backend,
+        "mock",
+        prompts(),
+        "",
+        6,
+        1,
+        GenerateOptions::new(),
+    )
+    .map(|(index, _)| index)
+    .collect()
+    .await;
+    assert_eq!(finished, [5, 4, 3, 2, 1, 0]);
+    assert_eq!(backend.max_in_flight.load(Ordering::SeqCst), 6);
+}
+
+#[test]
+fn test_top_level_container_and_primitive_targets() {
+    use llm_xml_caster::{cast_from_str, to_llm_xml};
+
+    let people = "Here they are:\n<response><item><SimpleStruct><name>Alice</name><age>28</age><is_student>false</is_student></SimpleStruct></item><item><SimpleStruct><name>Bob</name><age>19</age><is_student>yes</is_student></SimpleStruct></item></response>";
+    let people: Vec<SimpleStruct> = cast_from_str(people).unwrap();
+    assert_eq!(people.len(), 2);
+    assert_eq!(people[1].name, "Bob");
+    assert!(people[1].is_student);
+    assert_eq!(
+        cast_from_str::<Vec<SimpleStruct>>("<response/>").unwrap(),
+        []
+    );
+
+    // primitives go through the lenient parsers of the fields
+    assert!(cast_from_str::<bool>("The answer is <response> yes </response>").unwrap());
+    assert!(!cast_from_str::<bool>("<response>No</response>").unwrap());
+    assert_eq!(
+        cast_from_str::<u32>("<response>\"42\"</response>").unwrap(),
+        42
+    );
+    assert_eq!(
+        cast_from_str::<String>("<response><![CDATA[ hi ]]></response>").unwrap(),
+        "hi"
+    );
+    assert!(matches!(
+        cast_from_str::<bool>("<bool>true</bool>"),
+        Err(llm_xml_caster::Error::XmlExtraction(_))
+    ));
+
+    // options, maps and nested containers
+    assert_eq!(cast_from_str::<Option<i32>>("<response/>").unwrap(), None);
+    assert_eq!(
+        cast_from_str::<Option<i32>>("<response>7</response>").unwrap(),
+        Some(7)
+    );
+    let scores: HashMap<String, Vec<u8>> = cast_from_str(
+        "<response><entry><key>Alice</key><value><item>1</item><item>2</item></value></entry></response>",
+    )
+    .unwrap();
+    assert_eq!(scores["Alice"], [1, 2]);
+    let pair: (bool, String) =
+        cast_from_str("<response><first>on</first><second>x</second></response>").unwrap();
+    assert_eq!(pair, (true, "x".to_string()));
+
+    // what is written for the type is read back, and the example has the same shape
+    let xml = to_llm_xml(&people).unwrap();
+    assert!(
+        xml.starts_with("<response><item><SimpleStruct><name>"),
+        "{}",
+        xml
+    );
+    assert_eq!(cast_from_str::<Vec<SimpleStruct>>(&xml).unwrap(), people);
+    assert_eq!(to_llm_xml(&true).unwrap(), "<response>true</response>");
+    let example = Vec::<SimpleStruct>::example_xml();
+    assert!(
+        example.starts_with("<response><item><SimpleStruct>"),
+        "{}",
+        example
+    );
+    assert_eq!(
+        cast_from_str::<Vec<SimpleStruct>>(&example).unwrap().len(),
+        2
+    );
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_top_level_targets_with_mock_backend() {
+    use llm_xml_caster::{generate_as, generate_as_with_retries, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Who is in the class?")];
+
+    let llm = MockLlm::new([
+        "<Vec<SimpleStruct>><item>Alice</item></Vec<SimpleStruct>>",
+        &format!("<response><item>{}</item></response>", ALICE_XML),
+    ]);
+    let people: Vec<SimpleStruct> = generate_as_with_retries(&llm, "mock", prompt(), "", 3)
+        .await
+        .unwrap();
+    assert_eq!(people, [alice()]);
+    let requests = llm.requests();
+    let system = format!("{:?}", requests[0].request.messages);
+    assert!(system.contains("root name is response"), "{}", system);
+    assert!(system.contains("<response>A series"), "{}", system);
+    let retry = format!("{:?}", requests[1].request.messages);
+    assert!(retry.contains("cannot find the root response"), "{}", retry);
+
+    let llm = MockLlm::new(["Sure.\n<response>Yes</response>"]);
+    let enrolled: bool = generate_as(&llm, "mock", prompt(), "").await.unwrap();
+    assert!(enrolled);
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_prompt_forms() {
+    use genai::chat::{ChatMessage, ChatRequest};
+    use llm_xml_caster::{generate_as, generate_from_text, testing::MockLlm};
+
+    let schema_messages = |llm: &MockLlm| {
+        llm.requests()
+            .iter()
+            .map(|request| {
+                format!("{:?}", request.request)
+                    .matches("You must respond with a valid XML document")
+                    .count()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // the same base conversation is borrowed by every call
+    let base = vec![
+        ChatMessage::system("You read class lists."),
+        ChatMessage::user("Give me person info"),
+    ];
+    let llm = MockLlm::new([ALICE_XML, ALICE_XML, ALICE_XML]);
+    let first: SimpleStruct = generate_as(&llm, "mock", &base, "").await.unwrap();
+    let second: SimpleStruct = generate_as(&llm, "mock", base.as_slice(), "")
+        .await
+        .unwrap();
+    let request = ChatRequest::new(base.clone());
+    let third: SimpleStruct = generate_as(&llm, "mock", &request, "").await.unwrap();
+    assert_eq!([first, second, third], [alice(), alice(), alice()]);
+    assert_eq!(base.len(), 2);
+    assert_eq!(schema_messages(&llm), [1, 1, 1]);
+    let requests = llm.requests();
+    assert_eq!(requests[0].request.messages.len(), 3);
+    assert!(format!("{:?}", requests[1].request.messages[1]).contains("Give me person info"));
+
+    let llm = MockLlm::new(["<SimpleStruct><age>old</age></SimpleStruct>", ALICE_XML]);
+    let person: SimpleStruct = generate_from_text(&llm, "mock", "Who is Alice?", "")
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    // the retry keeps the one schema message of the first request
+    assert_eq!(schema_messages(&llm), [1, 1]);
+    let first = format!("{:?}", llm.requests()[0].request.messages);
+    assert!(first.contains("Who is Alice?"), "{}", first);
+    assert_eq!(llm.requests()[0].request.messages.len(), 2);
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_multi_part_and_empty_responses() {
+    use llm_xml_caster::{
+        AttemptFailure, BackendResponse, Error, generate_as_with_retries, testing::MockLlm,
+    };
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+
+    // the closing tag is only in the last part
+    let parts = BackendResponse::from_texts([
+        "Here it is:\n<SimpleStruct><name>Alice</name>",
+        "<age>28</age><is_student>false</is_student>",
+        "</SimpleStruct>",
+    ]);
+    let llm = MockLlm::default().with_response(parts);
+    let person: SimpleStruct = generate_as_with_retries(&llm, "mock", prompt(), "", 1)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    assert_eq!(BackendResponse::from_texts(Vec::<String>::new()).text, None);
+
+    // a response without text is recorded, and the request is sent again unchanged
+    let llm = MockLlm::default()
+        .with_response(BackendResponse::from_texts(Vec::<&str>::new()))
+        .with_text(ALICE_XML);
+    let person: SimpleStruct = generate_as_with_retries(&llm, "mock", prompt(), "", 2)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    let requests = llm.requests();
+    assert_eq!(
+        requests[0].request.messages.len(),
+        requests[1].request.messages.len()
+    );
+
+    let llm = MockLlm::default().with_response(BackendResponse::default());
+    let result = generate_as_with_retries::<SimpleStruct>(&llm, "mock", prompt(), "", 1).await;
+    assert!(
+        matches!(&result, Err(Error::RetryLimitExceeded(errs)) if matches!(errs[..], [AttemptFailure { error: Error::EmptyResponse, .. }])),
+        "{:?}",
+        result
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_fallback() {
+    use llm_xml_caster::{CastOptions, Error, cast_from_str, cast_from_str_with};
+
+    let fallback = CastOptions {
+        json_fallback: true,
+        ..CastOptions::default()
+    };
+    let json = "Sure! Here is the person:\n```json\n{\"name\": \"Alice\", \"age\": 28, \"is_student\": false}\n```";
+    let person: SimpleStruct = cast_from_str_with(json, fallback).unwrap();
+    assert_eq!(person.name, "Alice");
+    assert_eq!(person.age, 28);
+    // opt-in only
+    assert!(matches!(
+        cast_from_str::<SimpleStruct>(json),
+        Err(Error::XmlExtraction(_))
+    ));
+
+    // an object that doesn't match the struct falls through to the extraction error
+    let other = "{\"person\": {\"full_name\": \"Alice\"}, \"count\": 1}";
+    assert!(matches!(
+        cast_from_str_with::<SimpleStruct>(other, fallback),
+        Err(Error::XmlExtraction(_))
+    ));
+
+    // XML wins over JSON in the same response
+    let both = format!(
+        "{{\"name\": \"Bob\", \"age\": 40, \"is_student\": true}}\n{}",
+        "<SimpleStruct><name>Alice</name><age>28</age><is_student>false</is_student></SimpleStruct>"
+    );
+    let person: SimpleStruct = cast_from_str_with(&both, fallback).unwrap();
+    assert_eq!(person.name, "Alice");
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_continue_truncated_responses() {
+    use genai::chat::StopReason;
+    use llm_xml_caster::{
+        BackendResponse, GenerateOptions, generate_as_detailed, generate_as_with_options,
+        testing::MockLlm,
+    };
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let truncated = |text: &str| BackendResponse {
+        stop_reason: Some(StopReason::MaxTokens("length".to_string())),
+        ..BackendResponse::from_text(text)
+    };
+    let options = || GenerateOptions::new().with_max_continuations(2);
+
+    // cut off inside a CDATA section
+    let llm = MockLlm::default()
+        .with_response(truncated("<SimpleStruct><name><![CDATA[Ali"))
+        .with_text("ce]]></name><age>28</age><is_student>false</is_student></SimpleStruct>");
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "mock", prompt(), "", 1, options())
+        .await
+        .unwrap();
+    assert_eq!(result.value, alice());
+    assert_eq!(result.attempts, 1);
+    let requests = llm.requests();
+    let base = requests[0].request.messages.len();
+    let continuation = format!("{:?}", &requests[1].request.messages[base..]);
+    assert!(
+        continuation.contains("Continue exactly where you left off"),
+        "{}",
+        continuation
+    );
+    assert!(continuation.contains("<![CDATA[Ali"), "{}", continuation);
+
+    // cut off inside a tag name, twice, with the model repeating the end of the first part
+    let llm = MockLlm::default()
+        .with_response(truncated("<SimpleStruct><name>Alice</name><a"))
+        .with_response(truncated("ge>28</age><is_stu"))
+        .with_text("e>28</age><is_student>false</is_student></SimpleStruct>");
+    let person: SimpleStruct = generate_as_with_options(&llm, "mock", prompt(), "", 1, options())
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    assert_eq!(llm.calls(), 3);
+
+    // the continuations are capped, after which the response fails like any other
+    let llm = MockLlm::default()
+        .with_response(truncated("<SimpleStruct><name>Alice</name><a"))
+        .with_response(truncated("ge>28</age><is_stu"))
+        .with_response(truncated("dent>false</is_stu"))
+        .with_text(ALICE_XML);
+    let person: SimpleStruct = generate_as_with_options(&llm, "mock", prompt(), "", 2, options())
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    assert_eq!(llm.calls(), 4);
+
+    // off by default
+    let llm = MockLlm::default()
+        .with_response(truncated("<SimpleStruct><name>Alice</name><a"))
+        .with_text(ALICE_XML);
+    let person: SimpleStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "", 2, GenerateOptions::new())
+            .await
+            .unwrap();
+    assert_eq!(person, alice());
+    assert!(!format!("{:?}", llm.requests()[1].request).contains("Continue exactly"));
+}
+
+#[test]
+fn test_validate_xml() {
+    use llm_xml_caster::{IssueKind, validate_xml};
+
+    // documents that cast are valid
+    let nested = "<NestedStruct><person><name><![CDATA[Jane]]></name><age>25</age><is_student>no</is_student></person><score>85.5</score><state>true</state></NestedStruct>";
+    assert!(from_str::<NestedStruct>(nested).is_ok());
+    assert!(validate_xml::<NestedStruct>(nested).is_valid());
+    let collections = "<CollectionsStruct><tags><item><![CDATA[a]]></item></tags><description/></CollectionsStruct>";
+    assert!(validate_xml::<CollectionsStruct>(collections).is_valid());
+    assert!(
+        validate_xml::<TestEnum>("<WithFloatData><value>1.5</value></WithFloatData>").is_valid()
+    );
+    assert!(validate_xml::<Vec<SimpleStruct>>(
+        "<response><item><SimpleStruct><name>A</name><age>1</age><is_student>true</is_student></SimpleStruct></item></response>"
+    )
+    .is_valid());
+
+    // three problems, all reported in document order
+    let xml = "<NestedStruct><person><name>Jane</name><age>old</age><is_student>no</is_student><email>j@x.io</email></person><score>85.5</score></NestedStruct>";
+    let report = validate_xml::<NestedStruct>(xml);
+    println!("{}", report);
+    let found: Vec<(IssueKind, &str)> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.kind, issue.path.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (IssueKind::InvalidValue, "/NestedStruct/person/age"),
+            (IssueKind::UnknownElement, "/NestedStruct/person/email"),
+            (IssueKind::MissingElement, "/NestedStruct/state"),
+        ]
+    );
+    assert_eq!(report.issues[0].offset, xml.find("<age>").unwrap());
+    assert_eq!(report.issues[1].offset, xml.find("<email>").unwrap());
+    assert_eq!(report.issues[2].offset, 0);
+    assert!(report.issues[0].message.contains("can not parse 'old'"));
+
+    // constraints of the fields, inside sequences and maps too
+    let report = validate_xml::<TaggedArticle>(
+        "<TaggedArticle><tags><item>a</item><item>b</item><item>c</item><item>d</item></tags><related><item>1</item><item>x</item></related></TaggedArticle>",
+    );
+    let found: Vec<&str> = report.issues.iter().map(|i| i.path.as_str()).collect();
+    assert_eq!(
+        found,
+        ["/TaggedArticle/tags", "/TaggedArticle/related/item[2]"]
+    );
+    let report = validate_xml::<HashMap<String, u8>>(
+        "<response><entry><key>a</key><value>300</value></entry><entry><key>b</key></entry></response>",
+    );
+    let found: Vec<(IssueKind, &str)> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.kind, issue.path.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (IssueKind::InvalidValue, "/response/entry[1]/value"),
+            (IssueKind::MissingElement, "/response/entry[2]/value"),
+        ]
+    );
+
+    let report = validate_xml::<TestEnum>("<Unknown/>");
+    assert_eq!(report.issues[0].kind, IssueKind::UnknownElement);
+    let report = validate_xml::<SimpleStruct>("<SimpleStruct><name>A</name>");
+    assert_eq!(report.issues[0].kind, IssueKind::Malformed);
+}
+
+#[test]
+fn test_schema_tree() {
+    use llm_xml_caster::{LeafKind, SchemaNode};
+
+    let leaf_kind = |node: SchemaNode| match node {
+        SchemaNode::Leaf { kind, .. } => kind,
+        other => panic!("expected a leaf, got {:?}", other),
+    };
+
+    let SchemaNode::Struct { root, fields } = SimpleStruct::schema_node() else {
+        panic!("SimpleStruct is a struct");
+    };
+    assert_eq!(root, "SimpleStruct");
+    let shape: Vec<(&str, &str, bool, LeafKind)> = fields
+        .iter()
+        .map(|f| (f.tag, f.description, f.required, leaf_kind((f.node)())))
+        .collect();
+    assert_eq!(
+        shape,
+        [
+            ("name", "The name of the person", true, LeafKind::String),
+            ("age", "The age of the person", true, LeafKind::Integer),
+            (
+                "is_student",
+                "Whether the person is a student",
+                true,
+                LeafKind::Bool
+            ),
+        ]
+    );
+
+    let SchemaNode::Enum { name, variants } = TestEnum::schema_node() else {
+        panic!("TestEnum is an enum");
+    };
+    assert_eq!(name, "TestEnum");
+    let shape: Vec<(&str, &str, Vec<LeafKind>)> = variants
+        .iter()
+        .map(|v| {
+            let kinds = v.fields.iter().map(|f| leaf_kind((f.node)())).collect();
+            (v.tag, v.description, kinds)
+        })
+        .collect();
+    assert_eq!(
+        shape,
+        [
+            ("Simple", "A simple variant", vec![]),
+            (
+                "WithStringData",
+                "A variant with data string",
+                vec![LeafKind::String]
+            ),
+            (
+                "WithFloatData",
+                "A variant with data float",
+                vec![LeafKind::Float]
+            ),
+            (
+                "WithIntData",
+                "A variant with data int",
+                vec![LeafKind::Integer]
+            ),
+        ]
+    );
+
+    let SchemaNode::Struct { root, fields } = ComplexStruct::schema_node() else {
+        panic!("ComplexStruct is a struct");
+    };
+    assert_eq!(root, "ComplexStruct");
+    let tags: Vec<&str> = fields.iter().map(|f| f.tag).collect();
+    assert_eq!(tags, ["nested", "enum_list", "optional_float"]);
+    assert!(matches!(
+        (fields[0].node)(),
+        SchemaNode::Struct {
+            root: "NestedStruct",
+            ..
+        }
+    ));
+    let SchemaNode::Sequence {
+        item,
+        unique,
+        ordered,
+    } = (fields[1].node)()
+    else {
+        panic!("enum_list is a sequence");
+    };
+    assert!(!unique && ordered);
+    assert!(matches!(
+        item(),
+        SchemaNode::Enum {
+            name: "TestEnum",
+            ..
+        }
+    ));
+    assert!(!fields[2].required);
+    let SchemaNode::Optional(inner) = (fields[2].node)() else {
+        panic!("optional_float is optional");
+    };
+    assert_eq!(leaf_kind(inner()), LeafKind::Float);
+
+    // the prompt schema is rendered from the tree
+    assert_eq!(
+        SimpleStruct::schema_node().render(),
+        "<SimpleStruct>\n  <name>\n  return a string value. please use the format <![CDATA[{actual string content without any escaping}]]> to return the string content. Note that the CDATA tags must be exactly in this format, otherwise the parsing will fail. If you need to return an empty string, please return <![CDATA[]]>\n</name> <!-- The name of the person -->\n  <age>\n  integer value, a whole number without a fractional part, e.g., 42, -7, or 0\n</age> <!-- The age of the person -->\n  <is_student>\n  it is a boolean value, either `true` or `false`\n</is_student> <!-- Whether the person is a student -->\n</SimpleStruct>"
+    );
+    assert_eq!(
+        SimpleStruct::schema_node().render(),
+        SimpleStruct::get_prompt_schema()
+    );
+    assert_eq!(
+        SimpleStruct::schema_node().render_fields(),
+        SimpleStruct::get_fields_schema()
+    );
+    assert_eq!(
+        TestEnum::schema_node().render(),
+        TestEnum::get_prompt_schema()
+    );
+    assert_eq!(
+        ComplexStruct::schema_node().render(),
+        ComplexStruct::get_prompt_schema()
+    );
+    assert_eq!(
+        Option::<TestEnum>::schema_node().render_item(),
+        Option::<TestEnum>::get_item_schema()
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_schema() {
+    use serde_json::{Value, json};
+
+    assert_eq!(
+        SimpleStruct::json_schema(),
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "description": "The name of the person" },
+                "age": { "type": "integer", "description": "The age of the person" },
+                "is_student": { "type": "boolean", "description": "Whether the person is a student" },
+            },
+            "required": ["name", "age", "is_student"],
+            "additionalProperties": false,
+        })
+    );
+    let variant = |tag: &str, description: &str, value: Value| {
+        json!({
+            "type": "object",
+            "description": description,
+            "properties": {
+                tag: {
+                    "type": "object",
+                    "properties": { "value": value },
+                    "required": ["value"],
+                    "additionalProperties": false,
+                },
+            },
+            "required": [tag],
+            "additionalProperties": false,
+        })
+    };
+    assert_eq!(
+        TestEnum::json_schema(),
+        json!({
+            "oneOf": [
+                { "type": "string", "enum": ["Simple"], "description": "A simple variant" },
+                variant(
+                    "WithStringData",
+                    "A variant with data string",
+                    json!({ "type": "string", "description": "The value of the variant" }),
+                ),
+                variant(
+                    "WithFloatData",
+                    "A variant with data float",
+                    json!({ "type": "number", "description": "The float value of the variant" }),
+                ),
+                variant(
+                    "WithIntData",
+                    "A variant with data int",
+                    json!({ "type": "integer", "description": "The int value of the variant" }),
+                ),
+            ],
+        })
+    );
+
+    // constraints, with an `Option` left out of `required`
+    let schema = RangeStruct::json_schema();
+    assert_eq!(schema["properties"]["confidence"]["minimum"], 0);
+    assert_eq!(schema["properties"]["confidence"]["maximum"], 1);
+    assert_eq!(schema["properties"]["retries"]["minimum"], 1);
+    assert_eq!(schema["properties"]["temperature"]["maximum"], -10);
+    assert_eq!(schema["required"], json!(["confidence", "retries"]));
+    let schema = TaggedArticle::json_schema();
+    assert_eq!(schema["properties"]["tags"]["maxItems"], 3);
+    assert_eq!(schema["properties"]["related"]["type"], "array");
+    assert_eq!(
+        Review::json_schema()["properties"]["sentiment"]["enum"],
+        json!(["positive", "neutral", "negative"])
+    );
+    assert_eq!(
+        InventoryStruct::json_schema()["properties"]["stock"],
+        json!({
+            "type": "object",
+            "additionalProperties": { "type": "integer" },
+            "description": "The stock of each product",
+        })
+    );
+
+    // strict mode: every field required, and only the keywords providers accept
+    fn check_strict(schema: &Value) {
+        const ALLOWED: [&str; 10] = [
+            "type",
+            "properties",
+            "required",
+            "additionalProperties",
+            "items",
+            "enum",
+            "anyOf",
+            "description",
+            "$ref",
+            "$defs",
+        ];
+        let Value::Object(object) = schema else {
+            panic!("not a schema: {}", schema);
+        };
+        for (keyword, value) in object {
+            assert!(
+                ALLOWED.contains(&keyword.as_str()),
+                "{} in {}",
+                keyword,
+                schema
+            );
+            match keyword.as_str() {
+                "properties" | "$defs" => {
+                    value.as_object().unwrap().values().for_each(check_strict)
+                }
+                "items" => check_strict(value),
+                "anyOf" => value.as_array().unwrap().iter().for_each(check_strict),
+                _ => {}
+            }
+        }
+        if let Some(properties) = object.get("properties") {
+            let keys: Vec<&String> = properties.as_object().unwrap().keys().collect();
+            let required: Vec<&str> = object["required"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tag| tag.as_str().unwrap())
+                .collect();
+            assert_eq!(keys.len(), required.len(), "{}", schema);
+            assert!(keys.iter().all(|key| required.contains(&key.as_str())));
+            assert_eq!(object["additionalProperties"], false);
+        }
+    }
+    check_strict(&SimpleStruct::strict_json_schema());
+    check_strict(&TestEnum::strict_json_schema());
+    check_strict(&ComplexStruct::strict_json_schema());
+    check_strict(&RangeStruct::strict_json_schema());
+    check_strict(&TaggedArticle::strict_json_schema());
+    check_strict(&CollectionsStruct::strict_json_schema());
+    check_strict(&NestedContainersStruct::strict_json_schema());
+    check_strict(&InventoryStruct::strict_json_schema());
+    check_strict(&Palette::strict_json_schema());
+    let schema = PythonExecRequest::strict_json_schema();
+    check_strict(&schema);
+    assert!(schema["$defs"]["PythonValue"]["anyOf"].is_array());
+    // the names of a text enum keep their meanings, but in strict mode
+    assert_eq!(
+        Palette::json_schema()["properties"]["main"],
+        json!({
+            "type": "string",
+            "oneOf": [
+                { "const": "Red", "description": "The color of fire" },
+                { "const": "Green" },
+                { "const": "Blue" },
+            ],
+            "description": "The main color",
+        })
+    );
+    assert_eq!(
+        Palette::strict_json_schema()["properties"]["main"],
+        json!({
+            "type": "string",
+            "enum": ["Red", "Green", "Blue"],
+            "description": "The main color",
+        })
+    );
+    assert_eq!(
+        RangeStruct::strict_json_schema()["properties"]["temperature"],
+        json!({
+            "anyOf": [{ "type": "integer" }, { "type": "null" }],
+            "description": "The temperature in celsius (an integer less than or equal to -10)",
+        })
+    );
+}
+
+/// The `name` and `minOccurs` of every `xs:element` declared in `xsd`, which has to be
+/// well-formed.
+fn xsd_elements(xsd: &str) -> Vec<(String, Option<String>)> {
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+
+    let mut reader = Reader::from_str(xsd);
+    let mut elements = Vec::new();
+    let mut open = 0;
+    loop {
+        let event = reader.read_event().expect("the XSD is well-formed");
+        match &event {
+            Event::Start(_) => open += 1,
+            Event::End(_) => open -= 1,
+            Event::Eof => break,
+            _ => {}
+        }
+        if let Event::Start(e) | Event::Empty(e) = event
+            && e.name().as_ref() == b"xs:element"
+        {
+            let attribute = |key: &str| {
+                e.try_get_attribute(key)
+                    .unwrap()
+                    .map(|a| String::from_utf8(a.value.into_owned()).unwrap())
+            };
+            elements.push((attribute("name").unwrap(), attribute("minOccurs")));
+        }
+    }
+    assert_eq!(open, 0, "every element of the XSD is closed");
+    elements
+}
+
+#[test]
+fn test_xsd() {
+    let xsd = NestedStruct::xsd();
+    println!("{}", xsd);
+    assert!(xsd.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    assert!(xsd.contains("<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">"));
+    let names: Vec<String> = xsd_elements(&xsd)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "NestedStruct",
+            "name",
+            "age",
+            "is_student",
+            "person",
+            "SimpleStruct",
+            "score",
+            "state"
+        ]
+    );
+    assert!(xsd.contains("<xs:complexType name=\"NestedStruct\">"));
+    assert!(xsd.contains("<xs:complexType name=\"SimpleStruct\">"));
+    assert!(xsd.contains("<xs:element name=\"age\" type=\"xs:integer\">"));
+    assert!(xsd.contains("<xs:documentation>The person details</xs:documentation>"));
+
+    let xsd = CollectionsStruct::xsd();
+    println!("{}", xsd);
+    assert_eq!(
+        xsd_elements(&xsd),
+        [
+            ("CollectionsStruct".to_string(), None),
+            ("tags".to_string(), None),
+            ("item".to_string(), Some("0".to_string())),
+            ("description".to_string(), Some("0".to_string())),
+        ]
+    );
+    assert!(xsd.contains(
+        "<xs:element name=\"item\" minOccurs=\"0\" maxOccurs=\"unbounded\" type=\"xs:string\"/>"
+    ));
+
+    // enums choose a variant, maps repeat <entry>, and a recursive type is declared once
+    let xsd = TestEnum::xsd();
+    let names: Vec<String> = xsd_elements(&xsd)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "Simple",
+            "WithStringData",
+            "value",
+            "WithFloatData",
+            "value",
+            "WithIntData",
+            "value"
+        ]
+    );
+    let xsd = InventoryStruct::xsd();
+    let names: Vec<String> = xsd_elements(&xsd)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert!(names.windows(3).any(|w| w == ["entry", "key", "value"]));
+    assert_eq!(xsd.matches("<xs:group name=\"PythonValue\">").count(), 1);
+    assert!(xsd.contains("<xs:group ref=\"PythonValue\"/>"));
+    let xsd = Vec::<Option<u8>>::xsd();
+    xsd_elements(&xsd);
+    assert!(xsd.contains("<xs:element name=\"response\">"));
+    assert!(xsd.contains("<xs:union memberTypes=\"xs:integer\">"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_schema_nodes_of_library_types() {
+    use llm_xml_caster::{IssueKind, validate_xml};
+    use serde_json::json;
+    use std::num::NonZeroU32;
+    use std::time::Duration;
+
+    // ranges and tuples are their field elements, without an element of their own
+    assert_eq!(
+        <Between<i32>>::json_schema(),
+        json!({
+            "type": "object",
+            "properties": { "min": { "type": "integer" }, "max": { "type": "integer" } },
+            "required": [],
+            "additionalProperties": false,
+        })
+    );
+    assert_eq!(
+        <Between<i32>>::strict_json_schema()["properties"]["min"],
+        json!({ "anyOf": [{ "type": "integer" }, { "type": "null" }] })
+    );
+    assert_eq!(
+        <(String, u32)>::strict_json_schema(),
+        json!({
+            "type": "object",
+            "properties": { "first": { "type": "string" }, "second": { "type": "integer" } },
+            "required": ["first", "second"],
+            "additionalProperties": false,
+        })
+    );
+    let xsd = SearchFilter::xsd();
+    assert!(!xsd.contains("xs:anyType"), "{}", xsd);
+    assert!(xsd.contains("<xs:element name=\"min\" minOccurs=\"0\">"));
+    let report = validate_xml::<SearchFilter>(
+        "<SearchFilter><price><min>1</min><low>2</low></price></SearchFilter>",
+    );
+    assert_eq!(report.issues.len(), 1, "{}", report);
+    assert_eq!(report.issues[0].kind, IssueKind::UnknownElement);
+    assert_eq!(report.issues[0].path, "/SearchFilter/price/low");
+    let report = validate_xml::<(u32, bool)>("<response><first>x</first></response>");
+    let kinds: Vec<IssueKind> = report.issues.iter().map(|issue| issue.kind).collect();
+    assert_eq!(kinds, [IssueKind::InvalidValue, IssueKind::MissingElement]);
+
+    // scalars are leaves read by their own parsers
+    assert_eq!(NonZeroU32::json_schema(), json!({ "type": "integer" }));
+    assert!(!validate_xml::<NonZeroU32>("<response>0</response>").is_valid());
+    assert_eq!(Duration::strict_json_schema(), json!({ "type": "string" }));
+    assert!(validate_xml::<Duration>("<response>2h30m</response>").is_valid());
+    assert!(!validate_xml::<Duration>("<response>soon</response>").is_valid());
+    assert_eq!(std::net::IpAddr::json_schema(), json!({ "type": "string" }));
+    assert_eq!(
+        std::path::PathBuf::json_schema(),
+        json!({ "type": "string" })
+    );
+    assert!(!std::net::Ipv4Addr::xsd().contains("xs:anyType"));
+    #[cfg(feature = "third")]
+    {
+        assert_eq!(uuid::Uuid::json_schema(), json!({ "type": "string" }));
+        assert_eq!(url::Url::json_schema(), json!({ "type": "string" }));
+        assert_eq!(
+            rust_decimal::Decimal::json_schema(),
+            json!({ "type": "number" })
+        );
+        assert!(validate_xml::<rust_decimal::Decimal>("<response>$1,234.50</response>").is_valid());
+        assert_eq!(
+            <chrono::DateTime<chrono::Utc>>::json_schema(),
+            json!({ "type": "string" })
+        );
+        assert!(!validate_xml::<chrono::NaiveDate>("<response>May 1</response>").is_valid());
+        assert_eq!(time::Date::json_schema(), json!({ "type": "string" }));
+    }
+
+    // the indexmap collections are a map and a set that keep their order
+    #[cfg(feature = "indexmap")]
+    {
+        assert_eq!(
+            <indexmap::IndexMap<String, u32>>::json_schema(),
+            json!({ "type": "object", "additionalProperties": { "type": "integer" } })
+        );
+        assert_eq!(
+            <indexmap::IndexSet<u32>>::json_schema(),
+            json!({ "type": "array", "items": { "type": "integer" }, "uniqueItems": true })
+        );
+        let xsd = OrderedAttributes::xsd();
+        assert!(!xsd.contains("xs:anyType"), "{}", xsd);
+        let names: Vec<String> = xsd_elements(&xsd)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert!(names.windows(3).any(|w| w == ["entry", "key", "value"]));
+        let schema = OrderedAttributes::get_prompt_schema();
+        assert!(schema.contains("An ordered set(0 or more distinct elements)"));
+        assert!(OrderedAttributes::get_prompt_schema_compact().contains("map: <entry><key>"));
+    }
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Couple {
+    #[prompt("The first person")]
+    first: SimpleStruct,
+    #[prompt("The second person")]
+    second: SimpleStruct,
+}
+
+#[test]
+fn test_compact_schema() {
+    let full = ComplexStruct::get_prompt_schema();
+    let compact = ComplexStruct::get_prompt_schema_compact();
+    println!("{}", compact);
+    assert!(
+        compact.len() * 2 < full.len(),
+        "{} vs {}",
+        compact.len(),
+        full.len()
+    );
+    assert!(!compact.contains("\n "));
+    let tags = [
+        "ComplexStruct",
+        "nested",
+        "NestedStruct",
+        "person",
+        "SimpleStruct",
+        "name",
+        "age",
+        "is_student",
+        "score",
+        "state",
+        "enum_list",
+        "item",
+        "Simple",
+        "WithStringData",
+        "WithFloatData",
+        "WithIntData",
+        "value",
+        "optional_float",
+    ];
+    for tag in tags {
+        assert!(full.contains(&format!("<{}", tag)), "{}", tag);
+        assert!(compact.contains(&format!("<{}", tag)), "{}", tag);
+    }
+    assert!(compact.contains(
+        "<optional_float>opt. number</optional_float> <!-- An optional ordered float value -->"
+    ));
+    assert_eq!(
+        ComplexStruct::schema_node().render_with_style(llm_xml_caster::SchemaStyle::Full),
+        full
+    );
+
+    // a struct used twice is described once
+    let compact = Couple::get_prompt_schema_compact();
+    assert_eq!(
+        compact,
+        "<Couple>\n<first><SimpleStruct>\n<name>text as <![CDATA[...]]></name> <!-- The name of the person -->\n<age>integer</age> <!-- The age of the person -->\n<is_student>true or false</is_student> <!-- Whether the person is a student -->\n</SimpleStruct></first> <!-- The first person -->\n<second><SimpleStruct>same format as <SimpleStruct> above</SimpleStruct></second> <!-- The second person -->\n</Couple>"
+    );
+    assert_eq!(
+        Vec::<Option<u8>>::schema_node().render_with_style(llm_xml_caster::SchemaStyle::Compact),
+        "list: <item>opt., empty element if none: integer</item> per item, even a single one"
+    );
+}
+
+/// The names of the elements `schema` opens or closes, in order.
+fn schema_tags(schema: &str) -> Vec<&str> {
+    schema
+        .split('<')
+        .skip(1)
+        .filter_map(|part| {
+            let name = part.trim_start_matches('/');
+            let end = name
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(name.len());
+            (end > 0).then(|| &name[..end])
+        })
+        .collect()
+}
+
+#[test]
+fn test_schema_text() {
+    use llm_xml_caster::SchemaText;
+
+    let english = CollectionsStruct::get_prompt_schema();
+    let chinese = SchemaText::SIMPLIFIED_CHINESE.scope(CollectionsStruct::get_prompt_schema);
+    println!("{}", chinese);
+    assert_ne!(english, chinese);
+    assert!(english.contains("A series(0 or more elements) of items"));
+    assert!(chinese.contains("由若干项（0 个或多个元素）组成的序列"));
+    assert!(chinese.contains("可选。如果不提供，则不要包含任何标签"));
+    assert!(!chinese.contains("Optional"));
+    assert_eq!(schema_tags(english), schema_tags(chinese));
+    assert!(schema_tags(chinese).starts_with(&["CollectionsStruct", "tags", "item"]));
+
+    // each language has a schema of its own, so neither is served from the other's cache
+    assert_eq!(CollectionsStruct::get_prompt_schema(), english);
+    assert_eq!(
+        SchemaText::SIMPLIFIED_CHINESE.scope(CollectionsStruct::get_prompt_schema),
+        chinese
+    );
+    let compact =
+        SchemaText::SIMPLIFIED_CHINESE.scope(CollectionsStruct::get_prompt_schema_compact);
+    assert!(compact.contains("<tags>列表：每项写作 <item>"));
+
+    static TERSE: SchemaText = SchemaText {
+        language: "en-terse",
+        optional: "Optional: {}",
+        ..*SchemaText::ENGLISH
+    };
+    let terse = TERSE.scope(CollectionsStruct::get_prompt_schema);
+    assert!(terse.contains("<description>\n  Optional: return a string value."));
+    assert!(terse.contains("A series(0 or more elements) of items"));
+}
+
+#[test]
+fn test_schema_text_of_ranges_tuples_and_non_zero() {
+    use llm_xml_caster::SchemaText;
+    use std::num::{NonZeroI8, NonZeroU32};
+
+    let chinese = |schema: fn() -> &'static str| SchemaText::SIMPLIFIED_CHINESE.scope(schema);
+    assert!(<Between<i32>>::get_prompt_schema().starts_with("a range with an optional lower"));
+    let range = chinese(<Between<i32>>::get_prompt_schema);
+    assert!(
+        range.starts_with("一个带有可选下界和上界的范围，每个边界是 整数值"),
+        "{}",
+        range
+    );
+    assert!(range.contains("<min>{下界}</min><max>{上界}</max>"));
+    let tuple = chinese(<(u32, bool)>::get_prompt_schema);
+    assert!(
+        tuple.starts_with("由 2 个值按固定顺序组成的元组。XML 格式应为：<first>整数值"),
+        "{}",
+        tuple
+    );
+    assert!(tuple.contains("</first><second>布尔值"));
+    assert_eq!(
+        chinese(NonZeroU32::get_prompt_schema),
+        "正整数值，即不能为 0 的整数，例如 1、42 或 7"
+    );
+    assert!(chinese(NonZeroI8::get_prompt_schema).starts_with("非零整数值"));
+    assert!(NonZeroI8::get_prompt_schema().starts_with("non-zero integer value"));
+
+    #[cfg(feature = "indexmap")]
+    {
+        let map = chinese(<indexmap::IndexMap<String, u32>>::get_prompt_schema);
+        assert!(map.starts_with("由键值对组成的有序序列"), "{}", map);
+        assert!(map.ends_with("条目的顺序会被保留。"));
+        let set = chinese(<indexmap::IndexSet<u32>>::get_prompt_schema);
+        assert!(
+            set.starts_with("由若干项（0 个或多个互不相同的元素）组成的有序集合"),
+            "{}",
+            set
+        );
+    }
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ArrowStruct {
+    #[prompt("the score --> higher is better")]
+    score: i32,
+    #[prompt("wrap the title in <b>bold</b> if it matters")]
+    title: String,
+    #[prompt("🌍 地球上的位置 — naïve café")]
+    place: bool,
+}
+
+#[test]
+fn test_description_escaping() {
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+
+    let schema = ArrowStruct::get_prompt_schema();
+    println!("{}", schema);
+    let mut reader = Reader::from_str(schema);
+    let mut elements = Vec::new();
+    let mut comments = Vec::new();
+    loop {
+        match reader.read_event().expect("the schema is well-formed") {
+            Event::Start(e) => {
+                elements.push(String::from_utf8(e.name().as_ref().to_vec()).unwrap())
+            }
+            Event::Comment(c) => comments.push(String::from_utf8(c.to_vec()).unwrap()),
+            Event::Empty(e) => panic!("unexpected <{:?}/>", e.name()),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    assert_eq!(elements, ["ArrowStruct", "score", "title", "place"]);
+    assert_eq!(
+        comments,
+        [
+            " the score - -&gt; higher is better ",
+            " wrap the title in &lt;b&gt;bold&lt;/b&gt; if it matters ",
+            " 🌍 地球上的位置 — naïve café ",
+        ]
+    );
+    assert!(comments.iter().all(|c| !c.contains("--")));
+    assert!(
+        ArrowStruct::get_prompt_schema_compact()
+            .contains("<score>integer</score> <!-- the score - -&gt; higher is better -->")
+    );
+}
+
+#[test]
+fn test_legend_schema() {
+    use llm_xml_caster::SchemaStyle;
+
+    let schema = ComplexStruct::schema_node().render_with_style(SchemaStyle::Legend);
+    println!("{}", schema);
+    assert!(!schema.contains("<!--"));
+    let (skeleton, legend) = schema.split_once("\nField meanings").unwrap();
+    assert!(skeleton.starts_with("<ComplexStruct>\n  <nested>\n  <NestedStruct>"));
+    assert!(legend.contains("never write these descriptions"));
+    let descriptions = [
+        "A nested struct",
+        "The person details",
+        "The name of the person",
+        "The age of the person",
+        "Whether the person is a student",
+        "The score of the person",
+        "A list of enums",
+        "A simple variant",
+        "A variant with data string",
+        "The value of the variant",
+        "The int value of the variant",
+        "An optional ordered float value",
+    ];
+    for description in descriptions {
+        assert_eq!(schema.matches(description).count(), 1, "{}", description);
+    }
+    assert!(legend.contains("\n- SimpleStruct/name: The name of the person\n"));
+    assert!(legend.contains("\n- TestEnum/Simple: A simple variant\n"));
+    assert!(legend.contains("\n- WithIntData/value: The int value of the variant\n"));
+
+    // a struct used twice is listed once
+    let schema = Couple::schema_node().render_with_style(SchemaStyle::Legend);
+    assert_eq!(schema.matches("<name>").count(), 2);
+    assert_eq!(schema.matches("The name of the person").count(), 1);
+
+    // so are the meanings of the names of a text enum, listed once
+    let schema = Palette::schema_node().render_with_style(SchemaStyle::Legend);
+    println!("{}", schema);
+    assert!(!schema.contains("<!--"));
+    let (skeleton, legend) = schema.split_once("\nField meanings").unwrap();
+    assert!(skeleton.contains("one of: Red, Green, Blue. Write the value as plain text."));
+    assert!(!skeleton.contains("The color of fire") && !skeleton.contains("The values mean"));
+    assert!(legend.contains("\n- Palette/main: The main color\n"));
+    assert_eq!(legend.matches("\n- Red: The color of fire").count(), 1);
+    assert!(Palette::get_prompt_schema().contains("Red <!-- The color of fire -->"));
+    assert_eq!(
+        ComplexStruct::schema_node().render_with_style(SchemaStyle::Full),
+        ComplexStruct::get_prompt_schema()
+    );
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Folder {
+    #[prompt("The name of the folder")]
+    name: String,
+    #[prompt("The files in the folder")]
+    files: Vec<File>,
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct File {
+    #[prompt("The name of the file")]
+    name: String,
+    #[prompt("The folder the file is a link to, if it is one")]
+    link: Option<Box<Folder>>,
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Shipment {
+    #[prompt("The parcel of the shipment")]
+    parcel: Parcel,
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Parcel {
+    #[prompt("The label of the parcel")]
+    label: Label,
+}
+
+#[llm_prompt]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Label {
+    #[prompt("The recipient written on the label")]
+    recipient: String,
+}
+
+#[test]
+fn test_schema_max_depth() {
+    use llm_xml_caster::SchemaStyle;
+
+    // a mutually recursive pair refers back to the outer struct instead of expanding it again
+    let reference = "<Folder>a nested Folder with the same format as the enclosing <Folder> described above, recursively</Folder>";
+    let schema = Folder::get_prompt_schema();
+    println!("{}", schema);
+    assert!(schema.contains(reference));
+    assert_eq!(schema.matches("The name of the folder").count(), 1);
+    assert_eq!(schema.matches("The name of the file").count(), 1);
+    let schema = File::get_prompt_schema();
+    assert!(schema.contains("<File>a nested File with the same format"));
+    let compact = Folder::schema_node().render_with_style(SchemaStyle::Compact);
+    assert_eq!(compact.matches("The name of the folder").count(), 1);
+    let limited = Folder::schema_node().render_with_max_depth(SchemaStyle::Full, 2);
+    assert!(limited.contains(reference));
+
+    // a deep but finite type is cut off below the limit
+    let schema = Shipment::schema_node().render_with_max_depth(SchemaStyle::Full, 2);
+    println!("{}", schema);
+    assert!(schema.contains("<Parcel>"));
+    assert!(schema.contains(
+        "<Label>a nested Label element, whose fields are not shown as the schema is nested too deeply</Label>"
+    ));
+    assert!(!schema.contains("<recipient>"));
+    let schema = Shipment::schema_node().render_with_max_depth(SchemaStyle::Compact, 1);
+    assert!(schema.contains("<Parcel>a nested Parcel element"));
+    assert!(!schema.contains("<Label>"));
+    assert_eq!(
+        Shipment::schema_node().render_with_max_depth(SchemaStyle::Full, 3),
+        Shipment::get_prompt_schema()
+    );
+}
+
+#[test]
+fn test_estimate_tokens() {
+    use llm_xml_caster::estimate_tokens;
+
+    // the counts of the o200k_base encoding
+    let known = [
+        (
+            "Before sending a request I want to know roughly how many tokens the schema, the example and the prompt will consume, so I can pick a model and set the maximum number of tokens sensibly.",
+            39,
+        ),
+        (
+            "<SimpleStruct><name><![CDATA[John Doe]]></name><age>30</age><is_student>true</is_student></SimpleStruct>",
+            30,
+        ),
+        (
+            "fn main() {\n    let values: Vec<u32> = (0..10).map(|x| x * 2).collect();\n    println!(\"{:?}\", values);\n}\n",
+            37,
+        ),
+    ];
+    for (text, tokens) in known {
+        let estimate = estimate_tokens(text);
+        assert!(
+            estimate.abs_diff(tokens) * 5 <= tokens,
+            "{} tokens estimated for {} in {:?}",
+            estimate,
+            tokens,
+            text
+        );
+    }
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(
+        SimpleStruct::schema_token_estimate(),
+        estimate_tokens(SimpleStruct::get_prompt_schema())
+    );
+    assert!(ComplexStruct::schema_token_estimate() > SimpleStruct::schema_token_estimate());
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_max_prompt_tokens() {
+    use genai::chat::ChatMessage;
+    use llm_xml_caster::testing::MockLlm;
+    use llm_xml_caster::{Error, GenerateOptions, generate_as_with_options};
+
+    let valid = "<SimpleStruct><name><![CDATA[Ann]]></name><age>30</age><is_student>no</is_student></SimpleStruct>";
+    let prompt = ChatMessage::user("Give me person info");
+
+    // a prompt over the limit isn't sent at all
+    let llm = MockLlm::new([valid]);
+    let limit = SimpleStruct::schema_token_estimate() / 2;
+    let options = GenerateOptions::new().with_max_prompt_tokens(limit);
+    let result =
+        generate_as_with_options::<SimpleStruct>(&llm, "mock", prompt.clone(), "", 3, options)
+            .await;
+    let Err(Error::PromptTooLarge {
+        estimated,
+        limit: l,
+    }) = result
+    else {
+        panic!("expected PromptTooLarge, got {:?}", result);
+    };
+    assert_eq!(l, limit);
+    assert!(estimated > SimpleStruct::schema_token_estimate());
+    assert_eq!(llm.calls(), 0);
+
+    // the first request fits, but not once the correction resends the schema
+    let schema_tokens = ComplexStruct::schema_token_estimate();
+    let llm = MockLlm::new(["no XML here", valid]);
+    let options = GenerateOptions::new().with_max_prompt_tokens(schema_tokens + 300);
+    let result =
+        generate_as_with_options::<ComplexStruct>(&llm, "mock", prompt.clone(), "", 3, options)
+            .await;
+    assert!(matches!(result, Err(Error::PromptTooLarge { .. })));
+    assert_eq!(llm.calls(), 1);
+
+    let llm = MockLlm::new([valid]);
+    let options = GenerateOptions::new().with_max_prompt_tokens(schema_tokens);
+    let person = generate_as_with_options::<SimpleStruct>(&llm, "mock", prompt, "", 3, options)
+        .await
+        .unwrap();
+    assert_eq!(person.age, 30);
+}
+
+#[test]
+fn test_example_variants() {
+    let examples = TestEnum::example_variants(2);
+    assert_eq!(examples.len(), 4);
+    assert_eq!(examples[0], "<Simple/>");
+    assert!(examples[1].starts_with("<WithStringData><value>"));
+    let variants: Vec<TestEnum> = examples.iter().map(|xml| from_str(xml).unwrap()).collect();
+    assert!(matches!(variants[3], TestEnum::WithIntData { value: 42 }));
+    assert!(SimpleStruct::example_variants(2).is_empty());
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_few_shot_examples() {
+    use llm_xml_caster::{GenerateOptions, generate_as_with_options, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let bob = SimpleStruct {
+        name: "Bob".to_string(),
+        age: 41,
+        is_student: true,
+    };
+
+    let llm = MockLlm::new([ALICE_XML]);
+    let options = GenerateOptions::new()
+        .with_few_shot([&alice(), &bob])
+        .unwrap();
+    let person: SimpleStruct = generate_as_with_options(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    assert_eq!(person, alice());
+    let system = format!("{:?}", llm.requests()[0].request.messages);
+    assert!(system.contains("do not copy their values"), "{}", system);
+    let first = system.find("Example 1:\\n<SimpleStruct><name>").unwrap();
+    let second = system.find("Example 2:\\n<SimpleStruct><name>").unwrap();
+    assert!(first < system.find("Alice").unwrap());
+    assert!(system.find("Alice").unwrap() < second);
+    assert!(second < system.find("Bob").unwrap());
+    assert!(!system.contains("Example 3:"));
+
+    // without examples the system message is unchanged
+    let llm = MockLlm::new([ALICE_XML]);
+    let _: SimpleStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "", 3, GenerateOptions::new())
+            .await
+            .unwrap();
+    assert!(!format!("{:?}", llm.requests()[0].request).contains("Example 1:"));
+
+    // one example per variant, after the given ones
+    let llm = MockLlm::new(["<Simple/>"]);
+    let options = GenerateOptions::new()
+        .with_few_shot_xml(vec![
+            "<WithIntData><value>7</value></WithIntData>".to_string(),
+        ])
+        .with_variant_examples(true);
+    let value: TestEnum = generate_as_with_options(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    assert_eq!(value, TestEnum::Simple);
+    let system = format!("{:?}", llm.requests()[0].request.messages);
+    assert!(system.contains("Example 1:\\n<WithIntData><value>7</value></WithIntData>"));
+    assert!(system.contains("Example 2:\\n<Simple/>"), "{}", system);
+    assert!(system.contains("Example 3:\\n<WithStringData>"));
+    assert!(system.contains("Example 4:\\n<WithFloatData>"));
+    assert!(system.contains("Example 5:\\n<WithIntData><value>42</value>"));
+    assert!(!system.contains("Example 6:"));
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_bad_examples() {
+    use llm_xml_caster::{BadExample, GenerateOptions, generate_as_with_options, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("List the files of docs")];
+    let folder = Folder {
+        name: "docs".to_string(),
+        files: vec![File {
+            name: "a.md".to_string(),
+            link: None,
+        }],
+    };
+    let valid = "<Folder><name>docs</name><files><item><File><name>a.md</name></File></item></files></Folder>";
+
+    let llm = MockLlm::new([valid]);
+    let options = GenerateOptions::new().with_bad_examples(vec![BadExample::new(
+        "<name>docs/</name>",
+        "a folder name has no trailing slash",
+        "<name>docs</name>",
+    )]);
+    let value: Folder = generate_as_with_options(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    assert_eq!(value, folder);
+    let system = format!("{:?}", llm.requests()[0].request.messages);
+    assert!(system.contains("Avoid these common mistakes:\\nIncorrect: <name>docs/</name> — because a folder name has no trailing slash; Correct: <name>docs</name>"), "{}", system);
+    assert!(!system.contains("<![CDATA[Tom & Jerry]]>"));
+
+    // the built-in pairs follow, and a correction quotes the one its error matches
+    let llm = MockLlm::new([
+        "<Folder><name>docs</name><files><File><name>a.md</name></File></files></Folder>",
+        valid,
+    ]);
+    let options = GenerateOptions::new().with_built_in_bad_examples(true);
+    let value: Folder = generate_as_with_options(&llm, "mock", prompt(), "", 3, options)
+        .await
+        .unwrap();
+    assert_eq!(value, folder);
+    let requests = llm.requests();
+    let system = format!("{:?}", requests[0].request.messages);
+    for bad_example in BadExample::BUILT_IN {
+        let rendered = format!(
+            "Incorrect: {} — because {}; Correct: {}",
+            bad_example.incorrect, bad_example.explanation, bad_example.correct
+        );
+        assert!(system.contains(&rendered), "{}", system);
+    }
+    let base = requests[0].request.messages.len();
+    let correction = format!("{:?}", &requests[1].request.messages[base..]);
+    assert!(correction.contains("must be a sequence of <item> elements"));
+    assert!(correction.contains(&format!(
+        "Incorrect: {}",
+        BadExample::MISSING_ITEM.incorrect
+    )));
+    assert!(!correction.contains(&format!(
+        "Incorrect: {}",
+        BadExample::MISSING_ENTRY.incorrect
+    )));
+
+    // without the built-in pairs, the correction doesn't quote them either
+    let llm = MockLlm::new([
+        "<Folder><name>docs</name><files><File><name>a.md</name></File></files></Folder>",
+        valid,
+    ]);
+    let _: Folder = generate_as_with_options(&llm, "mock", prompt(), "", 3, GenerateOptions::new())
+        .await
+        .unwrap();
+    assert!(!format!("{:?}", llm.requests()[1].request).contains("Incorrect:"));
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_correction_trimming() {
+    use llm_xml_caster::{GenerateOptions, generate_as_detailed, testing::MockLlm};
+    use std::ops::ControlFlow;
+    use std::sync::{Arc, Mutex};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let with_age = |age: &str| {
+        format!(
+            "<SimpleStruct><name>Alice</name><age>{}</age><is_student>false</is_student></SimpleStruct>",
+            age
+        )
+    };
+    let padding = "Please fix it. ".repeat(100);
+    let correction_tokens = llm_xml_caster::estimate_tokens(&padding);
+    // room for the prompt and one correction, but not two
+    let limit = SimpleStruct::schema_token_estimate() + 100 + correction_tokens * 3 / 2;
+
+    let llm = MockLlm::new([
+        with_age("first"),
+        with_age("second"),
+        with_age("third"),
+        ALICE_XML.to_string(),
+    ]);
+    let trimmed = Arc::new(Mutex::new(Vec::new()));
+    let options = GenerateOptions::new()
+        .with_max_prompt_tokens(limit)
+        .with_correction_message(move |c| format!("{}\n{}", c.error, padding))
+        .with_on_attempt({
+            let trimmed = trimmed.clone();
+            move |attempt| {
+                trimmed.lock().unwrap().push(attempt.trimmed);
+                ControlFlow::Continue(())
+            }
+        });
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "mock", prompt(), "", 4, options)
+        .await
+        .unwrap();
+    assert_eq!(result.value, alice());
+    assert_eq!(result.trimmed, 2);
+    assert_eq!(*trimmed.lock().unwrap(), [0, 0, 1, 2]);
+
+    // the prompt and the newest error are always sent, the older corrections give way
+    let requests: Vec<String> = llm
+        .requests()
+        .iter()
+        .map(|r| format!("{:?}", r.request.messages))
+        .collect();
+    assert!(requests.iter().all(|r| r.contains("Give me person info")));
+    assert!(
+        requests
+            .iter()
+            .all(|r| r.contains("You must respond with a valid XML document"))
+    );
+    assert!(requests[1].contains("can not parse 'first'"));
+    assert!(requests[2].contains("can not parse 'second'"));
+    assert!(!requests[2].contains("can not parse 'first'"));
+    assert!(requests[3].contains("can not parse 'third'"));
+    assert!(!requests[3].contains("can not parse 'second'"));
+
+    // without a limit every correction is kept
+    let llm = MockLlm::new([with_age("first"), with_age("second"), ALICE_XML.to_string()]);
+    let result =
+        generate_as_detailed::<SimpleStruct>(&llm, "mock", prompt(), "", 3, GenerateOptions::new())
+            .await
+            .unwrap();
+    assert_eq!(result.trimmed, 0);
+    let last = format!("{:?}", llm.requests()[2].request.messages);
+    assert!(last.contains("can not parse 'first'") && last.contains("can not parse 'second'"));
+}
+
+#[test]
+fn test_deserialization_error_path() {
+    use llm_xml_caster::{DeserializationError, Error, cast_from_str};
+
+    fn error_of<T: serde::de::DeserializeOwned + LlmPrompt>(xml: &str) -> DeserializationError {
+        match cast_from_str::<T>(xml) {
+            Err(Error::XmlDeserialization(e)) => e,
+            other => panic!("expected a deserialization error, got {:?}", other.err()),
+        }
+    }
+
+    let e = error_of::<SimpleStruct>(
+        "<SimpleStruct><name>Ann</name><age>old</age><is_student>no</is_student></SimpleStruct>",
+    );
+    assert_eq!(e.path, ["SimpleStruct", "age"]);
+    assert_eq!(e.position, Some((1, 31)));
+    assert_eq!(
+        e.to_string(),
+        "can not parse 'old' as a i32 value (at SimpleStruct.age, line 1, column 31)"
+    );
+
+    // the position is the start tag of the element, although the parser read past it
+    let e = error_of::<NestedStruct>(
+        "<NestedStruct>\n  <person>\n    <name>Ann</name>\n    <age>old</age>\n    <is_student>no</is_student>\n  </person>\n  <score>1.5</score>\n  <state>true</state>\n</NestedStruct>",
+    );
+    assert_eq!(e.path, ["NestedStruct", "person", "age"]);
+    assert_eq!(e.position, Some((4, 5)));
+
+    let e = error_of::<ComplexStruct>(
+        "<ComplexStruct><nested><person><name>Ann</name><age>old</age><is_student>no</is_student></person><score>1.5</score><state>true</state></nested><enum_list/></ComplexStruct>",
+    );
+    assert_eq!(e.path, ["ComplexStruct", "nested", "person", "age"]);
+    assert!(e.error.to_string().contains("can not parse 'old'"));
+
+    // a missing field is reported in the element it is missing from
+    let e = error_of::<NestedStruct>(
+        "<NestedStruct><person><name>Ann</name><is_student>no</is_student></person><score>1.5</score><state>true</state></NestedStruct>",
+    );
+    assert_eq!(e.path, ["NestedStruct", "person"]);
+    assert_eq!(e.position, Some((1, 15)));
+
+    // an item is told apart from the ones before it by its position
+    let e = error_of::<Vec<i32>>("<response><item>1</item><item>two</item></response>");
+    assert_eq!(e.path, ["response", "item"]);
+    assert_eq!(e.position, Some((1, 25)));
+    let e = error_of::<ComplexStruct>(
+        "<ComplexStruct><nested><person><name>Ann</name><age>3</age><is_student>no</is_student></person><score>1.5</score><state>true</state></nested><enum_list><item><Simple/></item><item><WithIntData><value>x</value></WithIntData></item></enum_list></ComplexStruct>",
+    );
+    assert_eq!(
+        e.path,
+        ["ComplexStruct", "enum_list", "item", "WithIntData", "value"]
+    );
+
+    // a syntax error is reported where it is
+    let e = error_of::<SimpleStruct>(
+        "<SimpleStruct>\n<name>Tom & Jerry</name><age>3</age><is_student>no</is_student></SimpleStruct>",
+    );
+    assert!(matches!(e.error, quick_xml::DeError::InvalidXml(_)));
+    assert_eq!(e.position.map(|(line, _)| line), Some(2));
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_correction_quotes_error_path() {
+    use llm_xml_caster::{GenerateOptions, generate_as_with_options, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let bad_age = "<NestedStruct>\n<person><name>Ann</name><age>old</age><is_student>no</is_student></person>\n<score>1.5</score><state>true</state></NestedStruct>";
+    let valid = "<NestedStruct><person><name>Ann</name><age>3</age><is_student>no</is_student></person><score>1.5</score><state>true</state></NestedStruct>";
+    let llm = MockLlm::new([bad_age, valid]);
+    let value: NestedStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "", 2, GenerateOptions::new())
+            .await
+            .unwrap();
+    assert_eq!(value.person.age, 3);
+    let requests = llm.requests();
+    let base = requests[0].request.messages.len();
+    let correction = format!("{:?}", &requests[1].request.messages[base..]);
+    assert!(
+        correction.contains(
+            "The problem is inside <NestedStruct> → <person> → <age>, near line 2, column 25 of your XML."
+        ),
+        "{}",
+        correction
+    );
+    // the error itself is quoted without the location
+    assert!(correction.contains("The error was: can not parse 'old' as a i32 value\\n"));
+}
+
+#[test]
+fn test_extraction_errors() {
+    use llm_xml_caster::{Error, ExtractionError, cast_from_str};
+
+    fn error_of<T: serde::de::DeserializeOwned + LlmPrompt>(text: &str) -> ExtractionError {
+        match cast_from_str::<T>(text) {
+            Err(Error::XmlExtraction(e)) => e,
+            other => panic!("expected an extraction error, got {:?}", other.err()),
+        }
+    }
+
+    // a comparison in the prose isn't XML
+    assert_eq!(
+        error_of::<SimpleStruct>("Sorry, 7 < 10 is all I know."),
+        ExtractionError::NoXmlFound {
+            expected: vec!["SimpleStruct".to_string()]
+        }
+    );
+    assert_eq!(
+        error_of::<SimpleStruct>("<Person><name>Ann</name></Person>"),
+        ExtractionError::RootNotFound {
+            expected: "SimpleStruct".to_string()
+        }
+    );
+    let e = error_of::<Vec<i32>>("<numbers><item>1</item></numbers>");
+    assert_eq!(
+        e.to_string(),
+        "cannot find the root response of the structure"
+    );
+
+    let variants = ["Simple", "WithStringData", "WithFloatData", "WithIntData"].map(String::from);
+    assert_eq!(
+        error_of::<TestEnum>("no idea"),
+        ExtractionError::NoXmlFound {
+            expected: variants.to_vec()
+        }
+    );
+    assert_eq!(
+        error_of::<TestEnum>("I think <Other/> fits."),
+        ExtractionError::VariantNotFound {
+            expected: variants.to_vec()
+        }
+    );
+
+    // cut off at the token limit, after the reasoning
+    let text = "<think>Let me see.</think>Here:\n<SimpleStruct><name>Ann</name><age>3";
+    assert_eq!(
+        error_of::<SimpleStruct>(text),
+        ExtractionError::UnclosedRoot {
+            root: "SimpleStruct".to_string(),
+            found_at: 6
+        }
+    );
+    let e = error_of::<TestEnum>("`<Simple/>` won't do, so: <WithIntData><value>7");
+    assert_eq!(
+        e,
+        ExtractionError::UnclosedRoot {
+            root: "WithIntData".to_string(),
+            found_at: 26
+        }
+    );
+    assert_eq!(
+        e.to_string(),
+        "the element WithIntData starting at byte 26 is never closed"
+    );
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_extraction_corrections() {
+    use llm_xml_caster::{GenerateOptions, generate_as_with_options, testing::MockLlm};
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let llm = MockLlm::new([
+        "I don't know",
+        "<Person><name>Alice</name></Person>",
+        "<SimpleStruct><name>Alice</name><age>2",
+        ALICE_XML,
+    ]);
+    let person: SimpleStruct =
+        generate_as_with_options(&llm, "mock", prompt(), "", 4, GenerateOptions::new())
+            .await
+            .unwrap();
+    assert_eq!(person, alice());
+
+    // each correction says what went wrong in its own words
+    let requests = llm.requests();
+    let last = format!("{:?}", requests[3].request.messages);
+    let no_xml = last
+        .find("the XML document itself, in the required format")
+        .unwrap();
+    let wrong_root = last.find("inside the <SimpleStruct> root element").unwrap();
+    let unclosed = last.find("ending with </SimpleStruct>").unwrap();
+    assert!(no_xml < wrong_root && wrong_root < unclosed, "{}", last);
+    assert!(last.contains("the element SimpleStruct starting at byte 0 is never closed"));
+    assert!(!last.contains("strictly follows the required XML format"));
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_retry_limit_failures() {
+    use llm_xml_caster::{
+        BackendResponse, Error, GenerateOptions, generate_as_with_options, testing::MockLlm,
+    };
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let bad_age =
+        "<SimpleStruct><name>Alice</name><age>old</age><is_student>no</is_student></SimpleStruct>";
+    let no_name = "Here:\n<SimpleStruct><age>28</age><is_student>no</is_student></SimpleStruct>";
+    let llm = MockLlm::new(["I don't know", bad_age])
+        .with_response(BackendResponse::default())
+        .with_text(no_name);
+    let options = GenerateOptions::new().with_quote_limit(80);
+    let err = generate_as_with_options::<SimpleStruct>(&llm, "mock", prompt(), "", 4, options)
+        .await
+        .unwrap_err();
+
+    let failures = err.attempt_failures();
+    assert_eq!(
+        failures.iter().map(|f| f.attempt).collect::<Vec<_>>(),
+        [1, 2, 3, 4]
+    );
+    assert!(matches!(failures[0].error, Error::XmlExtraction(_)));
+    assert_eq!(failures[0].text.as_deref(), Some("I don't know"));
+    assert_eq!(failures[0].xml, None);
+    // the texts are cut off after the quote limit
+    assert!(matches!(failures[1].error, Error::XmlDeserialization(_)));
+    assert_eq!(
+        failures[1].xml.as_deref(),
+        Some(
+            "<SimpleStruct><name>Alice</name><age>old</age><is_student>no</is_student></Simpl... (8 more characters)"
+        )
+    );
+    assert!(matches!(failures[2].error, Error::EmptyResponse));
+    assert_eq!(
+        (failures[2].text.as_deref(), failures[2].xml.as_deref()),
+        (None, None)
+    );
+    assert_eq!(failures[3].text.as_deref(), Some(no_name));
+    assert_eq!(
+        failures[3].xml.as_deref(),
+        Some("<SimpleStruct><age>28</age><is_student>no</is_student></SimpleStruct>")
+    );
+
+    assert_eq!(err.raw_responses().len(), 3);
+    assert!(matches!(
+        err.last_error(),
+        Some(Error::XmlDeserialization(_))
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Retry limit exceeded after 4 attempts: 1 extraction failure, 2 deserialization failures, 1 empty response; last error: XML deserialization error: missing field `name` (at SimpleStruct, line 1, column 1)"
+    );
+}
+
+#[test]
+fn test_retry_limit_summary() {
+    use llm_xml_caster::{AttemptFailure, Error, ExtractionError};
+
+    let missing = || {
+        Error::XmlExtraction(ExtractionError::RootNotFound {
+            expected: "SimpleStruct".to_string(),
+        })
+    };
+    let err = Error::RetryLimitExceeded(vec![
+        AttemptFailure::new(1, missing()),
+        AttemptFailure {
+            text: Some("<SimpleStruct><age>old</age></SimpleStruct>".to_string()),
+            ..AttemptFailure::new(
+                2,
+                Error::XmlExtraction(ExtractionError::NoXmlFound {
+                    expected: Vec::new(),
+                }),
+            )
+        },
+        AttemptFailure::new(2, Error::Validation("too young".to_string())),
+    ]);
+    assert_eq!(
+        err.to_string(),
+        "Retry limit exceeded after 2 attempts: 2 extraction failures, 1 validation failure; last error: Validation error: too young"
+    );
+    assert_eq!(
+        err.raw_responses(),
+        ["<SimpleStruct><age>old</age></SimpleStruct>"]
+    );
+    assert!(matches!(err.last_error(), Some(Error::Validation(_))));
+
+    assert_eq!(
+        Error::RetryLimitExceeded(Vec::new()).to_string(),
+        "Retry limit exceeded after 0 attempts"
+    );
+    assert!(missing().last_error().is_none() && missing().attempt_failures().is_empty());
+}
+
+#[test]
+fn test_error_classification() {
+    use llm_xml_caster::{AttemptFailure, Error, ExtractionError};
+
+    let extraction = || {
+        Error::XmlExtraction(ExtractionError::NoXmlFound {
+            expected: vec!["SimpleStruct".to_string()],
+        })
+    };
+    let deserialization = || -> Error {
+        quick_xml::de::from_str::<SimpleStruct>("<SimpleStruct/>")
+            .unwrap_err()
+            .into()
+    };
+    for err in [extraction(), deserialization()] {
+        assert!(err.is_parse() && !err.is_transport() && !err.is_retryable());
+        assert_eq!(err.status_code(), None);
+    }
+    for err in [
+        Error::Validation("too young".to_string()),
+        Error::ValidationLimitExceeded(vec![Error::Validation("too young".to_string())]),
+        Error::XmlSerialization("unsupported".to_string()),
+    ] {
+        assert!(!err.is_parse() && !err.is_transport() && !err.is_retryable());
+    }
+
+    // the model kept writing something the type can't represent
+    let err = Error::RetryLimitExceeded(vec![
+        AttemptFailure::new(1, extraction()),
+        AttemptFailure::new(2, deserialization()),
+    ]);
+    assert!(err.is_parse() && !err.is_transport() && !err.is_retryable());
+    let err = Error::RetryLimitExceeded(vec![
+        AttemptFailure::new(1, extraction()),
+        AttemptFailure::new(2, Error::Validation("too young".to_string())),
+    ]);
+    assert!(!err.is_parse() && !err.is_retryable());
+    assert!(!Error::RetryLimitExceeded(Vec::new()).is_parse());
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_transport_error_classification() {
+    use llm_xml_caster::{AttemptFailure, Error};
+
+    #[derive(Debug)]
+    struct ProviderError {
+        status: u16,
+    }
+    impl std::fmt::Display for ProviderError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "the provider failed with {}", self.status)
+        }
+    }
+    impl std::error::Error for ProviderError {}
+    let provider = |status| Error::Backend(Box::new(ProviderError { status }));
+
+    for (status, retryable) in [
+        (429, true),
+        (503, true),
+        (408, true),
+        (401, false),
+        (400, false),
+    ] {
+        let err = provider(status);
+        assert_eq!(err.status_code(), Some(status));
+        assert!(err.is_transport() && !err.is_parse());
+        assert_eq!(err.is_retryable(), retryable, "{}", status);
+    }
+    // without a status, the request never got a response
+    let err = Error::Backend("connection reset by peer".into());
+    assert_eq!(err.status_code(), None);
+    assert!(err.is_transport() && err.is_retryable());
+    assert!(Error::EmptyResponse.is_retryable() && !Error::EmptyResponse.is_transport());
+    let too_large = Error::PromptTooLarge {
+        estimated: 9000,
+        limit: 8000,
+    };
+    assert!(!too_large.is_retryable() && !too_large.is_transport());
+
+    // a rate limit among the attempts makes the whole generation worth another go
+    let parse = || Error::Validation("too young".to_string());
+    let err = Error::RetryLimitExceeded(vec![
+        AttemptFailure::new(1, parse()),
+        AttemptFailure::new(2, provider(429)),
+        AttemptFailure::new(2, provider(500)),
+    ]);
+    assert!(err.is_transport() && err.is_retryable() && !err.is_parse());
+    assert_eq!(err.status_code(), Some(500));
+    let err = Error::RetryLimitExceeded(vec![
+        AttemptFailure::new(1, parse()),
+        AttemptFailure::new(2, provider(401)),
+    ]);
+    assert!(err.is_transport() && !err.is_retryable());
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_retry_after_header() {
+    use llm_xml_caster::{
+        Error, GenerateOptions, HttpStatusError, generate_as_detailed, testing::MockLlm,
+    };
+    use std::time::{Duration, Instant};
+
+    let rate_limit = |retry_after: &str| {
+        Error::Backend(Box::new(
+            HttpStatusError::new(429).with_retry_after(retry_after),
+        ))
+    };
+    assert_eq!(rate_limit("2").retry_after(), Some(Duration::from_secs(2)));
+    assert_eq!(rate_limit("2").status_code(), Some(429));
+    assert!(rate_limit("2").is_retryable());
+    // an HTTP date in any of its forms, no wait once past
+    for past in [
+        "Sun, 06 Nov 1994 08:49:37 GMT",
+        "Sunday, 06-Nov-94 08:49:37 GMT",
+        "Sun Nov  6 08:49:37 1994",
+    ] {
+        assert_eq!(
+            rate_limit(past).retry_after(),
+            Some(Duration::ZERO),
+            "{}",
+            past
+        );
+    }
+    let future = rate_limit("Fri, 01 Jan 2100 00:00:00 GMT")
+        .retry_after()
+        .unwrap();
+    assert!(
+        future > Duration::from_secs(60 * 60 * 24 * 365 * 50),
+        "{:?}",
+        future
+    );
+    assert_eq!(rate_limit("soon").retry_after(), None);
+    assert_eq!(
+        Error::Backend(Box::new(HttpStatusError::new(503))).retry_after(),
+        None
+    );
+    // the text of an error isn't searched for the header
+    assert_eq!(
+        Error::Backend("429, retry-after: 2".into()).retry_after(),
+        None
+    );
+
+    // the retry waits as long as the provider asked, longer than the backoff
+    let prompt = vec![genai::chat::ChatMessage::user("Give me person info")];
+    let llm = MockLlm::default()
+        .with_error(rate_limit("1"))
+        .with_text(ALICE_XML);
+    let options = GenerateOptions::new().with_transport_retries(1);
+    let start = Instant::now();
+    let result = generate_as_detailed::<SimpleStruct>(&llm, "mock", prompt, "", 3, options)
+        .await
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(result.value, alice());
+    assert_eq!(result.errors[0].retry_after(), Some(Duration::from_secs(1)));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_serialize_errors() {
+    use llm_xml_caster::{AttemptFailure, Error, ExtractionError, cast_from_str};
+    use serde_json::{Value, json};
+
+    let deserialization = cast_from_str::<NestedStruct>(
+        "<NestedStruct>\n<person><name>Ann</name><age>old</age><is_student>no</is_student></person><score>1.5</score><state>true</state></NestedStruct>",
+    )
+    .unwrap_err();
+    assert_eq!(
+        serde_json::to_value(&deserialization).unwrap(),
+        json!({
+            "kind": "xml_deserialization",
+            "message": "XML deserialization error: can not parse 'old' as a i32 value (at NestedStruct.person.age, line 2, column 25)",
+            "path": ["NestedStruct", "person", "age"],
+            "line": 2,
+            "column": 25,
+        })
+    );
+
+    let extraction = cast_from_str::<TestEnum>("I think <Other/> fits.").unwrap_err();
+    assert_eq!(
+        serde_json::to_value(&extraction).unwrap(),
+        json!({
+            "kind": "xml_extraction",
+            "message": "XML extraction error: cannot find the XML element of the enum, it must be one of: Simple, WithStringData, WithFloatData, WithIntData",
+            "extraction": {
+                "reason": "variant_not_found",
+                "expected": ["Simple", "WithStringData", "WithFloatData", "WithIntData"],
+            },
+        })
+    );
+    let unclosed = ExtractionError::UnclosedRoot {
+        root: "SimpleStruct".to_string(),
+        found_at: 7,
+    };
+    assert_eq!(
+        serde_json::to_value(&unclosed).unwrap(),
+        json!({"reason": "unclosed_root", "root": "SimpleStruct", "found_at": 7})
+    );
+
+    for (err, kind) in [
+        (Error::Validation("too young".to_string()), "validation"),
+        (
+            Error::XmlSerialization("unsupported".to_string()),
+            "xml_serialization",
+        ),
+    ] {
+        let message = err.to_string();
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({"kind": kind, "message": message})
+        );
+    }
+    let validation = Error::ValidationLimitExceeded(vec![Error::Validation("too young".into())]);
+    assert_eq!(
+        serde_json::to_value(&validation).unwrap()["errors"],
+        json!([{"kind": "validation", "message": "Validation error: too young"}])
+    );
+
+    // the failures nest their errors, and a huge response is cut off
+    let huge = format!("<SimpleStruct>{}</SimpleStruct>", "x".repeat(5000));
+    let retry = Error::RetryLimitExceeded(vec![
+        AttemptFailure {
+            text: Some(huge.clone()),
+            xml: Some(huge),
+            ..AttemptFailure::new(1, deserialization)
+        },
+        AttemptFailure::new(2, extraction),
+    ]);
+    let value = serde_json::to_value(&retry).unwrap();
+    assert_eq!(value["kind"], "retry_limit_exceeded");
+    assert_eq!(value["attempts"], 2);
+    let failures = value["failures"].as_array().unwrap();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0]["attempt"], 1);
+    assert_eq!(
+        failures[0]["error"]["path"],
+        json!(["NestedStruct", "person", "age"])
+    );
+    for text in [&failures[0]["text"], &failures[0]["xml"]] {
+        let text = text.as_str().unwrap();
+        assert!(text.ends_with("... (4029 more characters)"), "{}", text);
+        assert_eq!(
+            text.chars().count(),
+            1000 + "... (4029 more characters)".len()
+        );
+    }
+    assert_eq!(failures[1]["text"], Value::Null);
+    assert_eq!(
+        failures[1]["error"]["extraction"]["reason"],
+        "variant_not_found"
+    );
+}
+
+#[cfg(all(feature = "client", feature = "json"))]
+#[test]
+fn test_serialize_transport_errors() {
+    use llm_xml_caster::Error;
+    use serde_json::json;
+
+    let err = Error::Backend("connection reset by peer".into());
+    assert_eq!(
+        serde_json::to_value(&err).unwrap(),
+        json!({
+            "kind": "backend",
+            "message": "Backend request failed: connection reset by peer",
+            "status_code": null,
+        })
+    );
+    let err = Error::PromptTooLarge {
+        estimated: 9000,
+        limit: 8000,
+    };
+    assert_eq!(
+        serde_json::to_value(&err).unwrap(),
+        json!({
+            "kind": "prompt_too_large",
+            "message": "The prompt is estimated at 9000 tokens, over the limit of 8000",
+            "estimated": 9000,
+            "limit": 8000,
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(Error::EmptyResponse).unwrap()["kind"],
+        "empty_response"
+    );
+}
+
+#[test]
+fn test_schema_cache_threads() {
+    use llm_xml_caster::SchemaText;
+
+    // the threads render the schemas at the same time, then look them up over and over
+    let schemas = std::thread::scope(|scope| {
+        let handles = (0..16)
+            .map(|i| {
+                scope.spawn(move || {
+                    let lookup = Vec::<HashMap<String, i32>>::get_prompt_schema;
+                    let text = match i % 2 {
+                        0 => SchemaText::ENGLISH,
+                        _ => SchemaText::SIMPLIFIED_CHINESE,
+                    };
+                    let first = text.scope(lookup);
+                    for _ in 0..10_000 {
+                        assert!(std::ptr::eq(text.scope(lookup), first));
+                    }
+                    (first, lookup())
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let english = Vec::<HashMap<String, i32>>::get_prompt_schema();
+    let chinese =
+        SchemaText::SIMPLIFIED_CHINESE.scope(Vec::<HashMap<String, i32>>::get_prompt_schema);
+    assert!(english.contains("<entry>"));
+    assert_ne!(english, chinese);
+    // every thread got the one copy of each language
+    for (i, (scoped, default)) in schemas.into_iter().enumerate() {
+        let expected = if i % 2 == 0 { english } else { chinese };
+        assert!(std::ptr::eq(scoped, expected));
+        assert!(std::ptr::eq(default, english));
+    }
+}
+
+#[test]
+fn test_schema_cache_growth() {
+    use llm_xml_caster::{Cache, CacheInner};
+
+    // far more types than the first chunk holds, each keeping its own cache
+    fn caches<A: 'static>() -> Vec<&'static CacheInner> {
+        macro_rules! pairs {
+            ($($b:ty),*) => { vec![$(Cache::<(A, $b)>::get()),*] };
+        }
+        pairs!(
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            usize,
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            isize,
+            f32,
+            f64,
+            bool,
+            char,
+            String,
+            (),
+            [u8; 1],
+            [u8; 2],
+            [u8; 3],
+            [u8; 4],
+            [u8; 5],
+            [u8; 6],
+            [u8; 7],
+            [u8; 8],
+            Vec<u8>,
+            Option<u8>,
+            Box<u8>,
+            &'static str,
+            [u16; 1],
+            [u16; 2]
+        )
+    }
+    macro_rules! all {
+        ($($a:ty),*) => { vec![$(caches::<$a>()),*].concat() };
+    }
+    let lookup = || {
+        all!(
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            usize,
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            isize,
+            f32,
+            f64,
+            bool,
+            char,
+            String,
+            (),
+            [u8; 1],
+            [u8; 2],
+            [u8; 3],
+            [u8; 4],
+            [u8; 5],
+            [u8; 6],
+            [u8; 7],
+            [u8; 8],
+            Vec<u8>,
+            Option<u8>,
+            Box<u8>,
+            &'static str,
+            [u16; 1],
+            [u16; 2]
+        )
+    };
+    let first = lookup();
+    assert_eq!(first.len(), 1024);
+    first[1000].root_name.get_or_init(|| "marked".to_string());
+    let again = lookup();
+    assert!(first.iter().zip(&again).all(|(a, b)| std::ptr::eq(*a, *b)));
+    let distinct = first
+        .iter()
+        .map(|cache| *cache as *const CacheInner)
+        .collect::<HashSet<_>>();
+    assert_eq!(distinct.len(), 1024);
+    assert_eq!(again[1000].root_name.get().unwrap(), "marked");
+}
+
+#[test]
+fn test_schema_build_panic() {
+    use llm_xml_caster::Cache;
+
+    struct Broken;
+    let result = std::panic::catch_unwind(|| {
+        Cache::<Broken>::build_schema(|| {
+            assert!(Cache::<Broken>::is_building());
+            panic!("the schema can't be built")
+        })
+    });
+    assert!(result.is_err());
+    // the panic took the type off the schemas in progress on this thread
+    assert!(!Cache::<Broken>::is_building());
+}
+
+#[test]
+fn test_cached_container_schemas() {
+    use llm_xml_caster::SchemaText;
+
+    type Nested = Vec<Option<BTreeMap<String, Vec<i32>>>>;
+
+    // a cached schema is the one rendered afresh, in each language, whichever is asked first
+    let root = Nested::root_name();
+    assert_eq!(root, "Vec<Option<BTreeMap<string, Vec<i32>>>>");
+    assert!(std::ptr::eq(Nested::root_name(), root));
+    for text in [SchemaText::ENGLISH, SchemaText::SIMPLIFIED_CHINESE] {
+        text.scope(|| {
+            let schema = Nested::get_prompt_schema();
+            assert!(std::ptr::eq(Nested::get_prompt_schema(), schema));
+            assert_eq!(schema, Nested::schema_node().render());
+            assert_eq!(
+                Option::<Vec<i32>>::get_item_schema(),
+                Option::<Vec<i32>>::schema_node().render_item()
+            );
+            assert_eq!(
+                HashSet::<VecDeque<bool>>::get_prompt_schema(),
+                HashSet::<VecDeque<bool>>::schema_node().render()
+            );
+        });
+    }
+    assert_eq!(
+        HashMap::<String, Between<i64>>::root_name(),
+        "HashMap<string, Between<i64>>"
+    );
+}
+
+#[llm_prompt(root = "leaves")]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct LeafStruct {
+    #[prompt("A <b>bold</b> name --> shown as is")]
+    full_name: std::borrow::Cow<'static, str>,
+    #[prompt("A score", min = 0, max = 10)]
+    score: f64,
+    initial: char,
+    #[serde(rename = "n")]
+    count: u8,
+}
+
+#[test]
+fn test_compiled_schema() {
+    use llm_xml_caster::{Cache, SchemaText};
+
+    // a struct of leaves has its English schema written at compile time, as rendered at runtime
+    for schema in [
+        SimpleStruct::get_prompt_schema(),
+        LeafStruct::get_prompt_schema(),
+    ] {
+        println!("{}", schema);
+    }
+    assert_eq!(
+        SimpleStruct::get_prompt_schema(),
+        SimpleStruct::schema_node().render()
+    );
+    assert_eq!(
+        SimpleStruct::get_fields_schema(),
+        SimpleStruct::schema_node().render_fields()
+    );
+    assert_eq!(
+        LeafStruct::get_prompt_schema(),
+        LeafStruct::schema_node().render()
+    );
+    assert_eq!(
+        LeafStruct::get_fields_schema(),
+        LeafStruct::schema_node().render_fields()
+    );
+    assert!(LeafStruct::get_prompt_schema().contains("&lt;b&gt;bold&lt;/b&gt; name - -&gt;"));
+    assert!(Cache::<LeafStruct>::get().prompt_schema.get().is_none());
+
+    // other languages are rendered at runtime
+    let chinese = SchemaText::SIMPLIFIED_CHINESE.scope(LeafStruct::get_prompt_schema);
+    assert_ne!(chinese, LeafStruct::get_prompt_schema());
+    assert_eq!(
+        chinese,
+        SchemaText::SIMPLIFIED_CHINESE.scope(|| LeafStruct::schema_node().render())
+    );
+
+    // as are structs of other fields
+    let schema = CollectionsStruct::get_prompt_schema();
+    assert_eq!(schema, CollectionsStruct::schema_node().render());
+    assert!(std::ptr::eq(
+        Cache::<CollectionsStruct>::get()
+            .prompt_schema
+            .get()
+            .unwrap()
+            .as_str(),
+        schema
+    ));
+}
+
+#[test]
+fn test_cast_from_reader() {
+    use llm_xml_caster::{Error, cast_from_reader, cast_from_str};
+
+    let xml = "<SimpleStruct><name><![CDATA[Ann <b>]]></name><age>30</age><is_student>yes</is_student></SimpleStruct>";
+    let from_stream: SimpleStruct =
+        cast_from_reader(BufReader::with_capacity(5, xml.as_bytes())).unwrap();
+    assert_eq!(from_stream, cast_from_str::<SimpleStruct>(xml).unwrap());
+    assert_eq!(from_stream.name, "Ann <b>");
+
+    // a type without a root of its own is read from a `<response>`
+    let items: Vec<i32> =
+        cast_from_reader("<response><item>1</item><item>2</item></response>".as_bytes()).unwrap();
+    assert_eq!(items, vec![1, 2]);
+
+    let xml = "<SimpleStruct><name><![CDATA[Ann]]></name><age>old</age><is_student>no</is_student></SimpleStruct>";
+    match cast_from_reader::<SimpleStruct, _>(xml.as_bytes()) {
+        Err(Error::XmlDeserialization(e)) => {
+            assert_eq!(e.path, ["SimpleStruct", "age"]);
+            assert_eq!(e.position, None);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_cast_from_reader_split_cdata() {
+    use llm_xml_caster::cast_from_reader;
+
+    // read a few bytes at a time, so that CDATA sections, entities and tags are split across
+    // reads
+    let code = "\n    fn main() {\n        println!(\"hi\");\n    }\n";
+    let xml = format!(
+        "<SnippetStruct><code><![CDATA[{}]]></code><closing><![CDATA[and miles to go\n]]></closing><title><![CDATA[  hello  ]]></title></SnippetStruct>",
+        code
+    );
+    let snippet: SnippetStruct =
+        cast_from_reader(BufReader::with_capacity(7, xml.as_bytes())).unwrap();
+    assert_eq!(snippet.code, code);
+    assert_eq!(snippet.closing, "and miles to go\n");
+    assert_eq!(snippet.title, "hello");
+
+    let xml = r#"
+    <EntityStruct>
+        <named><![CDATA[&lt;tag&gt; &quot;a&quot;&nbsp;b]]></named>
+        <numeric><![CDATA[it&#8217;s &#x201C;fine&#x201D;]]></numeric>
+        <double><![CDATA[R&amp;amp;D &amp;lt;b&amp;gt;]]></double>
+        <bare><![CDATA[Tom & Jerry, AT&T; &unknown; & &#xZZ;]]></bare>
+        <raw><![CDATA[&lt;tag&gt;]]></raw>
+    </EntityStruct>
+    "#;
+    let decoded: EntityStruct =
+        cast_from_reader(BufReader::with_capacity(7, xml.as_bytes())).unwrap();
+    assert_eq!(decoded.named, "<tag> \"a\"\u{a0}b");
+    assert_eq!(decoded.numeric, "it\u{2019}s \u{201c}fine\u{201d}");
+    assert_eq!(decoded.double, "R&D <b>");
+    assert_eq!(decoded.bare, "Tom & Jerry, AT&T; &unknown; & &#xZZ;");
+    assert_eq!(decoded.raw, "&lt;tag&gt;");
+
+    let xml = r#"
+    <CleanStringStruct>
+        <author><![CDATA[ "John Doe" ]]></author>
+        <quote><![CDATA["Yes" and "no"]]></quote>
+        <function><![CDATA[`parse_args`]]></function>
+        <headline><![CDATA[**Important update**]]></headline>
+        <raw><![CDATA[**Important update**]]></raw>
+    </CleanStringStruct>
+    "#;
+    let decoded: CleanStringStruct =
+        cast_from_reader(BufReader::with_capacity(7, xml.as_bytes())).unwrap();
+    assert_eq!(decoded.author, "John Doe");
+    assert_eq!(decoded.quote, r#""Yes" and "no""#);
+    assert_eq!(decoded.function, "parse_args");
+    assert_eq!(decoded.headline, "Important update");
+}
+
+#[test]
+fn test_cast_from_reader_matches_cast_from_str() {
+    use llm_xml_caster::{Error, cast_from_reader, cast_from_str};
+
+    let parse = |value: &str| {
+        let xml = format!(
+            "<TextualFloatStruct><value>{0}</value><ordered>{0}</ordered></TextualFloatStruct>",
+            value
+        );
+        let from_stream =
+            cast_from_reader::<TextualFloatStruct, _>(BufReader::with_capacity(7, xml.as_bytes()));
+        let from_text = cast_from_str::<TextualFloatStruct>(&xml);
+        (from_stream, from_text)
+    };
+    for text in ["-INF", "1,234,567.25", "NaN"] {
+        let (from_stream, from_text) = parse(text);
+        let (from_stream, from_text) = (from_stream.unwrap(), from_text.unwrap());
+        assert_eq!(
+            from_stream.value.to_bits(),
+            from_text.value.to_bits(),
+            "{}",
+            text
+        );
+        assert_eq!(from_stream.ordered, from_text.ordered, "{}", text);
+    }
+    let (from_stream, from_text) = parse("1,2,3");
+    let message = "can not parse '1,2,3' as a f64 value";
+    assert!(from_stream.unwrap_err().to_string().contains(message));
+    assert!(from_text.unwrap_err().to_string().contains(message));
+
+    // the same error in the same element, without a position in the stream
+    let xml = "<UnsignedStruct><age>300</age><balance>-1</balance></UnsignedStruct>";
+    let stream = BufReader::with_capacity(7, xml.as_bytes());
+    match (
+        cast_from_reader::<UnsignedStruct, _>(stream),
+        cast_from_str::<UnsignedStruct>(xml),
+    ) {
+        (Err(Error::XmlDeserialization(stream)), Err(Error::XmlDeserialization(text))) => {
+            assert_eq!(stream.error.to_string(), text.error.to_string());
+            assert_eq!(stream.path, text.path);
+            assert_eq!(stream.position, None);
+        }
+        other => panic!("unexpected results: {:?}", other),
+    }
+}
+
+#[test]
+fn test_extract_from_reader() {
+    use llm_xml_caster::{Error, ExtractionError, cast_from_str, extract_from_reader};
+
+    // a large dict, with the prose, a draft in the reasoning and a tag quoted in backticks
+    // before it
+    let entries = (0..2000)
+        .map(|i| {
+            format!(
+                "<entry><key><String><val><![CDATA[key {i} <&>]]></val></String></key><value><List><val><item><Int><val>{i}</val></Int></item><item><Float><val>1.5</val></Float></item></val></List></value></entry>"
+            )
+        })
+        .collect::<String>();
+    let response = format!(
+        "<think>A <Dict> should do: <Dict><val></val></Dict></think>Here is the `<Dict>` you asked for:\n<Dict><val>{}</val></Dict>\nand a <Dict><val></val></Dict> after it.",
+        entries
+    );
+    let from_stream: PythonValue =
+        extract_from_reader(BufReader::with_capacity(5, response.as_bytes())).unwrap();
+    assert_eq!(
+        from_stream,
+        cast_from_str::<PythonValue>(&response).unwrap()
+    );
+    match &from_stream {
+        PythonValue::Dict { val } => {
+            assert_eq!(val.len(), 2000);
+            let key = PythonValueWeak(PythonValue::String {
+                val: "key 7 <&>".to_string(),
+            });
+            assert!(val.contains_key(&key));
+        }
+        other => panic!("unexpected value: {:?}", other),
+    }
+
+    let simple: SimpleStruct = extract_from_reader(
+        "Sure! <SimpleStruct><name><![CDATA[Bo]]></name><age>4</age><is_student>true</is_student></SimpleStruct>"
+            .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(simple.age, 4);
+
+    // without a document, the prose still says what it holds
+    let extraction = |response: &str| match extract_from_reader::<SimpleStruct, _>(
+        BufReader::with_capacity(3, response.as_bytes()),
+    ) {
+        Err(Error::XmlExtraction(e)) => e,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert!(matches!(
+        extraction("7 < 10, so <think><b>no</b></think> answer"),
+        ExtractionError::NoXmlFound { .. }
+    ));
+    assert!(matches!(
+        extraction("<Person><name>Bo</name></Person>"),
+        ExtractionError::RootNotFound { .. }
+    ));
+    assert!(matches!(
+        extract_from_reader::<PythonValue, _>("<Person/>".as_bytes()),
+        Err(Error::XmlExtraction(
+            ExtractionError::VariantNotFound { .. }
+        ))
+    ));
+}
+
+#[cfg(feature = "json")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Track {
+    title: String,
+    seconds: u32,
+}
+
+#[cfg(feature = "json")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+enum Release {
+    Single,
+    Album { label: String },
+}
+
+#[cfg(feature = "json")]
+#[llm_prompt]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Record {
+    name: String,
+    tracks: Vec<Track>,
+    genres: Vec<String>,
+    release: Release,
+    rating: Option<f64>,
+}
+
+#[cfg(feature = "json")]
+fn record_json() -> serde_json::Value {
+    serde_json::json!({
+        "name": "Rock & <Roll>",
+        "tracks": [{ "title": "One", "seconds": 180 }, { "title": "Two", "seconds": 95 }],
+        "genres": ["rock", "blues"],
+        "release": { "Album": { "label": "Sun" } },
+        "rating": null,
+    })
+}
+
+#[cfg(feature = "json")]
+fn record() -> Record {
+    Record {
+        name: "Rock & <Roll>".into(),
+        tracks: vec![
+            Track {
+                title: "One".into(),
+                seconds: 180,
+            },
+            Track {
+                title: "Two".into(),
+                seconds: 95,
+            },
+        ],
+        genres: vec!["rock".into(), "blues".into()],
+        release: Release::Album {
+            label: "Sun".into(),
+        },
+        rating: None,
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_cast_from_json() {
+    use llm_xml_caster::{Error, cast_from_json};
+    use serde_json::json;
+
+    // nested structs and vectors are read by the same parsers as their XML
+    assert_eq!(cast_from_json::<Record>(&record_json()).unwrap(), record());
+    assert_eq!(
+        cast_from_json::<Vec<Track>>(&json!([{ "title": "One", "seconds": "180" }])).unwrap(),
+        vec![Track {
+            title: "One".into(),
+            seconds: 180,
+        }]
+    );
+    assert_eq!(
+        cast_from_json::<Release>(&json!("Single")).unwrap(),
+        Release::Single
+    );
+    // the strict form of a map is an array of entries
+    let map = json!([{ "key": "a", "value": 1 }, { "key": "b", "value": 2 }]);
+    assert_eq!(
+        cast_from_json::<BTreeMap<String, i32>>(&map).unwrap(),
+        BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+    );
+
+    let mut wrong = record_json();
+    wrong["tracks"][1]["seconds"] = json!("long");
+    match cast_from_json::<Record>(&wrong) {
+        Err(Error::XmlDeserialization(e)) => {
+            assert_eq!(e.path, ["Record", "tracks", "item", "seconds"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[cfg(all(feature = "client", feature = "json"))]
+#[tokio::test]
+async fn test_tool_mode_selection() {
+    use llm_xml_caster::{GenerateOptions, ToolMode, generate_as_with_options, testing::MockLlm};
+
+    assert!(!ToolMode::Off.applies_to("gpt-4o"));
+    assert!(ToolMode::Auto.applies_to("gpt-4o-mini"));
+    assert!(ToolMode::Auto.applies_to("claude-sonnet-4-5"));
+    assert!(ToolMode::Auto.applies_to("openai::o3-mini"));
+    assert!(ToolMode::Auto.applies_to("gemini-2.5-flash"));
+    assert!(!ToolMode::Auto.applies_to("llama3.1:8b"));
+    assert!(ToolMode::Always.applies_to("llama3.1:8b"));
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Give me person info")];
+    let tools = |llm: &MockLlm| {
+        llm.requests()
+            .iter()
+            .map(|request| request.request.tools.as_ref().map_or(0, Vec::len))
+            .collect::<Vec<_>>()
+    };
+    for (mode, model, expected) in [
+        (ToolMode::Off, "gpt-4o", 0),
+        (ToolMode::Auto, "llama3.1:8b", 0),
+        (ToolMode::Auto, "gpt-4o", 1),
+        (ToolMode::Always, "llama3.1:8b", 1),
+    ] {
+        let llm = MockLlm::new([ALICE_XML, ALICE_XML]);
+        let options = GenerateOptions::new().with_tool_mode(mode);
+        let person: SimpleStruct = generate_as_with_options(&llm, model, prompt(), "", 3, options)
+            .await
+            .unwrap();
+        assert_eq!(person, alice());
+        // a response without a tool call is followed by a request for the XML
+        match expected {
+            0 => assert_eq!(tools(&llm), [0], "{:?} {}", mode, model),
+            _ => assert_eq!(tools(&llm), [1, 0], "{:?} {}", mode, model),
+        }
+    }
+}
+
+#[cfg(all(feature = "client", feature = "json"))]
+#[tokio::test]
+async fn test_tool_mode_with_mock_backend() {
+    use llm_xml_caster::{
+        Error, GenerateOptions, HttpStatusError, ToolMode, generate_as_detailed, testing::MockLlm,
+    };
+    use serde_json::json;
+
+    let prompt = || vec![genai::chat::ChatMessage::user("Describe the record")];
+    let options = || GenerateOptions::new().with_tool_mode(ToolMode::Always);
+
+    // the tool takes the JSON Schema of the type, and its arguments are the value
+    let llm = MockLlm::default().with_tool_call(record_json());
+    let result = generate_as_detailed::<Record>(&llm, "mock", prompt(), "", 3, options())
+        .await
+        .unwrap();
+    assert_eq!(result.value, record());
+    assert_eq!(result.attempts, 1);
+    assert!(result.xml.starts_with("<Record>"), "{}", result.xml);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&result.text).unwrap(),
+        record_json()
+    );
+    let requests = llm.requests();
+    let tools = requests[0].request.tools.as_ref().unwrap();
+    assert_eq!(tools[0].schema.as_ref(), Some(&Record::json_schema()));
+
+    // a type that isn't an object is the `value` parameter of the tool
+    let llm =
+        MockLlm::default().with_tool_call(json!({ "value": [{ "title": "One", "seconds": 180 }] }));
+    let tracks = generate_as_detailed::<Vec<Track>>(&llm, "mock", prompt(), "", 3, options())
+        .await
+        .unwrap();
+    assert_eq!(tracks.value.len(), 1);
+    let requests = llm.requests();
+    let schema = requests[0].request.tools.as_ref().unwrap()[0]
+        .schema
+        .clone()
+        .unwrap();
+    assert_eq!(schema["properties"]["value"], Vec::<Track>::json_schema());
+    assert_eq!(schema["required"], json!(["value"]));
+
+    // arguments that don't cast, and a provider rejecting the tool, fall back to the XML
+    let record_xml = "<Record><name>Rock &amp; &lt;Roll&gt;</name><tracks><item><Track><title>One</title><seconds>180</seconds></Track></item><item><Track><title>Two</title><seconds>95</seconds></Track></item></tracks><genres><item>rock</item><item>blues</item></genres><release><Album><label>Sun</label></Album></release></Record>";
+    let mut wrong = record_json();
+    wrong["tracks"] = json!("none");
+    let llm = MockLlm::default()
+        .with_tool_call(wrong)
+        .with_text(record_xml);
+    // in the same attempt, so a single one still asks for the XML
+    let result = generate_as_detailed::<Record>(&llm, "mock", prompt(), "", 1, options())
+        .await
+        .unwrap();
+    assert_eq!(result.value, record());
+    assert_eq!(result.attempts, 1);
+    assert!(matches!(result.errors[..], [Error::XmlDeserialization(_)]));
+    assert!(llm.requests()[1].request.tools.is_none());
+
+    // a rejected tool isn't sent again, however many transport retries are left
+    let llm = MockLlm::default()
+        .with_error(Error::Backend(Box::new(HttpStatusError::new(400))))
+        .with_text(record_xml);
+    let result = generate_as_detailed::<Record>(
+        &llm,
+        "mock",
+        prompt(),
+        "",
+        1,
+        options().with_transport_retries(2),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.attempts, 1);
+    assert_eq!(result.errors[0].status_code(), Some(400));
+    assert_eq!(llm.calls(), 2);
+
+    // nor do the transport retries running out on the tool fail the generation
+    let unavailable = || Error::Backend(Box::new(HttpStatusError::new(503)));
+    let llm = MockLlm::default()
+        .with_error(unavailable())
+        .with_error(unavailable())
+        .with_text(record_xml);
+    let result = generate_as_detailed::<Record>(
+        &llm,
+        "mock",
+        prompt(),
+        "",
+        1,
+        options().with_transport_retries(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.value, record());
+    assert_eq!(result.errors.len(), 2);
+
+    // a response without a tool call counts as a failed attempt
+    let llm = MockLlm::new(["Here it is!", record_xml]);
+    let result = generate_as_detailed::<Record>(&llm, "mock", prompt(), "", 3, options())
+        .await
+        .unwrap();
+    assert!(matches!(result.errors[..], [Error::MissingToolCall]));
+}
@@ -0,0 +1,22 @@
+#[test]
+fn test_strict_mode() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/strict_pass.rs");
+    t.compile_fail("tests/ui/strict_missing_prompt.rs");
+    t.compile_fail("tests/ui/strict_missing_variant_prompt.rs");
+}
+
+#[test]
+fn test_invalid_llm_prompt_arguments() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/unknown_llm_prompt_argument.rs");
+    t.compile_fail("tests/ui/malformed_llm_prompt_argument.rs");
+    t.compile_fail("tests/ui/root_with_serde_rename.rs");
+    t.compile_fail("tests/ui/fractional_integer_bound.rs");
+}
+
+#[test]
+fn test_ambiguous_field_types() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/bare_duration.rs");
+}
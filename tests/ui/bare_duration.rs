@@ -0,0 +1,12 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+use std::time::Duration;
+
+#[llm_prompt]
+#[derive(Deserialize)]
+struct Task {
+    #[prompt("The time the task takes")]
+    estimate: Duration,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+
+#[llm_prompt]
+#[derive(Deserialize)]
+struct Order {
+    #[prompt("The number of items", min = 0.5)]
+    count: u32,
+}
+
+fn main() {}
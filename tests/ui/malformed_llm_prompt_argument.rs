@@ -0,0 +1,11 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+
+#[llm_prompt(text_enum = "yes")]
+#[derive(Deserialize)]
+enum Color {
+    Red,
+    Green,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+
+#[llm_prompt(root = "person")]
+#[derive(Deserialize)]
+#[serde(rename = "Person")]
+struct PersonRecord {
+    #[prompt("The name of the person")]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+
+#[llm_prompt(strict)]
+#[derive(Deserialize)]
+struct Person {
+    #[prompt("The name of the person")]
+    name: String,
+    age: i32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+
+#[llm_prompt(strict)]
+#[derive(Deserialize)]
+enum Shape {
+    #[prompt("A circle")]
+    Circle { radius: f64 },
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use llm_xml_caster::{LlmPrompt, llm_prompt};
+use serde::Deserialize;
+
+#[llm_prompt(strict)]
+#[derive(Deserialize)]
+struct Person {
+    #[prompt("The name of the person")]
+    name: String,
+    #[prompt("The age of the person")]
+    age: i32,
+}
+
+#[llm_prompt(strict)]
+#[derive(Deserialize)]
+enum Shape {
+    #[prompt("A circle")]
+    Circle {
+        #[prompt("The radius of the circle")]
+        radius: f64,
+    },
+}
+
+fn main() {
+    assert!(Person::get_prompt_schema().contains("The age of the person"));
+    assert!(Shape::get_prompt_schema().contains("The radius of the circle"));
+}
@@ -0,0 +1,11 @@
+use llm_xml_caster::llm_prompt;
+use serde::Deserialize;
+
+#[llm_prompt(roott = "invoice")]
+#[derive(Deserialize)]
+struct ExtractedInvoice {
+    #[prompt("The total amount")]
+    total: f64,
+}
+
+fn main() {}
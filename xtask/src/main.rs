@@ -0,0 +1,91 @@
+//! Development tasks for `llm_xml_caster`, run with `cargo xtask <task>`.
+//!
+//! - `quick-xml-matrix [cargo test args...]`: runs the test suite once per supported
+//!   quick-xml release by pinning it in `Cargo.lock`, then restores the original lock file.
+//! - `no-client [cargo test args...]`: runs the test suite without the default `client` feature,
+//!   checking that the parsing core builds without `genai`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+/// quick-xml releases the parsing shim in `src/type/xml.rs` is verified against.
+const QUICK_XML_VERSIONS: &[&str] = &["0.38.4", "0.39.2"];
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("quick-xml-matrix") => quick_xml_matrix(args.collect()),
+        Some("no-client") => no_client(args.collect()),
+        _ => {
+            eprintln!("usage: cargo xtask <quick-xml-matrix|no-client> [cargo test args...]");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn quick_xml_matrix(test_args: Vec<String>) -> ExitCode {
+    let root = project_root();
+    let lock = root.join("Cargo.lock");
+    let original_lock = fs::read(&lock).ok();
+
+    let mut failed = Vec::new();
+    for version in QUICK_XML_VERSIONS {
+        println!("==> testing against quick-xml {}", version);
+        let pinned = cargo(
+            &root,
+            &["update", "-p", "quick-xml", "--precise", version],
+            &[],
+        );
+        if !pinned || !cargo(&root, &["test", "--all-features"], &test_args) {
+            failed.push(*version);
+        }
+    }
+
+    match original_lock {
+        Some(content) => {
+            if let Err(e) = fs::write(&lock, content) {
+                eprintln!("failed to restore {}: {}", lock.display(), e);
+            }
+        }
+        None => {
+            let _ = fs::remove_file(&lock);
+        }
+    }
+
+    if failed.is_empty() {
+        println!("all quick-xml versions passed: {:?}", QUICK_XML_VERSIONS);
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("failed quick-xml versions: {:?}", failed);
+        ExitCode::FAILURE
+    }
+}
+
+fn no_client(test_args: Vec<String>) -> ExitCode {
+    // every feature but `client`, which the optional types don't depend on
+    let args = ["test", "--no-default-features", "--features", "third,regex"];
+    match cargo(&project_root(), &args, &test_args) {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+fn cargo(root: &Path, args: &[&str], extra: &[String]) -> bool {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    Command::new(cargo)
+        .current_dir(root)
+        .args(args)
+        .args(extra)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn project_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives in a subdirectory of the project root")
+        .to_path_buf()
+}